@@ -134,6 +134,17 @@ pub const BLOCK_HAS_SIGNATURE: block_flags = 1 << 30;
 /// compiler
 pub const BLOCK_HAS_EXTENDED_LAYOUT: block_flags = 1 << 31;
 
+#[cfg(any(doc, feature = "apple"))]
+/// Mask for the layout of a byref structure in [`Block_byref_header::flags`].
+pub const BLOCK_BYREF_LAYOUT_MASK: block_flags = 0xf << 28;
+
+#[cfg(any(doc, feature = "apple"))]
+/// The byref structure is a [`Block_byref_extended`].
+pub const BLOCK_BYREF_LAYOUT_EXTENDED: block_flags = 1 << 28;
+
+/// The byref structure has copy and dispose helpers, see [`Block_byref`].
+pub const BLOCK_BYREF_HAS_COPY_DISPOSE: block_flags = 1 << 25;
+
 /// Flags used in the final argument to _Block_object_assign() and
 /// _Block_object_dispose().  These indicate the type of copy or dispose to
 /// perform.
@@ -4,6 +4,7 @@ use core::mem;
 use objc2::encode::{EncodeArgument, EncodeReturn, Encoding, RefEncode};
 
 use crate::ffi;
+use crate::verify::{block_signature, verify_block_signature, Inner, VerificationError};
 
 /// Types that may be used as the arguments of an Objective-C block.
 ///
@@ -16,6 +17,9 @@ use crate::ffi;
 /// This is a sealed trait, and should not need to be implemented. Open an
 /// issue if you know a use-case where this restrition should be lifted!
 pub unsafe trait BlockArguments: Sized {
+    /// The encodings for the arguments.
+    const ENCODINGS: &'static [Encoding];
+
     /// Calls the given method the block and arguments.
     #[doc(hidden)]
     unsafe fn __call_block<R: EncodeReturn>(
@@ -28,6 +32,10 @@ pub unsafe trait BlockArguments: Sized {
 macro_rules! block_args_impl {
     ($($a:ident: $t:ident),*) => (
         unsafe impl<$($t: EncodeArgument),*> BlockArguments for ($($t,)*) {
+            const ENCODINGS: &'static [Encoding] = &[
+                $($t::ENCODING_ARGUMENT),*
+            ];
+
             #[inline]
             unsafe fn __call_block<R: EncodeReturn>(
                 invoke: unsafe extern "C" fn(),
@@ -102,8 +110,50 @@ unsafe impl<A: BlockArguments, R: EncodeReturn> RefEncode for Block<A, R> {
 }
 
 impl<A: BlockArguments, R: EncodeReturn> Block<A, R> {
+    fn layout(&self) -> &ffi::Block_layout {
+        let ptr: *const Self = self;
+        unsafe { ptr.cast::<ffi::Block_layout>().as_ref().unwrap_unchecked() }
+    }
+
+    /// Verify that the block takes arguments `A` and returns `R`.
+    ///
+    /// This reads the type signature that the compiler stores in the block
+    /// when the `BLOCK_HAS_SIGNATURE` flag is set, and returns a
+    /// [`VerificationError`] if any encodings differ, or if the block has no
+    /// signature.
+    ///
+    /// Only the types of the return value and the arguments are compared.
+    /// Any stack offsets and type qualifiers in the signature are ignored.
+    ///
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use block2::{Block, ConcreteBlock};
+    ///
+    /// let block = ConcreteBlock::new(|a: i32, b: i32| a + b);
+    /// assert!(block.verify().is_ok());
+    ///
+    /// // Reinterpret the block as taking a single argument
+    /// let ptr: *const Block<(i32, i32), i32> = &*block;
+    /// let block = unsafe { &*ptr.cast::<Block<(i32,), i32>>() };
+    /// assert!(block.verify().is_err());
+    /// assert!(unsafe { block.try_call((5,)) }.is_err());
+    /// ```
+    #[allow(clippy::missing_errors_doc)] // Written differently in the docs
+    pub fn verify(&self) -> Result<(), VerificationError> {
+        // SAFETY: The block is valid.
+        let signature = unsafe { block_signature(self.layout()) }.ok_or(Inner::MissingSignature)?;
+        let signature = signature.to_str().map_err(|_| Inner::InvalidSignature)?;
+        verify_block_signature(signature, A::ENCODINGS, &R::ENCODING_RETURN)
+    }
+
     /// Call self with the given arguments.
     ///
+    /// If the `verify` feature is enabled, this verifies the signature of
+    /// the block with debug assertions enabled, similar to what `objc2` does
+    /// for message sends.
+    ///
     /// # Safety
     ///
     /// This invokes foreign code that the caller must verify doesn't violate
@@ -111,11 +161,55 @@ impl<A: BlockArguments, R: EncodeReturn> Block<A, R> {
     ///
     /// For example, if this block is shared with multiple references, the
     /// caller must ensure that calling it will not cause a data race.
+    ///
+    /// Note that the block may be a [`NoEscapeBlock`], which borrows from
+    /// its environment. Such a block must not be copied, e.g. with
+    /// [`RcBlock::copy`] or by a function that it is passed to, doing so
+    /// aborts the process.
+    ///
+    ///
+    /// # Panics
+    ///
+    /// Panics if the `verify` feature and debug assertions are enabled, and
+    /// the block has a signature that does not match `A` and `R`.
+    ///
+    /// [`NoEscapeBlock`]: crate::NoEscapeBlock
+    /// [`RcBlock::copy`]: crate::RcBlock::copy
     pub unsafe fn call(&self, args: A) -> R {
+        #[cfg(all(debug_assertions, feature = "verify"))]
+        match self.verify() {
+            // Blocks without a signature can't be verified
+            Ok(()) | Err(VerificationError(Inner::MissingSignature)) => {}
+            Err(err) => panic!("invalid block call: {err}"),
+        }
+
+        unsafe { self.call_unchecked(args) }
+    }
+
+    /// Call self with the given arguments, after verifying that the block
+    /// takes arguments `A` and returns `R`.
+    ///
+    /// See [`Block::verify`] for details, note that only the types are
+    /// compared, not the stack offsets in the signature.
+    ///
+    ///
+    /// # Errors
+    ///
+    /// Returns an error instead of calling the block if verification failed.
+    ///
+    ///
+    /// # Safety
+    ///
+    /// Same as [`Block::call`], except that the types are verified.
+    pub unsafe fn try_call(&self, args: A) -> Result<R, VerificationError> {
+        self.verify()?;
+        Ok(unsafe { self.call_unchecked(args) })
+    }
+
+    unsafe fn call_unchecked(&self, args: A) -> R {
         let ptr: *const Self = self;
-        let layout = unsafe { ptr.cast::<ffi::Block_layout>().as_ref().unwrap_unchecked() };
         // TODO: Is `invoke` actually ever null?
-        let invoke = layout.invoke.unwrap_or_else(|| unreachable!());
+        let invoke = self.layout().invoke.unwrap_or_else(|| unreachable!());
 
         unsafe { A::__call_block(invoke, ptr as *mut Self, args) }
     }
@@ -0,0 +1,337 @@
+use alloc::boxed::Box;
+use core::ffi::c_void;
+use core::fmt;
+use core::mem::{self, ManuallyDrop};
+use core::ptr::{self, NonNull};
+
+use crate::ffi;
+
+/// The layout of the `__block` variables that we create ourselves.
+#[repr(C)]
+struct ByRef<T> {
+    byref: ffi::Block_byref,
+    value: ManuallyDrop<T>,
+}
+
+/// A variable stored like a `__block` variable in Objective-C, which can
+/// be shared between blocks and mutated by them.
+///
+/// This follows the `Block_byref` layout of the [Block ABI][ABI], which
+/// allows passing the variable to C and Objective-C code that expects a
+/// byref structure, and taking ownership of byref structures created there.
+///
+/// The variable starts out in storage owned by the `BlockByRef`, similar to
+/// a `__block` variable on the stack. The first time the variable is shared
+/// (with [`Clone`], or by the runtime when copying a C block that captures
+/// the variable), the value is moved to the heap, and all handles to the
+/// variable are updated to refer to the heap copy through the forwarding
+/// pointer. The value is dropped when the last handle to it is dropped.
+///
+/// This does not implement [`Deref`](core::ops::Deref), since the variable
+/// may be mutated through other handles, including from foreign code; use
+/// [`BlockByRef::as_ptr`] to access it instead.
+///
+/// [ABI]: http://clang.llvm.org/docs/Block-ABI-Apple.html
+///
+///
+/// # Example
+///
+/// Share a counter between two blocks.
+///
+/// ```
+/// use block2::{BlockByRef, ConcreteBlock};
+///
+/// let counter = BlockByRef::new(0i32);
+///
+/// let increment = ConcreteBlock::new({
+///     let counter = counter.clone();
+///     // SAFETY: The counter is only accessed on this thread, and not while
+///     // holding any references to it.
+///     move || unsafe { *counter.as_ptr() += 1 }
+/// })
+/// .copy();
+///
+/// unsafe { increment.call(()) };
+/// unsafe { increment.call(()) };
+/// assert_eq!(unsafe { *counter.as_ptr() }, 2);
+/// ```
+pub struct BlockByRef<T> {
+    /// The byref structure as originally given, which may not be where the
+    /// value currently lives.
+    ptr: NonNull<ffi::Block_byref_header>,
+    /// Whether we own the storage that `ptr` points to, in which case we
+    /// must deallocate it ourselves.
+    storage: Option<NonNull<ByRef<T>>>,
+}
+
+/// The alignment of the heap copy of the byref structure, which the runtime
+/// allocates with `malloc`.
+const MAX_ALIGN: usize = 16;
+
+impl<T> BlockByRef<T> {
+    const FLAGS: ffi::block_flags = ffi::BLOCK_BYREF_HAS_COPY_DISPOSE;
+
+    const ASSERT_ALIGN: () = assert!(
+        mem::align_of::<T>() <= MAX_ALIGN,
+        "the value of a BlockByRef cannot be aligned to more than 16 bytes",
+    );
+
+    /// Create a new variable with the given initial value.
+    ///
+    /// The runtime does not guarantee the alignment of the heap copy of the
+    /// variable to be more than 16 bytes, so `T` cannot be aligned to more
+    /// than that. This is checked at compile-time:
+    ///
+    /// ```compile_fail
+    /// use block2::BlockByRef;
+    ///
+    /// #[repr(align(32))]
+    /// struct Aligned(u8);
+    ///
+    /// let var = BlockByRef::new(Aligned(1));
+    /// ```
+    pub fn new(value: T) -> Self {
+        // Evaluate the assertion
+        let () = Self::ASSERT_ALIGN;
+
+        let storage = Box::new(ByRef {
+            byref: ffi::Block_byref {
+                header: ffi::Block_byref_header {
+                    isa: ptr::null(),
+                    // Populated below
+                    forwarding: ptr::null_mut(),
+                    flags: Self::FLAGS,
+                    size: mem::size_of::<ByRef<T>>() as _,
+                },
+                keep: Some(byref_keep::<T>),
+                destroy: Some(byref_destroy::<T>),
+            },
+            value: ManuallyDrop::new(value),
+        });
+        let storage = NonNull::from(Box::leak(storage));
+        let ptr: NonNull<ffi::Block_byref_header> = storage.cast();
+        // The forwarding pointer initially points to the variable itself.
+        unsafe { (*ptr.as_ptr()).forwarding = ptr.as_ptr() };
+        Self {
+            ptr,
+            storage: Some(storage),
+        }
+    }
+
+    /// Take a reference to a byref structure that was created elsewhere,
+    /// moving it to the heap if it isn't already.
+    ///
+    /// This is similar to what the runtime does for the `__block` variables
+    /// that a block captures when the block is copied.
+    ///
+    ///
+    /// # Safety
+    ///
+    /// The pointer must point to a valid byref structure containing a
+    /// variable of type `T`.
+    ///
+    /// If the variable is not already on the heap, the structure must also
+    /// outlive the returned `BlockByRef`.
+    pub unsafe fn copy(ptr: *mut ffi::Block_byref_header) -> Self {
+        let mut dst: *mut ffi::Block_byref_header = ptr::null_mut();
+        // SAFETY: The caller ensures the pointer is a valid byref structure.
+        unsafe {
+            ffi::_Block_object_assign(
+                <*mut _>::cast(&mut dst),
+                ptr.cast(),
+                ffi::BLOCK_FIELD_IS_BYREF,
+            )
+        };
+        Self {
+            // SAFETY: The runtime does not check the result of allocating
+            // the heap copy, but writes the forwarding pointer and fields
+            // through it before returning, so a failed allocation crashes
+            // inside the runtime instead of producing a NULL pointer here.
+            ptr: unsafe { NonNull::new_unchecked(dst) },
+            storage: None,
+        }
+    }
+
+    /// A pointer to the byref structure, which can be given to code that
+    /// expects a `__block` variable.
+    ///
+    /// The pointer is valid for as long as `self` is.
+    #[inline]
+    pub fn as_byref_ptr(&self) -> *mut ffi::Block_byref_header {
+        self.ptr.as_ptr()
+    }
+
+    /// A pointer to the current location of the value.
+    ///
+    /// This changes when the variable is moved to the heap, so the pointer
+    /// should not be held across operations that may copy the variable,
+    /// such as cloning `self` or copying a block that captures it.
+    pub fn as_ptr(&self) -> *mut T {
+        // SAFETY: The byref structure is valid, and contains a `T`.
+        unsafe { value_ptr(self.ptr.as_ptr()) }
+    }
+}
+
+/// Find the value in a byref structure, following the forwarding pointer.
+///
+///
+/// # Safety
+///
+/// The structure must be valid, and contain a `T`.
+unsafe fn value_ptr<T>(byref: *mut ffi::Block_byref_header) -> *mut T {
+    let byref = unsafe { (*byref).forwarding };
+    let flags = unsafe { (*byref).flags };
+
+    // The compiler only adds the fields that the variable needs.
+    let mut offset = mem::size_of::<ffi::Block_byref_header>();
+    if flags & ffi::BLOCK_BYREF_HAS_COPY_DISPOSE != 0 {
+        offset = mem::size_of::<ffi::Block_byref>();
+    }
+    #[cfg(feature = "apple")]
+    if flags & ffi::BLOCK_BYREF_LAYOUT_MASK == ffi::BLOCK_BYREF_LAYOUT_EXTENDED {
+        offset += mem::size_of::<*const c_void>();
+    }
+    // Round up to the alignment of the value
+    let align = mem::align_of::<T>();
+    offset = (offset + align - 1) / align * align;
+
+    unsafe { byref.cast::<u8>().add(offset).cast() }
+}
+
+impl<T> Clone for BlockByRef<T> {
+    /// Create another handle to the variable, moving it to the heap if it
+    /// isn't already.
+    fn clone(&self) -> Self {
+        // SAFETY: The byref structure is valid and contains a `T`, and it
+        // outlives the new handle if it is not on the heap, since the
+        // returned handle will refer to the heap copy.
+        unsafe { Self::copy(self.ptr.as_ptr()) }
+    }
+}
+
+impl<T> Drop for BlockByRef<T> {
+    fn drop(&mut self) {
+        // SAFETY: The byref structure is valid. This releases the heap copy
+        // if there is one, and does nothing otherwise.
+        unsafe { ffi::_Block_object_dispose(self.ptr.as_ptr().cast(), ffi::BLOCK_FIELD_IS_BYREF) };
+
+        if let Some(storage) = self.storage {
+            let storage = storage.as_ptr();
+            // SAFETY: We own the storage, and nothing else refers to it any
+            // more, since any copies refer to the heap copy.
+            unsafe {
+                // The value was moved out if the variable was moved to the
+                // heap.
+                if ptr::eq((*storage).byref.header.forwarding, storage.cast()) {
+                    ManuallyDrop::drop(&mut (*storage).value);
+                }
+                drop(Box::from_raw(storage));
+            }
+        }
+    }
+}
+
+impl<T> fmt::Debug for BlockByRef<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BlockByRef")
+            .field("ptr", &self.ptr)
+            .field("value", &self.as_ptr())
+            .finish_non_exhaustive()
+    }
+}
+
+/// Called by the runtime when moving the variable to the heap.
+unsafe extern "C" fn byref_keep<T>(dst: *mut c_void, src: *mut c_void) {
+    let dst = dst.cast::<ByRef<T>>();
+    let src = src.cast::<ByRef<T>>();
+    // SAFETY: The runtime gives us valid pointers, and the value in `src` is
+    // never used again, since all accesses go through the forwarding pointer.
+    unsafe { ptr::copy_nonoverlapping(&(*src).value, &mut (*dst).value, 1) };
+}
+
+/// Called by the runtime when the last reference to the heap copy of the
+/// variable is released.
+unsafe extern "C" fn byref_destroy<T>(byref: *mut c_void) {
+    let byref = byref.cast::<ByRef<T>>();
+    // SAFETY: The runtime gives us a valid pointer to the heap copy, which
+    // owns the value.
+    unsafe { ManuallyDrop::drop(&mut (*byref).value) };
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::rc::Rc;
+
+    use super::*;
+    use crate::ConcreteBlock;
+
+    #[test]
+    fn test_value_layout() {
+        let var = BlockByRef::new(42u8);
+        let storage = var.storage.unwrap().as_ptr();
+        assert_eq!(var.as_ptr(), unsafe { &mut *(*storage).value } as *mut u8);
+        assert_eq!(unsafe { *var.as_ptr() }, 42);
+
+        #[repr(align(16))]
+        struct Aligned(u8);
+        let var = BlockByRef::new(Aligned(7));
+        assert_eq!(var.as_ptr() as usize % 16, 0);
+        assert_eq!(unsafe { (*var.as_ptr()).0 }, 7);
+
+        // The heap copy is aligned as well
+        let clone = var.clone();
+        assert_eq!(clone.as_ptr() as usize % 16, 0);
+        assert_eq!(var.as_ptr(), clone.as_ptr());
+        assert_eq!(unsafe { (*clone.as_ptr()).0 }, 7);
+    }
+
+    #[test]
+    fn test_shared() {
+        let var = BlockByRef::new(1i32);
+        let original = var.as_ptr();
+        let clone = var.clone();
+        // Moved to the heap
+        assert_ne!(var.as_ptr(), original);
+        assert_eq!(var.as_ptr(), clone.as_ptr());
+
+        unsafe { *clone.as_ptr() += 1 };
+        assert_eq!(unsafe { *var.as_ptr() }, 2);
+
+        drop(var);
+        assert_eq!(unsafe { *clone.as_ptr() }, 2);
+    }
+
+    #[test]
+    fn test_drop() {
+        let rc = Rc::new(());
+
+        // Dropped without being moved
+        let var = BlockByRef::new(rc.clone());
+        assert_eq!(Rc::strong_count(&rc), 2);
+        drop(var);
+        assert_eq!(Rc::strong_count(&rc), 1);
+
+        // Dropped after being moved to the heap
+        let var = BlockByRef::new(rc.clone());
+        let clone = var.clone();
+        drop(var);
+        assert_eq!(Rc::strong_count(&rc), 2);
+        drop(clone);
+        assert_eq!(Rc::strong_count(&rc), 1);
+    }
+
+    #[test]
+    fn test_in_block() {
+        let var = BlockByRef::new(0i32);
+        let block = ConcreteBlock::new({
+            let var = var.clone();
+            move |x: i32| unsafe { *var.as_ptr() += x }
+        })
+        .copy();
+        unsafe { block.call((5,)) };
+        unsafe { block.call((6,)) };
+        assert_eq!(unsafe { *var.as_ptr() }, 11);
+        drop(block);
+        assert_eq!(unsafe { *var.as_ptr() }, 11);
+    }
+}
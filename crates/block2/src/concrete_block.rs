@@ -1,4 +1,6 @@
+use core::cell::{Cell, RefCell};
 use core::ffi::c_void;
+use core::fmt;
 use core::marker::PhantomData;
 use core::mem::{self, ManuallyDrop};
 use core::ops::Deref;
@@ -7,6 +9,7 @@ use std::os::raw::c_ulong;
 
 use objc2::encode::{EncodeArgument, EncodeReturn, Encoding, RefEncode};
 
+use crate::encoding::{has_signature, signature, Signature};
 use crate::{ffi, Block, BlockArguments, RcBlock};
 
 mod private {
@@ -17,7 +20,9 @@ mod private {
 ///
 /// This is implemented for [`Fn`] closures of up to 12 arguments, where each
 /// argument implements [`EncodeArgument`] and the return type implements
-/// [`EncodeReturn`].
+/// [`EncodeReturn`]. It is also implemented for [`MutClosure`] and
+/// [`OnceClosure`], which wrap [`FnMut`] and [`FnOnce`] closures with the same
+/// restrictions.
 ///
 ///
 /// # Safety
@@ -64,6 +69,73 @@ macro_rules! concrete_block_impl {
                 unsafe { ConcreteBlock::with_invoke(f, self) }
             }
         }
+
+        impl<$($t: EncodeArgument,)* R: EncodeReturn, X> private::Sealed<($($t,)*)> for MutClosure<X>
+        where
+            X: FnMut($($t,)*) -> R,
+        {}
+
+        unsafe impl<$($t: EncodeArgument,)* R: EncodeReturn, X> IntoConcreteBlock<($($t,)*)> for MutClosure<X>
+        where
+            X: FnMut($($t,)*) -> R,
+        {
+            type Output = R;
+
+            fn __into_concrete_block(self) -> ConcreteBlock<($($t,)*), R, Self> {
+                extern "C" fn invoke<$($t,)* R, X>(
+                    block: &ConcreteBlock<($($t,)*), R, MutClosure<X>>,
+                    $($a: $t,)*
+                ) -> R
+                where
+                    X: FnMut($($t,)*) -> R,
+                {
+                    let mut closure = match block.closure.0.try_borrow_mut() {
+                        Ok(closure) => closure,
+                        Err(_) => abort("FnMut block called re-entrantly"),
+                    };
+                    (&mut *closure)($($a),*)
+                }
+
+                let f: extern "C" fn(&ConcreteBlock<($($t,)*), R, Self>, $($a: $t,)*) -> R = invoke;
+                let f: unsafe extern "C" fn() = unsafe { mem::transmute(f) };
+                unsafe { ConcreteBlock::with_invoke(f, self) }
+            }
+        }
+
+        impl<$($t: EncodeArgument,)* R: EncodeReturn, X> private::Sealed<($($t,)*)> for OnceClosure<X>
+        where
+            X: FnOnce($($t,)*) -> R,
+        {}
+
+        unsafe impl<$($t: EncodeArgument,)* R: EncodeReturn, X> IntoConcreteBlock<($($t,)*)> for OnceClosure<X>
+        where
+            X: FnOnce($($t,)*) -> R,
+        {
+            type Output = R;
+
+            fn __into_concrete_block(self) -> ConcreteBlock<($($t,)*), R, Self> {
+                extern "C" fn invoke<$($t,)* R, X>(
+                    block: &ConcreteBlock<($($t,)*), R, OnceClosure<X>>,
+                    $($a: $t,)*
+                ) -> R
+                where
+                    X: FnOnce($($t,)*) -> R,
+                    R: EncodeReturn,
+                {
+                    let closure = match block.closure.0.take() {
+                        Some(closure) => closure,
+                        // SAFETY: `R` is `()`.
+                        None if is_unit::<R>() => return unsafe { mem::zeroed() },
+                        None => abort("FnOnce block called more than once"),
+                    };
+                    closure($($a),*)
+                }
+
+                let f: extern "C" fn(&ConcreteBlock<($($t,)*), R, Self>, $($a: $t,)*) -> R = invoke;
+                let f: unsafe extern "C" fn() = unsafe { mem::transmute(f) };
+                unsafe { ConcreteBlock::with_invoke(f, self) }
+            }
+        }
     );
 }
 
@@ -160,6 +232,12 @@ concrete_block_impl!(
 
 /// An Objective-C block whose size is known at compile time and may be
 /// constructed on the stack.
+///
+/// The block is created with a type signature, which can be checked with
+/// [`Block::verify`]. The signature is omitted if it would be longer than
+/// 255 bytes, or if the block returns an aggregate such as a struct, since
+/// it is not known at compile-time whether those are returned via a pointer
+/// (which the signature would have to indicate).
 #[repr(C)]
 pub struct ConcreteBlock<A, R, F> {
     p: PhantomData<Block<A, R>>,
@@ -185,31 +263,131 @@ where
     }
 }
 
-impl<A, R, F> ConcreteBlock<A, R, F> {
-    // TODO: Use new ABI with BLOCK_HAS_SIGNATURE
-    const FLAGS: ffi::block_flags = if mem::needs_drop::<Self>() {
-        ffi::BLOCK_HAS_COPY_DISPOSE
-    } else {
-        0
+impl<A, R, F> ConcreteBlock<A, R, MutClosure<F>>
+where
+    A: BlockArguments,
+    R: EncodeReturn,
+    MutClosure<F>: IntoConcreteBlock<A, Output = R>,
+{
+    /// Constructs a `ConcreteBlock` with the given [`FnMut`] closure.
+    ///
+    /// The closure is allowed to mutate its captured state, but the block
+    /// aborts the process if it is called again while already being called
+    /// (for example if the closure somehow ends up calling the block itself).
+    ///
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use block2::ConcreteBlock;
+    ///
+    /// let mut count = 0;
+    /// let block = ConcreteBlock::new_mut(move || {
+    ///     count += 1;
+    ///     count
+    /// });
+    /// assert_eq!(unsafe { block.call(()) }, 1);
+    /// assert_eq!(unsafe { block.call(()) }, 2);
+    /// ```
+    pub fn new_mut(closure: F) -> Self {
+        MutClosure(RefCell::new(closure)).__into_concrete_block()
+    }
+}
+
+impl<A, R, F> ConcreteBlock<A, R, OnceClosure<F>>
+where
+    A: BlockArguments,
+    R: EncodeReturn,
+    OnceClosure<F>: IntoConcreteBlock<A, Output = R>,
+{
+    /// Constructs a `ConcreteBlock` with the given [`FnOnce`] closure.
+    ///
+    /// This is useful for completion handlers, which are usually only
+    /// called once, and may want to move captured state out of the closure.
+    /// If the block is called more than once, the subsequent calls do
+    /// nothing if it returns `()`, and otherwise abort the process.
+    ///
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use block2::ConcreteBlock;
+    ///
+    /// let data = vec![1, 2, 3];
+    /// let block = ConcreteBlock::new_once(move |extra: i32| {
+    ///     let mut data = data;
+    ///     data.push(extra);
+    ///     data.len()
+    /// });
+    /// assert_eq!(unsafe { block.call((4,)) }, 4);
+    /// ```
+    pub fn new_once(closure: F) -> Self {
+        OnceClosure(Cell::new(Some(closure))).__into_concrete_block()
+    }
+}
+
+impl<A: BlockArguments, R: EncodeReturn, F> ConcreteBlock<A, R, F> {
+    pub(crate) const HAS_SIGNATURE: bool = has_signature(&R::ENCODING_RETURN, A::ENCODINGS);
+
+    pub(crate) const SIGNATURE: &'static Signature = &signature(&R::ENCODING_RETURN, A::ENCODINGS);
+
+    const FLAGS: ffi::block_flags = {
+        let mut flags = 0;
+        if mem::needs_drop::<Self>() {
+            flags |= ffi::BLOCK_HAS_COPY_DISPOSE;
+        }
+        if Self::HAS_SIGNATURE {
+            flags |= ffi::BLOCK_HAS_SIGNATURE;
+        }
+        flags
     };
 
-    const DESCRIPTOR: ffi::Block_descriptor = ffi::Block_descriptor {
-        header: ffi::Block_descriptor_header {
-            reserved: 0,
-            size: mem::size_of::<Self>() as c_ulong,
-        },
-        copy: if mem::needs_drop::<Self>() {
+    const HEADER: ffi::Block_descriptor_header = ffi::Block_descriptor_header {
+        reserved: 0,
+        size: mem::size_of::<Self>() as c_ulong,
+    };
+
+    const COPY: Option<unsafe extern "C" fn(dst: *mut c_void, src: *mut c_void)> =
+        if mem::needs_drop::<Self>() {
             Some(block_context_copy::<Self>)
         } else {
             None
-        },
-        dispose: if mem::needs_drop::<Self>() {
-            Some(block_context_dispose::<Self>)
-        } else {
-            None
-        },
+        };
+
+    const DISPOSE: Option<unsafe extern "C" fn(src: *mut c_void)> = if mem::needs_drop::<Self>() {
+        Some(block_context_dispose::<Self>)
+    } else {
+        None
+    };
+
+    const DESCRIPTOR: ffi::Block_descriptor = ffi::Block_descriptor {
+        header: Self::HEADER,
+        copy: Self::COPY,
+        dispose: Self::DISPOSE,
     };
 
+    const DESCRIPTOR_BASIC: ffi::Block_descriptor_basic = ffi::Block_descriptor_basic {
+        header: Self::HEADER,
+        encoding: Self::SIGNATURE.as_ptr().cast(),
+    };
+
+    const DESCRIPTOR_WITH_SIGNATURE: ffi::Block_descriptor_with_signature =
+        ffi::Block_descriptor_with_signature {
+            header: Self::HEADER,
+            copy: Self::COPY,
+            dispose: Self::DISPOSE,
+            encoding: Self::SIGNATURE.as_ptr().cast(),
+        };
+
+    /// The descriptor matching [`Self::FLAGS`].
+    fn descriptor() -> *const c_void {
+        match (mem::needs_drop::<Self>(), Self::HAS_SIGNATURE) {
+            (_, false) => <*const _>::cast(&Self::DESCRIPTOR),
+            (false, true) => <*const _>::cast(&Self::DESCRIPTOR_BASIC),
+            (true, true) => <*const _>::cast(&Self::DESCRIPTOR_WITH_SIGNATURE),
+        }
+    }
+
     /// Constructs a `ConcreteBlock` with the given invoke function and closure.
     /// Unsafe because the caller must ensure the invoke function takes the
     /// correct arguments.
@@ -219,7 +397,7 @@ impl<A, R, F> ConcreteBlock<A, R, F> {
             flags: Self::FLAGS,
             reserved: 0,
             invoke: Some(invoke),
-            descriptor: &Self::DESCRIPTOR as *const ffi::Block_descriptor as *mut c_void,
+            descriptor: Self::descriptor(),
         };
         Self {
             p: PhantomData,
@@ -243,7 +421,18 @@ impl<A, R, F: 'static> ConcreteBlock<A, R, F> {
 
 impl<A, R, F: Clone> Clone for ConcreteBlock<A, R, F> {
     fn clone(&self) -> Self {
-        unsafe { Self::with_invoke(self.layout.invoke.unwrap(), self.closure.clone()) }
+        let layout = ffi::Block_layout {
+            isa: self.layout.isa,
+            flags: self.layout.flags,
+            reserved: 0,
+            invoke: self.layout.invoke,
+            descriptor: self.layout.descriptor,
+        };
+        Self {
+            p: PhantomData,
+            layout,
+            closure: self.closure.clone(),
+        }
     }
 }
 
@@ -258,6 +447,46 @@ impl<A, R, F> Deref for ConcreteBlock<A, R, F> {
     }
 }
 
+/// A [`FnMut`] closure in a [`ConcreteBlock`].
+///
+/// See [`ConcreteBlock::new_mut`].
+pub struct MutClosure<F>(RefCell<F>);
+
+impl<F> fmt::Debug for MutClosure<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MutClosure").finish_non_exhaustive()
+    }
+}
+
+/// A [`FnOnce`] closure in a [`ConcreteBlock`].
+///
+/// See [`ConcreteBlock::new_once`].
+pub struct OnceClosure<F>(Cell<Option<F>>);
+
+impl<F> fmt::Debug for OnceClosure<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("OnceClosure").finish_non_exhaustive()
+    }
+}
+
+/// Whether `R` is `()`.
+///
+/// `()` is the only zero-sized type that is encoded as `void`, since the
+/// encoding of other types must describe their values.
+fn is_unit<R: EncodeReturn>() -> bool {
+    matches!(R::ENCODING_RETURN, Encoding::Void) && mem::size_of::<R>() == 0
+}
+
+/// Abort the process with the given message.
+///
+/// Used instead of panicking in invoke functions, since those are called
+/// from C, and unwinding out of them is not possible.
+#[cold]
+pub(crate) fn abort(msg: &str) -> ! {
+    std::eprintln!("{msg}");
+    std::process::abort()
+}
+
 unsafe extern "C" fn block_context_dispose<B>(block: *mut c_void) {
     unsafe { ptr::drop_in_place(block.cast::<B>()) };
 }
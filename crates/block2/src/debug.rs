@@ -4,7 +4,7 @@ use core::fmt::{Debug, DebugStruct, Error, Formatter};
 use core::ptr;
 use std::ffi::CStr;
 
-use crate::{ffi, Block, ConcreteBlock, GlobalBlock, RcBlock};
+use crate::{ffi, Block, ConcreteBlock, GlobalBlock, NoEscapeBlock, RcBlock};
 
 #[derive(Clone, Copy, PartialEq, Eq)]
 struct Isa(*const ffi::Class);
@@ -80,6 +80,15 @@ impl<A, R, F: Debug> Debug for ConcreteBlock<A, R, F> {
     }
 }
 
+impl<A, R, F: Debug> Debug for NoEscapeBlock<A, R, F> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
+        let mut f = f.debug_struct("NoEscapeBlock");
+        debug_block_layout(&self.block.layout, &mut f);
+        f.field("closure", &self.block.closure);
+        f.finish()
+    }
+}
+
 impl<A, R> Debug for GlobalBlock<A, R> {
     fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
         let mut f = f.debug_struct("GlobalBlock");
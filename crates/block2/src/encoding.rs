@@ -0,0 +1,165 @@
+//! Computing the type encoding of blocks at compile-time.
+use objc2::encode::Encoding;
+
+/// The maximum size of a block signature, including the NUL terminator.
+///
+/// Blocks whose signature is longer than this are created without the
+/// [`ffi::BLOCK_HAS_SIGNATURE`] flag. This is documented on `ConcreteBlock`
+/// and `global_block!`, so remember to update those if changing this.
+///
+/// [`ffi::BLOCK_HAS_SIGNATURE`]: crate::ffi::BLOCK_HAS_SIGNATURE
+pub(crate) const SIGNATURE_CAPACITY: usize = 256;
+
+/// A NUL-terminated block signature.
+pub(crate) type Signature = [u8; SIGNATURE_CAPACITY];
+
+/// The encoding of the block itself, which is the first argument to the
+/// block's invoke function.
+const BLOCK_ENCODING: &[u8] = b"@?";
+
+/// The length of the signature of a block with the given return type and
+/// arguments, excluding the NUL terminator.
+pub(crate) const fn signature_len(ret: &Encoding, args: &[Encoding]) -> usize {
+    let mut len = ret.str_len() + BLOCK_ENCODING.len();
+    let mut i = 0;
+    while i < args.len() {
+        len += args[i].str_len();
+        i += 1;
+    }
+    len
+}
+
+/// Whether a value of the given type may be returned via a pointer passed
+/// in by the caller.
+///
+/// Blocks with a signature that return such values must set
+/// [`ffi::BLOCK_USE_STRET`] if that is the case, but whether it is depends
+/// on the size of the type and on the target, which we cannot compute at
+/// compile-time. So this conservatively includes every aggregate.
+///
+/// [`ffi::BLOCK_USE_STRET`]: crate::ffi::BLOCK_USE_STRET
+const fn may_use_stret(ret: &Encoding) -> bool {
+    match ret {
+        Encoding::Struct(_, _)
+        | Encoding::Union(_, _)
+        | Encoding::Array(_, _)
+        | Encoding::Vector(_, _, _)
+        | Encoding::FloatComplex
+        | Encoding::DoubleComplex
+        | Encoding::LongDoubleComplex => true,
+        Encoding::Atomic(t) | Encoding::Qualified(_, t) => may_use_stret(t),
+        _ => false,
+    }
+}
+
+/// Whether a block with the given return type and arguments should be
+/// created with a signature.
+///
+/// This is not the case if the signature does not fit in a [`Signature`],
+/// or if the return value may be passed indirectly, since we would then
+/// have to know whether to set [`ffi::BLOCK_USE_STRET`].
+///
+/// [`ffi::BLOCK_USE_STRET`]: crate::ffi::BLOCK_USE_STRET
+pub(crate) const fn has_signature(ret: &Encoding, args: &[Encoding]) -> bool {
+    !may_use_stret(ret) && signature_len(ret, args) < SIGNATURE_CAPACITY
+}
+
+const fn append(
+    mut buf: Signature,
+    mut len: usize,
+    data: &[u8],
+    data_len: usize,
+) -> (Signature, usize) {
+    let mut i = 0;
+    while i < data_len {
+        buf[len] = data[i];
+        len += 1;
+        i += 1;
+    }
+    (buf, len)
+}
+
+/// The signature of a block with the given return type and arguments, in
+/// the same format that Clang uses, except that stack offsets are omitted.
+///
+/// For example, the signature of `int32_t (^)(float)` is `"i@?f"`.
+///
+/// This is all zeroes if the signature does not fit, see [`has_signature`].
+pub(crate) const fn signature(ret: &Encoding, args: &[Encoding]) -> Signature {
+    let buf = [0; SIGNATURE_CAPACITY];
+    if !has_signature(ret, args) {
+        return buf;
+    }
+
+    let data: Signature = ret.str_array();
+    let (buf, len) = append(buf, 0, &data, ret.str_len());
+    let (mut buf, mut len) = append(buf, len, BLOCK_ENCODING, BLOCK_ENCODING.len());
+    let mut i = 0;
+    while i < args.len() {
+        let data: Signature = args[i].str_array();
+        (buf, len) = append(buf, len, &data, args[i].str_len());
+        i += 1;
+    }
+    buf
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::string::String;
+    use alloc::vec;
+
+    use super::*;
+
+    fn signature_str(ret: &Encoding, args: &[Encoding]) -> String {
+        let buf = signature(ret, args);
+        let len = buf.iter().position(|&b| b == 0).unwrap();
+        String::from_utf8(buf[..len].to_vec()).unwrap()
+    }
+
+    #[test]
+    fn test_signature() {
+        assert_eq!(signature_str(&Encoding::Void, &[]), "v@?");
+        assert_eq!(signature_str(&Encoding::Int, &[Encoding::Float]), "i@?f");
+        assert_eq!(
+            signature_str(
+                &Encoding::Object,
+                &[
+                    Encoding::Pointer(&Encoding::Struct(
+                        "CGPoint",
+                        &[Encoding::Double, Encoding::Double]
+                    )),
+                    Encoding::Block,
+                    Encoding::Bool,
+                ]
+            ),
+            "@@?^{CGPoint=dd}@?B",
+        );
+    }
+
+    #[test]
+    fn test_too_long() {
+        const LONG: Encoding = Encoding::Struct(
+            "AVeryLongStructNameThatIsLongerThanMostNames",
+            &[Encoding::Int, Encoding::Int, Encoding::Int],
+        );
+        let args = vec![LONG; 10];
+        assert!(!has_signature(&Encoding::Void, &args));
+        assert_eq!(signature(&Encoding::Void, &args), [0; SIGNATURE_CAPACITY]);
+
+        let args = vec![LONG; 2];
+        assert!(has_signature(&Encoding::Void, &args));
+        assert_eq!(signature_len(&Encoding::Void, &args), 3 + 2 * 50);
+    }
+
+    #[test]
+    fn test_aggregate_return() {
+        const POINT: Encoding = Encoding::Struct("CGPoint", &[Encoding::Double, Encoding::Double]);
+        assert!(!has_signature(&POINT, &[]));
+        assert!(!has_signature(&Encoding::DoubleComplex, &[Encoding::Int]));
+        assert_eq!(signature(&POINT, &[]), [0; SIGNATURE_CAPACITY]);
+
+        // Pointers to aggregates are returned directly
+        assert!(has_signature(&Encoding::Pointer(&POINT), &[]));
+        assert!(has_signature(&Encoding::Void, &[POINT]));
+    }
+}
@@ -8,6 +8,7 @@ use std::os::raw::c_ulong;
 use objc2::encode::EncodeReturn;
 
 use super::{ffi, Block};
+use crate::encoding::{has_signature, signature, Signature};
 use crate::BlockArguments;
 
 // TODO: Should this be a static to help the compiler deduplicating them?
@@ -45,14 +46,21 @@ where
 {
 }
 
-// Note: We can't put correct bounds on A and R because we have a const fn!
-//
-// Fortunately, we don't need them, since they're present on `Sync`, so
-// constructing the static in `global_block!` with an invalid `GlobalBlock`
-// triggers an error.
-impl<A, R> GlobalBlock<A, R> {
-    // TODO: Use new ABI with BLOCK_HAS_SIGNATURE
-    const FLAGS: ffi::block_flags = ffi::BLOCK_IS_GLOBAL | ffi::BLOCK_USE_STRET;
+impl<A: BlockArguments, R: EncodeReturn> GlobalBlock<A, R> {
+    const HAS_SIGNATURE: bool = has_signature(&R::ENCODING_RETURN, A::ENCODINGS);
+
+    const SIGNATURE: &'static Signature = &signature(&R::ENCODING_RETURN, A::ENCODINGS);
+
+    const FLAGS: ffi::block_flags = if Self::HAS_SIGNATURE {
+        ffi::BLOCK_IS_GLOBAL | ffi::BLOCK_HAS_SIGNATURE
+    } else {
+        ffi::BLOCK_IS_GLOBAL
+    };
+
+    const DESCRIPTOR: ffi::Block_descriptor_basic = ffi::Block_descriptor_basic {
+        header: GLOBAL_DESCRIPTOR,
+        encoding: Self::SIGNATURE.as_ptr().cast(),
+    };
 
     #[doc(hidden)]
     pub const __DEFAULT_LAYOUT: ffi::Block_layout = ffi::Block_layout {
@@ -62,9 +70,20 @@ impl<A, R> GlobalBlock<A, R> {
         reserved: 0,
         // Populated in `global_block!`
         invoke: None,
-        descriptor: &GLOBAL_DESCRIPTOR as *const ffi::Block_descriptor_header as *mut c_void,
+        descriptor: if Self::HAS_SIGNATURE {
+            &Self::DESCRIPTOR as *const ffi::Block_descriptor_basic as *mut c_void
+        } else {
+            &GLOBAL_DESCRIPTOR as *const ffi::Block_descriptor_header as *mut c_void
+        },
     };
+}
 
+// Note: We can't put correct bounds on A and R because we have a const fn!
+//
+// Fortunately, we don't need them, since they're present on `Sync`, so
+// constructing the static in `global_block!` with an invalid `GlobalBlock`
+// triggers an error.
+impl<A, R> GlobalBlock<A, R> {
     /// Use the [`global_block`] macro instead.
     #[doc(hidden)]
     pub const unsafe fn from_layout(layout: ffi::Block_layout) -> Self {
@@ -96,6 +115,10 @@ where
 /// be specified). Note that the block cannot capture its environment, and
 /// its argument types and return type must be [`EncodeReturn`].
 ///
+/// Like [`ConcreteBlock`], the block is created without a type signature if
+/// the signature would be longer than 255 bytes, or if the block returns an
+/// aggregate such as a struct.
+///
 /// # Examples
 ///
 /// ```
@@ -151,6 +174,7 @@ where
 /// ```
 ///
 /// [`Box`]: std::boxed::Box
+/// [`ConcreteBlock`]: crate::ConcreteBlock
 #[macro_export]
 macro_rules! global_block {
     // `||` is parsed as one token
@@ -219,7 +243,7 @@ mod tests {
 
     #[cfg(feature = "apple")]
     const DEBUG_BLOCKFLAGS: &str = r#"BlockFlags {
-        value: "00110000000000000000000000000000",
+        value: "01010000000000000000000000000000",
         deallocating: false,
         inline_layout_string: false,
         small_descriptor: false,
@@ -229,8 +253,8 @@ mod tests {
         has_ctor: false,
         is_gc: false,
         is_global: true,
-        use_stret: true,
-        has_signature: false,
+        use_stret: false,
+        has_signature: true,
         has_extended_layout: false,
         over_referenced: false,
         reference_count: 0,
@@ -239,12 +263,12 @@ mod tests {
 
     #[cfg(not(feature = "apple"))]
     const DEBUG_BLOCKFLAGS: &str = r#"BlockFlags {
-        value: "00110000000000000000000000000000",
+        value: "01010000000000000000000000000000",
         has_copy_dispose: false,
         has_ctor: false,
         is_global: true,
-        use_stret: true,
-        has_signature: false,
+        use_stret: false,
+        has_signature: true,
         over_referenced: false,
         reference_count: 0,
         ..
@@ -265,6 +289,9 @@ mod tests {
     descriptor: BlockDescriptor {{
         reserved: 0,
         size: {size},
+        encoding: Some(
+            \"v@?\",
+        ),
     }},
     ..
 }}"
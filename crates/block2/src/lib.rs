@@ -38,6 +38,13 @@
 //! Note the extra parentheses in the `call` method, since the arguments must
 //! be passed as a tuple.
 //!
+//! Since nothing guarantees that a block received from Objective-C actually
+//! takes the arguments and returns the type that we expect, you may want to
+//! use [`Block::try_call`] instead, which checks the block's type signature
+//! first (if the compiler emitted one). Alternatively, enable the `verify`
+//! feature to have [`Block::call`] do this check when debug assertions are
+//! enabled.
+//!
 //! ## Creating blocks
 //!
 //! Creating a block to pass to Objective-C can be done with the
@@ -71,6 +78,45 @@
 //! assert_eq!(unsafe { MY_BLOCK.call(()) }, 10.0);
 //! ```
 //!
+//! ## Blocks as methods
+//!
+//! Blocks whose first argument is a message receiver can be used as the
+//! implementation of an Objective-C method, which allows the method to
+//! capture state. See [`ClassBuilder::add_method_with_block`] and
+//! [`AnyClass::replace_method_with_block`].
+//!
+//! The method retains the block, so it must be an [`RcBlock`] or a
+//! [`GlobalBlock`]; a [`ConcreteBlock`] must be copied to the heap first.
+//!
+//! ```
+//! use std::sync::Arc;
+//!
+//! use block2::ConcreteBlock;
+//! use objc2::declare::ClassBuilder;
+//! use objc2::rc::Id;
+//! use objc2::runtime::NSObject;
+//! use objc2::{msg_send, msg_send_id, sel, ClassType};
+//!
+//! let offset = Arc::new(10);
+//! let captured = offset.clone();
+//! let block = ConcreteBlock::new(move |_this: &NSObject, n: u32| n + *captured).copy();
+//!
+//! let mut builder = ClassBuilder::new("BlockMethodExample", NSObject::class()).unwrap();
+//! unsafe { builder.add_method_with_block(sel!(addOffset:), &block) };
+//! let cls = builder.register();
+//!
+//! // The method keeps the block, and hence the captured value, alive.
+//! drop(block);
+//! assert_eq!(Arc::strong_count(&offset), 2);
+//!
+//! let obj: Id<NSObject> = unsafe { msg_send_id![cls, new] };
+//! let res: u32 = unsafe { msg_send![&obj, addOffset: 5u32] };
+//! assert_eq!(res, 15);
+//! ```
+//!
+//! [`ClassBuilder::add_method_with_block`]: objc2::declare::ClassBuilder::add_method_with_block
+//! [`AnyClass::replace_method_with_block`]: objc2::runtime::AnyClass::replace_method_with_block
+//!
 //! [lang]: https://clang.llvm.org/docs/BlockLanguageSpec.html
 //! [ABI]: http://clang.llvm.org/docs/Block-ABI-Apple.html
 
@@ -102,12 +148,20 @@ extern "C" {}
 pub use block_sys as ffi;
 
 mod block;
+mod byref;
 mod concrete_block;
 mod debug;
+mod encoding;
 mod global;
+mod method_block;
+mod noescape_block;
 mod rc_block;
+mod verify;
 
 pub use block::{Block, BlockArguments};
-pub use concrete_block::{ConcreteBlock, IntoConcreteBlock};
+pub use byref::BlockByRef;
+pub use concrete_block::{ConcreteBlock, IntoConcreteBlock, MutClosure, OnceClosure};
 pub use global::GlobalBlock;
+pub use noescape_block::NoEscapeBlock;
 pub use rc_block::RcBlock;
+pub use verify::VerificationError;
@@ -0,0 +1,55 @@
+//! Blocks as the implementation of Objective-C methods.
+use core::ffi::c_void;
+
+use objc2::encode::{EncodeArgument, EncodeReturn};
+use objc2::runtime::{MessageReceiver, MethodBlock};
+
+use crate::{Block, GlobalBlock, RcBlock};
+
+// Note: This is intentionally not implemented for `Block` or
+// `ConcreteBlock`, since those may live on the stack. Copying a stack block
+// moves the closure to the heap, after which the original must not be
+// dropped, which we cannot ensure when given a reference.
+macro_rules! method_block_impl {
+    ($ty:ident; $($t:ident),*) => {
+        // SAFETY: `imp_implementationWithBlock` calls the block with the
+        // receiver as the first argument, followed by the method arguments.
+        //
+        // The block is either a heap block or a global block, so copying it
+        // only retains it.
+        unsafe impl<T, R, $($t),*> MethodBlock for $ty<(T, $($t,)*), R>
+        where
+            T: MessageReceiver + EncodeArgument,
+            R: EncodeReturn,
+            $($t: EncodeArgument,)*
+        {
+            type Callee = T::__Inner;
+            type Arguments = ($($t,)*);
+            type Return = R;
+
+            #[inline]
+            fn __as_block_ptr(&self) -> *mut c_void {
+                let block: &Block<(T, $($t,)*), R> = self;
+                let ptr: *const Block<(T, $($t,)*), R> = block;
+                ptr as *mut c_void
+            }
+        }
+    };
+    ($($t:ident),*) => {
+        method_block_impl!(GlobalBlock; $($t),*);
+        method_block_impl!(RcBlock; $($t),*);
+    };
+}
+
+method_block_impl!();
+method_block_impl!(A);
+method_block_impl!(A, B);
+method_block_impl!(A, B, C);
+method_block_impl!(A, B, C, D);
+method_block_impl!(A, B, C, D, E);
+method_block_impl!(A, B, C, D, E, F);
+method_block_impl!(A, B, C, D, E, F, G);
+method_block_impl!(A, B, C, D, E, F, G, H);
+method_block_impl!(A, B, C, D, E, F, G, H, I);
+method_block_impl!(A, B, C, D, E, F, G, H, I, J);
+method_block_impl!(A, B, C, D, E, F, G, H, I, J, K);
@@ -0,0 +1,163 @@
+use core::ffi::c_void;
+use core::mem;
+use core::ops::Deref;
+use std::os::raw::c_ulong;
+
+use objc2::encode::{EncodeReturn, Encoding, RefEncode};
+
+use crate::concrete_block::abort;
+use crate::{
+    ffi, Block, BlockArguments, ConcreteBlock, IntoConcreteBlock, MutClosure, OnceClosure,
+};
+
+/// An Objective-C block that may borrow from its environment, for passing to
+/// functions that do not let the block escape.
+///
+/// Unlike [`ConcreteBlock`], this cannot be copied to the heap, and hence
+/// its closure does not have to be `'static`. This makes it suitable for
+/// synchronous APIs like `-[NSArray enumerateObjectsUsingBlock:]` or
+/// `dispatch_sync`, which only call the block before returning.
+///
+/// The block is marked with the `BLOCK_IS_NOESCAPE` flag (on Apple
+/// platforms), and must not be copied by the function it is passed to (e.g.
+/// with `_Block_copy`), since the copy could outlive the borrowed
+/// environment. Such a copy is detected, and aborts the process.
+///
+/// Note that this dereferences to a [`Block`], which can be passed to any
+/// function that takes a block, including ones that copy it. Ensuring that
+/// it is only passed to functions that do not copy it is up to the caller,
+/// see also the safety requirements of [`Block::call`] and
+/// [`RcBlock::copy`].
+///
+/// [`RcBlock::copy`]: crate::RcBlock::copy
+///
+///
+/// # Example
+///
+/// ```
+/// use block2::{Block, NoEscapeBlock};
+///
+/// unsafe fn enumerate(block: &Block<(i32,), ()>) {
+///     for i in 0..3 {
+///         block.call((i,));
+///     }
+/// }
+///
+/// let mut items = Vec::new();
+/// let block = NoEscapeBlock::new_mut(|i: i32| items.push(i));
+/// unsafe { enumerate(&block) };
+/// drop(block);
+/// assert_eq!(items, [0, 1, 2]);
+/// ```
+#[repr(transparent)]
+pub struct NoEscapeBlock<A, R, F> {
+    pub(crate) block: ConcreteBlock<A, R, F>,
+}
+
+unsafe impl<A: BlockArguments, R: EncodeReturn, F> RefEncode for NoEscapeBlock<A, R, F> {
+    const ENCODING_REF: Encoding = Encoding::Block;
+}
+
+impl<A, R, F> NoEscapeBlock<A, R, F>
+where
+    A: BlockArguments,
+    R: EncodeReturn,
+    F: IntoConcreteBlock<A, Output = R>,
+{
+    /// Constructs a `NoEscapeBlock` with the given [`Fn`] closure.
+    pub fn new(closure: F) -> Self {
+        Self::from_concrete(ConcreteBlock::new(closure))
+    }
+}
+
+impl<A, R, F> NoEscapeBlock<A, R, MutClosure<F>>
+where
+    A: BlockArguments,
+    R: EncodeReturn,
+    MutClosure<F>: IntoConcreteBlock<A, Output = R>,
+{
+    /// Constructs a `NoEscapeBlock` with the given [`FnMut`] closure.
+    ///
+    /// See [`ConcreteBlock::new_mut`] for details.
+    pub fn new_mut(closure: F) -> Self {
+        Self::from_concrete(ConcreteBlock::new_mut(closure))
+    }
+}
+
+impl<A, R, F> NoEscapeBlock<A, R, OnceClosure<F>>
+where
+    A: BlockArguments,
+    R: EncodeReturn,
+    OnceClosure<F>: IntoConcreteBlock<A, Output = R>,
+{
+    /// Constructs a `NoEscapeBlock` with the given [`FnOnce`] closure.
+    ///
+    /// See [`ConcreteBlock::new_once`] for details.
+    pub fn new_once(closure: F) -> Self {
+        Self::from_concrete(ConcreteBlock::new_once(closure))
+    }
+}
+
+impl<A: BlockArguments, R: EncodeReturn, F> NoEscapeBlock<A, R, F> {
+    // The copy and dispose helpers are always present, to be able to detect
+    // copies of the block.
+    const FLAGS: ffi::block_flags = {
+        let mut flags = ffi::BLOCK_HAS_COPY_DISPOSE;
+        #[cfg(feature = "apple")]
+        {
+            flags |= ffi::BLOCK_IS_NOESCAPE;
+        }
+        if ConcreteBlock::<A, R, F>::HAS_SIGNATURE {
+            flags |= ffi::BLOCK_HAS_SIGNATURE;
+        }
+        flags
+    };
+
+    const HEADER: ffi::Block_descriptor_header = ffi::Block_descriptor_header {
+        reserved: 0,
+        size: mem::size_of::<Self>() as c_ulong,
+    };
+
+    const DESCRIPTOR: ffi::Block_descriptor = ffi::Block_descriptor {
+        header: Self::HEADER,
+        copy: Some(block_context_copy),
+        dispose: Some(block_context_dispose),
+    };
+
+    const DESCRIPTOR_WITH_SIGNATURE: ffi::Block_descriptor_with_signature =
+        ffi::Block_descriptor_with_signature {
+            header: Self::HEADER,
+            copy: Some(block_context_copy),
+            dispose: Some(block_context_dispose),
+            encoding: ConcreteBlock::<A, R, F>::SIGNATURE.as_ptr().cast(),
+        };
+
+    fn from_concrete(mut block: ConcreteBlock<A, R, F>) -> Self {
+        block.layout.flags = Self::FLAGS;
+        block.layout.descriptor = if ConcreteBlock::<A, R, F>::HAS_SIGNATURE {
+            <*const _>::cast(&Self::DESCRIPTOR_WITH_SIGNATURE)
+        } else {
+            <*const _>::cast(&Self::DESCRIPTOR)
+        };
+        Self { block }
+    }
+}
+
+impl<A, R, F> Deref for NoEscapeBlock<A, R, F> {
+    type Target = Block<A, R>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.block
+    }
+}
+
+unsafe extern "C" fn block_context_copy(_dst: *mut c_void, _src: *mut c_void) {
+    // The copy would borrow from the environment of the original block, and
+    // could outlive it. This is the only place where we can catch that, so
+    // always abort (unwinding into the runtime is not possible).
+    abort("attempted to copy a NoEscapeBlock");
+}
+
+unsafe extern "C" fn block_context_dispose(_block: *mut c_void) {
+    // The closure is owned, and dropped, by the original block.
+}
@@ -25,6 +25,12 @@ impl<A, R> RcBlock<A, R> {
     /// # Safety
     ///
     /// The given pointer must point to a valid `Block`.
+    ///
+    /// The block must not be a [`NoEscapeBlock`], since the copy could
+    /// outlive the environment that it borrows from. Copying such a block
+    /// aborts the process.
+    ///
+    /// [`NoEscapeBlock`]: crate::NoEscapeBlock
     pub unsafe fn copy(ptr: *mut Block<A, R>) -> Self {
         // SAFETY: The caller ensures the pointer is valid.
         let ptr: *mut Block<A, R> = unsafe { ffi::_Block_copy(ptr.cast()) }.cast();
@@ -0,0 +1,286 @@
+use alloc::string::String;
+use core::fmt;
+use core::hash::Hash;
+use std::error::Error;
+use std::ffi::CStr;
+
+use objc2::encode::{Encoding, EncodingBox, MethodEncoding, ParseError};
+
+use crate::ffi;
+
+#[derive(Debug, PartialEq, Eq, Hash)]
+pub(crate) enum Inner {
+    MissingSignature,
+    InvalidSignature,
+    EncodingParseError(ParseError),
+    MissingBlockArgument,
+    InvalidBlockArgument(EncodingBox),
+    MismatchedReturn(EncodingBox, Encoding),
+    MismatchedArgumentsCount(usize, usize),
+    MismatchedArgument(usize, EncodingBox, Encoding),
+}
+
+impl fmt::Display for Inner {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MissingSignature => write!(f, "block has no signature"),
+            Self::InvalidSignature => write!(f, "block signature is not valid UTF-8"),
+            Self::EncodingParseError(e) => write!(f, "{e}"),
+            Self::MissingBlockArgument => {
+                write!(f, "expected first argument to be the block itself")
+            }
+            Self::InvalidBlockArgument(found) => {
+                write!(
+                    f,
+                    "expected first argument to be the block itself, but found '{found}'",
+                )
+            }
+            Self::MismatchedReturn(expected, actual) => {
+                write!(
+                    f,
+                    "expected return to have type code '{expected}', but found '{actual}'",
+                )
+            }
+            Self::MismatchedArgumentsCount(expected, actual) => {
+                write!(f, "expected {expected} arguments, but {actual} were given",)
+            }
+            Self::MismatchedArgument(i, expected, actual) => {
+                write!(
+                    f,
+                    "expected argument at index {i} to have type code '{expected}', but found '{actual}'",
+                )
+            }
+        }
+    }
+}
+
+/// Failed verifying the signature of a block.
+///
+/// This is returned in the error case of [`Block::verify`] and
+/// [`Block::try_call`], see those for details.
+///
+/// This implements [`Error`], and a description of the error can be retrieved
+/// using [`fmt::Display`].
+///
+/// [`Block::verify`]: crate::Block::verify
+/// [`Block::try_call`]: crate::Block::try_call
+#[derive(Debug, PartialEq, Eq, Hash)]
+pub struct VerificationError(pub(crate) Inner);
+
+impl From<ParseError> for VerificationError {
+    fn from(e: ParseError) -> Self {
+        Self(Inner::EncodingParseError(e))
+    }
+}
+
+impl From<Inner> for VerificationError {
+    fn from(inner: Inner) -> Self {
+        Self(inner)
+    }
+}
+
+impl fmt::Display for VerificationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Delegate to inner
+        fmt::Display::fmt(&self.0, f)
+    }
+}
+
+impl Error for VerificationError {}
+
+/// The signature stored in the block's descriptor, if any.
+///
+///
+/// # Safety
+///
+/// The layout must be of a valid block.
+pub(crate) unsafe fn block_signature(layout: &ffi::Block_layout) -> Option<&CStr> {
+    if layout.flags & ffi::BLOCK_HAS_SIGNATURE == 0 {
+        return None;
+    }
+
+    #[cfg(feature = "apple")]
+    if layout.flags & ffi::BLOCK_SMALL_DESCRIPTOR != 0 {
+        // The descriptor stores 32-bit offsets relative to each field,
+        // after a 32-bit size.
+        let field = unsafe { layout.descriptor.cast::<i32>().add(1) };
+        let offset = unsafe { *field };
+        if offset == 0 {
+            return None;
+        }
+        let encoding = unsafe { field.cast::<u8>().offset(offset as isize) };
+        return Some(unsafe { CStr::from_ptr(encoding.cast()) });
+    }
+
+    let encoding = if layout.flags & ffi::BLOCK_HAS_COPY_DISPOSE != 0 {
+        let descriptor = layout
+            .descriptor
+            .cast::<ffi::Block_descriptor_with_signature>();
+        unsafe { (*descriptor).encoding }
+    } else {
+        let descriptor = layout.descriptor.cast::<ffi::Block_descriptor_basic>();
+        unsafe { (*descriptor).encoding }
+    };
+
+    if encoding.is_null() {
+        None
+    } else {
+        Some(unsafe { CStr::from_ptr(encoding) })
+    }
+}
+
+/// Remove the class names from objects (`@"NSString"`) and the signatures
+/// from blocks (`@?<v@?@>`) that Clang emits in block signatures, since
+/// those cannot be parsed as an [`EncodingBox`].
+fn strip_extended_encodings(signature: &str) -> String {
+    let mut res = String::with_capacity(signature.len());
+    let mut chars = signature.chars().peekable();
+    while let Some(c) = chars.next() {
+        res.push(c);
+        if c != '@' {
+            continue;
+        }
+        match chars.peek() {
+            Some('"') => {
+                chars.next();
+                for c in chars.by_ref() {
+                    if c == '"' {
+                        break;
+                    }
+                }
+            }
+            Some('?') => {
+                res.push('?');
+                chars.next();
+                if chars.peek() == Some(&'<') {
+                    let mut depth = 0;
+                    for c in chars.by_ref() {
+                        match c {
+                            '<' => depth += 1,
+                            '>' => depth -= 1,
+                            _ => {}
+                        }
+                        if depth == 0 {
+                            break;
+                        }
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    res
+}
+
+pub(crate) fn verify_block_signature(
+    signature: &str,
+    args: &[Encoding],
+    ret: &Encoding,
+) -> Result<(), VerificationError> {
+    let signature: MethodEncoding = strip_extended_encodings(signature).parse()?;
+
+    // Only the types are compared, see `Block::verify`.
+    let expected = &signature.return_type.encoding;
+    if !ret.equivalent_to_box(expected) {
+        return Err(Inner::MismatchedReturn(expected.clone(), ret.clone()).into());
+    }
+
+    let mut iter = signature.arguments.into_iter();
+    match iter.next() {
+        Some(block) if Encoding::Block.equivalent_to_box(&block.encoding) => {}
+        Some(block) => return Err(Inner::InvalidBlockArgument(block.encoding).into()),
+        None => return Err(Inner::MissingBlockArgument.into()),
+    }
+
+    let expected_count = iter.len();
+    if expected_count != args.len() {
+        return Err(Inner::MismatchedArgumentsCount(expected_count, args.len()).into());
+    }
+
+    for (i, (actual, expected)) in args.iter().zip(iter).enumerate() {
+        if !actual.equivalent_to_box(&expected.encoding) {
+            return Err(Inner::MismatchedArgument(i, expected.encoding, actual.clone()).into());
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::string::ToString;
+
+    use super::*;
+
+    #[test]
+    fn test_strip_extended_encodings() {
+        assert_eq!(strip_extended_encodings("v@?"), "v@?");
+        assert_eq!(
+            strip_extended_encodings(r#"v24@?0@"NSString"8@16"#),
+            "v24@?0@8@16"
+        );
+        assert_eq!(
+            strip_extended_encodings(r#"v16@?0@?<v@?@"NSError"@?<v@?>>8"#),
+            "v16@?0@?8"
+        );
+        assert_eq!(strip_extended_encodings("@@?^{S=@?}"), "@@?^{S=@?}");
+    }
+
+    #[test]
+    fn test_verify_block_signature() {
+        verify_block_signature("v@?", &[], &Encoding::Void).unwrap();
+        verify_block_signature(
+            "i16@?0i8f12",
+            &[Encoding::Int, Encoding::Float],
+            &Encoding::Int,
+        )
+        .unwrap();
+        verify_block_signature(
+            r#"v24@?0@"NSString"8@?<v@?>16"#,
+            &[Encoding::Object, Encoding::Block],
+            &Encoding::Void,
+        )
+        .unwrap();
+        verify_block_signature(
+            "rv@?r^i",
+            &[Encoding::Pointer(&Encoding::Int)],
+            &Encoding::Void,
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn test_verify_block_signature_errors() {
+        let err = verify_block_signature("i@?", &[], &Encoding::Void).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "expected return to have type code 'i', but found 'v'"
+        );
+
+        let err = verify_block_signature("v@?i", &[], &Encoding::Void).unwrap_err();
+        assert_eq!(err.to_string(), "expected 1 arguments, but 0 were given");
+
+        let err = verify_block_signature("v@?", &[Encoding::Int], &Encoding::Void).unwrap_err();
+        assert_eq!(err.to_string(), "expected 0 arguments, but 1 were given");
+
+        let err = verify_block_signature("v@?i", &[Encoding::UInt], &Encoding::Void).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "expected argument at index 0 to have type code 'i', but found 'I'"
+        );
+
+        let err = verify_block_signature("v@:", &[], &Encoding::Void).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "expected first argument to be the block itself, but found '@'"
+        );
+
+        let err = verify_block_signature("v", &[], &Encoding::Void).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "expected first argument to be the block itself"
+        );
+
+        assert!(verify_block_signature("v@?{", &[], &Encoding::Void).is_err());
+    }
+}
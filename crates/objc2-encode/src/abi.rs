@@ -0,0 +1,628 @@
+//! Checking whether two encodings are passed the same way in function calls.
+use core::fmt;
+
+use crate::helper::{
+    ContainerKind, EncodingType, Helper, IndirectionKind, NestingLevel, Primitive,
+};
+use crate::layout::{layout, struct_fields, union_member_layout, Field, Layout};
+use crate::DataModel;
+
+/// A calling convention, which determines how arguments and return values
+/// are passed in function calls.
+///
+/// This is used when checking whether two encodings are ABI-compatible, see
+/// [`Encoding::abi_compatible_with`].
+///
+/// [`Encoding::abi_compatible_with`]: crate::Encoding::abi_compatible_with
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum CallingConvention {
+    /// The System V AMD64 ABI, used on 64-bit x86 targets except Windows.
+    ///
+    /// Aggregates of up to 16 bytes are split into eightbytes, which are
+    /// each passed in either an integer or an SSE register.
+    SysV64,
+    /// The Microsoft x64 calling convention, used on 64-bit x86 Windows.
+    ///
+    /// Aggregates of 1, 2, 4 or 8 bytes are passed in integer registers,
+    /// everything else is passed by reference.
+    Win64,
+    /// The AArch64 procedure call standard, used on 64-bit ARM targets
+    /// except Windows (including Apple's variant of it).
+    ///
+    /// Homogeneous floating-point aggregates are passed in floating-point
+    /// registers, other aggregates of up to 16 bytes are passed in integer
+    /// registers.
+    Aarch64,
+    /// Any other calling convention, using the given data model.
+    ///
+    /// Scalars are assumed to be passed in either integer or floating-point
+    /// registers, and aggregates in memory.
+    Other(DataModel),
+}
+
+impl CallingConvention {
+    /// The calling convention of the current target.
+    pub const CURRENT: Self = {
+        if cfg!(all(target_arch = "x86_64", not(windows))) {
+            Self::SysV64
+        } else if cfg!(all(target_arch = "x86_64", windows)) {
+            Self::Win64
+        } else if cfg!(all(target_arch = "aarch64", not(windows))) {
+            Self::Aarch64
+        } else {
+            Self::Other(DataModel::CURRENT)
+        }
+    };
+
+    /// The data model used by the calling convention.
+    pub const fn data_model(self) -> DataModel {
+        match self {
+            Self::SysV64 | Self::Aarch64 => DataModel::Lp64,
+            Self::Win64 => DataModel::Llp64,
+            Self::Other(model) => model,
+        }
+    }
+}
+
+/// The kind of register that (part of) a value is passed in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum RegisterClass {
+    /// A general-purpose register.
+    Integer,
+    /// A floating-point or vector register, such as the SSE registers on
+    /// x86_64.
+    Float,
+}
+
+impl fmt::Display for RegisterClass {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Integer => write!(f, "integer"),
+            Self::Float => write!(f, "floating-point"),
+        }
+    }
+}
+
+/// How a value is passed as an argument or return value under a given
+/// [`CallingConvention`].
+///
+/// See [`Encoding::abi_class`].
+///
+/// [`Encoding::abi_class`]: crate::Encoding::abi_class
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum AbiClass {
+    /// The value has no size, and is not passed at all.
+    Ignore,
+    /// The value is passed in one or two registers, one for each eightbyte
+    /// of the value.
+    Registers(RegisterClass, Option<RegisterClass>),
+    /// The value is a homogeneous aggregate (or a single floating-point
+    /// value), passed in the given number of floating-point registers.
+    ///
+    /// This is only used with [`CallingConvention::Aarch64`].
+    Homogeneous(u8),
+    /// The value is passed in memory, either on the stack or by reference.
+    Memory,
+}
+
+impl fmt::Display for AbiClass {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Ignore => write!(f, "not passed"),
+            Self::Registers(lo, None) => write!(f, "passed in one {lo} register"),
+            Self::Registers(lo, Some(hi)) if lo == hi => {
+                write!(f, "passed in two {lo} registers")
+            }
+            Self::Registers(lo, Some(hi)) => {
+                write!(f, "passed in one {lo} and one {hi} register")
+            }
+            Self::Homogeneous(1) => write!(f, "passed in one floating-point register"),
+            Self::Homogeneous(n) => {
+                write!(
+                    f,
+                    "passed in {n} floating-point registers as a homogeneous aggregate"
+                )
+            }
+            Self::Memory => write!(f, "passed in memory"),
+        }
+    }
+}
+
+/// The reason why two encodings are not ABI-compatible.
+///
+/// This is returned by [`Encoding::abi_compatible_with`], and the
+/// [`Display`][`fmt::Display`] implementation describes the difference.
+///
+/// [`Encoding::abi_compatible_with`]: crate::Encoding::abi_compatible_with
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum AbiMismatch {
+    /// The size or alignment of one of the types could not be determined,
+    /// see [`Encoding::size`] for when that happens.
+    ///
+    /// [`Encoding::size`]: crate::Encoding::size
+    UnknownLayout,
+    /// The types have different sizes.
+    Size(usize, usize),
+    /// The types have different alignments.
+    Align(usize, usize),
+    /// The types are passed differently.
+    Class(AbiClass, AbiClass),
+}
+
+impl fmt::Display for AbiMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnknownLayout => write!(f, "could not determine the layout of the types"),
+            Self::Size(a, b) => write!(f, "sizes differ ({a} and {b} bytes)"),
+            Self::Align(a, b) => write!(f, "alignments differ ({a} and {b} bytes)"),
+            Self::Class(a, b) => write!(f, "one is {a}, the other is {b}"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for AbiMismatch {}
+
+/// The layout of a type when passed to or returned from a function.
+///
+/// Same as [`layout`], except that `void` is allowed here.
+fn abi_layout<E: EncodingType>(encoding: &E, model: DataModel) -> Option<Layout> {
+    match encoding.helper(NestingLevel::new()) {
+        Helper::Primitive(Primitive::Void) => Some(Layout { size: 0, align: 1 }),
+        _ => layout(encoding, model),
+    }
+}
+
+/// A scalar part of a value.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Scalar {
+    Integer,
+    /// A floating-point value or vector of the given size.
+    Float(usize),
+}
+
+/// Visit each scalar part of a value, along with its offset and size.
+fn visit_scalars<E: EncodingType>(
+    encoding: &E,
+    offset: usize,
+    model: DataModel,
+    visit: &mut impl FnMut(usize, usize, Scalar) -> Option<()>,
+) -> Option<()> {
+    match encoding.helper(NestingLevel::new()) {
+        Helper::Primitive(primitive) => {
+            let size = abi_layout(encoding, model)?.size;
+            match primitive {
+                Primitive::Void => Some(()),
+                Primitive::Float | Primitive::Double => visit(offset, size, Scalar::Float(size)),
+                Primitive::FloatComplex | Primitive::DoubleComplex => {
+                    // Complex numbers are laid out as two floats
+                    let half = size / 2;
+                    visit(offset, half, Scalar::Float(half))?;
+                    visit(offset + half, half, Scalar::Float(half))
+                }
+                _ => visit(offset, size, Scalar::Integer),
+            }
+        }
+        Helper::BitField(_, _, _) => None,
+        Helper::Indirection(IndirectionKind::Pointer, _, _) => {
+            visit(offset, layout(encoding, model)?.size, Scalar::Integer)
+        }
+        Helper::Indirection(IndirectionKind::Atomic, t, _) => {
+            visit_scalars(t, offset, model, visit)
+        }
+        Helper::Array(len, item, _) => {
+            let size = layout(item, model)?.size;
+            if size == 0 {
+                return Some(());
+            }
+            for i in 0..usize::try_from(len).ok()? {
+                visit_scalars(item, offset + i * size, model, visit)?;
+            }
+            Some(())
+        }
+        Helper::Vector(_, _, _, _) => {
+            let size = layout(encoding, model)?.size;
+            visit(offset, size, Scalar::Float(size))
+        }
+        Helper::Qualified(_, t, _) => visit_scalars(t, offset, model, visit),
+        Helper::Container(ContainerKind::Struct, _, items, _) => {
+            struct_fields(items?, model, |field| match field {
+                Field::Typed(field, field_offset) => {
+                    visit_scalars(field, offset + field_offset, model, visit)
+                }
+                Field::Bits(start, end) => visit(offset + start, end - start, Scalar::Integer),
+            })?;
+            Some(())
+        }
+        Helper::Container(ContainerKind::Union, _, items, _) => {
+            for member in items? {
+                if let Helper::BitField(_, _, _) = member.helper(NestingLevel::new()) {
+                    let size = union_member_layout(member, model)?.size;
+                    visit(offset, size, Scalar::Integer)?;
+                } else {
+                    visit_scalars(member, offset, model, visit)?;
+                }
+            }
+            Some(())
+        }
+    }
+}
+
+/// Whether the type is a scalar, as opposed to an aggregate.
+fn is_scalar<E: EncodingType>(encoding: &E) -> bool {
+    match encoding.helper(NestingLevel::new()) {
+        Helper::Primitive(Primitive::FloatComplex | Primitive::DoubleComplex) => false,
+        Helper::Primitive(_) | Helper::Indirection(IndirectionKind::Pointer, _, _) => true,
+        Helper::Indirection(IndirectionKind::Atomic, t, _) | Helper::Qualified(_, t, _) => {
+            is_scalar(t)
+        }
+        _ => false,
+    }
+}
+
+fn sysv64_class<E: EncodingType>(encoding: &E, layout: Layout) -> Option<AbiClass> {
+    if layout.size > 16 {
+        return Some(AbiClass::Memory);
+    }
+    let mut eightbytes: [Option<RegisterClass>; 2] = [None, None];
+    let mut memory = false;
+    visit_scalars(encoding, 0, DataModel::Lp64, &mut |offset, size, scalar| {
+        let class = match scalar {
+            // Vectors larger than an SSE register are passed in memory
+            Scalar::Float(size) if size > 16 => {
+                memory = true;
+                return Some(());
+            }
+            Scalar::Float(_) => RegisterClass::Float,
+            Scalar::Integer => RegisterClass::Integer,
+        };
+        for eightbyte in &mut eightbytes[offset / 8..(offset + size + 7) / 8] {
+            *eightbyte = match (*eightbyte, class) {
+                (Some(RegisterClass::Integer), _) => Some(RegisterClass::Integer),
+                (_, class) => Some(class),
+            };
+        }
+        Some(())
+    })?;
+
+    if memory {
+        return Some(AbiClass::Memory);
+    }
+    Some(match eightbytes {
+        [None, None] => AbiClass::Ignore,
+        // Padding-only eightbytes are not passed in any specific register
+        [lo, hi] => AbiClass::Registers(lo.unwrap_or(RegisterClass::Integer), hi),
+    })
+}
+
+fn aarch64_class<E: EncodingType>(encoding: &E, layout: Layout) -> Option<AbiClass> {
+    if layout.size == 0 {
+        return Some(AbiClass::Ignore);
+    }
+    // Homogeneous aggregates consist of at most four 16-byte vectors
+    if layout.size > 64 {
+        return Some(AbiClass::Memory);
+    }
+
+    // Check for homogeneous floating-point (or vector) aggregates.
+    let mut base: Option<usize> = None;
+    let mut homogeneous = true;
+    visit_scalars(
+        encoding,
+        0,
+        DataModel::Lp64,
+        &mut |_offset, _size, scalar| {
+            match (scalar, base) {
+                (Scalar::Float(size), None) => base = Some(size),
+                (Scalar::Float(size), Some(base)) if size == base => {}
+                _ => homogeneous = false,
+            }
+            Some(())
+        },
+    )?;
+    if let (true, Some(base)) = (homogeneous, base) {
+        let count = layout.size / base;
+        if layout.size % base == 0 && count <= 4 {
+            return Some(AbiClass::Homogeneous(count as u8));
+        }
+    }
+
+    if layout.size > 16 {
+        Some(AbiClass::Memory)
+    } else if layout.size > 8 {
+        Some(AbiClass::Registers(
+            RegisterClass::Integer,
+            Some(RegisterClass::Integer),
+        ))
+    } else {
+        Some(AbiClass::Registers(RegisterClass::Integer, None))
+    }
+}
+
+fn scalar_class<E: EncodingType>(
+    encoding: &E,
+    layout: Layout,
+    model: DataModel,
+) -> Option<AbiClass> {
+    let mut class = RegisterClass::Integer;
+    visit_scalars(encoding, 0, model, &mut |_offset, _size, scalar| {
+        if let Scalar::Float(_) = scalar {
+            class = RegisterClass::Float;
+        }
+        Some(())
+    })?;
+    if layout.size > 8 {
+        Some(AbiClass::Registers(class, Some(class)))
+    } else {
+        Some(AbiClass::Registers(class, None))
+    }
+}
+
+/// Classify how a value with the given encoding is passed.
+pub(crate) fn abi_class<E: EncodingType>(encoding: &E, cc: CallingConvention) -> Option<AbiClass> {
+    let model = cc.data_model();
+    let layout = abi_layout(encoding, model)?;
+    match cc {
+        CallingConvention::SysV64 => sysv64_class(encoding, layout),
+        CallingConvention::Aarch64 => aarch64_class(encoding, layout),
+        CallingConvention::Win64 | CallingConvention::Other(_) if layout.size == 0 => {
+            Some(AbiClass::Ignore)
+        }
+        CallingConvention::Win64 => {
+            if is_scalar(encoding) && layout.size <= 8 {
+                scalar_class(encoding, layout, model)
+            } else if matches!(layout.size, 1 | 2 | 4 | 8) {
+                Some(AbiClass::Registers(RegisterClass::Integer, None))
+            } else {
+                Some(AbiClass::Memory)
+            }
+        }
+        CallingConvention::Other(_) => {
+            if is_scalar(encoding) {
+                scalar_class(encoding, layout, model)
+            } else {
+                Some(AbiClass::Memory)
+            }
+        }
+    }
+}
+
+/// Check whether two encodings are passed the same way.
+pub(crate) fn abi_compatible<E1: EncodingType, E2: EncodingType>(
+    enc1: &E1,
+    enc2: &E2,
+    cc: CallingConvention,
+) -> Result<(), AbiMismatch> {
+    let model = cc.data_model();
+    let (layout1, layout2) = match (abi_layout(enc1, model), abi_layout(enc2, model)) {
+        (Some(layout1), Some(layout2)) => (layout1, layout2),
+        _ => return Err(AbiMismatch::UnknownLayout),
+    };
+    if layout1.size != layout2.size {
+        return Err(AbiMismatch::Size(layout1.size, layout2.size));
+    }
+    if layout1.align != layout2.align {
+        return Err(AbiMismatch::Align(layout1.align, layout2.align));
+    }
+    let (class1, class2) = match (abi_class(enc1, cc), abi_class(enc2, cc)) {
+        (Some(class1), Some(class2)) => (class1, class2),
+        _ => return Err(AbiMismatch::UnknownLayout),
+    };
+    if class1 != class2 {
+        return Err(AbiMismatch::Class(class1, class2));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Encoding, EncodingBox};
+    use alloc::string::ToString;
+    use AbiClass::*;
+    use RegisterClass::*;
+
+    const ALL: [CallingConvention; 4] = [
+        CallingConvention::SysV64,
+        CallingConvention::Win64,
+        CallingConvention::Aarch64,
+        CallingConvention::Other(DataModel::Ilp32),
+    ];
+
+    const POINT: Encoding = Encoding::Struct("CGPoint", &[Encoding::Double, Encoding::Double]);
+    const NSPOINT: Encoding = Encoding::Struct("NSPoint", &[Encoding::Double, Encoding::Double]);
+    const MIXED: Encoding = Encoding::Struct("Mixed", &[Encoding::Int, Encoding::Float]);
+    const SWAPPED: Encoding = Encoding::Struct("Swapped", &[Encoding::Float, Encoding::Int]);
+    const FLOATS: Encoding = Encoding::Struct("Floats", &[Encoding::Float, Encoding::Float]);
+    const INTS: Encoding = Encoding::Struct("Ints", &[Encoding::Int, Encoding::Int]);
+
+    #[track_caller]
+    fn assert_class(enc: &Encoding, cc: CallingConvention, expected: AbiClass) {
+        assert_eq!(abi_class(enc, cc), Some(expected), "{enc} {cc:?}");
+        let boxed: EncodingBox = enc.to_string().parse().unwrap();
+        assert_eq!(abi_class(&boxed, cc), Some(expected), "{enc} {cc:?} boxed");
+    }
+
+    #[track_caller]
+    fn assert_compatible(enc1: &Encoding, enc2: &Encoding, ccs: &[CallingConvention]) {
+        for &cc in ccs {
+            assert_eq!(
+                abi_compatible(enc1, enc2, cc),
+                Ok(()),
+                "{enc1} {enc2} {cc:?}"
+            );
+            assert_eq!(
+                abi_compatible(enc2, enc1, cc),
+                Ok(()),
+                "{enc2} {enc1} {cc:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn scalars() {
+        for cc in ALL {
+            assert_class(&Encoding::Void, cc, Ignore);
+            assert_class(&Encoding::Int, cc, Registers(Integer, None));
+            assert_class(&Encoding::Object, cc, Registers(Integer, None));
+        }
+        assert_class(
+            &Encoding::Double,
+            CallingConvention::SysV64,
+            Registers(Float, None),
+        );
+        assert_class(
+            &Encoding::Double,
+            CallingConvention::Win64,
+            Registers(Float, None),
+        );
+        assert_class(
+            &Encoding::Double,
+            CallingConvention::Aarch64,
+            Homogeneous(1),
+        );
+        assert_class(
+            &Encoding::Int128,
+            CallingConvention::SysV64,
+            Registers(Integer, Some(Integer)),
+        );
+        assert_class(&Encoding::Int128, CallingConvention::Win64, Memory);
+        assert_eq!(
+            abi_class(&Encoding::LongDouble, CallingConvention::SysV64),
+            None
+        );
+    }
+
+    #[test]
+    fn aggregates() {
+        let sysv = CallingConvention::SysV64;
+        assert_class(&POINT, sysv, Registers(Float, Some(Float)));
+        assert_class(&MIXED, sysv, Registers(Integer, None));
+        assert_class(
+            &Encoding::Struct("A", &[Encoding::Int, Encoding::Double]),
+            sysv,
+            Registers(Integer, Some(Float)),
+        );
+        assert_class(
+            &Encoding::Struct("A", &[POINT, Encoding::Int]),
+            sysv,
+            Memory,
+        );
+        assert_class(
+            &Encoding::DoubleComplex,
+            sysv,
+            Registers(Float, Some(Float)),
+        );
+
+        let win = CallingConvention::Win64;
+        assert_class(&FLOATS, win, Registers(Integer, None));
+        assert_class(&POINT, win, Memory);
+
+        let arm = CallingConvention::Aarch64;
+        assert_class(&POINT, arm, Homogeneous(2));
+        assert_class(&FLOATS, arm, Homogeneous(2));
+        assert_class(&Encoding::Array(4, &Encoding::Float), arm, Homogeneous(4));
+        assert_class(&Encoding::Array(5, &Encoding::Float), arm, Memory);
+        assert_class(&MIXED, arm, Registers(Integer, None));
+        assert_class(
+            &Encoding::Array(3, &Encoding::Int),
+            arm,
+            Registers(Integer, Some(Integer)),
+        );
+
+        let other = CallingConvention::Other(DataModel::Ilp32);
+        assert_class(&INTS, other, Memory);
+    }
+
+    #[test]
+    fn bitfields() {
+        let enc = Encoding::Struct("A", &[Encoding::Float, Encoding::BitField(3, None)]);
+        assert_class(&enc, CallingConvention::SysV64, Registers(Integer, None));
+        assert_class(&enc, CallingConvention::Aarch64, Registers(Integer, None));
+    }
+
+    #[test]
+    fn compatible() {
+        assert_compatible(&Encoding::Int, &Encoding::UInt, &ALL);
+        assert_compatible(&POINT, &NSPOINT, &ALL);
+        assert_compatible(
+            &Encoding::DoubleComplex,
+            &POINT,
+            &[CallingConvention::SysV64, CallingConvention::Aarch64],
+        );
+        assert_compatible(
+            &Encoding::Pointer(&Encoding::Void),
+            &Encoding::Pointer(&POINT),
+            &ALL,
+        );
+        assert_compatible(&Encoding::Object, &Encoding::Class, &ALL);
+        assert_compatible(&FLOATS, &Encoding::Array(2, &Encoding::Float), &ALL);
+        assert_compatible(&Encoding::Void, &Encoding::Struct("Empty", &[]), &ALL);
+        assert_compatible(&MIXED, &SWAPPED, &ALL);
+        assert_compatible(&FLOATS, &INTS, &[CallingConvention::Win64]);
+    }
+
+    #[test]
+    fn incompatible() {
+        let sysv = CallingConvention::SysV64;
+        assert_eq!(
+            abi_compatible(&Encoding::Int, &Encoding::LongLong, sysv),
+            Err(AbiMismatch::Size(4, 8)),
+        );
+        assert_eq!(
+            abi_compatible(&Encoding::Double, &FLOATS, sysv),
+            Err(AbiMismatch::Align(8, 4)),
+        );
+        assert_eq!(
+            abi_compatible(&Encoding::Float, &Encoding::Int, sysv),
+            Err(AbiMismatch::Class(
+                Registers(Float, None),
+                Registers(Integer, None)
+            )),
+        );
+        assert_eq!(
+            abi_compatible(&FLOATS, &INTS, sysv),
+            Err(AbiMismatch::Class(
+                Registers(Float, None),
+                Registers(Integer, None)
+            )),
+        );
+        assert_eq!(
+            abi_compatible(&FLOATS, &INTS, CallingConvention::Aarch64),
+            Err(AbiMismatch::Class(Homogeneous(2), Registers(Integer, None))),
+        );
+        let a = Encoding::Struct("A", &[Encoding::Int, Encoding::Int, Encoding::Double]);
+        let b = Encoding::Struct("B", &[Encoding::Double, Encoding::Int, Encoding::Int]);
+        assert_eq!(
+            abi_compatible(&a, &b, sysv),
+            Err(AbiMismatch::Class(
+                Registers(Integer, Some(Float)),
+                Registers(Float, Some(Integer))
+            )),
+        );
+        assert_eq!(
+            abi_compatible(&Encoding::LongDouble, &Encoding::LongDouble, sysv),
+            Err(AbiMismatch::UnknownLayout),
+        );
+        assert_eq!(
+            abi_compatible(&Encoding::Void, &Encoding::Int, sysv),
+            Err(AbiMismatch::Size(0, 4)),
+        );
+    }
+
+    #[test]
+    fn display() {
+        assert_eq!(
+            AbiMismatch::Class(Registers(Float, None), Registers(Integer, Some(Integer))).to_string(),
+            "one is passed in one floating-point register, the other is passed in two integer registers",
+        );
+        assert_eq!(
+            AbiMismatch::Class(Registers(Integer, Some(Float)), Homogeneous(3)).to_string(),
+            "one is passed in one integer and one floating-point register, the other is passed in 3 floating-point registers as a homogeneous aggregate",
+        );
+    }
+}
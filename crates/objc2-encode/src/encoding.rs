@@ -1,8 +1,11 @@
 use core::fmt;
 
+use crate::abi::{abi_class, abi_compatible};
 use crate::helper::{compare_encodings, Helper, NestingLevel};
+use crate::layout::layout;
 use crate::parse::Parser;
-use crate::EncodingBox;
+use crate::static_str::{static_encoding_str_array, static_encoding_str_len};
+use crate::{AbiClass, AbiMismatch, CallingConvention, DataModel, EncodingBox, Qualifier};
 
 /// An Objective-C type-encoding.
 ///
@@ -53,6 +56,8 @@ pub enum Encoding {
     Long,
     /// A C `long long`. Corresponds to the `"q"` code.
     LongLong,
+    /// A 128-bit signed integer (`__int128`). Corresponds to the `"t"` code.
+    Int128,
     /// A C `unsigned char`. Corresponds to the `"C"` code.
     UChar,
     /// A C `unsigned short`. Corresponds to the `"S"` code.
@@ -65,6 +70,9 @@ pub enum Encoding {
     ULong,
     /// A C `unsigned long long`. Corresponds to the `"Q"` code.
     ULongLong,
+    /// A 128-bit unsigned integer (`unsigned __int128`). Corresponds to the
+    /// `"T"` code.
+    UInt128,
     /// A C `float`. Corresponds to the `"f"` code.
     Float,
     /// A C `double`. Corresponds to the `"d"` code.
@@ -136,10 +144,22 @@ pub enum Encoding {
     ///
     /// Corresponds to the `"(" name "=" fields... ")"` code.
     Union(&'static str, &'static [Encoding]),
-    // TODO: "Vector" types have the '!' encoding, but are not implemented in
-    // clang
-
-    // TODO: `t` and `T` codes for i128 and u128?
+    /// A SIMD vector type with the given size and alignment in bytes, and
+    /// the given element type.
+    ///
+    /// Corresponds to the `"!" "[" size "," alignment type "]"` code. This is
+    /// emitted by GCC for types declared with `__attribute__((vector_size))`,
+    /// `clang` does not emit it.
+    Vector(u64, u64, &'static Encoding),
+    /// A type with the given qualifier, such as `const`.
+    ///
+    /// Corresponds to the qualifier's code followed by the type, see
+    /// [`Qualifier`] for the possible codes.
+    ///
+    /// Qualifiers are ignored when checking for equivalence, so e.g.
+    /// `Qualified(Qualifier::Const, &Encoding::Int)` is equivalent to
+    /// `Encoding::Int`.
+    Qualified(Qualifier, &'static Encoding),
 }
 
 impl Encoding {
@@ -185,7 +205,7 @@ impl Encoding {
     ///
     /// Currently, equivalence testing mostly requires that the encodings are
     /// equal, except for:
-    /// - Any leading qualifiers that the encoding may have.
+    /// - Any qualifiers that the encoding may have.
     /// - Structs or unions behind multiple pointers are considered
     ///   equivalent, since Objective-C compilers strip this information to
     ///   avoid unnecessary nesting.
@@ -207,8 +227,6 @@ impl Encoding {
     pub fn equivalent_to_str(&self, s: &str) -> bool {
         let mut parser = Parser::new(s);
 
-        parser.strip_leading_qualifiers();
-
         if let Some(()) = parser.expect_encoding(self, NestingLevel::new()) {
             // if the given encoding can be successfully removed from the
             // start and an empty string remains, they were fully equivalent!
@@ -225,6 +243,152 @@ impl Encoding {
     pub fn equivalent_to_box(&self, other: &EncodingBox) -> bool {
         compare_encodings(self, NestingLevel::new(), other, NestingLevel::new(), false)
     }
+
+    /// The size in bytes of the type described by the encoding, when
+    /// compiled for a target with the given data model.
+    ///
+    /// Returns `None` if the type has no size (such as `void` or functions),
+    /// if its size is not determined by the data model (such as `long
+    /// double`), or if the encoding does not contain enough information
+    /// (such as structs without their fields, or bitfields outside a struct).
+    ///
+    /// Primitive types are naturally aligned, except for `double` and `long
+    /// long` (and their complex counterparts) in [`DataModel::I386`], which
+    /// are only 4-byte aligned, like they are inside structs on `i686`.
+    ///
+    /// Apple's runtimes do not encode the type of bitfields, in that case
+    /// the type is assumed to be `unsigned int`.
+    ///
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use objc2_encode::{DataModel, Encoding};
+    ///
+    /// let range = Encoding::Struct("_NSRange", &[Encoding::ULong, Encoding::ULong]);
+    /// assert_eq!(range.size(DataModel::Ilp32), Some(8));
+    /// assert_eq!(range.size(DataModel::Lp64), Some(16));
+    ///
+    /// assert_eq!(Encoding::Void.size(DataModel::CURRENT), None);
+    /// ```
+    pub fn size(&self, model: DataModel) -> Option<usize> {
+        layout(self, model).map(|layout| layout.size)
+    }
+
+    /// The alignment in bytes of the type described by the encoding, when
+    /// compiled for a target with the given data model.
+    ///
+    /// See [`Encoding::size`] for when this returns `None`.
+    pub fn align(&self, model: DataModel) -> Option<usize> {
+        layout(self, model).map(|layout| layout.align)
+    }
+
+    /// How a value with this encoding is passed as an argument or return
+    /// value under the given calling convention.
+    ///
+    /// Returns `None` in the same cases as [`Encoding::size`], except that
+    /// `void` is classified as [`AbiClass::Ignore`].
+    pub fn abi_class(&self, cc: CallingConvention) -> Option<AbiClass> {
+        abi_class(self, cc)
+    }
+
+    /// Check whether two encodings are passed identically in function calls
+    /// under the given calling convention.
+    ///
+    /// That is, whether they have the same size and alignment, and are
+    /// classified the same (e.g. an aggregate is placed in the same kind of
+    /// registers). Unlike [`Encoding::equivalent_to`], this does not care
+    /// about the names of structs, the signedness of integers, or what a
+    /// pointer points to.
+    ///
+    ///
+    /// # Errors
+    ///
+    /// Returns the reason the encodings are not ABI-compatible, which can be
+    /// displayed to the user.
+    ///
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use objc2_encode::{CallingConvention, Encoding};
+    ///
+    /// let cc = CallingConvention::SysV64;
+    ///
+    /// let point = Encoding::Struct("CGPoint", &[Encoding::Double, Encoding::Double]);
+    /// let ns_point = Encoding::Struct("NSPoint", &[Encoding::Double, Encoding::Double]);
+    /// assert!(point.abi_compatible_with(&ns_point, cc).is_ok());
+    ///
+    /// let ints = Encoding::Struct("Ints", &[Encoding::Int, Encoding::Int]);
+    /// let floats = Encoding::Struct("Floats", &[Encoding::Float, Encoding::Float]);
+    /// let err = ints.abi_compatible_with(&floats, cc).unwrap_err();
+    /// assert_eq!(
+    ///     err.to_string(),
+    ///     "one is passed in one integer register, the other is passed in one floating-point register",
+    /// );
+    /// ```
+    pub fn abi_compatible_with(
+        &self,
+        other: &Self,
+        cc: CallingConvention,
+    ) -> Result<(), AbiMismatch> {
+        abi_compatible(self, other, cc)
+    }
+
+    /// Check whether an encoding is ABI-compatible with a boxed encoding.
+    ///
+    /// See [`Encoding::abi_compatible_with`] for details.
+    ///
+    ///
+    /// # Errors
+    ///
+    /// Returns the reason the encodings are not ABI-compatible.
+    pub fn abi_compatible_with_box(
+        &self,
+        other: &EncodingBox,
+        cc: CallingConvention,
+    ) -> Result<(), AbiMismatch> {
+        abi_compatible(self, other, cc)
+    }
+
+    /// The length of the string representation of the encoding.
+    ///
+    /// This is the same as `self.to_string().len()`, but can be used in
+    /// `const` contexts, and is mostly useful together with
+    /// [`Encoding::str_array`].
+    pub const fn str_len(&self) -> usize {
+        static_encoding_str_len(self, NestingLevel::new())
+    }
+
+    /// The string representation of the encoding, as an array of bytes.
+    ///
+    /// This is the same as `self.to_string()`, but can be computed at
+    /// compile-time. Any bytes in the array after the encoding are zero, so
+    /// using a `LEN` of `self.str_len() + 1` gives a NUL-terminated string.
+    ///
+    ///
+    /// # Panics
+    ///
+    /// Panics (or fails to compile when used in a `const`) if `LEN` is less
+    /// than [`Encoding::str_len`].
+    ///
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::ffi::CStr;
+    /// use objc2_encode::Encoding;
+    ///
+    /// const ENC: Encoding = Encoding::Pointer(&Encoding::Struct("CGPoint", &[Encoding::Double, Encoding::Double]));
+    /// const DATA: [u8; ENC.str_len() + 1] = ENC.str_array();
+    /// // SAFETY: The data is NUL-terminated, and contains no interior NUL
+    /// const CSTR: &CStr = unsafe { CStr::from_bytes_with_nul_unchecked(&DATA) };
+    ///
+    /// assert_eq!(CSTR.to_str(), Ok("^{CGPoint=dd}"));
+    /// ```
+    pub const fn str_array<const LEN: usize>(&self) -> [u8; LEN] {
+        static_encoding_str_array(self, NestingLevel::new())
+    }
 }
 
 /// Formats this [`Encoding`] in a similar way that the `@encode` directive
@@ -242,8 +406,6 @@ impl fmt::Display for Encoding {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::helper::NestingLevel;
-    use crate::static_str::{static_encoding_str_array, static_encoding_str_len};
     use alloc::string::ToString;
     use alloc::vec;
     use core::str::FromStr;
@@ -330,7 +492,7 @@ mod tests {
                 )*
 
                 // Check static str
-                const STATIC_ENCODING_DATA: [u8; static_encoding_str_len(&E, NestingLevel::new())] = static_encoding_str_array(&E, NestingLevel::new());
+                const STATIC_ENCODING_DATA: [u8; E.str_len()] = E.str_array();
                 const STATIC_ENCODING_STR: &str = unsafe { core::str::from_utf8_unchecked(&STATIC_ENCODING_DATA) };
                 assert_eq!(STATIC_ENCODING_STR, $string, "static");
             }
@@ -556,6 +718,59 @@ mod tests {
             "{abc=^[8B](def=@?)^^b255c?}";
         }
 
+        fn int128() {
+            Encoding::Int128;
+            !Encoding::UInt128;
+            !Encoding::LongLong;
+            "t";
+        }
+
+        fn uint128() {
+            Encoding::UInt128;
+            !Encoding::Int128;
+            "T";
+        }
+
+        fn vector() {
+            Encoding::Vector(16, 16, &Encoding::Float);
+            !Encoding::Vector(16, 8, &Encoding::Float);
+            !Encoding::Vector(32, 16, &Encoding::Float);
+            !Encoding::Vector(16, 16, &Encoding::Int);
+            !Encoding::Array(4, &Encoding::Float);
+            "![16,16f]";
+            !"![16,16f";
+            !"![16f]";
+            !"[4f]";
+        }
+
+        fn vector_in_struct() {
+            Encoding::Struct("S", &[Encoding::Vector(8, 8, &Encoding::Short), Encoding::Char]);
+            "{S=![8,8s]c}";
+        }
+
+        fn qualified() {
+            Encoding::Qualified(Qualifier::Const, &Encoding::Char);
+            ~Encoding::Char;
+            ~Encoding::Qualified(Qualifier::Out, &Encoding::Char);
+            !Encoding::Qualified(Qualifier::Const, &Encoding::Int);
+            "rc";
+            ~"c";
+            ~"Nc";
+            ~"rVc";
+            !"ri";
+        }
+
+        fn qualified_pointer() {
+            Encoding::Pointer(&Encoding::Qualified(Qualifier::Const, &Encoding::String));
+            ~Encoding::Pointer(&Encoding::String);
+            ~Encoding::Qualified(Qualifier::Out, &Encoding::Pointer(&Encoding::String));
+            !Encoding::Pointer(&Encoding::Qualified(Qualifier::Const, &Encoding::Char));
+            "^r*";
+            ~"^*";
+            ~"r^r*";
+            !"^rc";
+        }
+
         fn identifier() {
             Encoding::Struct("_abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789", &[]);
             "{_abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789=}";
@@ -4,9 +4,11 @@ use alloc::vec::Vec;
 use core::fmt;
 use core::str::FromStr;
 
+use crate::abi::{abi_class, abi_compatible};
 use crate::helper::{compare_encodings, Helper, NestingLevel};
+use crate::layout::layout;
 use crate::parse::{ParseError, Parser};
-use crate::Encoding;
+use crate::{AbiClass, AbiMismatch, CallingConvention, DataModel, Encoding, Qualifier};
 
 /// The boxed version of [`Encoding`].
 ///
@@ -38,6 +40,8 @@ pub enum EncodingBox {
     Long,
     /// Same as [`Encoding::LongLong`].
     LongLong,
+    /// Same as [`Encoding::Int128`].
+    Int128,
     /// Same as [`Encoding::UChar`].
     UChar,
     /// Same as [`Encoding::UShort`].
@@ -48,6 +52,8 @@ pub enum EncodingBox {
     ULong,
     /// Same as [`Encoding::ULongLong`].
     ULongLong,
+    /// Same as [`Encoding::UInt128`].
+    UInt128,
     /// Same as [`Encoding::Float`].
     Float,
     /// Same as [`Encoding::Double`].
@@ -88,6 +94,10 @@ pub enum EncodingBox {
     Struct(String, Option<Vec<Self>>),
     /// Same as [`Encoding::Union`].
     Union(String, Option<Vec<Self>>),
+    /// Same as [`Encoding::Vector`].
+    Vector(u64, u64, Box<Self>),
+    /// Same as [`Encoding::Qualified`].
+    Qualified(Qualifier, Box<Self>),
 }
 
 impl EncodingBox {
@@ -118,7 +128,6 @@ impl EncodingBox {
     /// Returns an error if the string was an ill-formatted encoding string.
     pub fn from_start_of_str(s: &mut &str) -> Result<Self, ParseError> {
         let mut parser = Parser::new(s);
-        parser.strip_leading_qualifiers();
 
         match parser.parse_encoding() {
             Err(err) => Err(ParseError::new(parser, err)),
@@ -130,6 +139,84 @@ impl EncodingBox {
             }
         }
     }
+
+    /// Parse and consume an encoding from the start of a string, recovering
+    /// as much of the encoding as possible if it is malformed.
+    ///
+    /// On success, this is the same as [`from_start_of_str`].
+    ///
+    /// On failure, the string is advanced to where the error occurred, and
+    /// the part of the encoding that could be parsed is returned along with
+    /// the error. Structs and unions retain the fields that were parsed
+    /// before the error, and pointers, arrays and similar retain their
+    /// contents if those could be partially parsed.
+    ///
+    /// This is useful for tools that want to inspect encodings that this
+    /// crate does not (yet) fully understand.
+    ///
+    /// [`from_start_of_str`]: Self::from_start_of_str
+    ///
+    ///
+    /// # Errors
+    ///
+    /// Returns the partially parsed encoding, if any, along with the error if
+    /// the string was an ill-formatted encoding string.
+    ///
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use objc2_encode::{EncodingBox, ParseErrorKind};
+    ///
+    /// let mut s = "{S=i^d%f}";
+    /// let (partial, err) = EncodingBox::from_start_of_str_partial(&mut s).unwrap_err();
+    /// assert_eq!(partial.unwrap().to_string(), "{S=i^d}");
+    /// assert_eq!(err.kind(), &ParseErrorKind::Unknown(b'%'));
+    /// assert_eq!(s, "%f}");
+    /// ```
+    pub fn from_start_of_str_partial(s: &mut &str) -> Result<Self, (Option<Self>, ParseError)> {
+        let mut parser = Parser::new(s);
+
+        match parser.parse_encoding_partial() {
+            (Some(encoding), Ok(())) => {
+                *s = parser.remaining();
+                Ok(encoding)
+            }
+            (_, Ok(())) => unreachable!("parsed encoding without result"),
+            (encoding, Err(err)) => {
+                *s = parser.remaining();
+                Err((encoding, ParseError::new(parser, err)))
+            }
+        }
+    }
+
+    /// Same as [`Encoding::size`].
+    pub fn size(&self, model: DataModel) -> Option<usize> {
+        layout(self, model).map(|layout| layout.size)
+    }
+
+    /// Same as [`Encoding::align`].
+    pub fn align(&self, model: DataModel) -> Option<usize> {
+        layout(self, model).map(|layout| layout.align)
+    }
+
+    /// Same as [`Encoding::abi_class`].
+    pub fn abi_class(&self, cc: CallingConvention) -> Option<AbiClass> {
+        abi_class(self, cc)
+    }
+
+    /// Same as [`Encoding::abi_compatible_with`].
+    ///
+    /// # Errors
+    ///
+    /// Returns the reason the encodings are not ABI-compatible.
+    pub fn abi_compatible_with(
+        &self,
+        other: &Self,
+        cc: CallingConvention,
+    ) -> Result<(), AbiMismatch> {
+        abi_compatible(self, other, cc)
+    }
 }
 
 /// Same formatting as [`Encoding`]'s `Display` implementation.
@@ -156,7 +243,6 @@ impl FromStr for EncodingBox {
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         let mut parser = Parser::new(s);
-        parser.strip_leading_qualifiers();
 
         parser
             .parse_encoding()
@@ -168,6 +254,7 @@ impl FromStr for EncodingBox {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::{ContainerKind, ParseErrorKind};
     use alloc::string::ToString;
     use alloc::vec;
 
@@ -239,6 +326,89 @@ mod tests {
         assert_eq!(expected.to_string(), "AA{a}");
     }
 
+    #[test]
+    fn parse_qualifiers() {
+        let enc = EncodingBox::from_str("rN^r*").unwrap();
+        let expected = EncodingBox::Qualified(
+            Qualifier::Const,
+            Box::new(EncodingBox::Qualified(
+                Qualifier::Inout,
+                Box::new(EncodingBox::Pointer(Box::new(EncodingBox::Qualified(
+                    Qualifier::Const,
+                    Box::new(EncodingBox::String),
+                )))),
+            )),
+        );
+        assert_eq!(enc, expected);
+        assert_eq!(enc.to_string(), "rN^r*");
+
+        // Qualifiers are taken into account for equality, but not for
+        // equivalence.
+        const ENC: Encoding = Encoding::Pointer(&Encoding::String);
+        assert_ne!(enc, ENC);
+        assert!(ENC.equivalent_to_box(&enc));
+    }
+
+    #[test]
+    fn parse_partial() {
+        #[track_caller]
+        fn assert_partial(
+            input: &str,
+            expected: Option<&str>,
+            kind: ParseErrorKind,
+            offset: usize,
+            remaining: &str,
+        ) {
+            let mut s = input;
+            let (enc, err) = EncodingBox::from_start_of_str_partial(&mut s).unwrap_err();
+            assert_eq!(enc.map(|enc| enc.to_string()).as_deref(), expected);
+            assert_eq!(err.kind(), &kind);
+            assert_eq!(err.offset(), offset);
+            assert_eq!(err.input(), input);
+            assert_eq!(err.remaining(), remaining);
+            assert_eq!(s, remaining);
+        }
+
+        assert_partial("", None, ParseErrorKind::UnexpectedEnd, 0, "");
+        assert_partial("%", None, ParseErrorKind::Unknown(b'%'), 0, "%");
+        assert_partial("^%", None, ParseErrorKind::Unknown(b'%'), 1, "%");
+        assert_partial(
+            "{a=ic",
+            Some("{a=ic}"),
+            ParseErrorKind::WrongEndContainer(ContainerKind::Struct),
+            5,
+            "",
+        );
+        assert_partial(
+            "^{a=i{b=d[3x]}c}",
+            Some("^{a=i{b=d}}"),
+            ParseErrorKind::Unknown(b'x'),
+            11,
+            "x]}c}",
+        );
+        assert_partial(
+            "[4(u=cjx)]",
+            Some("[4(u=c)]"),
+            ParseErrorKind::UnknownAfterComplex(b'x'),
+            7,
+            "x)]",
+        );
+        assert_partial("[4i", Some("[4i]"), ParseErrorKind::WrongEndArray, 3, "");
+        assert_partial(
+            "r{a-b=i}",
+            None,
+            ParseErrorKind::InvalidIdentifier(ContainerKind::Struct),
+            2,
+            "a-b=i}",
+        );
+
+        // Success
+        let mut s = "r^{a=i}16";
+        let enc = EncodingBox::from_start_of_str_partial(&mut s).unwrap();
+        assert_eq!(enc.to_string(), "r^{a=i}");
+        assert_eq!(s, "16");
+    }
+
     #[test]
     fn parse_part_of_string() {
         let mut s = "{a}cb0i16";
@@ -4,6 +4,7 @@ use core::write;
 use crate::parse::verify_name;
 use crate::Encoding;
 use crate::EncodingBox;
+use crate::Qualifier;
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub(crate) enum NestingLevel {
@@ -41,6 +42,16 @@ impl NestingLevel {
         self
     }
 
+    const fn vector(self) -> Self {
+        // Vectors can only contain primitive types
+        self
+    }
+
+    const fn qualified(self) -> Self {
+        // Qualifiers do not affect nesting
+        self
+    }
+
     const fn container(self) -> Self {
         match self {
             // Move top one step down
@@ -83,6 +94,13 @@ pub(crate) fn compare_encodings<E1: EncodingType, E2: EncodingType>(
     // TODO: Are level1 and level2 ever be different?
 
     match (enc1.helper(level1), enc2.helper(level2)) {
+        // Qualifiers are only taken into account when checking for equality.
+        (Qualified(q1, t1, level1), Qualified(q2, t2, level2)) if include_all => {
+            q1 == q2 && compare_encodings(t1, level1, t2, level2, include_all)
+        }
+        (Qualified(_, _, _), _) | (_, Qualified(_, _, _)) if include_all => false,
+        (Qualified(_, t1, level1), _) => compare_encodings(t1, level1, enc2, level2, include_all),
+        (_, Qualified(_, t2, level2)) => compare_encodings(enc1, level1, t2, level2, include_all),
         (Primitive(p1), Primitive(p2)) => p1 == p2,
         (
             BitField(size1, Some((offset1, type1)), level1),
@@ -103,6 +121,11 @@ pub(crate) fn compare_encodings<E1: EncodingType, E2: EncodingType>(
         (Array(len1, item1, level1), Array(len2, item2, level2)) => {
             len1 == len2 && compare_encodings(item1, level1, item2, level2, include_all)
         }
+        (Vector(size1, align1, item1, level1), Vector(size2, align2, item2, level2)) => {
+            size1 == size2
+                && align1 == align2
+                && compare_encodings(item1, level1, item2, level2, include_all)
+        }
         (Container(kind1, name1, items1, level1), Container(kind2, name2, items2, level2)) => {
             kind1 == kind2
                 && name1 == name2
@@ -141,11 +164,13 @@ pub(crate) enum Primitive {
     Int,
     Long,
     LongLong,
+    Int128,
     UChar,
     UShort,
     UInt,
     ULong,
     ULongLong,
+    UInt128,
     Float,
     Double,
     LongDouble,
@@ -171,11 +196,13 @@ impl Primitive {
             Int => "i",
             Long => "l",
             LongLong => "q",
+            Int128 => "t",
             UChar => "C",
             UShort => "S",
             UInt => "I",
             ULong => "L",
             ULongLong => "Q",
+            UInt128 => "T",
             Float => "f",
             Double => "d",
             LongDouble => "D",
@@ -213,9 +240,14 @@ impl IndirectionKind {
     }
 }
 
+/// The kind of a container encoding.
+///
+/// Used in [`ParseErrorKind`][crate::ParseErrorKind].
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
-pub(crate) enum ContainerKind {
+pub enum ContainerKind {
+    /// A struct, `{...}`.
     Struct,
+    /// A union, `(...)`.
     Union,
 }
 
@@ -263,6 +295,8 @@ pub(crate) enum Helper<'a, E = Encoding> {
     BitField(u8, Option<&'a (u64, E)>, NestingLevel),
     Indirection(IndirectionKind, &'a E, NestingLevel),
     Array(u64, &'a E, NestingLevel),
+    Vector(u64, u64, &'a E, NestingLevel),
+    Qualified(Qualifier, &'a E, NestingLevel),
     Container(ContainerKind, &'a str, Option<&'a [E]>, NestingLevel),
 }
 
@@ -282,6 +316,12 @@ impl<E: EncodingType> fmt::Display for Helper<'_, E> {
             Self::Array(len, item, level) => {
                 write!(f, "[{}{}]", len, item.helper(*level))
             }
+            Self::Vector(size, align, item, level) => {
+                write!(f, "![{},{}{}]", size, align, item.helper(*level))
+            }
+            Self::Qualified(qualifier, t, level) => {
+                write!(f, "{}{}", qualifier, t.helper(*level))
+            }
             Self::Container(kind, name, items, level) => {
                 write!(f, "{}", kind.start())?;
                 write!(f, "{name}")?;
@@ -306,11 +346,13 @@ impl Helper<'_> {
             Int => Self::Primitive(Primitive::Int),
             Long => Self::Primitive(Primitive::Long),
             LongLong => Self::Primitive(Primitive::LongLong),
+            Int128 => Self::Primitive(Primitive::Int128),
             UChar => Self::Primitive(Primitive::UChar),
             UShort => Self::Primitive(Primitive::UShort),
             UInt => Self::Primitive(Primitive::UInt),
             ULong => Self::Primitive(Primitive::ULong),
             ULongLong => Self::Primitive(Primitive::ULongLong),
+            UInt128 => Self::Primitive(Primitive::UInt128),
             Float => Self::Primitive(Primitive::Float),
             Double => Self::Primitive(Primitive::Double),
             LongDouble => Self::Primitive(Primitive::LongDouble),
@@ -329,6 +371,8 @@ impl Helper<'_> {
             Pointer(t) => Self::Indirection(IndirectionKind::Pointer, t, level.pointer()),
             Atomic(t) => Self::Indirection(IndirectionKind::Atomic, t, level.atomic()),
             Array(len, item) => Self::Array(*len, item, level.array()),
+            Vector(size, align, item) => Self::Vector(*size, *align, item, level.vector()),
+            Qualified(qualifier, t) => Self::Qualified(*qualifier, t, level.qualified()),
             Struct(name, fields) => {
                 if !verify_name(name) {
                     panic!("Struct name was not a valid identifier");
@@ -364,11 +408,13 @@ impl<'a> Helper<'a, EncodingBox> {
             Int => Self::Primitive(Primitive::Int),
             Long => Self::Primitive(Primitive::Long),
             LongLong => Self::Primitive(Primitive::LongLong),
+            Int128 => Self::Primitive(Primitive::Int128),
             UChar => Self::Primitive(Primitive::UChar),
             UShort => Self::Primitive(Primitive::UShort),
             UInt => Self::Primitive(Primitive::UInt),
             ULong => Self::Primitive(Primitive::ULong),
             ULongLong => Self::Primitive(Primitive::ULongLong),
+            UInt128 => Self::Primitive(Primitive::UInt128),
             Float => Self::Primitive(Primitive::Float),
             Double => Self::Primitive(Primitive::Double),
             LongDouble => Self::Primitive(Primitive::LongDouble),
@@ -387,6 +433,8 @@ impl<'a> Helper<'a, EncodingBox> {
             Pointer(t) => Self::Indirection(IndirectionKind::Pointer, t, level.pointer()),
             Atomic(t) => Self::Indirection(IndirectionKind::Atomic, t, level.atomic()),
             Array(len, item) => Self::Array(*len, item, level.array()),
+            Vector(size, align, item) => Self::Vector(*size, *align, item, level.vector()),
+            Qualified(qualifier, t) => Self::Qualified(*qualifier, t, level.qualified()),
             Struct(name, fields) => {
                 if !verify_name(name) {
                     panic!("Struct name was not a valid identifier");
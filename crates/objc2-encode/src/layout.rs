@@ -0,0 +1,609 @@
+//! Computing the size and alignment of the type an encoding describes.
+use crate::helper::{
+    ContainerKind, EncodingType, Helper, IndirectionKind, NestingLevel, Primitive,
+};
+
+/// The C data model of a target, that is, the widths of the fundamental
+/// integer types and of pointers.
+///
+/// This is used when computing the size and alignment of an encoding, see
+/// [`Encoding::size`] and [`Encoding::align`].
+///
+/// See [Wikipedia][wiki] for details.
+///
+/// [wiki]: https://en.wikipedia.org/wiki/64-bit_computing#64-bit_data_models
+/// [`Encoding::size`]: crate::Encoding::size
+/// [`Encoding::align`]: crate::Encoding::align
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum DataModel {
+    /// `int`, `long` and pointers are 32 bits wide.
+    ///
+    /// Used by 32-bit ARM targets such as `armv7`, and by 32-bit Windows.
+    Ilp32,
+    /// Same widths as [`Ilp32`][Self::Ilp32], but with the reduced alignment
+    /// of the i386 System V ABI, where `double` and `long long` are only
+    /// 4-byte aligned.
+    ///
+    /// Used by 32-bit x86 targets except Windows, such as `i686` Linux and
+    /// macOS.
+    I386,
+    /// `int` is 32 bits wide, `long` and pointers are 64 bits wide.
+    ///
+    /// Used by 64-bit Unix-like targets, including all 64-bit Apple targets.
+    Lp64,
+    /// `int` and `long` are 32 bits wide, pointers are 64 bits wide.
+    ///
+    /// Used by 64-bit Windows.
+    Llp64,
+}
+
+impl DataModel {
+    /// The data model of the current target.
+    pub const CURRENT: Self = {
+        if cfg!(target_pointer_width = "64") {
+            if cfg!(windows) {
+                Self::Llp64
+            } else {
+                Self::Lp64
+            }
+        } else if cfg!(all(target_arch = "x86", not(windows))) {
+            Self::I386
+        } else {
+            Self::Ilp32
+        }
+    };
+
+    const fn long(self) -> usize {
+        match self {
+            Self::Ilp32 | Self::I386 | Self::Llp64 => 4,
+            Self::Lp64 => 8,
+        }
+    }
+
+    const fn pointer(self) -> usize {
+        match self {
+            Self::Ilp32 | Self::I386 => 4,
+            Self::Lp64 | Self::Llp64 => 8,
+        }
+    }
+
+    /// The alignment of `double` and `long long`.
+    const fn align_8(self) -> usize {
+        match self {
+            Self::I386 => 4,
+            Self::Ilp32 | Self::Lp64 | Self::Llp64 => 8,
+        }
+    }
+
+    /// Whether bitfields are laid out using Microsoft's algorithm instead of
+    /// the one from the Itanium / System V ABI.
+    const fn ms_bitfields(self) -> bool {
+        matches!(self, Self::Llp64)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub(crate) struct Layout {
+    pub(crate) size: usize,
+    pub(crate) align: usize,
+}
+
+impl Layout {
+    const fn new(size: usize, align: usize) -> Self {
+        Self { size, align }
+    }
+}
+
+fn round_up(n: usize, align: usize) -> Option<usize> {
+    debug_assert!(align.is_power_of_two());
+    Some(n.checked_add(align - 1)? & !(align - 1))
+}
+
+pub(crate) fn primitive_layout(primitive: Primitive, model: DataModel) -> Option<Layout> {
+    use Primitive::*;
+    let pointer = model.pointer();
+    match primitive {
+        Char | UChar | Bool => Some(Layout::new(1, 1)),
+        Short | UShort => Some(Layout::new(2, 2)),
+        Int | UInt | Float => Some(Layout::new(4, 4)),
+        Long | ULong => Some(Layout::new(model.long(), model.long())),
+        LongLong | ULongLong | Double => Some(Layout::new(8, model.align_8())),
+        Int128 | UInt128 => Some(Layout::new(16, 16)),
+        FloatComplex => Some(Layout::new(8, 4)),
+        DoubleComplex => Some(Layout::new(16, model.align_8())),
+        String | Object | Block | Class | Sel => Some(Layout::new(pointer, pointer)),
+        // The layout of `long double` varies between architectures, even
+        // within the same data model.
+        LongDouble | LongDoubleComplex => None,
+        // Neither `void` nor functions have a size.
+        Void | Unknown => None,
+    }
+}
+
+/// The layout of the storage unit of a bitfield.
+///
+/// Apple's runtime does not include the type of the bitfield in the
+/// encoding, in that case we assume `unsigned int`, since that is by far the
+/// most common type for bitfields.
+fn bitfield_unit<E: EncodingType>(
+    size: u8,
+    ty: Option<&(u64, E)>,
+    model: DataModel,
+) -> Option<Layout> {
+    let unit = match ty {
+        Some((_offset, ty)) => layout(ty, model)?,
+        None => Layout::new(4, 4),
+    };
+    if usize::from(size) > unit.size.checked_mul(8)? {
+        // The bitfield is wider than its type
+        return None;
+    }
+    Some(unit)
+}
+
+/// A field in a struct, with its offset in bytes.
+#[derive(Debug)]
+pub(crate) enum Field<'a, E> {
+    /// A field with a type.
+    Typed(&'a E, usize),
+    /// The storage of one or more bitfields, between the two offsets.
+    Bits(usize, usize),
+}
+
+/// Compute the layout of a struct.
+///
+/// See the "Itanium C++ ABI" section 2.4 and the documentation for
+/// `/d1reportSingleClassLayout` for the bitfield algorithms.
+fn struct_layout<E: EncodingType>(fields: &[E], model: DataModel) -> Option<Layout> {
+    struct_fields(fields, model, |_| Some(()))
+}
+
+/// Compute the layout of a struct, and visit each of its fields.
+pub(crate) fn struct_fields<'a, E: EncodingType>(
+    fields: &'a [E],
+    model: DataModel,
+    mut visit: impl FnMut(Field<'a, E>) -> Option<()>,
+) -> Option<Layout> {
+    // The end of the previous field, in bits.
+    let mut end: usize = 0;
+    let mut align = 1;
+    // The size and remaining bits of the current MS bitfield storage unit.
+    let mut ms_unit: Option<(usize, usize)> = None;
+
+    for field in fields {
+        if let Helper::BitField(width, ty, _) = field.helper(NestingLevel::new()) {
+            let unit = bitfield_unit(width, ty, model)?;
+            let width = usize::from(width);
+            let unit_bits = unit.size.checked_mul(8)?;
+
+            if model.ms_bitfields() {
+                match &mut ms_unit {
+                    // A zero-width bitfield ends the current storage unit
+                    _ if width == 0 => ms_unit = None,
+                    Some((size, remaining)) if *size == unit.size && width <= *remaining => {
+                        *remaining -= width;
+                    }
+                    _ => {
+                        let offset = round_up(end.checked_add(7)? / 8, unit.align)?;
+                        end = offset.checked_add(unit.size)?.checked_mul(8)?;
+                        align = align.max(unit.align);
+                        ms_unit = Some((unit.size, unit_bits - width));
+                        visit(Field::Bits(offset, end / 8))?;
+                    }
+                }
+            } else {
+                let unit_align_bits = unit.align.checked_mul(8)?;
+                if width == 0 {
+                    // A zero-width bitfield pads to the next storage unit
+                    end = round_up(end, unit_align_bits)?;
+                } else {
+                    // Bitfields may not straddle a storage unit boundary
+                    let start_of_unit = end / unit_align_bits * unit_align_bits;
+                    if end - start_of_unit + width > unit_bits {
+                        end = round_up(end, unit_align_bits)?;
+                    }
+                    let start = end;
+                    end = end.checked_add(width)?;
+                    align = align.max(unit.align);
+                    visit(Field::Bits(start / 8, end.checked_add(7)? / 8))?;
+                }
+            }
+        } else {
+            ms_unit = None;
+            let layout = layout(field, model)?;
+            let offset = round_up(end.checked_add(7)? / 8, layout.align)?;
+            end = offset.checked_add(layout.size)?.checked_mul(8)?;
+            align = align.max(layout.align);
+            visit(Field::Typed(field, offset))?;
+        }
+    }
+
+    let size = round_up(end.checked_add(7)? / 8, align)?;
+    Some(Layout::new(size, align))
+}
+
+/// The layout of a member of a union.
+pub(crate) fn union_member_layout<E: EncodingType>(member: &E, model: DataModel) -> Option<Layout> {
+    match member.helper(NestingLevel::new()) {
+        Helper::BitField(width, ty, _) => bitfield_unit(width, ty, model),
+        _ => layout(member, model),
+    }
+}
+
+fn union_layout<E: EncodingType>(members: &[E], model: DataModel) -> Option<Layout> {
+    let mut size = 0;
+    let mut align = 1;
+    for member in members {
+        let member = union_member_layout(member, model)?;
+        size = size.max(member.size);
+        align = align.max(member.align);
+    }
+    Some(Layout::new(round_up(size, align)?, align))
+}
+
+/// Compute the layout of the type described by the encoding.
+///
+/// Returns `None` if the encoding does not describe a type with a known
+/// size, or if the size would overflow.
+pub(crate) fn layout<E: EncodingType>(encoding: &E, model: DataModel) -> Option<Layout> {
+    // We never look behind pointers, so the nesting level is always reset,
+    // such that we always get the fields of containers.
+    match encoding.helper(NestingLevel::new()) {
+        Helper::Primitive(primitive) => primitive_layout(primitive, model),
+        // A bitfield only has a layout as part of a struct or union
+        Helper::BitField(_, _, _) => None,
+        Helper::Indirection(kind, t, _) => match kind {
+            IndirectionKind::Pointer => Some(Layout::new(model.pointer(), model.pointer())),
+            IndirectionKind::Atomic => {
+                let layout = layout(t, model)?;
+                // Small power-of-two sized atomics are aligned to their size,
+                // such that they can be accessed using atomic instructions.
+                if layout.size.is_power_of_two() && layout.size <= 8 {
+                    Some(Layout::new(layout.size, layout.size.max(layout.align)))
+                } else {
+                    Some(layout)
+                }
+            }
+        },
+        Helper::Array(len, item, _) => {
+            let item = layout(item, model)?;
+            let len = usize::try_from(len).ok()?;
+            Some(Layout::new(item.size.checked_mul(len)?, item.align))
+        }
+        Helper::Vector(size, align, _, _) => {
+            let size = usize::try_from(size).ok()?;
+            let align = usize::try_from(align).ok()?;
+            if !align.is_power_of_two() || size % align != 0 {
+                return None;
+            }
+            Some(Layout::new(size, align))
+        }
+        Helper::Qualified(_, t, _) => layout(t, model),
+        Helper::Container(kind, _, items, _) => match kind {
+            ContainerKind::Struct => struct_layout(items?, model),
+            ContainerKind::Union => union_layout(items?, model),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Encoding, EncodingBox, Qualifier};
+    use alloc::boxed::Box;
+    use alloc::string::ToString;
+    use core::mem::{align_of, size_of};
+
+    const MODELS: [DataModel; 3] = [DataModel::Ilp32, DataModel::Lp64, DataModel::Llp64];
+
+    #[track_caller]
+    fn assert_layout(enc: &Encoding, models: &[DataModel], size: usize, align: usize) {
+        let boxed: EncodingBox = enc.to_string().parse().unwrap();
+        for &model in models {
+            assert_eq!(enc.size(model), Some(size), "size {enc} {model:?}");
+            assert_eq!(enc.align(model), Some(align), "align {enc} {model:?}");
+            assert_eq!(boxed.size(model), Some(size), "boxed size {enc} {model:?}");
+            assert_eq!(
+                boxed.align(model),
+                Some(align),
+                "boxed align {enc} {model:?}"
+            );
+        }
+    }
+
+    #[track_caller]
+    fn assert_no_layout(enc: &EncodingBox) {
+        for model in MODELS {
+            assert_eq!(enc.size(model), None, "{enc} {model:?}");
+            assert_eq!(enc.align(model), None, "{enc} {model:?}");
+        }
+    }
+
+    #[test]
+    fn primitives() {
+        assert_layout(&Encoding::Char, &MODELS, 1, 1);
+        assert_layout(&Encoding::Bool, &MODELS, 1, 1);
+        assert_layout(&Encoding::UShort, &MODELS, 2, 2);
+        assert_layout(&Encoding::Int, &MODELS, 4, 4);
+        assert_layout(&Encoding::Float, &MODELS, 4, 4);
+        assert_layout(&Encoding::LongLong, &MODELS, 8, 8);
+        assert_layout(&Encoding::Double, &MODELS, 8, 8);
+        assert_layout(&Encoding::FloatComplex, &MODELS, 8, 4);
+        assert_layout(&Encoding::DoubleComplex, &MODELS, 16, 8);
+
+        assert_layout(&Encoding::Long, &[DataModel::Ilp32, DataModel::Llp64], 4, 4);
+        assert_layout(&Encoding::ULong, &[DataModel::Lp64], 8, 8);
+
+        for enc in [
+            Encoding::String,
+            Encoding::Object,
+            Encoding::Block,
+            Encoding::Class,
+            Encoding::Sel,
+            Encoding::Pointer(&Encoding::Unknown),
+            Encoding::Pointer(&Encoding::Void),
+        ] {
+            assert_layout(&enc, &[DataModel::Ilp32], 4, 4);
+            assert_layout(&enc, &[DataModel::Lp64, DataModel::Llp64], 8, 8);
+        }
+    }
+
+    #[test]
+    fn int128_vector_and_qualified() {
+        assert_layout(&Encoding::Int128, &MODELS, 16, 16);
+        assert_layout(&Encoding::UInt128, &MODELS, 16, 16);
+        assert_layout(&Encoding::Vector(16, 16, &Encoding::Float), &MODELS, 16, 16);
+        assert_layout(&Encoding::Vector(8, 8, &Encoding::Char), &MODELS, 8, 8);
+        assert_layout(
+            &Encoding::Qualified(Qualifier::Const, &Encoding::Short),
+            &MODELS,
+            2,
+            2,
+        );
+        assert_layout(
+            &Encoding::Struct(
+                "S",
+                &[
+                    Encoding::Qualified(Qualifier::Const, &Encoding::Char),
+                    Encoding::Vector(16, 16, &Encoding::Int),
+                ],
+            ),
+            &MODELS,
+            32,
+            16,
+        );
+        assert_no_layout(&"![12,8i]".parse().unwrap());
+        assert_no_layout(&"![16,3i]".parse().unwrap());
+    }
+
+    #[test]
+    fn no_layout() {
+        for s in [
+            "v", "?", "D", "jD", "b4", "b0i4", "{S}", "(U)", "{S=iv}", "[4v]",
+        ] {
+            assert_no_layout(&s.parse().unwrap());
+        }
+    }
+
+    #[test]
+    fn arrays() {
+        assert_layout(&Encoding::Array(0, &Encoding::Int), &MODELS, 0, 4);
+        assert_layout(&Encoding::Array(3, &Encoding::Short), &MODELS, 6, 2);
+        assert_layout(
+            &Encoding::Array(2, &Encoding::Array(3, &Encoding::Double)),
+            &MODELS,
+            48,
+            8,
+        );
+        assert_layout(
+            &Encoding::Array(4, &Encoding::Object),
+            &[DataModel::Lp64],
+            32,
+            8,
+        );
+
+        let overflow = EncodingBox::Array(u64::MAX, Box::new(EncodingBox::Int));
+        assert_no_layout(&overflow);
+    }
+
+    #[test]
+    fn structs_and_unions() {
+        assert_layout(&Encoding::Struct("Empty", &[]), &MODELS, 0, 1);
+
+        const RANGE: Encoding = Encoding::Struct("_NSRange", &[Encoding::ULong, Encoding::ULong]);
+        assert_layout(&RANGE, &[DataModel::Ilp32, DataModel::Llp64], 8, 4);
+        assert_layout(&RANGE, &[DataModel::Lp64], 16, 8);
+
+        const PADDED: Encoding = Encoding::Struct(
+            "Padded",
+            &[Encoding::Char, Encoding::Double, Encoding::Short],
+        );
+        assert_layout(&PADDED, &MODELS, 24, 8);
+
+        const NESTED: Encoding = Encoding::Struct(
+            "Nested",
+            &[Encoding::Char, PADDED, Encoding::Array(3, &Encoding::Char)],
+        );
+        assert_layout(&NESTED, &MODELS, 40, 8);
+
+        const UNION: Encoding = Encoding::Union(
+            "Union",
+            &[
+                Encoding::Char,
+                Encoding::Array(5, &Encoding::Char),
+                Encoding::Int,
+            ],
+        );
+        assert_layout(&UNION, &MODELS, 8, 4);
+
+        const MIXED: Encoding = Encoding::Struct(
+            "Mixed",
+            &[UNION, Encoding::Pointer(&NESTED), Encoding::Char],
+        );
+        assert_layout(&MIXED, &[DataModel::Ilp32], 16, 4);
+        assert_layout(&MIXED, &[DataModel::Lp64, DataModel::Llp64], 24, 8);
+    }
+
+    #[test]
+    fn i386() {
+        let i386 = &[DataModel::I386];
+        assert_layout(&Encoding::Long, i386, 4, 4);
+        assert_layout(&Encoding::Object, i386, 4, 4);
+        assert_layout(&Encoding::LongLong, i386, 8, 4);
+        assert_layout(&Encoding::Double, i386, 8, 4);
+        assert_layout(&Encoding::DoubleComplex, i386, 16, 4);
+
+        // `double` is 4-byte aligned inside structs on i386, but 8-byte
+        // aligned on 32-bit ARM.
+        const PADDED: Encoding = Encoding::Struct(
+            "Padded",
+            &[Encoding::Char, Encoding::Double, Encoding::Short],
+        );
+        assert_layout(&PADDED, i386, 16, 4);
+        assert_layout(&PADDED, &[DataModel::Ilp32], 24, 8);
+
+        const NESTED: Encoding = Encoding::Struct(
+            "Nested",
+            &[Encoding::Char, PADDED, Encoding::Array(3, &Encoding::Char)],
+        );
+        assert_layout(&NESTED, i386, 24, 4);
+    }
+
+    #[test]
+    fn atomic() {
+        assert_layout(&Encoding::Atomic(&Encoding::Int), &MODELS, 4, 4);
+        assert_layout(
+            &Encoding::Atomic(&Encoding::Array(3, &Encoding::Char)),
+            &MODELS,
+            3,
+            1,
+        );
+
+        // The fields of atomic structs are not emitted by `Display`
+        for (s, size, align) in [("A{S=ss}", 4, 4), ("A{S=cc}", 2, 2), ("A{S=ccc}", 3, 1)] {
+            let enc: EncodingBox = s.parse().unwrap();
+            for model in MODELS {
+                assert_eq!(enc.size(model), Some(size), "{s}");
+                assert_eq!(enc.align(model), Some(align), "{s}");
+            }
+        }
+    }
+
+    #[test]
+    fn bitfields() {
+        // struct { unsigned a : 3; unsigned b : 5; }
+        assert_layout(
+            &Encoding::Struct(
+                "S",
+                &[Encoding::BitField(3, None), Encoding::BitField(5, None)],
+            ),
+            &MODELS,
+            4,
+            4,
+        );
+
+        // struct { char c; unsigned a : 3; }
+        const INT_3: (u64, Encoding) = (8, Encoding::UInt);
+        assert_layout(
+            &Encoding::Struct("S", &[Encoding::Char, Encoding::BitField(3, Some(&INT_3))]),
+            &[DataModel::Ilp32, DataModel::Lp64],
+            4,
+            4,
+        );
+        assert_layout(
+            &Encoding::Struct("S", &[Encoding::Char, Encoding::BitField(3, Some(&INT_3))]),
+            &[DataModel::Llp64],
+            8,
+            4,
+        );
+
+        // struct { unsigned char a : 7; unsigned char b : 2; }
+        const CHAR_0: (u64, Encoding) = (0, Encoding::UChar);
+        const CHAR_8: (u64, Encoding) = (8, Encoding::UChar);
+        assert_layout(
+            &Encoding::Struct(
+                "S",
+                &[
+                    Encoding::BitField(7, Some(&CHAR_0)),
+                    Encoding::BitField(2, Some(&CHAR_8)),
+                ],
+            ),
+            &MODELS,
+            2,
+            1,
+        );
+
+        // struct { unsigned short a : 4; unsigned b : 4; }
+        const SHORT_0: (u64, Encoding) = (0, Encoding::UShort);
+        const INT_4: (u64, Encoding) = (4, Encoding::UInt);
+        let enc = Encoding::Struct(
+            "S",
+            &[
+                Encoding::BitField(4, Some(&SHORT_0)),
+                Encoding::BitField(4, Some(&INT_4)),
+            ],
+        );
+        assert_layout(&enc, &[DataModel::Ilp32, DataModel::Lp64], 4, 4);
+        assert_layout(&enc, &[DataModel::Llp64], 8, 4);
+
+        // struct { unsigned a : 4; unsigned : 0; unsigned b : 4; }
+        let enc = Encoding::Struct(
+            "S",
+            &[
+                Encoding::BitField(4, None),
+                Encoding::BitField(0, None),
+                Encoding::BitField(4, None),
+            ],
+        );
+        assert_layout(&enc, &MODELS, 8, 4);
+
+        // union { unsigned a : 3; char b; }
+        assert_layout(
+            &Encoding::Union("U", &[Encoding::BitField(3, None), Encoding::Char]),
+            &MODELS,
+            4,
+            4,
+        );
+
+        // Wider than the underlying type
+        assert_no_layout(&"{S=b0c9}".parse().unwrap());
+    }
+
+    #[test]
+    fn matches_rust_layout() {
+        #[repr(C)]
+        struct Inner {
+            a: u8,
+            b: *const u8,
+            c: u16,
+        }
+
+        #[repr(C)]
+        struct Outer {
+            a: [Inner; 3],
+            b: u8,
+            c: f64,
+            d: [u32; 0],
+        }
+
+        const INNER: Encoding = Encoding::Struct(
+            "Inner",
+            &[Encoding::UChar, Encoding::String, Encoding::UShort],
+        );
+        const OUTER: Encoding = Encoding::Struct(
+            "Outer",
+            &[
+                Encoding::Array(3, &INNER),
+                Encoding::UChar,
+                Encoding::Double,
+                Encoding::Array(0, &Encoding::UInt),
+            ],
+        );
+
+        assert_eq!(INNER.size(DataModel::CURRENT), Some(size_of::<Inner>()));
+        assert_eq!(INNER.align(DataModel::CURRENT), Some(align_of::<Inner>()));
+        assert_eq!(OUTER.size(DataModel::CURRENT), Some(size_of::<Outer>()));
+        assert_eq!(OUTER.align(DataModel::CURRENT), Some(align_of::<Outer>()));
+    }
+}
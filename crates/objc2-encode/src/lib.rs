@@ -64,15 +64,25 @@ extern crate std;
 #[cfg(any(feature = "alloc", test))]
 extern crate alloc;
 
+mod abi;
 mod encoding;
 mod encoding_box;
 mod helper;
+mod layout;
+mod method_encoding;
 mod parse;
-
-// Will be used at some point when generic constants are available
-#[allow(dead_code)]
+mod qualifier;
+#[cfg(feature = "serde")]
+mod serde_impls;
+#[cfg(feature = "serde")]
+pub mod serde_tree;
 mod static_str;
 
+pub use self::abi::{AbiClass, AbiMismatch, CallingConvention, RegisterClass};
 pub use self::encoding::Encoding;
 pub use self::encoding_box::EncodingBox;
-pub use self::parse::ParseError;
+pub use self::helper::ContainerKind;
+pub use self::layout::DataModel;
+pub use self::method_encoding::{MethodEncoding, MethodEncodingItem};
+pub use self::parse::{ParseError, ParseErrorKind};
+pub use self::qualifier::Qualifier;
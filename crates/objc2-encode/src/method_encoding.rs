@@ -0,0 +1,291 @@
+use alloc::vec::Vec;
+use core::fmt;
+use core::str::FromStr;
+
+use crate::parse::{ParseError, ParseErrorKind, Parser};
+use crate::{EncodingBox, Qualifier};
+
+/// The type-encoding of an Objective-C method.
+///
+/// This is what is returned by `method_getTypeEncoding`, and what is stored
+/// in protocol method descriptions. It consists of the return type followed
+/// by the type of each argument, including the receiver and the selector.
+///
+/// Each type may be preceded by qualifiers, and followed by a number. For the
+/// return type, this number is the total size of the arguments on the stack,
+/// and for each argument it is the argument's offset into that.
+///
+/// The [`Display`][`fmt::Display`] implementation formats the encoding back
+/// into the string it was parsed from, including qualifiers and offsets (as
+/// long as [`EncodingBox`] formats each type the same way as the compiler
+/// did, which is usually the case).
+///
+///
+/// # Examples
+///
+/// Parse the encoding of `- (void)setObject:(id)obj`.
+///
+/// ```
+/// use objc2_encode::{Encoding, MethodEncoding};
+///
+/// let method: MethodEncoding = "v24@0:8@16".parse()?;
+/// assert!(Encoding::Void.equivalent_to_box(&method.return_type.encoding));
+/// assert_eq!(method.return_type.offset, Some(24));
+///
+/// assert_eq!(method.arguments.len(), 3);
+/// assert!(Encoding::Object.equivalent_to_box(&method.arguments[0].encoding));
+/// assert!(Encoding::Sel.equivalent_to_box(&method.arguments[1].encoding));
+/// assert_eq!(method.arguments[2].offset, Some(16));
+///
+/// assert_eq!(method.to_string(), "v24@0:8@16");
+/// # Ok::<(), objc2_encode::ParseError>(())
+/// ```
+///
+/// Qualifiers are parsed as well.
+///
+/// ```
+/// use objc2_encode::{MethodEncoding, Qualifier};
+///
+/// let method: MethodEncoding = "Vv@:r*o^@".parse()?;
+/// assert_eq!(method.return_type.qualifiers, [Qualifier::Oneway]);
+/// assert_eq!(method.arguments[2].qualifiers, [Qualifier::Const]);
+/// assert_eq!(method.arguments[3].qualifiers, [Qualifier::Out]);
+/// # Ok::<(), objc2_encode::ParseError>(())
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct MethodEncoding {
+    /// The return type.
+    ///
+    /// The offset of this is the total size of the arguments.
+    pub return_type: MethodEncodingItem,
+    /// The arguments, including the receiver and the selector.
+    pub arguments: Vec<MethodEncodingItem>,
+}
+
+/// The return type or an argument in a [`MethodEncoding`].
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct MethodEncodingItem {
+    /// The qualifiers in front of the type, in the order they appeared.
+    pub qualifiers: Vec<Qualifier>,
+    /// The type.
+    pub encoding: EncodingBox,
+    /// The stack offset following the type, if any.
+    pub offset: Option<isize>,
+    /// Whether the offset was prefixed with a `+`.
+    ///
+    /// Older versions of GCC use this to mark arguments that are passed in
+    /// registers.
+    pub register: bool,
+}
+
+impl MethodEncodingItem {
+    /// Create an item with the given encoding, and no qualifiers or offset.
+    pub fn new(encoding: EncodingBox) -> Self {
+        Self {
+            qualifiers: Vec::new(),
+            encoding,
+            offset: None,
+            register: false,
+        }
+    }
+
+    fn parse(parser: &mut Parser<'_>) -> Result<Self, ParseErrorKind> {
+        let qualifiers = parser.parse_qualifiers();
+        let encoding = parser.parse_encoding()?;
+        let (offset, register) = match parser.parse_stack_offset()? {
+            Some((offset, register)) => (Some(offset), register),
+            None => (None, false),
+        };
+        Ok(Self {
+            qualifiers,
+            encoding,
+            offset,
+            register,
+        })
+    }
+}
+
+impl fmt::Display for MethodEncodingItem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for qualifier in &self.qualifiers {
+            write!(f, "{qualifier}")?;
+        }
+        write!(f, "{}", self.encoding)?;
+        if self.register {
+            write!(f, "+")?;
+        }
+        if let Some(offset) = self.offset {
+            write!(f, "{offset}")?;
+        }
+        Ok(())
+    }
+}
+
+impl MethodEncoding {
+    /// Create a method encoding with the given return type and arguments,
+    /// without any qualifiers or offsets.
+    pub fn new(return_type: EncodingBox, arguments: impl IntoIterator<Item = EncodingBox>) -> Self {
+        Self {
+            return_type: MethodEncodingItem::new(return_type),
+            arguments: arguments.into_iter().map(MethodEncodingItem::new).collect(),
+        }
+    }
+
+    fn parse(parser: &mut Parser<'_>) -> Result<Self, ParseErrorKind> {
+        let return_type = MethodEncodingItem::parse(parser)?;
+        let mut arguments = Vec::new();
+        while !parser.is_empty() {
+            arguments.push(MethodEncodingItem::parse(parser)?);
+        }
+        Ok(Self {
+            return_type,
+            arguments,
+        })
+    }
+}
+
+impl FromStr for MethodEncoding {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parser = Parser::new(s);
+        Self::parse(&mut parser).map_err(|err| ParseError::new(parser, err))
+    }
+}
+
+/// Formats the method encoding, including qualifiers and offsets.
+impl fmt::Display for MethodEncoding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.return_type)?;
+        for argument in &self.arguments {
+            write!(f, "{argument}")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::boxed::Box;
+    use alloc::string::ToString;
+    use alloc::vec;
+
+    #[track_caller]
+    fn assert_roundtrip(s: &str) -> MethodEncoding {
+        let method: MethodEncoding = s.parse().unwrap();
+        assert_eq!(method.to_string(), s);
+        method
+    }
+
+    #[test]
+    fn simple() {
+        let method = assert_roundtrip("v16@0:8");
+        assert_eq!(
+            method,
+            MethodEncoding {
+                return_type: MethodEncodingItem {
+                    offset: Some(16),
+                    ..MethodEncodingItem::new(EncodingBox::Void)
+                },
+                arguments: vec![
+                    MethodEncodingItem {
+                        offset: Some(0),
+                        ..MethodEncodingItem::new(EncodingBox::Object)
+                    },
+                    MethodEncodingItem {
+                        offset: Some(8),
+                        ..MethodEncodingItem::new(EncodingBox::Sel)
+                    },
+                ],
+            }
+        );
+    }
+
+    #[test]
+    fn without_offsets() {
+        let method = assert_roundtrip("v@:");
+        assert_eq!(
+            method,
+            MethodEncoding::new(EncodingBox::Void, [EncodingBox::Object, EncodingBox::Sel])
+        );
+        assert_eq!(MethodEncoding::new(EncodingBox::Int, []).to_string(), "i");
+    }
+
+    #[test]
+    fn complex() {
+        let method = assert_roundtrip("@48@0:8Ad16r^*24{bitfield=b64b1}32i48");
+        assert_eq!(method.return_type.encoding, EncodingBox::Object);
+        assert_eq!(method.arguments.len(), 6);
+        assert_eq!(
+            method.arguments[2].encoding,
+            EncodingBox::Atomic(Box::new(EncodingBox::Double))
+        );
+        assert_eq!(method.arguments[3].qualifiers, [Qualifier::Const]);
+        assert_eq!(
+            method.arguments[3].encoding,
+            EncodingBox::Pointer(Box::new(EncodingBox::String))
+        );
+        assert_eq!(method.arguments[5].offset, Some(48));
+
+        assert_roundtrip("jf16@0:8");
+        assert_roundtrip("{CGRect={CGPoint=dd}{CGSize=dd}}16@0:8");
+        assert_roundtrip("v40@0:8^{_NSZone=}16[4c]24^?32");
+    }
+
+    #[test]
+    fn qualifiers() {
+        let method = assert_roundtrip("Vv40@0:8rnNoORV@16o^@24N^i32");
+        assert_eq!(method.return_type.qualifiers, [Qualifier::Oneway]);
+        assert_eq!(
+            method.arguments[2].qualifiers,
+            [
+                Qualifier::Const,
+                Qualifier::In,
+                Qualifier::Inout,
+                Qualifier::Out,
+                Qualifier::Bycopy,
+                Qualifier::Byref,
+                Qualifier::Oneway,
+            ]
+        );
+        assert_eq!(method.arguments[3].qualifiers, [Qualifier::Out]);
+        assert_eq!(method.arguments[4].qualifiers, [Qualifier::Inout]);
+
+        // Order is preserved
+        assert_roundtrip("v@:Rr@");
+        assert_roundtrip("v@:rR@");
+    }
+
+    #[test]
+    fn signed_offsets() {
+        let method = assert_roundtrip("v12@+8:+12i-4");
+        assert_eq!(method.arguments[0].offset, Some(8));
+        assert!(method.arguments[0].register);
+        assert_eq!(method.arguments[1].offset, Some(12));
+        assert!(method.arguments[1].register);
+        assert_eq!(method.arguments[2].offset, Some(-4));
+        assert!(!method.arguments[2].register);
+    }
+
+    #[test]
+    fn errors() {
+        #[track_caller]
+        fn assert_err(s: &str, kind: ParseErrorKind) {
+            let err = s.parse::<MethodEncoding>().unwrap_err();
+            assert_eq!(err.kind(), &kind, "{s}");
+        }
+
+        assert_err("", ParseErrorKind::UnexpectedEnd);
+        assert_err("r", ParseErrorKind::UnexpectedEnd);
+        assert_err("v@:+", ParseErrorKind::ExpectedInteger);
+        assert_err("v@:+a", ParseErrorKind::ExpectedInteger);
+        assert_err("v@:-", ParseErrorKind::ExpectedInteger);
+        assert_err("v@:99999999999999999999", ParseErrorKind::IntegerTooLarge);
+        assert_err(
+            "v@:{a=i",
+            ParseErrorKind::WrongEndContainer(crate::ContainerKind::Struct),
+        );
+        assert_err("v@:x", ParseErrorKind::Unknown(b'x'));
+    }
+}
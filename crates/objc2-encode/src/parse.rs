@@ -4,9 +4,10 @@ use alloc::boxed::Box;
 use alloc::string::{String, ToString};
 use alloc::vec::Vec;
 use core::fmt;
+use core::str::FromStr;
 
 use crate::helper::{ContainerKind, Helper, NestingLevel};
-use crate::{Encoding, EncodingBox};
+use crate::{Encoding, EncodingBox, Qualifier};
 
 /// Check whether a struct or union name is a valid identifier
 pub(crate) const fn verify_name(name: &str) -> bool {
@@ -32,21 +33,73 @@ pub(crate) const fn verify_name(name: &str) -> bool {
 }
 
 /// The error that was encountered while parsing an encoding string.
+///
+/// The [`Display`][`fmt::Display`] implementation contains a human-readable
+/// description of the error, while the methods on this allow inspecting the
+/// details programmatically.
+///
+///
+/// # Examples
+///
+/// ```
+/// use objc2_encode::{EncodingBox, ParseErrorKind};
+///
+/// let err = "{CGPoint=dd".parse::<EncodingBox>().unwrap_err();
+/// assert!(matches!(err.kind(), ParseErrorKind::WrongEndContainer(_)));
+/// assert_eq!(err.offset(), 11);
+/// assert_eq!(err.expected(), "'}'");
+/// assert_eq!(err.found(), None);
+/// ```
 #[derive(Debug, PartialEq, Eq, Hash)]
 pub struct ParseError {
-    kind: ErrorKind,
+    kind: ParseErrorKind,
     data: String,
     split_point: usize,
 }
 
 impl ParseError {
-    pub(crate) fn new(parser: Parser<'_>, kind: ErrorKind) -> Self {
+    pub(crate) fn new(parser: Parser<'_>, kind: ParseErrorKind) -> Self {
         Self {
             kind,
             data: parser.data.to_string(),
             split_point: parser.split_point,
         }
     }
+
+    /// The kind of error that occurred.
+    pub fn kind(&self) -> &ParseErrorKind {
+        &self.kind
+    }
+
+    /// The byte offset into the input at which the error occurred.
+    pub fn offset(&self) -> usize {
+        self.split_point
+    }
+
+    /// The input that was being parsed.
+    pub fn input(&self) -> &str {
+        &self.data
+    }
+
+    /// The part of the input that was not parsed, starting at
+    /// [`offset`][Self::offset].
+    pub fn remaining(&self) -> &str {
+        &self.data[self.split_point..]
+    }
+
+    /// The character that was found at the offset, or `None` if the error
+    /// occurred at the end of the input.
+    pub fn found(&self) -> Option<char> {
+        self.remaining().chars().next()
+    }
+
+    /// A short human-readable description of what the parser expected to
+    /// find at the offset, e.g. `"']'"` or `"an integer"`.
+    ///
+    /// See also [`ParseErrorKind::expected`].
+    pub fn expected(&self) -> &'static str {
+        self.kind.expected()
+    }
 }
 
 impl fmt::Display for ParseError {
@@ -62,20 +115,59 @@ impl fmt::Display for ParseError {
 #[cfg(feature = "std")]
 impl std::error::Error for ParseError {}
 
-#[derive(Debug, PartialEq, Eq, Hash)]
-pub(crate) enum ErrorKind {
+/// The different kinds of errors that can occur while parsing an encoding.
+///
+/// Retrieved with [`ParseError::kind`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum ParseErrorKind {
+    /// The input ended where an encoding was expected.
     UnexpectedEnd,
+    /// The given byte is not the start of any known encoding.
     Unknown(u8),
+    /// The given byte after `j` is not the type of a complex number.
     UnknownAfterComplex(u8),
+    /// Expected an integer, e.g. the length of an array.
     ExpectedInteger,
+    /// The integer was too large to be represented.
     IntegerTooLarge,
+    /// An array was not closed with `]`.
     WrongEndArray,
+    /// A vector `!` was not followed by `[`.
+    WrongStartVector,
+    /// The size and alignment of a vector were not separated by `,`.
+    WrongSeparatorVector,
+    /// A vector was not closed with `]`.
+    WrongEndVector,
+    /// A struct or union was not closed.
     WrongEndContainer(ContainerKind),
+    /// A struct or union had an invalid name.
     InvalidIdentifier(ContainerKind),
+    /// There was remaining input after the encoding.
     NotAllConsumed,
 }
 
-impl fmt::Display for ErrorKind {
+impl ParseErrorKind {
+    /// A short human-readable description of what the parser expected to
+    /// find when this error occurred.
+    pub const fn expected(&self) -> &'static str {
+        match self {
+            Self::UnexpectedEnd | Self::Unknown(_) => "an encoding",
+            Self::UnknownAfterComplex(_) => "one of 'f', 'd' or 'D'",
+            Self::ExpectedInteger => "an integer",
+            Self::IntegerTooLarge => "a smaller integer",
+            Self::WrongEndArray | Self::WrongEndVector => "']'",
+            Self::WrongStartVector => "'['",
+            Self::WrongSeparatorVector => "','",
+            Self::WrongEndContainer(ContainerKind::Struct) => "'}'",
+            Self::WrongEndContainer(ContainerKind::Union) => "')'",
+            Self::InvalidIdentifier(_) => "an identifier",
+            Self::NotAllConsumed => "the end of the input",
+        }
+    }
+}
+
+impl fmt::Display for ParseErrorKind {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             Self::UnexpectedEnd => write!(f, "unexpected end"),
@@ -88,6 +180,11 @@ impl fmt::Display for ErrorKind {
             Self::ExpectedInteger => write!(f, "expected integer"),
             Self::IntegerTooLarge => write!(f, "integer too large"),
             Self::WrongEndArray => write!(f, "expected array to be closed"),
+            Self::WrongStartVector => write!(f, "expected vector to be opened"),
+            Self::WrongSeparatorVector => {
+                write!(f, "expected comma between vector size and alignment")
+            }
+            Self::WrongEndVector => write!(f, "expected vector to be closed"),
             Self::WrongEndContainer(kind) => {
                 write!(f, "expected {kind} to be closed")
             }
@@ -101,7 +198,7 @@ impl fmt::Display for ErrorKind {
     }
 }
 
-type Result<T, E = ErrorKind> = core::result::Result<T, E>;
+type Result<T, E = ParseErrorKind> = core::result::Result<T, E>;
 
 #[derive(Debug, PartialEq, Eq, Hash, Clone)]
 pub(crate) struct Parser<'a> {
@@ -123,7 +220,7 @@ impl<'a> Parser<'a> {
     }
 
     fn peek(&self) -> Result<u8> {
-        self.try_peek().ok_or(ErrorKind::UnexpectedEnd)
+        self.try_peek().ok_or(ParseErrorKind::UnexpectedEnd)
     }
 
     fn try_peek(&self) -> Option<u8> {
@@ -160,7 +257,7 @@ impl<'a> Parser<'a> {
         if self.is_empty() {
             Ok(())
         } else {
-            Err(ErrorKind::NotAllConsumed)
+            Err(ParseErrorKind::NotAllConsumed)
         }
     }
 }
@@ -168,19 +265,36 @@ impl<'a> Parser<'a> {
 impl Parser<'_> {
     /// Strip leading qualifiers, if any.
     pub(crate) fn strip_leading_qualifiers(&mut self) {
-        // TODO: Add API for accessing and outputting qualifiers.
-        const QUALIFIERS: &[u8] = &[
-            b'r', // const
-            b'n', // in
-            b'N', // inout
-            b'o', // out
-            b'O', // bycopy
-            b'R', // byref
-            b'V', // oneway
-        ];
-        // TODO: b'|', // GCINVISIBLE
+        self.consume_while(|b| Qualifier::from_code(b).is_some());
+    }
 
-        self.consume_while(|b| QUALIFIERS.contains(&b));
+    /// Parse leading qualifiers, if any.
+    pub(crate) fn parse_qualifiers(&mut self) -> Vec<Qualifier> {
+        let mut qualifiers = Vec::new();
+        while let Some(qualifier) = self.try_peek().and_then(Qualifier::from_code) {
+            self.advance();
+            qualifiers.push(qualifier);
+        }
+        qualifiers
+    }
+
+    /// Parse the stack offset that follows each type in a method encoding.
+    ///
+    /// Returns the offset, and whether it was prefixed with `+`.
+    pub(crate) fn parse_stack_offset(&mut self) -> Result<Option<(isize, bool)>> {
+        let register = self.expect_byte(b'+').is_some();
+        // Include the sign when parsing, to allow `isize::MIN`
+        let start = self.split_point;
+        let negative = !register && self.expect_byte(b'-').is_some();
+        if !register && !negative && !matches!(self.try_peek(), Some(b) if b.is_ascii_digit()) {
+            return Ok(None);
+        }
+        self.chomp_digits()?;
+        let offset = self.data[start..self.split_point].parse().map_err(|_| {
+            self.split_point = start;
+            ParseErrorKind::IntegerTooLarge
+        })?;
+        Ok(Some((offset, register)))
     }
 
     /// Chomp until we hit a non-digit.
@@ -190,8 +304,8 @@ impl Parser<'_> {
         let old_split_point = self.split_point;
 
         // Parse first digit (which must be present).
-        if !self.peek()?.is_ascii_digit() {
-            return Err(ErrorKind::ExpectedInteger);
+        if !self.try_peek().map_or(false, |b| b.is_ascii_digit()) {
+            return Err(ParseErrorKind::ExpectedInteger);
         }
 
         // Parse the rest, stopping if we hit a non-digit.
@@ -200,16 +314,22 @@ impl Parser<'_> {
         Ok(&self.data[old_split_point..self.split_point])
     }
 
+    fn parse_int<T: FromStr>(&mut self) -> Result<T> {
+        let old_split_point = self.split_point;
+        let res = self.chomp_digits()?.parse();
+        res.map_err(|_| {
+            // Report the error at the start of the integer
+            self.split_point = old_split_point;
+            ParseErrorKind::IntegerTooLarge
+        })
+    }
+
     fn parse_u64(&mut self) -> Result<u64> {
-        self.chomp_digits()?
-            .parse()
-            .map_err(|_| ErrorKind::IntegerTooLarge)
+        self.parse_int()
     }
 
     fn parse_u8(&mut self) -> Result<u8> {
-        self.chomp_digits()?
-            .parse()
-            .map_err(|_| ErrorKind::IntegerTooLarge)
+        self.parse_int()
     }
 }
 
@@ -250,6 +370,9 @@ impl Parser<'_> {
     }
 
     pub(crate) fn expect_encoding(&mut self, enc: &Encoding, level: NestingLevel) -> Option<()> {
+        // Qualifiers are ignored when checking equivalence
+        self.strip_leading_qualifiers();
+
         let helper = Helper::new(enc, level);
         match helper {
             Helper::Primitive(primitive) => self.expect_str(primitive.to_str()),
@@ -273,6 +396,15 @@ impl Parser<'_> {
                 self.expect_encoding(item, level)?;
                 self.expect_byte(b']')
             }
+            Helper::Vector(size, align, item, level) => {
+                self.expect_str("![")?;
+                self.expect_u64(size)?;
+                self.expect_byte(b',')?;
+                self.expect_u64(align)?;
+                self.expect_encoding(item, level)?;
+                self.expect_byte(b']')
+            }
+            Helper::Qualified(_, t, level) => self.expect_encoding(t, level),
             Helper::Container(kind, name, items, level) => {
                 self.expect_byte(kind.start_byte())?;
                 self.expect_str(name)?;
@@ -288,13 +420,18 @@ impl Parser<'_> {
     }
 }
 
-impl Parser<'_> {
-    fn parse_container(&mut self, kind: ContainerKind) -> Result<(&str, Option<Vec<EncodingBox>>)> {
+impl<'a> Parser<'a> {
+    /// Parse the name of a struct or union, and the following `=` or end.
+    ///
+    /// Returns the name, and whether the container has items.
+    fn parse_container_name(&mut self, kind: ContainerKind) -> Result<(&'a str, bool)> {
         let old_split_point = self.split_point;
 
         // Parse name until hits `=`
         let has_items = loop {
-            let b = self.try_peek().ok_or(ErrorKind::WrongEndContainer(kind))?;
+            let b = self
+                .try_peek()
+                .ok_or(ParseErrorKind::WrongEndContainer(kind))?;
             if b == b'=' {
                 break true;
             } else if b == kind.end_byte() {
@@ -306,16 +443,29 @@ impl Parser<'_> {
         let s = &self.data[old_split_point..self.split_point];
 
         if !verify_name(s) {
-            return Err(ErrorKind::InvalidIdentifier(kind));
+            // Report the error at the start of the name
+            self.split_point = old_split_point;
+            return Err(ParseErrorKind::InvalidIdentifier(kind));
         }
 
         self.advance();
 
+        Ok((s, has_items))
+    }
+
+    fn parse_container(
+        &mut self,
+        kind: ContainerKind,
+    ) -> Result<(&'a str, Option<Vec<EncodingBox>>)> {
+        let (s, has_items) = self.parse_container_name(kind)?;
+
         if has_items {
             let mut items = Vec::new();
             // Parse items until hits end
             loop {
-                let b = self.try_peek().ok_or(ErrorKind::WrongEndContainer(kind))?;
+                let b = self
+                    .try_peek()
+                    .ok_or(ParseErrorKind::WrongEndContainer(kind))?;
                 if b == kind.end_byte() {
                     self.advance();
                     break;
@@ -330,9 +480,103 @@ impl Parser<'_> {
         }
     }
 
+    /// Parse an encoding, and if an error occurs, return the part of the
+    /// encoding that could be parsed along with the error.
+    ///
+    /// Structs and unions retain the items that were parsed before the
+    /// error, while other encodings are only retained if their contents
+    /// could be (partially) parsed.
+    pub(crate) fn parse_encoding_partial(&mut self) -> (Option<EncodingBox>, Result<()>) {
+        let b = match self.try_peek() {
+            Some(b) => b,
+            None => return (None, Err(ParseErrorKind::UnexpectedEnd)),
+        };
+
+        match b {
+            b'^' | b'A' => {
+                self.advance();
+                let (t, res) = self.parse_encoding_partial();
+                let t = t.map(Box::new).map(if b == b'^' {
+                    EncodingBox::Pointer
+                } else {
+                    EncodingBox::Atomic
+                });
+                (t, res)
+            }
+            b'[' => {
+                self.advance();
+                let len = match self.parse_u64() {
+                    Ok(len) => len,
+                    Err(err) => return (None, Err(err)),
+                };
+                let (item, res) = self.parse_encoding_partial();
+                let enc = item.map(|item| EncodingBox::Array(len, Box::new(item)));
+                if res.is_err() {
+                    return (enc, res);
+                }
+                let res = self.expect_byte(b']').ok_or(ParseErrorKind::WrongEndArray);
+                (enc, res)
+            }
+            b'{' | b'(' => {
+                self.advance();
+                let kind = if b == b'{' {
+                    ContainerKind::Struct
+                } else {
+                    ContainerKind::Union
+                };
+                let (name, has_items) = match self.parse_container_name(kind) {
+                    Ok(res) => res,
+                    Err(err) => return (None, Err(err)),
+                };
+                let mut items = Vec::new();
+                let res = if has_items {
+                    loop {
+                        match self.try_peek() {
+                            None => break Err(ParseErrorKind::WrongEndContainer(kind)),
+                            Some(b) if b == kind.end_byte() => {
+                                self.advance();
+                                break Ok(());
+                            }
+                            Some(_) => {
+                                let (item, res) = self.parse_encoding_partial();
+                                items.extend(item);
+                                if res.is_err() {
+                                    break res;
+                                }
+                            }
+                        }
+                    }
+                } else {
+                    Ok(())
+                };
+                let name = name.to_string();
+                let items = if has_items { Some(items) } else { None };
+                let enc = match kind {
+                    ContainerKind::Struct => EncodingBox::Struct(name, items),
+                    ContainerKind::Union => EncodingBox::Union(name, items),
+                };
+                (Some(enc), res)
+            }
+            b => {
+                if let Some(qualifier) = Qualifier::from_code(b) {
+                    self.advance();
+                    let (t, res) = self.parse_encoding_partial();
+                    let t = t.map(|t| EncodingBox::Qualified(qualifier, Box::new(t)));
+                    return (t, res);
+                }
+                match self.parse_encoding() {
+                    Ok(enc) => (Some(enc), Ok(())),
+                    Err(err) => (None, Err(err)),
+                }
+            }
+        }
+    }
+}
+
+impl Parser<'_> {
     pub(crate) fn parse_encoding(&mut self) -> Result<EncodingBox> {
         self.try_parse_encoding()
-            .and_then(|res| res.ok_or(ErrorKind::UnexpectedEnd))
+            .and_then(|res| res.ok_or(ParseErrorKind::UnexpectedEnd))
     }
 
     fn try_parse_encoding(&mut self) -> Result<Option<EncodingBox>> {
@@ -351,11 +595,13 @@ impl Parser<'_> {
             b'i' => EncodingBox::Int,
             b'l' => EncodingBox::Long,
             b'q' => EncodingBox::LongLong,
+            b't' => EncodingBox::Int128,
             b'C' => EncodingBox::UChar,
             b'S' => EncodingBox::UShort,
             b'I' => EncodingBox::UInt,
             b'L' => EncodingBox::ULong,
             b'Q' => EncodingBox::ULongLong,
+            b'T' => EncodingBox::UInt128,
             b'f' => EncodingBox::Float,
             b'd' => EncodingBox::Double,
             b'D' => EncodingBox::LongDouble,
@@ -364,7 +610,7 @@ impl Parser<'_> {
                     b'f' => EncodingBox::FloatComplex,
                     b'd' => EncodingBox::DoubleComplex,
                     b'D' => EncodingBox::LongDoubleComplex,
-                    b => return Err(ErrorKind::UnknownAfterComplex(b)),
+                    b => return Err(ParseErrorKind::UnknownAfterComplex(b)),
                 };
                 self.advance();
                 res
@@ -392,7 +638,7 @@ impl Parser<'_> {
                 } else {
                     let size = size_or_offset
                         .try_into()
-                        .map_err(|_| ErrorKind::IntegerTooLarge)?;
+                        .map_err(|_| ParseErrorKind::IntegerTooLarge)?;
                     EncodingBox::BitField(size, None)
                 }
             }
@@ -401,9 +647,22 @@ impl Parser<'_> {
             b'[' => {
                 let len = self.parse_u64()?;
                 let item = self.parse_encoding()?;
-                self.expect_byte(b']').ok_or(ErrorKind::WrongEndArray)?;
+                self.expect_byte(b']')
+                    .ok_or(ParseErrorKind::WrongEndArray)?;
                 EncodingBox::Array(len, Box::new(item))
             }
+            b'!' => {
+                self.expect_byte(b'[')
+                    .ok_or(ParseErrorKind::WrongStartVector)?;
+                let size = self.parse_u64()?;
+                self.expect_byte(b',')
+                    .ok_or(ParseErrorKind::WrongSeparatorVector)?;
+                let align = self.parse_u64()?;
+                let item = self.parse_encoding()?;
+                self.expect_byte(b']')
+                    .ok_or(ParseErrorKind::WrongEndVector)?;
+                EncodingBox::Vector(size, align, Box::new(item))
+            }
             b'{' => {
                 let kind = ContainerKind::Struct;
                 let (name, items) = self.parse_container(kind)?;
@@ -414,7 +673,16 @@ impl Parser<'_> {
                 let (name, items) = self.parse_container(kind)?;
                 EncodingBox::Union(name.to_string(), items)
             }
-            b => return Err(ErrorKind::Unknown(b)),
+            b => match Qualifier::from_code(b) {
+                Some(qualifier) => {
+                    EncodingBox::Qualified(qualifier, Box::new(self.parse_encoding()?))
+                }
+                None => {
+                    // Report the error at the unknown byte
+                    self.split_point -= 1;
+                    return Err(ParseErrorKind::Unknown(b));
+                }
+            },
         })
     }
 
@@ -475,12 +743,12 @@ mod tests {
         );
         assert_name("_=}.a'", Ok(("_", Some(vec![]))));
         assert_name("abc}def", Ok(("abc", None)));
-        assert_name("=def}", Err(ErrorKind::InvalidIdentifier(KIND)));
-        assert_name(".=def}", Err(ErrorKind::InvalidIdentifier(KIND)));
-        assert_name("}xyz", Err(ErrorKind::InvalidIdentifier(KIND)));
-        assert_name("", Err(ErrorKind::WrongEndContainer(KIND)));
-        assert_name("abc", Err(ErrorKind::WrongEndContainer(KIND)));
-        assert_name("abc)def", Err(ErrorKind::WrongEndContainer(KIND)));
+        assert_name("=def}", Err(ParseErrorKind::InvalidIdentifier(KIND)));
+        assert_name(".=def}", Err(ParseErrorKind::InvalidIdentifier(KIND)));
+        assert_name("}xyz", Err(ParseErrorKind::InvalidIdentifier(KIND)));
+        assert_name("", Err(ParseErrorKind::WrongEndContainer(KIND)));
+        assert_name("abc", Err(ParseErrorKind::WrongEndContainer(KIND)));
+        assert_name("abc)def", Err(ParseErrorKind::WrongEndContainer(KIND)));
     }
 
     #[test]
@@ -497,7 +765,7 @@ mod tests {
         }
 
         assert_bitfield("b8", Ok(EncodingBox::BitField(8, None)));
-        assert_bitfield("b8C", Err(ErrorKind::NotAllConsumed));
+        assert_bitfield("b8C", Err(ParseErrorKind::NotAllConsumed));
         assert_bitfield(
             "b8C4",
             Ok(EncodingBox::BitField(
@@ -514,7 +782,7 @@ mod tests {
             )),
         );
 
-        assert_bitfield("b2000", Err(ErrorKind::IntegerTooLarge));
+        assert_bitfield("b2000", Err(ParseErrorKind::IntegerTooLarge));
         assert_bitfield(
             "b2000c100",
             Ok(EncodingBox::BitField(
@@ -522,6 +790,6 @@ mod tests {
                 Some(Box::new((2000, EncodingBox::Char))),
             )),
         );
-        assert_bitfield("b2000C257", Err(ErrorKind::IntegerTooLarge));
+        assert_bitfield("b2000C257", Err(ParseErrorKind::IntegerTooLarge));
     }
 }
@@ -0,0 +1,70 @@
+use core::fmt;
+
+/// A type qualifier, as may appear in front of a type-encoding.
+///
+/// These are emitted by compilers for method parameters and return types
+/// declared with e.g. `const` or the Distributed Objects keywords `in`,
+/// `out`, `inout`, `bycopy`, `byref` and `oneway`.
+///
+/// The [`Display`][`fmt::Display`] implementation outputs the qualifier's
+/// code.
+///
+/// ```
+/// use objc2_encode::Qualifier;
+/// assert_eq!(Qualifier::Const.to_string(), "r");
+/// assert_eq!(Qualifier::from_code(b'V'), Some(Qualifier::Oneway));
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Qualifier {
+    /// `const`. Corresponds to the `"r"` code.
+    Const,
+    /// `in`. Corresponds to the `"n"` code.
+    In,
+    /// `inout`. Corresponds to the `"N"` code.
+    Inout,
+    /// `out`. Corresponds to the `"o"` code.
+    Out,
+    /// `bycopy`. Corresponds to the `"O"` code.
+    Bycopy,
+    /// `byref`. Corresponds to the `"R"` code.
+    Byref,
+    /// `oneway`. Corresponds to the `"V"` code.
+    Oneway,
+}
+
+impl Qualifier {
+    /// Get the qualifier corresponding to the given code, if any.
+    pub const fn from_code(code: u8) -> Option<Self> {
+        match code {
+            b'r' => Some(Self::Const),
+            b'n' => Some(Self::In),
+            b'N' => Some(Self::Inout),
+            b'o' => Some(Self::Out),
+            b'O' => Some(Self::Bycopy),
+            b'R' => Some(Self::Byref),
+            b'V' => Some(Self::Oneway),
+            // TODO: b'|', // GCINVISIBLE
+            _ => None,
+        }
+    }
+
+    /// The code for this qualifier.
+    pub const fn code(self) -> u8 {
+        match self {
+            Self::Const => b'r',
+            Self::In => b'n',
+            Self::Inout => b'N',
+            Self::Out => b'o',
+            Self::Bycopy => b'O',
+            Self::Byref => b'R',
+            Self::Oneway => b'V',
+        }
+    }
+}
+
+impl fmt::Display for Qualifier {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.code() as char)
+    }
+}
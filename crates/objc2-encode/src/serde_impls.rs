@@ -0,0 +1,161 @@
+//! `serde` implementations that use the string representation of encodings.
+use core::fmt;
+use core::marker::PhantomData;
+use core::str::FromStr;
+
+use serde::de::{self, Deserialize, Deserializer, Visitor};
+use serde::ser::{Serialize, Serializer};
+
+use crate::{Encoding, EncodingBox, MethodEncoding, Qualifier};
+
+struct FromStrVisitor<T> {
+    expecting: &'static str,
+    p: PhantomData<T>,
+}
+
+impl<T> FromStrVisitor<T> {
+    fn new(expecting: &'static str) -> Self {
+        Self {
+            expecting,
+            p: PhantomData,
+        }
+    }
+}
+
+impl<'de, T: FromStr> Visitor<'de> for FromStrVisitor<T>
+where
+    T::Err: fmt::Display,
+{
+    type Value = T;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.expecting)
+    }
+
+    fn visit_str<E: de::Error>(self, s: &str) -> Result<T, E> {
+        s.parse().map_err(E::custom)
+    }
+}
+
+/// Serializes as the encoding string, e.g. `"{CGPoint=dd}"`.
+impl Serialize for Encoding {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+/// Serializes as the encoding string, e.g. `"{CGPoint=dd}"`.
+///
+/// Use the [`serde_tree`][crate::serde_tree] module to serialize as a tree of
+/// tagged variants instead.
+impl Serialize for EncodingBox {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+/// Deserializes from an encoding string, e.g. `"{CGPoint=dd}"`.
+impl<'de> Deserialize<'de> for EncodingBox {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_str(FromStrVisitor::new("an encoding string"))
+    }
+}
+
+/// Serializes as the method encoding string, e.g. `"v24@0:8@16"`.
+impl Serialize for MethodEncoding {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+/// Deserializes from a method encoding string, e.g. `"v24@0:8@16"`.
+impl<'de> Deserialize<'de> for MethodEncoding {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_str(FromStrVisitor::new("a method encoding string"))
+    }
+}
+
+struct QualifierVisitor;
+
+impl<'de> Visitor<'de> for QualifierVisitor {
+    type Value = Qualifier;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("a qualifier code")
+    }
+
+    fn visit_str<E: de::Error>(self, s: &str) -> Result<Qualifier, E> {
+        match s.as_bytes() {
+            [code] => Qualifier::from_code(*code),
+            _ => None,
+        }
+        .ok_or_else(|| E::invalid_value(de::Unexpected::Str(s), &self))
+    }
+}
+
+/// Serializes as the qualifier's code, e.g. `"r"`.
+impl Serialize for Qualifier {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+/// Deserializes from the qualifier's code, e.g. `"r"`.
+impl<'de> Deserialize<'de> for Qualifier {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_str(QualifierVisitor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::string::ToString;
+    use alloc::vec;
+
+    use super::*;
+
+    #[test]
+    fn encoding_string() {
+        let enc = Encoding::Struct("CGPoint", &[Encoding::Double, Encoding::Double]);
+        assert_eq!(serde_json::to_string(&enc).unwrap(), r#""{CGPoint=dd}""#);
+
+        let enc_box: EncodingBox = serde_json::from_str(r#""{CGPoint=dd}""#).unwrap();
+        assert!(enc.equivalent_to_box(&enc_box));
+        assert_eq!(
+            serde_json::to_string(&enc_box).unwrap(),
+            r#""{CGPoint=dd}""#
+        );
+    }
+
+    #[test]
+    fn method_encoding_string() {
+        let method: MethodEncoding = "Vv24@0:8r*16".parse().unwrap();
+        let s = serde_json::to_string(&method).unwrap();
+        assert_eq!(s, r#""Vv24@0:8r*16""#);
+        assert_eq!(serde_json::from_str::<MethodEncoding>(&s).unwrap(), method);
+    }
+
+    #[test]
+    fn qualifiers() {
+        let s = serde_json::to_string(&vec![Qualifier::Const, Qualifier::Oneway]).unwrap();
+        assert_eq!(s, r#"["r","V"]"#);
+        assert_eq!(
+            serde_json::from_str::<Qualifier>(r#""N""#).unwrap(),
+            Qualifier::Inout
+        );
+    }
+
+    #[test]
+    fn invalid() {
+        let err = serde_json::from_str::<EncodingBox>(r#""{CGPoint=dd""#).unwrap_err();
+        assert!(err.to_string().contains("CGPoint"), "{err}");
+
+        let err = serde_json::from_str::<Qualifier>(r#""rr""#).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "invalid value: string \"rr\", expected a qualifier code at line 1 column 4"
+        );
+
+        assert!(serde_json::from_str::<EncodingBox>("1").is_err());
+    }
+}
@@ -0,0 +1,515 @@
+//! Serialize encodings as a tree of tagged variants.
+//!
+//! By default, [`EncodingBox`] and [`MethodEncoding`] are serialized as
+//! their string representation. This module can be used with serde's
+//! `#[serde(with = "...")]` attribute to instead serialize them as a tree,
+//! where each encoding is a map with a `"kind"` key containing the name of
+//! the variant, and the contents of the variant in separate keys.
+//!
+//! This is useful when the output is to be read by humans or other tools
+//! that do not understand type-encodings, e.g. when diffing metadata dumped
+//! from the runtime.
+//!
+//!
+//! # Examples
+//!
+//! ```
+//! use objc2_encode::{EncodingBox, MethodEncoding};
+//! use serde::{Deserialize, Serialize};
+//!
+//! #[derive(Serialize, Deserialize, PartialEq, Debug)]
+//! struct Ivar {
+//!     name: String,
+//!     #[serde(with = "objc2_encode::serde_tree")]
+//!     encoding: EncodingBox,
+//! }
+//!
+//! let ivar = Ivar {
+//!     name: "_origin".into(),
+//!     encoding: "{CGPoint=dd}".parse()?,
+//! };
+//!
+//! let json = serde_json::to_value(&ivar).unwrap();
+//! assert_eq!(json, serde_json::json!({
+//!     "name": "_origin",
+//!     "encoding": {
+//!         "kind": "Struct",
+//!         "name": "CGPoint",
+//!         "fields": [{ "kind": "Double" }, { "kind": "Double" }],
+//!     },
+//! }));
+//!
+//! assert_eq!(serde_json::from_value::<Ivar>(json).unwrap(), ivar);
+//! # Ok::<(), objc2_encode::ParseError>(())
+//! ```
+use alloc::boxed::Box;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use serde::de::{self, Deserialize, DeserializeOwned, Deserializer};
+use serde::ser::{Serialize, Serializer};
+
+use crate::{EncodingBox, MethodEncoding, MethodEncodingItem, Qualifier};
+
+/// Types that can be serialized as a tree with this module.
+///
+/// This is implemented for [`EncodingBox`] and [`MethodEncoding`], and is
+/// sealed.
+pub trait TreeRepr: private::Sealed {}
+
+impl TreeRepr for EncodingBox {}
+impl TreeRepr for MethodEncoding {}
+
+/// Serialize the value as a tree.
+///
+/// See the [module-level documentation](self) for details.
+///
+///
+/// # Errors
+///
+/// Returns any error from the serializer.
+pub fn serialize<T, S>(value: &T, serializer: S) -> Result<S::Ok, S::Error>
+where
+    T: TreeRepr,
+    S: Serializer,
+{
+    value.to_tree().serialize(serializer)
+}
+
+/// Deserialize a value from a tree.
+///
+/// See the [module-level documentation](self) for details.
+///
+///
+/// # Errors
+///
+/// Returns an error if the input was not a valid tree, or if the resulting
+/// encoding was invalid (e.g. a struct with a name that is not an
+/// identifier).
+pub fn deserialize<'de, T, D>(deserializer: D) -> Result<T, D::Error>
+where
+    T: TreeRepr,
+    D: Deserializer<'de>,
+{
+    let tree = T::Tree::deserialize(deserializer)?;
+    T::from_tree(tree).map_err(de::Error::custom)
+}
+
+mod private {
+    use super::*;
+    use serde::{Deserialize, Serialize};
+
+    use crate::parse::verify_name;
+
+    pub trait Sealed: Sized {
+        type Tree: Serialize + DeserializeOwned;
+
+        fn to_tree(&self) -> Self::Tree;
+
+        fn from_tree(tree: Self::Tree) -> Result<Self, &'static str>;
+    }
+
+    impl Sealed for EncodingBox {
+        type Tree = Tree;
+
+        fn to_tree(&self) -> Tree {
+            Tree::from_encoding(self)
+        }
+
+        fn from_tree(tree: Tree) -> Result<Self, &'static str> {
+            tree.into_encoding()
+        }
+    }
+
+    impl Sealed for MethodEncoding {
+        type Tree = MethodTree;
+
+        fn to_tree(&self) -> MethodTree {
+            MethodTree {
+                return_type: ItemTree::from_item(&self.return_type),
+                arguments: self.arguments.iter().map(ItemTree::from_item).collect(),
+            }
+        }
+
+        fn from_tree(tree: MethodTree) -> Result<Self, &'static str> {
+            Ok(Self {
+                return_type: tree.return_type.into_item()?,
+                arguments: tree
+                    .arguments
+                    .into_iter()
+                    .map(ItemTree::into_item)
+                    .collect::<Result<_, _>>()?,
+            })
+        }
+    }
+
+    /// The tree representation of an [`EncodingBox`].
+    #[derive(Debug, Serialize, Deserialize)]
+    #[serde(tag = "kind")]
+    pub enum Tree {
+        Char,
+        Short,
+        Int,
+        Long,
+        LongLong,
+        Int128,
+        UChar,
+        UShort,
+        UInt,
+        ULong,
+        ULongLong,
+        UInt128,
+        Float,
+        Double,
+        LongDouble,
+        FloatComplex,
+        DoubleComplex,
+        LongDoubleComplex,
+        Bool,
+        Void,
+        String,
+        Object,
+        Block,
+        Class,
+        Sel,
+        Unknown,
+        BitField {
+            size: u8,
+            #[serde(default, skip_serializing_if = "Option::is_none")]
+            offset: Option<u64>,
+            #[serde(default, skip_serializing_if = "Option::is_none")]
+            item: Option<Box<Tree>>,
+        },
+        Pointer {
+            item: Box<Tree>,
+        },
+        Atomic {
+            item: Box<Tree>,
+        },
+        Array {
+            len: u64,
+            item: Box<Tree>,
+        },
+        Struct {
+            name: String,
+            #[serde(default, skip_serializing_if = "Option::is_none")]
+            fields: Option<Vec<Tree>>,
+        },
+        Union {
+            name: String,
+            #[serde(default, skip_serializing_if = "Option::is_none")]
+            fields: Option<Vec<Tree>>,
+        },
+        Vector {
+            size: u64,
+            align: u64,
+            item: Box<Tree>,
+        },
+        Qualified {
+            qualifier: Qualifier,
+            item: Box<Tree>,
+        },
+    }
+
+    impl Tree {
+        fn from_encoding(encoding: &EncodingBox) -> Self {
+            let boxed = |encoding| Box::new(Self::from_encoding(encoding));
+            let fields = |fields: &Option<Vec<EncodingBox>>| {
+                fields
+                    .as_ref()
+                    .map(|fields| fields.iter().map(Self::from_encoding).collect())
+            };
+
+            match encoding {
+                EncodingBox::Char => Self::Char,
+                EncodingBox::Short => Self::Short,
+                EncodingBox::Int => Self::Int,
+                EncodingBox::Long => Self::Long,
+                EncodingBox::LongLong => Self::LongLong,
+                EncodingBox::Int128 => Self::Int128,
+                EncodingBox::UChar => Self::UChar,
+                EncodingBox::UShort => Self::UShort,
+                EncodingBox::UInt => Self::UInt,
+                EncodingBox::ULong => Self::ULong,
+                EncodingBox::ULongLong => Self::ULongLong,
+                EncodingBox::UInt128 => Self::UInt128,
+                EncodingBox::Float => Self::Float,
+                EncodingBox::Double => Self::Double,
+                EncodingBox::LongDouble => Self::LongDouble,
+                EncodingBox::FloatComplex => Self::FloatComplex,
+                EncodingBox::DoubleComplex => Self::DoubleComplex,
+                EncodingBox::LongDoubleComplex => Self::LongDoubleComplex,
+                EncodingBox::Bool => Self::Bool,
+                EncodingBox::Void => Self::Void,
+                EncodingBox::String => Self::String,
+                EncodingBox::Object => Self::Object,
+                EncodingBox::Block => Self::Block,
+                EncodingBox::Class => Self::Class,
+                EncodingBox::Sel => Self::Sel,
+                EncodingBox::Unknown => Self::Unknown,
+                EncodingBox::BitField(size, None) => Self::BitField {
+                    size: *size,
+                    offset: None,
+                    item: None,
+                },
+                EncodingBox::BitField(size, Some(inner)) => Self::BitField {
+                    size: *size,
+                    offset: Some(inner.0),
+                    item: Some(boxed(&inner.1)),
+                },
+                EncodingBox::Pointer(item) => Self::Pointer { item: boxed(item) },
+                EncodingBox::Atomic(item) => Self::Atomic { item: boxed(item) },
+                EncodingBox::Array(len, item) => Self::Array {
+                    len: *len,
+                    item: boxed(item),
+                },
+                EncodingBox::Struct(name, f) => Self::Struct {
+                    name: name.clone(),
+                    fields: fields(f),
+                },
+                EncodingBox::Union(name, f) => Self::Union {
+                    name: name.clone(),
+                    fields: fields(f),
+                },
+                EncodingBox::Vector(size, align, item) => Self::Vector {
+                    size: *size,
+                    align: *align,
+                    item: boxed(item),
+                },
+                EncodingBox::Qualified(qualifier, item) => Self::Qualified {
+                    qualifier: *qualifier,
+                    item: boxed(item),
+                },
+            }
+        }
+
+        fn into_encoding(self) -> Result<EncodingBox, &'static str> {
+            let boxed = |tree: Box<Self>| tree.into_encoding().map(Box::new);
+            let fields = |fields: Option<Vec<Self>>| {
+                fields
+                    .map(|fields| fields.into_iter().map(Self::into_encoding).collect())
+                    .transpose()
+            };
+            let name = |name: String| {
+                if verify_name(&name) {
+                    Ok(name)
+                } else {
+                    Err("struct or union name was not a valid identifier")
+                }
+            };
+
+            Ok(match self {
+                Self::Char => EncodingBox::Char,
+                Self::Short => EncodingBox::Short,
+                Self::Int => EncodingBox::Int,
+                Self::Long => EncodingBox::Long,
+                Self::LongLong => EncodingBox::LongLong,
+                Self::Int128 => EncodingBox::Int128,
+                Self::UChar => EncodingBox::UChar,
+                Self::UShort => EncodingBox::UShort,
+                Self::UInt => EncodingBox::UInt,
+                Self::ULong => EncodingBox::ULong,
+                Self::ULongLong => EncodingBox::ULongLong,
+                Self::UInt128 => EncodingBox::UInt128,
+                Self::Float => EncodingBox::Float,
+                Self::Double => EncodingBox::Double,
+                Self::LongDouble => EncodingBox::LongDouble,
+                Self::FloatComplex => EncodingBox::FloatComplex,
+                Self::DoubleComplex => EncodingBox::DoubleComplex,
+                Self::LongDoubleComplex => EncodingBox::LongDoubleComplex,
+                Self::Bool => EncodingBox::Bool,
+                Self::Void => EncodingBox::Void,
+                Self::String => EncodingBox::String,
+                Self::Object => EncodingBox::Object,
+                Self::Block => EncodingBox::Block,
+                Self::Class => EncodingBox::Class,
+                Self::Sel => EncodingBox::Sel,
+                Self::Unknown => EncodingBox::Unknown,
+                Self::BitField {
+                    size,
+                    offset: None,
+                    item: None,
+                } => EncodingBox::BitField(size, None),
+                Self::BitField {
+                    size,
+                    offset: Some(offset),
+                    item: Some(item),
+                } => EncodingBox::BitField(size, Some(Box::new((offset, item.into_encoding()?)))),
+                Self::BitField { .. } => {
+                    return Err("bitfield must have either both or neither of offset and item")
+                }
+                Self::Pointer { item } => EncodingBox::Pointer(boxed(item)?),
+                Self::Atomic { item } => EncodingBox::Atomic(boxed(item)?),
+                Self::Array { len, item } => EncodingBox::Array(len, boxed(item)?),
+                Self::Struct { name: n, fields: f } => EncodingBox::Struct(name(n)?, fields(f)?),
+                Self::Union { name: n, fields: f } => EncodingBox::Union(name(n)?, fields(f)?),
+                Self::Vector { size, align, item } => {
+                    EncodingBox::Vector(size, align, boxed(item)?)
+                }
+                Self::Qualified { qualifier, item } => {
+                    EncodingBox::Qualified(qualifier, boxed(item)?)
+                }
+            })
+        }
+    }
+
+    /// The tree representation of a [`MethodEncoding`].
+    #[derive(Debug, Serialize, Deserialize)]
+    pub struct MethodTree {
+        return_type: ItemTree,
+        arguments: Vec<ItemTree>,
+    }
+
+    /// The tree representation of a [`MethodEncodingItem`].
+    #[derive(Debug, Serialize, Deserialize)]
+    struct ItemTree {
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        qualifiers: Vec<Qualifier>,
+        encoding: Tree,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        offset: Option<isize>,
+        #[serde(default, skip_serializing_if = "is_false")]
+        register: bool,
+    }
+
+    #[allow(clippy::trivially_copy_pass_by_ref)]
+    fn is_false(b: &bool) -> bool {
+        !*b
+    }
+
+    impl ItemTree {
+        fn from_item(item: &MethodEncodingItem) -> Self {
+            Self {
+                qualifiers: item.qualifiers.clone(),
+                encoding: Tree::from_encoding(&item.encoding),
+                offset: item.offset,
+                register: item.register,
+            }
+        }
+
+        fn into_item(self) -> Result<MethodEncodingItem, &'static str> {
+            Ok(MethodEncodingItem {
+                qualifiers: self.qualifiers,
+                encoding: self.encoding.into_encoding()?,
+                offset: self.offset,
+                register: self.register,
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::string::ToString;
+    use serde_json::{json, Value};
+
+    use super::*;
+
+    fn to_tree<T: TreeRepr>(value: &T) -> Value {
+        serialize(value, serde_json::value::Serializer).unwrap()
+    }
+
+    fn from_tree<T: TreeRepr>(value: Value) -> Result<T, serde_json::Error> {
+        deserialize(value)
+    }
+
+    #[test]
+    fn roundtrip() {
+        let encodings = [
+            "i",
+            "^{CGPoint=dd}",
+            "{CGPoint}",
+            "(Union=iQ)",
+            "[4^*]",
+            "A^v",
+            "b4",
+            "b8I4",
+            "![16,16f]",
+            "r^@",
+            "{Nested={Inner=B}[2{Inner}]}",
+        ];
+        for s in encodings {
+            let enc: EncodingBox = s.parse().unwrap();
+            let tree = to_tree(&enc);
+            assert_eq!(from_tree::<EncodingBox>(tree).unwrap(), enc, "{s}");
+        }
+    }
+
+    #[test]
+    fn tree_shape() {
+        let enc: EncodingBox = "^[2b8I4]".parse().unwrap();
+        assert_eq!(
+            to_tree(&enc),
+            json!({
+                "kind": "Pointer",
+                "item": {
+                    "kind": "Array",
+                    "len": 2,
+                    "item": {
+                        "kind": "BitField",
+                        "size": 4,
+                        "offset": 8,
+                        "item": { "kind": "UInt" },
+                    },
+                },
+            })
+        );
+
+        let enc: EncodingBox = "rb3".parse().unwrap();
+        assert_eq!(
+            to_tree(&enc),
+            json!({
+                "kind": "Qualified",
+                "qualifier": "r",
+                "item": { "kind": "BitField", "size": 3 },
+            })
+        );
+    }
+
+    #[test]
+    fn method() {
+        let method: MethodEncoding = "Vv24@0:8r*+16".parse().unwrap();
+        let tree = to_tree(&method);
+        let expected: Value = serde_json::from_str(
+            r#"{
+                "return_type": {
+                    "qualifiers": ["V"],
+                    "encoding": { "kind": "Void" },
+                    "offset": 24
+                },
+                "arguments": [
+                    { "encoding": { "kind": "Object" }, "offset": 0 },
+                    { "encoding": { "kind": "Sel" }, "offset": 8 },
+                    {
+                        "qualifiers": ["r"],
+                        "encoding": { "kind": "String" },
+                        "offset": 16,
+                        "register": true
+                    }
+                ]
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(tree, expected);
+        assert_eq!(from_tree::<MethodEncoding>(tree).unwrap(), method);
+    }
+
+    #[test]
+    fn invalid() {
+        let err = from_tree::<EncodingBox>(json!({ "kind": "Struct", "name": "a b" })).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "struct or union name was not a valid identifier"
+        );
+
+        let err = from_tree::<EncodingBox>(json!({ "kind": "BitField", "size": 2, "offset": 0 }))
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "bitfield must have either both or neither of offset and item"
+        );
+
+        assert!(from_tree::<EncodingBox>(json!({ "kind": "Foo" })).is_err());
+        assert!(from_tree::<EncodingBox>(json!("i")).is_err());
+    }
+}
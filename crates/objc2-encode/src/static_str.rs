@@ -32,7 +32,6 @@ pub(crate) const fn static_int_str_array<const RES: usize>(mut n: u64) -> [u8; R
     while 0 < i {
         i -= 1;
         rev[rev_i] = res[i];
-        n /= 10;
         rev_i += 1;
     }
     rev
@@ -53,6 +52,14 @@ pub(crate) const fn static_encoding_str_len(encoding: &Encoding, level: NestingL
         Array(len, item, level) => {
             1 + static_int_str_len(len) + static_encoding_str_len(item, level) + 1
         }
+        Vector(size, align, item, level) => {
+            2 + static_int_str_len(size)
+                + 1
+                + static_int_str_len(align)
+                + static_encoding_str_len(item, level)
+                + 1
+        }
+        Qualified(_qualifier, t, level) => 1 + static_encoding_str_len(t, level),
         Container(_, name, items, level) => {
             let mut res = 1 + name.len();
             if let Some(items) = items {
@@ -170,6 +177,59 @@ pub(crate) const fn static_encoding_str_array<const LEN: usize>(
 
             res[res_i] = b']';
         }
+        Vector(size, align, item, level) => {
+            let mut res_i = 0;
+
+            res[res_i] = b'!';
+            res_i += 1;
+            res[res_i] = b'[';
+            res_i += 1;
+
+            let mut i = 0;
+            // We use 20 even though it creates an oversized array
+            let arr = static_int_str_array::<20>(size);
+            while i < static_int_str_len(size) {
+                res[res_i] = arr[i];
+                res_i += 1;
+                i += 1;
+            }
+
+            res[res_i] = b',';
+            res_i += 1;
+
+            let mut i = 0;
+            // We use 20 even though it creates an oversized array
+            let arr = static_int_str_array::<20>(align);
+            while i < static_int_str_len(align) {
+                res[res_i] = arr[i];
+                res_i += 1;
+                i += 1;
+            }
+
+            let mut i = 0;
+            // We use LEN even though it creates an oversized array
+            let arr = static_encoding_str_array::<LEN>(item, level);
+            while i < static_encoding_str_len(item, level) {
+                res[res_i] = arr[i];
+                res_i += 1;
+                i += 1;
+            }
+
+            res[res_i] = b']';
+        }
+        Qualified(qualifier, t, level) => {
+            res[res_i] = qualifier.code();
+            res_i += 1;
+
+            let mut i = 0;
+            // We use LEN even though it creates an oversized array
+            let arr = static_encoding_str_array::<LEN>(t, level);
+            while i < static_encoding_str_len(t, level) {
+                res[res_i] = arr[i];
+                res_i += 1;
+                i += 1;
+            }
+        }
         Container(kind, name, items, level) => {
             let mut res_i = 0;
 
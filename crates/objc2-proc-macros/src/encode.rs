@@ -0,0 +1,368 @@
+//! Implementation of `#[derive(Encode)]` and `#[derive(RefEncode)]`.
+//!
+//! We don't depend on `syn` and `quote`, so the parsing here is done by hand,
+//! and only handles the subset of Rust syntax that is relevant for `repr(C)`
+//! structs and unions.
+
+use proc_macro::{Delimiter, Group, Ident, Literal, Punct, Spacing, Span, TokenStream, TokenTree};
+
+/// The kind of item that the derive was applied to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Kind {
+    Struct,
+    Union,
+}
+
+impl Kind {
+    fn variant(self) -> &'static str {
+        match self {
+            Self::Struct => "Struct",
+            Self::Union => "Union",
+        }
+    }
+}
+
+/// The parsed parts of a `struct` or `union` item that we care about.
+#[derive(Debug)]
+pub(crate) struct Item {
+    kind: Kind,
+    ident: Ident,
+    /// The expression given in `#[encoding(name = ...)]`, if any.
+    name: Option<TokenStream>,
+    /// The type of each field, in declaration order.
+    fields: Vec<TokenStream>,
+}
+
+/// An error together with the span it should be reported at.
+#[derive(Debug)]
+pub(crate) struct Error {
+    span: Span,
+    message: String,
+}
+
+impl Error {
+    fn new(span: Span, message: impl Into<String>) -> Self {
+        Self {
+            span,
+            message: message.into(),
+        }
+    }
+
+    /// Emit the error as a `compile_error!` invocation.
+    pub(crate) fn into_compile_error(self) -> TokenStream {
+        let mut message = Literal::string(&self.message);
+        message.set_span(self.span);
+        [
+            respan(Ident::new("compile_error", self.span).into(), self.span),
+            respan(Punct::new('!', Spacing::Alone).into(), self.span),
+            respan(
+                Group::new(Delimiter::Parenthesis, TokenTree::from(message).into()).into(),
+                self.span,
+            ),
+            respan(Punct::new(';', Spacing::Alone).into(), self.span),
+        ]
+        .into_iter()
+        .collect()
+    }
+}
+
+fn respan(mut token: TokenTree, span: Span) -> TokenTree {
+    token.set_span(span);
+    token
+}
+
+fn parse(s: &str) -> TokenStream {
+    s.parse().expect("valid tokens")
+}
+
+fn is_punct(token: &TokenTree, c: char) -> bool {
+    matches!(token, TokenTree::Punct(punct) if punct.as_char() == c)
+}
+
+fn is_ident(token: &TokenTree, s: &str) -> bool {
+    matches!(token, TokenTree::Ident(ident) if ident.to_string() == s)
+}
+
+/// Split a token stream on top-level commas.
+///
+/// Commas inside groups are already hidden from us, but commas inside
+/// generic arguments (e.g. `Foo<A, B>`) are not, so we track the nesting of
+/// angle brackets as well.
+fn split_commas(stream: TokenStream) -> Vec<Vec<TokenTree>> {
+    let mut res = vec![];
+    let mut current = vec![];
+    let mut depth: usize = 0;
+    let mut prev_joint_minus = false;
+
+    for token in stream {
+        let mut joint_minus = false;
+        if let TokenTree::Punct(punct) = &token {
+            match punct.as_char() {
+                ',' if depth == 0 => {
+                    res.push(core::mem::take(&mut current));
+                    continue;
+                }
+                '<' => depth += 1,
+                // Don't count the `>` in `->`
+                '>' if !prev_joint_minus => depth = depth.saturating_sub(1),
+                '-' => joint_minus = punct.spacing() == Spacing::Joint,
+                _ => {}
+            }
+        }
+        prev_joint_minus = joint_minus;
+        current.push(token);
+    }
+
+    if !current.is_empty() {
+        res.push(current);
+    }
+    res
+}
+
+/// Skip outer attributes and visibility at the start of a field.
+fn strip_attrs_and_vis(tokens: &[TokenTree]) -> &[TokenTree] {
+    let mut tokens = tokens;
+    loop {
+        match tokens {
+            [pound, TokenTree::Group(group), rest @ ..]
+                if is_punct(pound, '#') && group.delimiter() == Delimiter::Bracket =>
+            {
+                tokens = rest;
+            }
+            [vis, TokenTree::Group(group), rest @ ..]
+                if is_ident(vis, "pub") && group.delimiter() == Delimiter::Parenthesis =>
+            {
+                tokens = rest;
+            }
+            [vis, rest @ ..] if is_ident(vis, "pub") => {
+                tokens = rest;
+            }
+            _ => return tokens,
+        }
+    }
+}
+
+fn parse_named_fields(stream: TokenStream) -> Result<Vec<TokenStream>, Error> {
+    split_commas(stream)
+        .into_iter()
+        .map(|field| match strip_attrs_and_vis(&field) {
+            [TokenTree::Ident(_), colon, ty @ ..] if is_punct(colon, ':') && !ty.is_empty() => {
+                Ok(ty.iter().cloned().collect())
+            }
+            _ => Err(Error::new(
+                field.first().map_or_else(Span::call_site, |t| t.span()),
+                "could not parse field",
+            )),
+        })
+        .collect()
+}
+
+fn parse_unnamed_fields(stream: TokenStream) -> Result<Vec<TokenStream>, Error> {
+    split_commas(stream)
+        .into_iter()
+        .map(|field| match strip_attrs_and_vis(&field) {
+            [] => Err(Error::new(
+                field.first().map_or_else(Span::call_site, |t| t.span()),
+                "could not parse field",
+            )),
+            ty => Ok(ty.iter().cloned().collect()),
+        })
+        .collect()
+}
+
+/// Check the contents of a `#[repr(...)]` attribute.
+fn check_repr(group: &Group, has_repr_c: &mut bool) -> Result<(), Error> {
+    for token in group.stream() {
+        if let TokenTree::Ident(ident) = &token {
+            match &*ident.to_string() {
+                "C" => *has_repr_c = true,
+                "packed" => {
+                    return Err(Error::new(
+                        ident.span(),
+                        "`#[repr(packed)]` cannot be represented by a type-encoding",
+                    ))
+                }
+                "transparent" => {
+                    return Err(Error::new(
+                        ident.span(),
+                        "`#[repr(transparent)]` is not supported, implement `Encode` manually by delegating to the inner type instead",
+                    ))
+                }
+                // `align(N)` is fine, and integer reprs are only valid on
+                // enums, which we reject elsewhere.
+                _ => {}
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Parse the contents of an `#[encoding(...)]` attribute.
+fn parse_encoding_attr(group: &Group, name: &mut Option<TokenStream>) -> Result<(), Error> {
+    for arg in split_commas(group.stream()) {
+        match &*arg {
+            [key, eq, value @ ..] if is_ident(key, "name") && is_punct(eq, '=') => {
+                if value.is_empty() {
+                    return Err(Error::new(eq.span(), "expected a name after `=`"));
+                }
+                if name.is_some() {
+                    return Err(Error::new(key.span(), "duplicate `name` attribute"));
+                }
+                *name = Some(value.iter().cloned().collect());
+            }
+            [token, ..] => {
+                return Err(Error::new(
+                    token.span(),
+                    "unknown attribute, expected `name = \"...\"`",
+                ))
+            }
+            [] => {}
+        }
+    }
+    Ok(())
+}
+
+impl Item {
+    pub(crate) fn parse(input: TokenStream) -> Result<Self, Error> {
+        let mut iter = input.into_iter().peekable();
+        let mut has_repr_c = false;
+        let mut name = None;
+
+        // Attributes
+        while let Some(token) = iter.peek() {
+            if !is_punct(token, '#') {
+                break;
+            }
+            iter.next();
+            let attr = match iter.next() {
+                Some(TokenTree::Group(group)) if group.delimiter() == Delimiter::Bracket => group,
+                _ => return Err(Error::new(Span::call_site(), "expected attribute")),
+            };
+            let mut attr = attr.stream().into_iter();
+            match (attr.next(), attr.next()) {
+                (Some(TokenTree::Ident(ident)), Some(TokenTree::Group(group)))
+                    if ident.to_string() == "repr" =>
+                {
+                    check_repr(&group, &mut has_repr_c)?;
+                }
+                (Some(TokenTree::Ident(ident)), Some(TokenTree::Group(group)))
+                    if ident.to_string() == "encoding" =>
+                {
+                    parse_encoding_attr(&group, &mut name)?;
+                }
+                (Some(TokenTree::Ident(ident)), _) if ident.to_string() == "encoding" => {
+                    return Err(Error::new(
+                        ident.span(),
+                        "expected `#[encoding(name = \"...\")]`",
+                    ));
+                }
+                _ => {}
+            }
+        }
+
+        // Visibility
+        if matches!(iter.peek(), Some(token) if is_ident(token, "pub")) {
+            iter.next();
+            if matches!(iter.peek(), Some(TokenTree::Group(group)) if group.delimiter() == Delimiter::Parenthesis)
+            {
+                iter.next();
+            }
+        }
+
+        let kind = match iter.next() {
+            Some(TokenTree::Ident(ident)) => match &*ident.to_string() {
+                "struct" => Kind::Struct,
+                "union" => Kind::Union,
+                "enum" => {
+                    return Err(Error::new(
+                        ident.span(),
+                        "enums are not supported, only structs and unions",
+                    ))
+                }
+                _ => return Err(Error::new(ident.span(), "expected `struct` or `union`")),
+            },
+            _ => return Err(Error::new(Span::call_site(), "expected `struct` or `union`")),
+        };
+
+        let ident = match iter.next() {
+            Some(TokenTree::Ident(ident)) => ident,
+            _ => return Err(Error::new(Span::call_site(), "expected identifier")),
+        };
+
+        if !has_repr_c {
+            return Err(Error::new(
+                ident.span(),
+                "the type must be `#[repr(C)]` to have a well-defined type-encoding",
+            ));
+        }
+
+        let fields = match iter.next() {
+            Some(TokenTree::Group(group)) if group.delimiter() == Delimiter::Brace => {
+                parse_named_fields(group.stream())?
+            }
+            Some(TokenTree::Group(group)) if group.delimiter() == Delimiter::Parenthesis => {
+                parse_unnamed_fields(group.stream())?
+            }
+            Some(token) if is_punct(&token, ';') => vec![],
+            Some(token) if is_punct(&token, '<') || is_ident(&token, "where") => {
+                return Err(Error::new(token.span(), "generic types are not supported"))
+            }
+            _ => return Err(Error::new(ident.span(), "expected fields")),
+        };
+
+        Ok(Self {
+            kind,
+            ident,
+            name,
+            fields,
+        })
+    }
+
+    /// Generate an `Encode` implementation.
+    pub(crate) fn encode_impl(&self) -> TokenStream {
+        let name = self.name.clone().unwrap_or_else(|| {
+            let mut lit = Literal::string(&self.ident.to_string());
+            lit.set_span(self.ident.span());
+            TokenTree::from(lit).into()
+        });
+
+        let mut fields = TokenStream::new();
+        for ty in &self.fields {
+            fields.extend(parse("<"));
+            fields.extend(ty.clone());
+            fields.extend(parse("as ::objc2::encode::Encode>::ENCODING,"));
+        }
+
+        let mut args = name;
+        args.extend(parse(", &"));
+        args.extend([TokenTree::from(Group::new(Delimiter::Bracket, fields))]);
+
+        let mut body = parse(&format!(
+            "const ENCODING: ::objc2::encode::Encoding = ::objc2::encode::Encoding::{}",
+            self.kind.variant()
+        ));
+        body.extend([TokenTree::from(Group::new(Delimiter::Parenthesis, args))]);
+        body.extend(parse(";"));
+
+        self.impl_block("Encode", body)
+    }
+
+    /// Generate a `RefEncode` implementation.
+    pub(crate) fn ref_encode_impl(&self) -> TokenStream {
+        let body = parse(
+            "const ENCODING_REF: ::objc2::encode::Encoding = ::objc2::encode::Encoding::Pointer(&<Self as ::objc2::encode::Encode>::ENCODING);",
+        );
+        self.impl_block("RefEncode", body)
+    }
+
+    fn impl_block(&self, trait_: &str, body: TokenStream) -> TokenStream {
+        let mut res = parse(&format!(
+            "#[automatically_derived] unsafe impl ::objc2::encode::{trait_} for"
+        ));
+        res.extend([
+            TokenTree::from(self.ident.clone()),
+            Group::new(Delimiter::Brace, body).into(),
+        ]);
+        res
+    }
+}
@@ -21,6 +21,8 @@
 #[doc = include_str!("../README.md")]
 extern "C" {}
 
+mod encode;
+
 use core::hash::{Hash, Hasher};
 
 use proc_macro::Ident;
@@ -76,3 +78,37 @@ pub fn __hash_idents(input: TokenStream) -> TokenStream {
     let s = format!("{:016x}", hasher.finish());
     TokenTree::Literal(Literal::string(&s)).into()
 }
+
+/// Derive an implementation of `Encode` for a `#[repr(C)]` struct or union.
+///
+/// The encoding is `Encoding::Struct` or `Encoding::Union` with the type's
+/// name, and the encodings of each field in declaration order. The name can
+/// be overridden with `#[encoding(name = "...")]`, which accepts any constant
+/// expression of type `&'static str`.
+///
+/// Types that are not `#[repr(C)]`, as well as generic types and enums, are
+/// rejected at compile-time.
+///
+/// This is re-exported as `objc2::encode::Encode`, see that for details.
+#[proc_macro_derive(Encode, attributes(encoding))]
+pub fn derive_encode(input: TokenStream) -> TokenStream {
+    match encode::Item::parse(input) {
+        Ok(item) => item.encode_impl(),
+        Err(err) => err.into_compile_error(),
+    }
+}
+
+/// Derive an implementation of `RefEncode` for a `#[repr(C)]` struct or
+/// union.
+///
+/// The encoding is a pointer to the type's `Encode` encoding, so the type
+/// must implement `Encode` as well (usually via `#[derive(Encode)]`).
+///
+/// This is re-exported as `objc2::encode::RefEncode`, see that for details.
+#[proc_macro_derive(RefEncode, attributes(encoding))]
+pub fn derive_ref_encode(input: TokenStream) -> TokenStream {
+    match encode::Item::parse(input) {
+        Ok(item) => item.ref_encode_impl(),
+        Err(err) => err.into_compile_error(),
+    }
+}
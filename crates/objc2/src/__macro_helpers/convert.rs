@@ -24,8 +24,19 @@ pub trait ConvertArgument: argument_private::Sealed {
     #[doc(hidden)]
     type __StoredBeforeMessage: Sized;
 
+    /// A helper type for out parameters in `declare_class!`, which does the
+    /// necessary writeback when dropped at the end of the method.
     #[doc(hidden)]
-    fn __from_declared_param(inner: Self::__Inner) -> Self;
+    type __WritebackOnDrop: Sized;
+
+    /// # Safety
+    ///
+    /// The inner value must be valid for the parameter type, see
+    /// `writeback.rs` for the requirements on out parameters.
+    #[doc(hidden)]
+    unsafe fn __from_declared_param(inner: Self::__Inner) -> (Self, Self::__WritebackOnDrop)
+    where
+        Self: Sized;
 
     #[doc(hidden)]
     fn __into_argument(self) -> (Self::__Inner, Self::__StoredBeforeMessage);
@@ -47,9 +58,11 @@ impl<T: EncodeArgument> ConvertArgument for T {
 
     type __StoredBeforeMessage = ();
 
+    type __WritebackOnDrop = ();
+
     #[inline]
-    fn __from_declared_param(inner: Self::__Inner) -> Self {
-        inner
+    unsafe fn __from_declared_param(inner: Self::__Inner) -> (Self, Self::__WritebackOnDrop) {
+        (inner, ())
     }
 
     #[inline]
@@ -64,9 +77,11 @@ impl ConvertArgument for bool {
 
     type __StoredBeforeMessage = ();
 
+    type __WritebackOnDrop = ();
+
     #[inline]
-    fn __from_declared_param(inner: Self::__Inner) -> Self {
-        inner.as_bool()
+    unsafe fn __from_declared_param(inner: Self::__Inner) -> (Self, Self::__WritebackOnDrop) {
+        (inner.as_bool(), ())
     }
 
     #[inline]
@@ -295,7 +310,10 @@ mod tests {
             TypeId::of::<<i32 as ConvertArgument>::__Inner>(),
             TypeId::of::<i32>()
         );
-        assert_eq!(<i32 as ConvertArgument>::__from_declared_param(42), 42);
+        assert_eq!(
+            unsafe { <i32 as ConvertArgument>::__from_declared_param(42) }.0,
+            42
+        );
         assert_eq!(ConvertArgument::__into_argument(42i32).0, 42);
     }
 
@@ -305,14 +323,17 @@ mod tests {
             TypeId::of::<<i8 as ConvertArgument>::__Inner>(),
             TypeId::of::<i8>()
         );
-        assert_eq!(<i8 as ConvertArgument>::__from_declared_param(-3), -3);
+        assert_eq!(
+            unsafe { <i8 as ConvertArgument>::__from_declared_param(-3) }.0,
+            -3
+        );
         assert_eq!(ConvertArgument::__into_argument(-3i32).0, -3);
     }
 
     #[test]
     fn convert_bool() {
-        assert!(!<bool as ConvertArgument>::__from_declared_param(Bool::NO));
-        assert!(<bool as ConvertArgument>::__from_declared_param(Bool::YES));
+        assert!(!unsafe { <bool as ConvertArgument>::__from_declared_param(Bool::NO) }.0);
+        assert!(unsafe { <bool as ConvertArgument>::__from_declared_param(Bool::YES) }.0);
         assert!(!<bool as ConvertReturn>::__from_return(Bool::NO));
         assert!(<bool as ConvertReturn>::__from_return(Bool::YES));
 
@@ -9,13 +9,18 @@ use crate::runtime::{AnyProtocol, MethodDescription};
 
 use objc2_encode::Encoding;
 
+#[cfg(feature = "invocation")]
+use crate::declare::ForwardedMessage;
 use crate::declare::{ClassBuilder, IvarType};
 use crate::encode::Encode;
 use crate::rc::{Allocated, Id};
-use crate::runtime::{AnyClass, MethodImplementation, Sel};
+#[cfg(feature = "invocation")]
+use crate::runtime::Value;
+use crate::runtime::{AnyClass, MethodImplementation, PropertyAttributes, Sel};
 use crate::runtime::{AnyObject, MessageReceiver};
 use crate::{ClassType, Message, ProtocolType};
 
+use super::property::{add_ivar_property, add_method_property, PropertyIvar};
 use super::{CopyOrMutCopy, Init, MaybeUnwrap, New, Other};
 use crate::mutability;
 
@@ -206,6 +211,12 @@ where
 pub struct ClassBuilderHelper<T: ?Sized> {
     builder: ClassBuilder,
     p: PhantomData<T>,
+    /// Required instance methods that were not implemented, which is only
+    /// an error if messages are not forwarded.
+    #[cfg(all(debug_assertions, feature = "verify"))]
+    missing_instance_methods: Vec<(&'static AnyProtocol, Sel)>,
+    #[cfg(all(debug_assertions, feature = "verify"))]
+    forwards_messages: bool,
 }
 
 #[track_caller]
@@ -229,6 +240,10 @@ impl<T: ?Sized + ClassType> ClassBuilderHelper<T> {
         Self {
             builder,
             p: PhantomData,
+            #[cfg(all(debug_assertions, feature = "verify"))]
+            missing_instance_methods: Vec::new(),
+            #[cfg(all(debug_assertions, feature = "verify"))]
+            forwards_messages: false,
         }
     }
 
@@ -295,8 +310,54 @@ impl<T: ?Sized + ClassType> ClassBuilderHelper<T> {
         self.builder.add_static_ivar::<I>()
     }
 
+    #[inline]
+    pub unsafe fn add_ivar_property<I>(&mut self, name: &str, attributes: &PropertyAttributes<'_>)
+    where
+        I: IvarType,
+        I::Type: PropertyIvar,
+    {
+        // SAFETY: Checked by caller
+        unsafe { add_ivar_property::<I>(&mut self.builder, name, attributes) }
+    }
+
+    // Addition: This restricts to callee `T`
+    #[inline]
+    pub unsafe fn add_method_property<F>(
+        &mut self,
+        name: &str,
+        attributes: &PropertyAttributes<'_>,
+        getter: F,
+    ) where
+        F: MethodImplementation<Callee = T>,
+    {
+        // SAFETY: Checked by caller
+        unsafe { add_method_property(&mut self.builder, name, attributes, &getter) }
+    }
+
+    #[inline]
+    #[cfg(feature = "invocation")]
+    pub unsafe fn add_forwarding_handler(&mut self, handler: fn(&T, ForwardedMessage<'_>) -> Value)
+    where
+        T: Message + Sized + 'static,
+    {
+        // SAFETY: Checked by caller
+        unsafe { self.builder.add_forwarding_handler(handler) };
+
+        #[cfg(all(debug_assertions, feature = "verify"))]
+        {
+            self.forwards_messages = true;
+        }
+    }
+
     #[inline]
     pub fn register(self) -> &'static AnyClass {
+        #[cfg(all(debug_assertions, feature = "verify"))]
+        if !self.forwards_messages {
+            if let Some((protocol, sel)) = self.missing_instance_methods.first() {
+                panic!("must implement required protocol method -[{protocol} {sel}]")
+            }
+        }
+
         self.builder.register()
     }
 }
@@ -337,8 +398,8 @@ impl<T: ?Sized + ClassType> ClassProtocolMethodsBuilder<'_, T> {
                 .required_instance_methods
                 .iter()
                 .chain(&self.optional_instance_methods)
-                .find(|desc| desc.sel == sel)
-                .map(|desc| desc.types)
+                .find(|desc| desc.sel() == sel)
+                .map(|desc| desc.types())
                 .unwrap_or_else(|| {
                     panic!(
                         "failed overriding protocol method -[{protocol} {sel}]: method not found"
@@ -355,6 +416,19 @@ impl<T: ?Sized + ClassType> ClassProtocolMethodsBuilder<'_, T> {
         }
     }
 
+    #[inline]
+    pub unsafe fn add_method_property<F>(
+        &mut self,
+        name: &str,
+        attributes: &PropertyAttributes<'_>,
+        getter: F,
+    ) where
+        F: MethodImplementation<Callee = T>,
+    {
+        // SAFETY: Checked by caller
+        unsafe { self.builder.add_method_property(name, attributes, getter) }
+    }
+
     #[inline]
     pub unsafe fn add_class_method<F>(&mut self, sel: Sel, func: F)
     where
@@ -366,8 +440,8 @@ impl<T: ?Sized + ClassType> ClassProtocolMethodsBuilder<'_, T> {
                 .required_class_methods
                 .iter()
                 .chain(&self.optional_class_methods)
-                .find(|desc| desc.sel == sel)
-                .map(|desc| desc.types)
+                .find(|desc| desc.sel() == sel)
+                .map(|desc| desc.types())
                 .unwrap_or_else(|| {
                     panic!(
                         "failed overriding protocol method +[{protocol} {sel}]: method not found"
@@ -384,40 +458,50 @@ impl<T: ?Sized + ClassType> ClassProtocolMethodsBuilder<'_, T> {
         }
     }
 
+    #[inline]
+    #[cfg(feature = "invocation")]
+    pub unsafe fn add_forwarding_handler(&mut self, handler: fn(&T, ForwardedMessage<'_>) -> Value)
+    where
+        T: Message + Sized + 'static,
+    {
+        // SAFETY: Checked by caller
+        unsafe { self.builder.add_forwarding_handler(handler) }
+    }
+
     #[cfg(all(debug_assertions, feature = "verify"))]
     pub fn finish(self) {
         let superclass = self.builder.builder.superclass();
+        let mut missing_instance_methods = Vec::new();
 
         if let Some(protocol) = self.protocol {
             for desc in &self.required_instance_methods {
-                if self.registered_instance_methods.contains(&desc.sel) {
+                if self.registered_instance_methods.contains(&desc.sel()) {
                     continue;
                 }
 
                 // TODO: Don't do this when `NS_PROTOCOL_REQUIRES_EXPLICIT_IMPLEMENTATION`
                 if superclass
-                    .and_then(|superclass| superclass.instance_method(desc.sel))
+                    .and_then(|superclass| superclass.instance_method(desc.sel()))
                     .is_some()
                 {
                     continue;
                 }
 
-                panic!(
-                    "must implement required protocol method -[{protocol} {}]",
-                    desc.sel
-                )
+                // Checked when registering, since the method may be
+                // forwarded instead.
+                missing_instance_methods.push((protocol, desc.sel()));
             }
         }
 
         if let Some(protocol) = self.protocol {
             for desc in &self.required_class_methods {
-                if self.registered_class_methods.contains(&desc.sel) {
+                if self.registered_class_methods.contains(&desc.sel()) {
                     continue;
                 }
 
                 // TODO: Don't do this when `NS_PROTOCOL_REQUIRES_EXPLICIT_IMPLEMENTATION`
                 if superclass
-                    .and_then(|superclass| superclass.class_method(desc.sel))
+                    .and_then(|superclass| superclass.class_method(desc.sel()))
                     .is_some()
                 {
                     continue;
@@ -425,10 +509,14 @@ impl<T: ?Sized + ClassType> ClassProtocolMethodsBuilder<'_, T> {
 
                 panic!(
                     "must implement required protocol method +[{protocol} {}]",
-                    desc.sel
+                    desc.sel()
                 );
             }
         }
+
+        self.builder
+            .missing_instance_methods
+            .extend(missing_instance_methods);
     }
 
     #[inline]
@@ -1,6 +1,7 @@
 pub use core::borrow::{Borrow, BorrowMut};
 pub use core::cell::UnsafeCell;
 pub use core::convert::{AsMut, AsRef};
+pub use core::default::Default;
 pub use core::marker::{PhantomData, Sized};
 pub use core::mem::{needs_drop, size_of, ManuallyDrop};
 pub use core::ops::{Deref, DerefMut};
@@ -9,6 +10,7 @@ pub use core::primitive::{bool, str, u8};
 pub use core::ptr::drop_in_place;
 pub use core::{compile_error, concat, panic, stringify};
 // TODO: Use `core::cell::LazyCell`
+pub use std::ffi::CStr;
 pub use std::sync::Once;
 
 mod cache;
@@ -18,6 +20,8 @@ mod declare_class;
 mod method_family;
 mod msg_send;
 mod msg_send_id;
+mod property;
+mod static_encoding;
 mod writeback;
 
 pub use self::cache::{CachedClass, CachedSel};
@@ -33,6 +37,10 @@ pub use self::method_family::{
 };
 pub use self::msg_send::MsgSend;
 pub use self::msg_send_id::{MaybeUnwrap, MsgSendId};
+pub use self::property::PropertyIvar;
+pub use self::static_encoding::{
+    cstr_from_encoding_bytes, method_types_array, method_types_len, EncodingStr, MethodTypes,
+};
 
 /// Helper struct for emitting the module info that macOS 32-bit requires.
 ///
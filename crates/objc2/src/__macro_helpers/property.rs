@@ -0,0 +1,295 @@
+//! Accessors for `#[property(...)]` in `declare_class!`.
+use core::ptr::{self, NonNull};
+
+use crate::declare::{
+    default_setter, ClassBuilder, InnerIvarType, IvarBool, IvarDrop, IvarEncode, IvarType,
+};
+use crate::encode::{Encode, EncodeArguments, EncodeReturn, Encoding};
+use crate::mutability::IsRetainable;
+use crate::rc::{Id, WeakId};
+use crate::runtime::{
+    AnyObject, Bool, MethodImplementation, PropertyAttributes, PropertyOwnership, Sel,
+};
+use crate::{msg_send_id, Message};
+
+/// Instance variable types that `#[property(...)]` can generate accessors
+/// for.
+///
+/// # Safety
+///
+/// `get` and `set` must implement the memory management semantics of the
+/// ownerships that `check_ownership` allows.
+pub unsafe trait PropertyIvar: InnerIvarType {
+    /// The type returned by the getter, and taken by the setter.
+    type Value: Encode;
+
+    /// Panics if the given ownership is not supported by the type.
+    fn check_ownership(name: &str, ownership: PropertyOwnership);
+
+    /// # Safety
+    ///
+    /// The pointer must point to a valid instance variable of this type.
+    unsafe fn get(ivar: NonNull<Self>) -> Self::Value;
+
+    /// # Safety
+    ///
+    /// The pointer must point to a valid instance variable of this type, and
+    /// the value must be valid for the property. No references to the
+    /// instance variable may be alive.
+    unsafe fn set(ivar: NonNull<Self>, value: Self::Value, copy: bool);
+}
+
+#[track_caller]
+fn assert_ownership(name: &str, ownership: PropertyOwnership, allowed: &[PropertyOwnership]) {
+    assert!(
+        allowed.contains(&ownership),
+        "property {name} cannot use the {ownership:?} ownership, expected one of {allowed:?}",
+    );
+}
+
+// SAFETY: The value is just copied.
+unsafe impl<T: Encode + Copy> PropertyIvar for IvarEncode<T> {
+    type Value = T;
+
+    fn check_ownership(name: &str, ownership: PropertyOwnership) {
+        assert_ownership(name, ownership, &[PropertyOwnership::Assign]);
+    }
+
+    unsafe fn get(ivar: NonNull<Self>) -> T {
+        // SAFETY: The ivar is zero initialized or has been initialized by
+        // the user, and `IvarEncode` is `#[repr(transparent)]`.
+        unsafe { ivar.cast::<T>().as_ptr().read() }
+    }
+
+    unsafe fn set(ivar: NonNull<Self>, value: T, _copy: bool) {
+        // SAFETY: Checked by caller
+        unsafe { ivar.cast::<T>().as_ptr().write(value) }
+    }
+}
+
+// SAFETY: The value is just copied.
+//
+// The getter and setter use `BOOL`, since that is what Objective-C code will
+// expect from a boolean property.
+unsafe impl PropertyIvar for IvarBool {
+    type Value = Bool;
+
+    fn check_ownership(name: &str, ownership: PropertyOwnership) {
+        assert_ownership(name, ownership, &[PropertyOwnership::Assign]);
+    }
+
+    unsafe fn get(ivar: NonNull<Self>) -> Bool {
+        // SAFETY: `IvarBool` is `#[repr(transparent)]`, and `bool` is safe
+        // to zero-initialize.
+        Bool::new(unsafe { ivar.cast::<bool>().as_ptr().read() })
+    }
+
+    unsafe fn set(ivar: NonNull<Self>, value: Bool, _copy: bool) {
+        // SAFETY: Checked by caller
+        unsafe { ivar.cast::<bool>().as_ptr().write(value.as_bool()) }
+    }
+}
+
+/// Retain or copy the new value of an object property.
+///
+/// # Safety
+///
+/// The pointer must be a valid object or NULL.
+unsafe fn retain_or_copy<T: Message + IsRetainable>(value: *mut T, copy: bool) -> Option<Id<T>> {
+    if copy {
+        // SAFETY: The object is valid, and the type of the property is
+        // assumed to be the same as that of the copy (which is usually
+        // true, see e.g. `NSCopying`).
+        unsafe { value.as_ref() }.and_then(|value| unsafe { msg_send_id![value, copy] })
+    } else {
+        // SAFETY: The object is valid or NULL.
+        unsafe { Id::retain(value) }
+    }
+}
+
+// SAFETY: The getter retains and autoreleases the object, and the setter
+// retains or copies it before storing.
+unsafe impl<T: Message + IsRetainable> PropertyIvar for IvarDrop<Id<T>> {
+    type Value = *mut T;
+
+    fn check_ownership(name: &str, ownership: PropertyOwnership) {
+        assert_ownership(
+            name,
+            ownership,
+            &[PropertyOwnership::Retain, PropertyOwnership::Copy],
+        );
+    }
+
+    unsafe fn get(ivar: NonNull<Self>) -> *mut T {
+        // SAFETY: Same layout
+        unsafe { <IvarDrop<Option<Id<T>>>>::get(ivar.cast()) }
+    }
+
+    unsafe fn set(ivar: NonNull<Self>, value: *mut T, copy: bool) {
+        // The instance variable must always contain an object, so setting
+        // the property to `nil` keeps the old value.
+        if value.is_null() {
+            return;
+        }
+        // SAFETY: Same layout
+        unsafe { <IvarDrop<Option<Id<T>>>>::set(ivar.cast(), value, copy) }
+    }
+}
+
+// SAFETY: Same as for `IvarDrop<Id<T>>`.
+unsafe impl<T: Message + IsRetainable> PropertyIvar for IvarDrop<Option<Id<T>>> {
+    type Value = *mut T;
+
+    fn check_ownership(name: &str, ownership: PropertyOwnership) {
+        <IvarDrop<Id<T>>>::check_ownership(name, ownership)
+    }
+
+    unsafe fn get(ivar: NonNull<Self>) -> *mut T {
+        // SAFETY: The ivar is valid, and is not modified while we retain it.
+        let obj = unsafe { ivar.as_ref().__deref() }
+            .as_ref()
+            .map(|obj| Id::as_ptr(obj) as *mut T);
+        // Retain and autorelease the object, such that it is kept alive even
+        // if the property is set again while the caller uses the object.
+        let obj = obj.and_then(|obj| unsafe { Id::retain(obj) });
+        Id::autorelease_return_option(obj)
+    }
+
+    unsafe fn set(ivar: NonNull<Self>, value: *mut T, copy: bool) {
+        // SAFETY: Checked by caller
+        let new = unsafe { retain_or_copy(value, copy) };
+        // The old value is released after the new one has been stored, in
+        // case they are the same object.
+        let _old = unsafe { ptr::replace(ivar.cast::<Option<Id<T>>>().as_ptr(), new) };
+    }
+}
+
+// SAFETY: The getter loads the weak reference and autoreleases the result,
+// and the setter stores a new weak reference.
+unsafe impl<T: Message + IsRetainable> PropertyIvar for IvarDrop<Option<WeakId<T>>> {
+    type Value = *mut T;
+
+    fn check_ownership(name: &str, ownership: PropertyOwnership) {
+        assert_ownership(name, ownership, &[PropertyOwnership::Weak]);
+    }
+
+    unsafe fn get(ivar: NonNull<Self>) -> *mut T {
+        // SAFETY: Loading a weak reference is thread safe.
+        let weak = unsafe { ivar.as_ref().__deref() };
+        Id::autorelease_return_option(weak.as_ref().and_then(WeakId::load))
+    }
+
+    unsafe fn set(ivar: NonNull<Self>, value: *mut T, _copy: bool) {
+        // SAFETY: The object is valid or NULL.
+        let new = (!value.is_null()).then(|| unsafe { WeakId::new_inner(value) });
+        let _old = unsafe { ptr::replace(ivar.cast::<Option<WeakId<T>>>().as_ptr(), new) };
+    }
+}
+
+fn ivar<I: IvarType>(this: &AnyObject) -> NonNull<I::Type> {
+    let ptr = NonNull::from(this);
+    // SAFETY: The ivar was added to the class of the object in
+    // `__objc2_declare_ivars`.
+    let offset = unsafe { I::__offset(ptr) };
+    // SAFETY: The offset is valid
+    unsafe { AnyObject::ivar_at_offset::<I::Type>(ptr, offset) }
+}
+
+extern "C" fn property_getter<I>(this: &AnyObject, _cmd: Sel) -> <I::Type as PropertyIvar>::Value
+where
+    I: IvarType,
+    I::Type: PropertyIvar,
+{
+    // SAFETY: The ivar is valid
+    unsafe { <I::Type as PropertyIvar>::get(ivar::<I>(this)) }
+}
+
+extern "C" fn property_setter<I, const COPY: bool>(
+    this: &AnyObject,
+    _cmd: Sel,
+    value: <I::Type as PropertyIvar>::Value,
+) where
+    I: IvarType,
+    I::Type: PropertyIvar,
+{
+    // SAFETY: The ivar is valid, and the user of `declare_class!` ensures
+    // that no references to it are alive while the setter is called.
+    unsafe { <I::Type as PropertyIvar>::set(ivar::<I>(this), value, COPY) }
+}
+
+/// Add a property backed by the instance variable `I`, along with its
+/// accessors.
+///
+/// # Safety
+///
+/// The instance variable must not be referenced while the setter is called.
+pub(crate) unsafe fn add_ivar_property<I>(
+    builder: &mut ClassBuilder,
+    name: &str,
+    attributes: &PropertyAttributes<'_>,
+) where
+    I: IvarType,
+    I::Type: PropertyIvar,
+{
+    assert!(
+        attributes.nonatomic,
+        "property {name} must be nonatomic, atomic properties are not supported",
+    );
+    <I::Type as PropertyIvar>::check_ownership(name, attributes.ownership);
+    builder.assert_has_ivar(I::NAME, name);
+
+    let getter = Sel::register(attributes.getter.unwrap_or(name));
+    let get: extern "C" fn(_, _) -> _ = property_getter::<I>;
+    // SAFETY: The getter has the correct signature
+    unsafe { builder.add_method(getter, get) };
+    if !attributes.readonly {
+        let setter = match attributes.setter {
+            Some(setter) => Sel::register(setter),
+            None => Sel::register(&default_setter(name)),
+        };
+        let set: extern "C" fn(_, _, _) = if attributes.ownership == PropertyOwnership::Copy {
+            property_setter::<I, true>
+        } else {
+            property_setter::<I, false>
+        };
+        // SAFETY: The setter has the correct signature, and the caller
+        // ensures that it is only called when the ivar is not referenced.
+        unsafe { builder.add_method(setter, set) };
+    }
+
+    let encoding = <<I::Type as PropertyIvar>::Value>::ENCODING;
+    // Only reference the ivar if it has the same type as the property (which
+    // is not the case for e.g. weak properties).
+    let ivar_name = if encoding.equivalent_to(&<I::Type>::ENCODING) {
+        Some(I::NAME)
+    } else {
+        None
+    };
+    let attributes = PropertyAttributes {
+        ivar: ivar_name,
+        ..attributes.clone()
+    };
+    // SAFETY: The accessors were added above.
+    unsafe { builder.add_property_with_encoding(name, &encoding, &attributes) };
+}
+
+/// Add a property whose getter is the given method.
+///
+/// # Safety
+///
+/// The setter, if the property is not `readonly`, must be implemented
+/// separately, and take the type that the getter returns.
+pub(crate) unsafe fn add_method_property<F: MethodImplementation>(
+    builder: &mut ClassBuilder,
+    name: &str,
+    attributes: &PropertyAttributes<'_>,
+    _getter: &F,
+) {
+    let encoding = F::Return::ENCODING_RETURN;
+    assert!(
+        F::Arguments::ENCODINGS.is_empty() && !matches!(encoding, Encoding::Void),
+        "the getter of property {name} must take no arguments, and return a value",
+    );
+    // SAFETY: The getter exists, and the caller ensures that the setter does.
+    unsafe { builder.add_property_with_encoding(name, &encoding, attributes) };
+}
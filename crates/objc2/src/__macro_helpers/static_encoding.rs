@@ -0,0 +1,196 @@
+use core::marker::PhantomData;
+use std::ffi::CStr;
+
+use crate::encode::{Encode, EncodeArguments, EncodeReturn, Encoding};
+use crate::runtime::{AnyObject, Sel};
+
+/// The length of the type-encoding of a method with the given return type
+/// and arguments, not including the NUL terminator.
+///
+/// The first two arguments are always the receiver and the selector, so
+/// these should not be part of `args`.
+pub const fn method_types_len(ret: &Encoding, args: &[Encoding]) -> usize {
+    let mut len =
+        ret.str_len() + <*mut AnyObject>::ENCODING.str_len() + <Sel as Encode>::ENCODING.str_len();
+    let mut i = 0;
+    while i < args.len() {
+        len += args[i].str_len();
+        i += 1;
+    }
+    len
+}
+
+const fn write_encoding<const LEN: usize>(
+    mut res: [u8; LEN],
+    mut res_i: usize,
+    encoding: &Encoding,
+) -> ([u8; LEN], usize) {
+    // We use LEN even though it creates an oversized array
+    let data: [u8; LEN] = encoding.str_array();
+    let mut i = 0;
+    while i < encoding.str_len() {
+        res[res_i] = data[i];
+        res_i += 1;
+        i += 1;
+    }
+    (res, res_i)
+}
+
+/// The type-encoding of a method with the given return type and arguments.
+///
+/// `LEN` must be at least [`method_types_len`]; any remaining bytes are
+/// zero, so using one more than that gives a NUL-terminated string.
+pub const fn method_types_array<const LEN: usize>(ret: &Encoding, args: &[Encoding]) -> [u8; LEN] {
+    let (res, res_i) = write_encoding([0; LEN], 0, ret);
+    let (res, res_i) = write_encoding(res, res_i, &<*mut AnyObject>::ENCODING);
+    let (mut res, mut res_i) = write_encoding(res, res_i, &<Sel as Encode>::ENCODING);
+    let mut i = 0;
+    while i < args.len() {
+        (res, res_i) = write_encoding(res, res_i, &args[i]);
+        i += 1;
+    }
+    res
+}
+
+/// Convert the NUL-terminated bytes of an encoding to a `CStr`.
+///
+/// Encodings never contain NUL bytes, but we check anyhow, since this is
+/// evaluated at compile-time.
+pub const fn cstr_from_encoding_bytes(bytes: &[u8]) -> &CStr {
+    let mut i = 0;
+    while i < bytes.len() - 1 {
+        if bytes[i] == 0 {
+            panic!("encoding contained NUL byte");
+        }
+        i += 1;
+    }
+    if bytes[i] != 0 {
+        panic!("encoding was not NUL-terminated");
+    }
+    // SAFETY: Just checked that the bytes are NUL-terminated, and that they
+    // do not contain any interior NUL bytes.
+    unsafe { CStr::from_bytes_with_nul_unchecked(bytes) }
+}
+
+/// The capacity of the data in [`MethodTypes`].
+///
+/// Long enough for most methods, including ones taking e.g. `CGRect`.
+const CAPACITY: usize = 128;
+
+/// Compile-time computed type-encodings for methods.
+///
+/// Array lengths cannot depend on generic parameters (yet), so instead we
+/// use a fixed capacity, and let the caller fall back to computing the
+/// encoding at runtime if it does not fit.
+#[derive(Debug)]
+pub struct MethodTypes<Ret, Args> {
+    p: PhantomData<(Ret, Args)>,
+}
+
+impl<Ret: EncodeReturn, Args: EncodeArguments> MethodTypes<Ret, Args> {
+    const LEN: usize = method_types_len(&Ret::ENCODING_RETURN, Args::ENCODINGS);
+
+    const DATA: [u8; CAPACITY] = if Self::LEN < CAPACITY {
+        method_types_array(&Ret::ENCODING_RETURN, Args::ENCODINGS)
+    } else {
+        [0; CAPACITY]
+    };
+
+    /// The method type-encoding, or `None` if it could not be computed at
+    /// compile-time.
+    #[inline]
+    pub fn cstr() -> Option<&'static CStr> {
+        to_cstr(&Self::DATA, Self::LEN)
+    }
+}
+
+/// Compile-time computed type-encoding for ivars.
+///
+/// See [`MethodTypes`].
+#[derive(Debug)]
+pub struct EncodingStr<T> {
+    p: PhantomData<T>,
+}
+
+impl<T: Encode> EncodingStr<T> {
+    const LEN: usize = T::ENCODING.str_len();
+
+    const DATA: [u8; CAPACITY] = if Self::LEN < CAPACITY {
+        T::ENCODING.str_array()
+    } else {
+        [0; CAPACITY]
+    };
+
+    /// The type-encoding, or `None` if it could not be computed at
+    /// compile-time.
+    #[inline]
+    pub fn cstr() -> Option<&'static CStr> {
+        to_cstr(&Self::DATA, Self::LEN)
+    }
+}
+
+#[inline]
+fn to_cstr(data: &'static [u8; CAPACITY], len: usize) -> Option<&'static CStr> {
+    if len < CAPACITY {
+        // SAFETY: The data after `len` is zero-filled, and encodings never
+        // contain NUL bytes (struct and union names are verified to be
+        // valid identifiers).
+        Some(unsafe { CStr::from_bytes_with_nul_unchecked(&data[..=len]) })
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runtime::AnyClass;
+
+    #[test]
+    fn static_method_types() {
+        #[track_caller]
+        fn assert_types<Ret: EncodeReturn, Args: EncodeArguments>(expected: &str) {
+            let types = MethodTypes::<Ret, Args>::cstr().unwrap();
+            assert_eq!(types.to_str().unwrap(), expected);
+        }
+
+        assert_types::<(), ()>("v@:");
+        assert_types::<i32, (u8, *const i8)>("i@:C*");
+        assert_types::<*mut AnyObject, (&AnyClass, Sel, f64)>("@@:#:d");
+    }
+
+    #[test]
+    fn static_ivar_encoding() {
+        assert_eq!(EncodingStr::<i32>::cstr().unwrap().to_str(), Ok("i"));
+        assert_eq!(
+            EncodingStr::<*const *const AnyClass>::cstr()
+                .unwrap()
+                .to_str(),
+            Ok("^#"),
+        );
+    }
+
+    #[test]
+    fn too_long() {
+        struct Long;
+
+        unsafe impl Encode for Long {
+            const ENCODING: Encoding = Encoding::Struct(
+                "AVeryLongStructNameThatIsLongerThanTheCapacityOfTheStaticData\
+                 SoThatWeHaveToFallBackToComputingTheEncodingAtRuntimeInsteadOfCompileTime",
+                &[],
+            );
+        }
+
+        assert_eq!(EncodingStr::<Long>::cstr(), None);
+        assert_eq!(MethodTypes::<(), (Long,)>::cstr(), None);
+    }
+
+    #[test]
+    fn const_method_types() {
+        const ARGS: &[Encoding] = &[Encoding::Int, Encoding::Object];
+        const LEN: usize = method_types_len(&Encoding::Float, ARGS);
+        const DATA: [u8; LEN + 1] = method_types_array(&Encoding::Float, ARGS);
+        assert_eq!(&DATA, b"f@:i@\0");
+    }
+}
@@ -1,4 +1,5 @@
-//! Support for passing "out"-parameters to `msg_send!` and family.
+//! Support for passing "out"-parameters to `msg_send!` and family, and for
+//! receiving them in `declare_class!`.
 //!
 //! See clang's documentation:
 //! <https://clang.llvm.org/docs/AutomaticReferenceCounting.html#passing-to-an-out-parameter-by-writeback>
@@ -7,6 +8,15 @@
 //! address we then work on; instead, we directly reuse the pointer that the
 //! user provides (since, if it's a mutable pointer, we know that it's not
 //! shared elsewhere in the program, and hence it is safe to modify directly).
+//!
+//! On the receiving side, the pointer points to an `__autoreleasing` value,
+//! that is, one which the method does not own. We retain that value when
+//! the method is entered, such that the user can work with it as an owned
+//! `Id`, and autorelease whatever value is stored when the method returns.
+//! This is equivalent to what clang does when assigning to an
+//! `__autoreleasing` out parameter, except that we (needlessly) retain and
+//! autorelease the value if it was not modified.
+use core::marker::PhantomData;
 use core::mem::ManuallyDrop;
 use core::ptr::NonNull;
 
@@ -14,6 +24,52 @@ use super::ConvertArgument;
 use crate::rc::Id;
 use crate::Message;
 
+/// Autoreleases the value of an out parameter when dropped, to hand it back
+/// to the caller of a method in `declare_class!`.
+#[derive(Debug)]
+pub struct AutoreleaseOnDrop<T: Message> {
+    ptr: NonNull<*mut T>,
+    p: PhantomData<Id<T>>,
+}
+
+impl<T: Message> AutoreleaseOnDrop<T> {
+    /// Take ownership of the value that the out parameter points to.
+    ///
+    ///
+    /// # Safety
+    ///
+    /// The pointer must be valid for reads and writes for as long as the
+    /// returned value is alive, and must point to a valid object or NULL,
+    /// which the caller does not own (i.e. it has +0 retain count).
+    #[inline]
+    unsafe fn new(ptr: NonNull<*mut T>) -> Self {
+        // SAFETY: The pointer is valid for reads, and the value is a valid
+        // object or NULL.
+        let value: Option<Id<T>> = unsafe { Id::retain(*ptr.as_ptr()) };
+        // The pointer now holds an owned (+1) value, which is autoreleased
+        // again when `self` is dropped.
+        let _ = ManuallyDrop::new(value);
+        Self {
+            ptr,
+            p: PhantomData,
+        }
+    }
+}
+
+impl<T: Message> Drop for AutoreleaseOnDrop<T> {
+    #[inline]
+    fn drop(&mut self) {
+        // SAFETY: The pointer is valid, and holds an owned value (possibly
+        // modified by the user through `&mut Option<Id<T>>` or `&mut Id<T>`).
+        let value: Option<Id<T>> = unsafe { Id::new(*self.ptr.as_ptr()) };
+        // Autoreleasing returns the same pointer, so there is no need to
+        // write it back.
+        if let Some(value) = value {
+            let _ = Id::autorelease_inner(value);
+        }
+    }
+}
+
 // Note the `'static` bound here - this may not be necessary, but I'm unsure
 // of the exact requirements, so we better keep it for now.
 impl<T: Message + 'static> ConvertArgument for &mut Id<T> {
@@ -33,9 +89,21 @@ impl<T: Message + 'static> ConvertArgument for &mut Id<T> {
         NonNull<T>,
     );
 
+    type __WritebackOnDrop = AutoreleaseOnDrop<T>;
+
     #[inline]
-    fn __from_declared_param(_inner: Self::__Inner) -> Self {
-        todo!("`&mut Id<_>` is not supported in `declare_class!` yet")
+    unsafe fn __from_declared_param(inner: Self::__Inner) -> (Self, Self::__WritebackOnDrop) {
+        // SAFETY: Caller ensures that the pointer is valid for reads.
+        if unsafe { *inner.as_ptr() }.is_null() {
+            panic!("received NULL in `&mut Id<_>` out parameter, which is UB! You should handle this with `&mut Option<Id<_>>` instead");
+        }
+        // SAFETY: Upheld by caller.
+        let writeback = unsafe { AutoreleaseOnDrop::new(inner) };
+        // SAFETY: The value is non-NULL, and is now owned by the pointer
+        // until `writeback` is dropped, which happens after the method has
+        // stopped using the reference.
+        let this = unsafe { inner.cast::<Id<T>>().as_mut() };
+        (this, writeback)
     }
 
     #[inline]
@@ -114,9 +182,16 @@ impl<T: Message + 'static> ConvertArgument for &mut Option<Id<T>> {
 
     type __StoredBeforeMessage = (Self::__Inner, *mut T);
 
+    type __WritebackOnDrop = AutoreleaseOnDrop<T>;
+
     #[inline]
-    fn __from_declared_param(_inner: Self::__Inner) -> Self {
-        todo!("`&mut Option<Id<_>>` is not supported in `declare_class!` yet")
+    unsafe fn __from_declared_param(inner: Self::__Inner) -> (Self, Self::__WritebackOnDrop) {
+        // SAFETY: Upheld by caller.
+        let writeback = unsafe { AutoreleaseOnDrop::new(inner) };
+        // SAFETY: Same as for `&mut Id`, `Option<Id<T>>` has the same memory
+        // layout as `*mut T`.
+        let this = unsafe { inner.cast::<Option<Id<T>>>().as_mut() };
+        (this, writeback)
     }
 
     #[inline]
@@ -161,9 +236,17 @@ impl<T: Message + 'static> ConvertArgument for Option<&mut Id<T>> {
 
     type __StoredBeforeMessage = Option<(NonNull<*mut T>, NonNull<T>)>;
 
+    type __WritebackOnDrop = Option<AutoreleaseOnDrop<T>>;
+
     #[inline]
-    fn __from_declared_param(_inner: Self::__Inner) -> Self {
-        todo!("`Option<&mut Id<_>>` is not supported in `declare_class!` yet")
+    unsafe fn __from_declared_param(inner: Self::__Inner) -> (Self, Self::__WritebackOnDrop) {
+        if let Some(inner) = inner {
+            // SAFETY: Checked by caller
+            let (this, writeback) = unsafe { <&mut Id<T>>::__from_declared_param(inner) };
+            (Some(this), Some(writeback))
+        } else {
+            (None, None)
+        }
     }
 
     #[inline]
@@ -190,9 +273,17 @@ impl<T: Message + 'static> ConvertArgument for Option<&mut Option<Id<T>>> {
 
     type __StoredBeforeMessage = Option<(NonNull<*mut T>, *mut T)>;
 
+    type __WritebackOnDrop = Option<AutoreleaseOnDrop<T>>;
+
     #[inline]
-    fn __from_declared_param(_inner: Self::__Inner) -> Self {
-        todo!("`Option<&mut Option<Id<_>>>` is not supported in `declare_class!` yet")
+    unsafe fn __from_declared_param(inner: Self::__Inner) -> (Self, Self::__WritebackOnDrop) {
+        if let Some(inner) = inner {
+            // SAFETY: Checked by caller
+            let (this, writeback) = unsafe { <&mut Option<Id<T>>>::__from_declared_param(inner) };
+            (Some(this), Some(writeback))
+        } else {
+            (None, None)
+        }
     }
 
     #[inline]
@@ -217,8 +308,10 @@ impl<T: Message + 'static> ConvertArgument for Option<&mut Option<Id<T>>> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::mutability::Immutable;
     use crate::rc::{__RcTestObject, __ThreadTestData, autoreleasepool};
-    use crate::{msg_send, msg_send_id, ClassType};
+    use crate::runtime::NSObject;
+    use crate::{declare_class, msg_send, msg_send_id, ClassType};
 
     #[test]
     fn test_bool_error() {
@@ -343,4 +436,91 @@ mod tests {
         expected.dealloc += 1;
         expected.assert_current();
     }
+
+    declare_class!(
+        struct DeclaredOutParam;
+
+        unsafe impl ClassType for DeclaredOutParam {
+            type Super = NSObject;
+            type Mutability = Immutable;
+            const NAME: &'static str = "DeclaredOutParam";
+        }
+
+        unsafe impl DeclaredOutParam {
+            #[method(unmodified:)]
+            fn unmodified(_param: &mut Option<Id<__RcTestObject>>) {}
+
+            #[method(replace:)]
+            fn replace(param: Option<&mut Option<Id<__RcTestObject>>>) {
+                if let Some(param) = param {
+                    *param = Some(__RcTestObject::new());
+                }
+            }
+        }
+    );
+
+    #[test]
+    fn test_declared_unmodified() {
+        let mut expected = __ThreadTestData::current();
+        let cls = DeclaredOutParam::class();
+
+        let mut param = Some(__RcTestObject::new());
+        expected.alloc += 1;
+        expected.init += 1;
+        expected.assert_current();
+
+        autoreleasepool(|_| {
+            let _: () = unsafe { msg_send![cls, unmodified: &mut param] };
+            // Retained and autoreleased by the method, and retained and
+            // released by the caller.
+            expected.retain += 2;
+            expected.autorelease += 1;
+            expected.release += 1;
+            expected.assert_current();
+        });
+        expected.release += 1;
+        expected.assert_current();
+
+        drop(param);
+        expected.release += 1;
+        expected.dealloc += 1;
+        expected.assert_current();
+    }
+
+    #[test]
+    fn test_declared_replace() {
+        let mut expected = __ThreadTestData::current();
+        let cls = DeclaredOutParam::class();
+
+        let _: () = unsafe { msg_send![cls, replace: None::<&mut Option<Id<__RcTestObject>>>] };
+        expected.assert_current();
+
+        let mut param = Some(__RcTestObject::new());
+        expected.alloc += 1;
+        expected.init += 1;
+        expected.assert_current();
+
+        autoreleasepool(|_| {
+            let _: () = unsafe { msg_send![cls, replace: Some(&mut param)] };
+            // The old value is retained by the method, and released when
+            // replaced, and then released by the caller.
+            expected.retain += 1;
+            expected.release += 2;
+            expected.dealloc += 1;
+            // The new value is autoreleased by the method, and retained by
+            // the caller.
+            expected.alloc += 1;
+            expected.init += 1;
+            expected.autorelease += 1;
+            expected.retain += 1;
+            expected.assert_current();
+        });
+        expected.release += 1;
+        expected.assert_current();
+
+        drop(param);
+        expected.release += 1;
+        expected.dealloc += 1;
+        expected.assert_current();
+    }
 }
@@ -0,0 +1,344 @@
+//! Forwarding messages that a declared class does not implement.
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+use core::ffi::c_void;
+use core::fmt;
+use core::ptr;
+use std::ffi::CString;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+
+use libffi::low::{ffi_arg, ffi_cif};
+use libffi::middle::{Cif, Type};
+
+use crate::__macro_helpers::retain_semantics;
+use crate::encode::MethodEncoding;
+use crate::ffi;
+use crate::rc::Id;
+use crate::runtime::{
+    leak_closure, AnyClass, AnyObject, Bool, Imp, Invocation, MessageReceiver, Sel, Value,
+};
+use crate::{msg_send, sel};
+
+/// A message that was sent to an instance of a declared class, but which
+/// the class does not implement.
+///
+/// This is given to the handler added with
+/// [`ClassBuilder::add_forwarding_handler`], or to the method marked with
+/// `#[forward]` in [`declare_class!`].
+///
+/// [`ClassBuilder::add_forwarding_handler`]: crate::declare::ClassBuilder::add_forwarding_handler
+/// [`declare_class!`]: crate::declare_class
+pub struct ForwardedMessage<'a> {
+    invocation: &'a Invocation,
+    arguments: Vec<Value>,
+}
+
+impl ForwardedMessage<'_> {
+    /// The selector of the message.
+    #[inline]
+    pub fn sel(&self) -> Sel {
+        self.invocation.sel()
+    }
+
+    /// The encoding of the message, including the receiver and the
+    /// selector.
+    #[inline]
+    pub fn encoding(&self) -> &MethodEncoding {
+        self.invocation.encoding()
+    }
+
+    /// The arguments of the message, excluding the receiver and the
+    /// selector.
+    #[inline]
+    pub fn arguments(&self) -> &[Value] {
+        &self.arguments
+    }
+
+    /// Send the message to another object, and return the result.
+    ///
+    ///
+    /// # Safety
+    ///
+    /// The target must implement a method for the selector with the
+    /// encoding of the message.
+    // The arguments were read using the encoding, so they always match it.
+    #[allow(clippy::missing_panics_doc)]
+    pub unsafe fn forward_to<T: MessageReceiver>(&self, target: T) -> Value {
+        // SAFETY: Upheld by caller.
+        unsafe { self.invocation.invoke(target, &self.arguments) }
+            .expect("the arguments to always match the encoding")
+    }
+}
+
+impl fmt::Debug for ForwardedMessage<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ForwardedMessage")
+            .field("sel", &self.sel())
+            .field("encoding", self.encoding())
+            .field("arguments", &self.arguments)
+            .finish()
+    }
+}
+
+pub(super) type Handler = Box<dyn Fn(&AnyObject, ForwardedMessage<'_>) -> Value>;
+
+/// The state shared by all the forwarding methods of a class.
+struct Forwarder {
+    handler: Handler,
+    superclass: Option<&'static AnyClass>,
+}
+
+/// The state of a single forwarding method.
+struct ForwardedMethod {
+    forwarder: &'static Forwarder,
+    invocation: Invocation,
+}
+
+impl Forwarder {
+    /// Add a forwarding method for the selector to the class, if one of the
+    /// protocols it adopts declares the method.
+    fn resolve(&'static self, cls: &AnyClass, sel: Sel) -> bool {
+        let description = successors(cls).find_map(|cls| {
+            cls.adopted_protocols().iter().find_map(|protocol| {
+                protocol
+                    .method_description(sel, true, true)
+                    .or_else(|| protocol.method_description(sel, false, true))
+            })
+        });
+        let description = match description {
+            Some(description) => description,
+            None => return self.resolve_super(cls, sel),
+        };
+        let invocation = match description
+            .encoding()
+            .map_err(Into::into)
+            .and_then(|encoding| Invocation::new(sel, encoding))
+        {
+            Ok(invocation) => invocation,
+            // Leave methods that cannot be forwarded unimplemented.
+            Err(_) => return self.resolve_super(cls, sel),
+        };
+
+        let method: &'static ForwardedMethod = Box::leak(Box::new(ForwardedMethod {
+            forwarder: self,
+            invocation,
+        }));
+        let imp = leak_closure(method.invocation.cif().clone(), forward, method);
+
+        let types = CString::new(description.types()).unwrap();
+        // SAFETY: The closure has the signature described by the types. This
+        // fails if the method was added concurrently, which is fine.
+        unsafe {
+            ffi::class_addMethod(
+                cls as *const AnyClass as *mut _,
+                sel.as_ptr(),
+                Some(imp),
+                types.as_ptr(),
+            )
+        };
+        true
+    }
+
+    fn resolve_super(&self, cls: &AnyClass, sel: Sel) -> bool {
+        match self.superclass {
+            Some(superclass)
+                if superclass
+                    .class_method(sel!(resolveInstanceMethod:))
+                    .is_some() =>
+            {
+                // SAFETY: The superclass implements the method, with the
+                // signature that `NSObject` uses.
+                let res: Bool = unsafe {
+                    msg_send![super(cls, superclass.metaclass()), resolveInstanceMethod: sel]
+                };
+                res.as_bool()
+            }
+            _ => false,
+        }
+    }
+
+    /// Handle the message, preferring the object returned from
+    /// `forwardingTargetForSelector:`, if any.
+    fn forward(&self, receiver: &AnyObject, message: ForwardedMessage<'_>) -> Value {
+        if receiver
+            .class()
+            .responds_to(sel!(forwardingTargetForSelector:))
+        {
+            // SAFETY: The method takes a selector and returns an object.
+            let target: *mut AnyObject =
+                unsafe { msg_send![receiver, forwardingTargetForSelector: message.sel()] };
+            // SAFETY: The returned object is valid or NULL.
+            if let Some(target) = unsafe { target.as_ref() } {
+                if !ptr::eq(target, receiver) {
+                    // SAFETY: The target must respond to the selector, same
+                    // as in Objective-C.
+                    return unsafe { message.forward_to(target) };
+                }
+            }
+        }
+        (self.handler)(receiver, message)
+    }
+}
+
+fn successors(cls: &AnyClass) -> impl Iterator<Item = &AnyClass> {
+    core::iter::successors(Some(cls), |cls| cls.superclass())
+}
+
+/// The implementation of `+resolveInstanceMethod:`.
+unsafe extern "C" fn resolve(
+    _cif: &ffi_cif,
+    result: &mut c_void,
+    args: *const *const c_void,
+    forwarder: &&'static Forwarder,
+) {
+    // SAFETY: The closure is called with a class, a selector and a
+    // selector.
+    let (cls, sel) = unsafe {
+        let cls = &**(*args).cast::<*const AnyClass>();
+        let sel = Sel::from_ptr(*(*args.add(2)).cast::<*const ffi::objc_selector>());
+        (cls, sel)
+    };
+    let res = match sel {
+        Some(sel) => forwarder.resolve(cls, sel),
+        None => false,
+    };
+    // SAFETY: `libffi` widens small integer return values.
+    unsafe {
+        (result as *mut c_void)
+            .cast::<ffi_arg>()
+            .write(Bool::new(res).as_raw() as ffi_arg)
+    };
+}
+
+/// The implementation of each forwarded method.
+unsafe extern "C" fn forward(
+    _cif: &ffi_cif,
+    result: &mut c_void,
+    args: *const *const c_void,
+    method: &ForwardedMethod,
+) {
+    let invocation = &method.invocation;
+    // SAFETY: The closure is called with a receiver and the arguments in
+    // the encoding.
+    let receiver = unsafe { &**(*args).cast::<*const AnyObject>() };
+    let arguments = unsafe { invocation.read_arguments(args) };
+    let message = ForwardedMessage {
+        invocation,
+        arguments,
+    };
+
+    // Unwinding out of the closure (and through `libffi`) is not possible,
+    // so abort if the handler panics, or returns a value of the wrong type.
+    let res = catch_unwind(AssertUnwindSafe(|| {
+        let ret = method.forwarder.forward(receiver, message);
+        // SAFETY: The result pointer is the one given to the closure.
+        unsafe { invocation.write_return(ret, result) };
+    }));
+    if res.is_err() {
+        std::process::abort();
+    }
+
+    // `init` consumes the receiver.
+    if retain_semantics(invocation.sel().name()) == 3 {
+        // SAFETY: The receiver is valid, and we own it.
+        let _ = unsafe { Id::new(receiver as *const AnyObject as *mut AnyObject) };
+    }
+}
+
+/// Create an implementation of `+resolveInstanceMethod:` that adds methods
+/// calling the handler for instance methods declared in the adopted
+/// protocols of the class.
+pub(super) fn resolve_instance_method(
+    superclass: Option<&'static AnyClass>,
+    handler: Handler,
+) -> Imp {
+    let forwarder: &'static Forwarder = Box::leak(Box::new(Forwarder {
+        handler,
+        superclass,
+    }));
+    // The callback receives a reference to the user data, which must
+    // itself be `'static` for the forwarded methods to reference it.
+    let userdata: &'static &'static Forwarder = Box::leak(Box::new(forwarder));
+    // `BOOL` is a single byte on all platforms.
+    let cif = Cif::new(
+        [Type::pointer(), Type::pointer(), Type::pointer()],
+        Type::u8(),
+    );
+    leak_closure(cif, resolve, userdata)
+}
+
+#[cfg(test)]
+mod tests {
+    use core::sync::atomic::{AtomicI32, AtomicPtr, Ordering};
+
+    use super::*;
+    use crate::declare::ClassBuilder;
+    use crate::runtime::NSObject;
+    use crate::test_utils;
+    use crate::{msg_send_id, ClassType};
+
+    #[test]
+    fn test_forwarding_handler() {
+        static BAR: AtomicI32 = AtomicI32::new(0);
+
+        fn handler(_obj: &NSObject, message: ForwardedMessage<'_>) -> Value {
+            match (message.sel().name(), message.arguments()) {
+                ("setBar:", [Value::I32(bar)]) => {
+                    BAR.store(*bar, Ordering::Relaxed);
+                    Value::Void
+                }
+                ("calculateFoo:", [Value::U32(foo)]) => Value::U32(foo + 1),
+                ("getName", []) => Value::Pointer(b"forwarded\0".as_ptr() as *mut c_void),
+                _ => panic!("unexpected message {message:?}"),
+            }
+        }
+
+        let mut builder = ClassBuilder::new("TestForwardingHandler", NSObject::class()).unwrap();
+        builder.add_protocol(test_utils::custom_subprotocol());
+        unsafe { builder.add_forwarding_handler(handler) };
+        let cls = builder.register();
+        let obj: Id<NSObject> = unsafe { msg_send_id![cls, new] };
+
+        let _: () = unsafe { msg_send![&obj, setBar: 42i32] };
+        assert_eq!(BAR.load(Ordering::Relaxed), 42);
+        let res: u32 = unsafe { msg_send![&obj, calculateFoo: 4u32] };
+        assert_eq!(res, 5);
+        let name: *const u8 = unsafe { msg_send![&obj, getName] };
+        assert_eq!(name, b"forwarded\0".as_ptr());
+
+        // Methods are only forwarded if they're declared in a protocol
+        assert!(cls.responds_to(sel!(setBar:)));
+        assert!(!cls.responds_to(sel!(unknownMethod)));
+    }
+
+    #[test]
+    fn test_forwarding_target() {
+        static TARGET: AtomicPtr<AnyObject> = AtomicPtr::new(ptr::null_mut());
+
+        extern "C" fn forwarding_target(_this: &NSObject, _cmd: Sel, _sel: Sel) -> *mut AnyObject {
+            TARGET.load(Ordering::Relaxed)
+        }
+
+        fn handler(_obj: &NSObject, message: ForwardedMessage<'_>) -> Value {
+            panic!("unexpected message {message:?}")
+        }
+
+        let mut builder = ClassBuilder::new("TestForwardingTarget", NSObject::class()).unwrap();
+        builder.add_protocol(test_utils::custom_protocol());
+        unsafe {
+            builder.add_method(
+                sel!(forwardingTargetForSelector:),
+                forwarding_target as extern "C" fn(_, _, _) -> _,
+            );
+            builder.add_forwarding_handler(handler);
+        }
+        let cls = builder.register();
+        let obj: Id<NSObject> = unsafe { msg_send_id![cls, new] };
+
+        let target = test_utils::custom_object();
+        TARGET.store(Id::as_ptr(&target) as *mut AnyObject, Ordering::Relaxed);
+        let _: () = unsafe { msg_send![&obj, setBar: 7i32] };
+        let foo: u32 = unsafe { msg_send![&target, foo] };
+        assert_eq!(foo, 7);
+    }
+}
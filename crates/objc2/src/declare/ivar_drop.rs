@@ -2,7 +2,8 @@ use alloc::boxed::Box;
 use core::ffi::c_void;
 
 use crate::encode::{Encode, Encoding};
-use crate::rc::Id;
+use crate::mutability::IsRetainable;
+use crate::rc::{Id, WeakId};
 use crate::Message;
 
 use super::InnerIvarType;
@@ -23,6 +24,7 @@ mod private {
 /// - `Option<Box<T>>`
 /// - `Id<T>`
 /// - `Option<Id<T>>`
+/// - `Option<WeakId<T>>`
 ///
 /// Further may be added when the standard library guarantee their layout.
 #[repr(transparent)]
@@ -160,6 +162,34 @@ unsafe impl<T: Message> InnerIvarType for IvarDrop<Option<Id<T>>> {
     }
 }
 
+unsafe impl<T: Message + IsRetainable> Encode for IvarDrop<Option<WeakId<T>>> {
+    const ENCODING: Encoding = <*const c_void>::ENCODING;
+}
+
+// SAFETY: `Option<WeakId<T>>` is safe to zero-initialize
+unsafe impl<T: Message + IsRetainable> private::IvarDropHelper for Option<WeakId<T>> {
+    type Inner = Option<WeakId<T>>;
+}
+
+// SAFETY: `WeakId<T>` is a `#[repr(transparent)]` wrapper around a `Box`, so
+// `Option<WeakId<T>>` has the same layout as `Option<Box<T>>`.
+//
+// This is valid to initialize as all-zeroes, so the user doesn't have to do
+// anything to initialize it.
+unsafe impl<T: Message + IsRetainable> InnerIvarType for IvarDrop<Option<WeakId<T>>> {
+    type Output = Option<WeakId<T>>;
+
+    #[inline]
+    unsafe fn __deref(&self) -> &Self::Output {
+        &self.0
+    }
+
+    #[inline]
+    unsafe fn __deref_mut(&mut self) -> &mut Self::Output {
+        &mut self.0
+    }
+}
+
 // TODO: Allow the following once their layout is guaranteed by `std`:
 // - Arc<T>
 // - Option<Arc<T>>
@@ -171,7 +201,7 @@ unsafe impl<T: Message> InnerIvarType for IvarDrop<Option<Id<T>>> {
 // - String
 
 // TODO: Allow `WeakId` once we figure out how to allow it being initialized
-// by default.
+// by default (`Option<WeakId<T>>` is supported in the meantime).
 
 #[inline]
 #[track_caller]
@@ -4,39 +4,62 @@
 //! variables and methods can then be added before the class is ultimately
 //! registered.
 
+#[cfg(feature = "invocation")]
+mod forwarding;
 mod ivar;
 mod ivar_bool;
 mod ivar_drop;
 mod ivar_encode;
 mod ivar_forwarding_impls;
+mod owned_class;
+mod property;
 
+use alloc::borrow::Cow;
 use alloc::format;
 use alloc::string::ToString;
 use core::mem;
 use core::mem::ManuallyDrop;
 use core::ptr;
 use core::ptr::NonNull;
-use std::ffi::CString;
+use std::ffi::{CStr, CString};
 
+use crate::__macro_helpers::{EncodingStr, MethodTypes};
 use crate::encode::{Encode, EncodeArguments, EncodeReturn, Encoding};
 use crate::ffi;
-use crate::runtime::{AnyClass, AnyObject, AnyProtocol, Bool, Imp, MethodImplementation, Sel};
+#[cfg(feature = "invocation")]
+use crate::runtime::Value;
+use crate::runtime::{
+    imp_from_block, remove_block, AnyClass, AnyObject, AnyProtocol, Bool, Imp, MethodBlock,
+    MethodImplementation, Sel,
+};
 use crate::sel;
 use crate::Message;
 
+#[cfg(feature = "invocation")]
+pub use forwarding::ForwardedMessage;
 pub use ivar::{InnerIvarType, Ivar, IvarType};
 pub use ivar_bool::IvarBool;
 pub use ivar_drop::IvarDrop;
 pub use ivar_encode::IvarEncode;
-
-fn method_type_encoding(ret: &Encoding, args: &[Encoding]) -> CString {
+pub use owned_class::OwnedClass;
+pub(crate) use property::default_setter;
+
+pub(crate) fn method_type_encoding(
+    ret: &Encoding,
+    args: &[Encoding],
+    types: Option<&'static CStr>,
+) -> Cow<'static, CStr> {
+    // Usually computed at compile-time, but may be too long for that.
+    if let Some(types) = types {
+        return Cow::Borrowed(types);
+    }
     // First two arguments are always self and the selector
     let mut types = format!("{ret}{}{}", <*mut AnyObject>::ENCODING, Sel::ENCODING);
     for enc in args {
         use core::fmt::Write;
         write!(&mut types, "{enc}").unwrap();
     }
-    CString::new(types).unwrap()
+    Cow::Owned(CString::new(types).unwrap())
 }
 
 trait Log2Alignment {
@@ -58,6 +81,11 @@ impl<T> Log2Alignment for T {
 /// A type for declaring a new class and adding new methods and ivars to it
 /// before registering it.
 ///
+/// If the builder is dropped without being registered, the class is
+/// disposed of again. Registered classes live for the rest of the program,
+/// unless registered with [`register_owned`][Self::register_owned] and
+/// disposed of with [`OwnedClass::dispose`].
+///
 /// **Note**: You likely don't need the dynamicism that this provides!
 /// Consider using the [`declare_class!`][crate::declare_class] macro instead.
 ///
@@ -213,7 +241,13 @@ impl ClassBuilder {
     fn with_superclass(name: &str, superclass: Option<&AnyClass>) -> Option<Self> {
         let name = CString::new(name).unwrap();
         let super_ptr = superclass.map_or(ptr::null(), |c| c).cast();
-        let cls = unsafe { ffi::objc_allocateClassPair(super_ptr, name.as_ptr(), 0) };
+        // In debug builds, allocate space for tracking the number of live
+        // instances, in case the class is registered with `register_owned`.
+        #[cfg(debug_assertions)]
+        let extra_bytes = owned_class::tracking::EXTRA_BYTES;
+        #[cfg(not(debug_assertions))]
+        let extra_bytes = 0;
+        let cls = unsafe { ffi::objc_allocateClassPair(super_ptr, name.as_ptr(), extra_bytes) };
         NonNull::new(cls).map(|cls| Self { cls })
     }
 
@@ -276,18 +310,79 @@ impl ClassBuilder {
                 sel,
                 F::Arguments::ENCODINGS,
                 &F::Return::ENCODING_RETURN,
+                MethodTypes::<F::Return, F::Arguments>::cstr(),
                 func.__imp(),
             )
         }
     }
 
-    unsafe fn add_method_inner(
+    /// Adds a method whose implementation is the given block.
+    ///
+    /// This allows the method to capture state, for example when the class
+    /// is generated at runtime. The block is called with the receiver as its
+    /// first argument, followed by the arguments of the method.
+    ///
+    /// The block is copied, and the copy is kept alive for as long as the
+    /// class exists.
+    ///
+    /// See [`block2`] for how to create blocks from Rust closures. Blocks
+    /// created on the stack must be copied to the heap before being used
+    /// here.
+    ///
+    /// [`block2`]: https://docs.rs/block2/
+    ///
+    ///
+    /// # Panics
+    ///
+    /// Panics in the same cases as [`add_method`][Self::add_method].
+    ///
+    ///
+    /// # Safety
+    ///
+    /// The caller must ensure that the types match those that are expected
+    /// when the method is invoked from Objective-C.
+    ///
+    /// Additionally, the block, and everything it captures, must be safe to
+    /// use from any thread that the method may be called on, and must remain
+    /// valid for as long as the class exists.
+    #[doc(alias = "imp_implementationWithBlock")]
+    pub unsafe fn add_method_with_block<T, B>(&mut self, sel: Sel, block: &B)
+    where
+        T: Message + ?Sized,
+        B: MethodBlock<Callee = T>,
+    {
+        let enc_args = B::Arguments::ENCODINGS;
+        let enc_ret = &B::Return::ENCODING_RETURN;
+        // Verify before creating the implementation, since that copies the
+        // block, which would be leaked if verification panicked.
+        self.verify_method(sel, enc_args, enc_ret);
+        // SAFETY: Checked by caller
+        let imp = unsafe { imp_from_block(block) };
+        let types = MethodTypes::<B::Return, B::Arguments>::cstr();
+        // SAFETY: Checked by caller
+        let success = unsafe { self.add_method_unverified(sel, enc_args, enc_ret, types, imp) };
+        if !success {
+            // SAFETY: The implementation was not added, so it is unused.
+            unsafe { remove_block(imp) };
+        }
+        assert!(success, "failed to add method {sel}");
+    }
+
+    pub(crate) unsafe fn add_method_inner(
         &mut self,
         sel: Sel,
         enc_args: &[Encoding],
         enc_ret: &Encoding,
+        types: Option<&'static CStr>,
         func: Imp,
     ) {
+        self.verify_method(sel, enc_args, enc_ret);
+        // SAFETY: Checked by caller
+        let success = unsafe { self.add_method_unverified(sel, enc_args, enc_ret, types, func) };
+        assert!(success, "failed to add method {sel}");
+    }
+
+    fn verify_method(&self, sel: Sel, enc_args: &[Encoding], enc_ret: &Encoding) {
         let sel_args = sel.number_of_arguments();
         assert_eq!(
             sel_args,
@@ -307,12 +402,22 @@ impl ClassBuilder {
                 }
             }
         }
+    }
 
-        let types = method_type_encoding(enc_ret, enc_args);
+    /// Returns whether the method was added.
+    unsafe fn add_method_unverified(
+        &mut self,
+        sel: Sel,
+        enc_args: &[Encoding],
+        enc_ret: &Encoding,
+        types: Option<&'static CStr>,
+        func: Imp,
+    ) -> bool {
+        let types = method_type_encoding(enc_ret, enc_args, types);
         let success = Bool::from_raw(unsafe {
             ffi::class_addMethod(self.as_mut_ptr(), sel.as_ptr(), Some(func), types.as_ptr())
         });
-        assert!(success.as_bool(), "failed to add method {sel}");
+        success.as_bool()
     }
 
     fn metaclass_mut(&mut self) -> *mut ffi::objc_class {
@@ -340,18 +445,65 @@ impl ClassBuilder {
                 sel,
                 F::Arguments::ENCODINGS,
                 &F::Return::ENCODING_RETURN,
+                MethodTypes::<F::Return, F::Arguments>::cstr(),
                 func.__imp(),
             )
         }
     }
 
+    /// Adds a class method whose implementation is the given block.
+    ///
+    /// See [`add_method_with_block`][Self::add_method_with_block] for
+    /// details.
+    ///
+    ///
+    /// # Panics
+    ///
+    /// Panics in the same cases as [`add_method`][Self::add_method].
+    ///
+    ///
+    /// # Safety
+    ///
+    /// Same as [`add_method_with_block`][Self::add_method_with_block].
+    #[doc(alias = "imp_implementationWithBlock")]
+    pub unsafe fn add_class_method_with_block<B>(&mut self, sel: Sel, block: &B)
+    where
+        B: MethodBlock<Callee = AnyClass>,
+    {
+        let enc_args = B::Arguments::ENCODINGS;
+        let enc_ret = &B::Return::ENCODING_RETURN;
+        // Verify before creating the implementation, same as in
+        // `add_method_with_block`.
+        self.verify_class_method(sel, enc_args, enc_ret);
+        // SAFETY: Checked by caller
+        let imp = unsafe { imp_from_block(block) };
+        let types = MethodTypes::<B::Return, B::Arguments>::cstr();
+        // SAFETY: Checked by caller
+        let success =
+            unsafe { self.add_class_method_unverified(sel, enc_args, enc_ret, types, imp) };
+        if !success {
+            // SAFETY: The implementation was not added, so it is unused.
+            unsafe { remove_block(imp) };
+        }
+        assert!(success, "failed to add class method {sel}");
+    }
+
     unsafe fn add_class_method_inner(
         &mut self,
         sel: Sel,
         enc_args: &[Encoding],
         enc_ret: &Encoding,
+        types: Option<&'static CStr>,
         func: Imp,
     ) {
+        self.verify_class_method(sel, enc_args, enc_ret);
+        // SAFETY: Checked by caller
+        let success =
+            unsafe { self.add_class_method_unverified(sel, enc_args, enc_ret, types, func) };
+        assert!(success, "failed to add class method {sel}");
+    }
+
+    fn verify_class_method(&self, sel: Sel, enc_args: &[Encoding], enc_ret: &Encoding) {
         let sel_args = sel.number_of_arguments();
         assert_eq!(
             sel_args,
@@ -371,8 +523,18 @@ impl ClassBuilder {
                 }
             }
         }
+    }
 
-        let types = method_type_encoding(enc_ret, enc_args);
+    /// Returns whether the class method was added.
+    unsafe fn add_class_method_unverified(
+        &mut self,
+        sel: Sel,
+        enc_args: &[Encoding],
+        enc_ret: &Encoding,
+        types: Option<&'static CStr>,
+        func: Imp,
+    ) -> bool {
+        let types = method_type_encoding(enc_ret, enc_args, types);
         let success = Bool::from_raw(unsafe {
             ffi::class_addMethod(
                 self.metaclass_mut(),
@@ -381,7 +543,67 @@ impl ClassBuilder {
                 types.as_ptr(),
             )
         });
-        assert!(success.as_bool(), "failed to add class method {sel}");
+        success.as_bool()
+    }
+
+    /// Forward messages that instances of the class do not implement to the
+    /// given handler.
+    ///
+    /// This makes it possible to implement proxies and mock objects: When
+    /// an instance receives a message that is declared as an instance
+    /// method by one of the protocols that the class (or one of its
+    /// superclasses) adopts, but which it does not implement, a method is
+    /// added to the class that calls the handler with the selector and
+    /// arguments of the message. The value returned from the handler is then
+    /// returned from the method.
+    ///
+    /// If the object responds to `forwardingTargetForSelector:`, and that
+    /// returns another object, the message is sent directly to that object
+    /// instead of calling the handler.
+    ///
+    /// This works by adding a `+resolveInstanceMethod:`, so the class must
+    /// not implement that itself.
+    ///
+    /// See [`Invocation`][crate::runtime::Invocation] for the supported
+    /// argument and return types; methods using any other types are not
+    /// forwarded.
+    ///
+    /// The handler is called from Objective-C, where unwinding is not
+    /// possible, so the process is aborted if the handler panics, or if it
+    /// returns a value that does not match the return type of the method.
+    ///
+    ///
+    /// # Safety
+    ///
+    /// `T` must be the type of the instances of the class, and the encodings
+    /// of the methods in the adopted protocols must be correct.
+    #[cfg(feature = "invocation")]
+    pub unsafe fn add_forwarding_handler<T: Message + 'static>(
+        &mut self,
+        handler: fn(&T, ForwardedMessage<'_>) -> Value,
+    ) {
+        // SAFETY: Classes are never deallocated while instances of them (or
+        // of their subclasses) exist.
+        let superclass = unsafe { AnyClass::superclass_raw(self.cls.as_ptr()) };
+        let imp = forwarding::resolve_instance_method(
+            superclass,
+            alloc::boxed::Box::new(move |obj, message| {
+                // SAFETY: The caller ensures that the object is a `T`.
+                let obj = unsafe { &*(obj as *const AnyObject).cast::<T>() };
+                handler(obj, message)
+            }),
+        );
+        // SAFETY: The implementation has the signature of
+        // `+resolveInstanceMethod:`.
+        unsafe {
+            self.add_class_method_inner(
+                sel!(resolveInstanceMethod:),
+                &[Sel::ENCODING],
+                &Bool::ENCODING_RETURN,
+                None,
+                imp,
+            )
+        }
     }
 
     /// Adds an ivar with type `T` and the provided name.
@@ -393,7 +615,7 @@ impl ClassBuilder {
     /// happens if there already was an ivar with that name.
     pub fn add_ivar<T: Encode>(&mut self, name: &str) {
         // SAFETY: The encoding is correct
-        unsafe { self.add_ivar_inner::<T>(name, &T::ENCODING) }
+        unsafe { self.add_ivar_inner::<T>(name) }
     }
 
     // Monomorphized version
@@ -403,6 +625,7 @@ impl ClassBuilder {
         size: usize,
         align: u8,
         encoding: &Encoding,
+        types: Option<&'static CStr>,
     ) {
         // `class_addIvar` sadly doesn't check this for us.
         //
@@ -418,7 +641,10 @@ impl ClassBuilder {
         }
 
         let c_name = CString::new(name).unwrap();
-        let encoding = CString::new(encoding.to_string()).unwrap();
+        // Usually computed at compile-time, but may be too long for that.
+        let encoding = types
+            .map(Cow::Borrowed)
+            .unwrap_or_else(|| Cow::Owned(CString::new(encoding.to_string()).unwrap()));
 
         // Note: The Objective-C runtime contains functionality to do stuff
         // with "instance variable layouts", but we don't have to touch any of
@@ -435,8 +661,16 @@ impl ClassBuilder {
         assert!(success.as_bool(), "failed to add ivar {name}");
     }
 
-    unsafe fn add_ivar_inner<T>(&mut self, name: &str, encoding: &Encoding) {
-        unsafe { self.add_ivar_inner_mono(name, mem::size_of::<T>(), T::LOG2_ALIGNMENT, encoding) }
+    unsafe fn add_ivar_inner<T: Encode>(&mut self, name: &str) {
+        unsafe {
+            self.add_ivar_inner_mono(
+                name,
+                mem::size_of::<T>(),
+                T::LOG2_ALIGNMENT,
+                &T::ENCODING,
+                EncodingStr::<T>::cstr(),
+            )
+        }
     }
 
     /// Adds an instance variable from an [`IvarType`].
@@ -447,7 +681,7 @@ impl ClassBuilder {
     /// Same as [`ClassBuilder::add_ivar`].
     pub fn add_static_ivar<T: IvarType>(&mut self) {
         // SAFETY: The encoding is correct
-        unsafe { self.add_ivar_inner::<T::Type>(T::NAME, &T::Type::ENCODING) }
+        unsafe { self.add_ivar_inner::<T::Type>(T::NAME) }
     }
 
     /// Adds the given protocol to self.
@@ -473,6 +707,17 @@ impl ClassBuilder {
         unsafe { ffi::objc_registerClassPair(this.as_mut_ptr()) };
         unsafe { this.cls.cast::<AnyClass>().as_ref() }
     }
+
+    /// Registers the [`ClassBuilder`], consuming it, and returns an owned
+    /// handle to the newly registered class, which allows disposing of it
+    /// again.
+    ///
+    /// See [`OwnedClass`] for details.
+    pub fn register_owned(self) -> OwnedClass {
+        let cls = NonNull::from(self.register());
+        // SAFETY: The class was allocated by us, and just registered.
+        unsafe { OwnedClass::new(cls) }
+    }
 }
 
 impl Drop for ClassBuilder {
@@ -531,6 +776,7 @@ impl ProtocolBuilder {
         sel: Sel,
         enc_args: &[Encoding],
         enc_ret: &Encoding,
+        types: Option<&'static CStr>,
         required: bool,
         instance_method: bool,
     ) {
@@ -541,7 +787,7 @@ impl ProtocolBuilder {
             "selector {sel} accepts {sel_args} arguments, but function accepts {}",
             enc_args.len(),
         );
-        let types = method_type_encoding(enc_ret, enc_args);
+        let types = method_type_encoding(enc_ret, enc_args, types);
         unsafe {
             ffi::protocol_addMethodDescription(
                 self.as_mut_ptr(),
@@ -563,6 +809,7 @@ impl ProtocolBuilder {
             sel,
             Args::ENCODINGS,
             &Ret::ENCODING_RETURN,
+            MethodTypes::<Ret, Args>::cstr(),
             required,
             true,
         )
@@ -578,6 +825,7 @@ impl ProtocolBuilder {
             sel,
             Args::ENCODINGS,
             &Ret::ENCODING_RETURN,
+            MethodTypes::<Ret, Args>::cstr(),
             required,
             false,
         )
@@ -619,7 +867,9 @@ mod tests {
     use crate::mutability::Immutable;
     use crate::rc::Id;
     use crate::runtime::{NSObject, NSObjectProtocol};
-    use crate::{declare_class, extern_methods, msg_send, test_utils, ClassType, ProtocolType};
+    use crate::{
+        declare_class, extern_methods, msg_send, msg_send_id, test_utils, ClassType, ProtocolType,
+    };
 
     #[test]
     fn test_alignment() {
@@ -714,7 +964,7 @@ mod tests {
 
     #[test]
     #[cfg_attr(
-        debug_assertions,
+        all(debug_assertions, not(feature = "relax-void-encoding")),
         should_panic = "declared invalid method -[TestClassBuilderInvalidMethod foo]: expected return to have type code 'I', but found 'i'"
     )]
     fn invalid_method() {
@@ -732,7 +982,7 @@ mod tests {
 
     #[test]
     #[cfg_attr(
-        debug_assertions,
+        all(debug_assertions, not(feature = "relax-void-encoding")),
         should_panic = "declared invalid method +[TestClassBuilderInvalidClassMethod classFoo]: expected return to have type code 'I', but found 'i'"
     )]
     fn invalid_class_method() {
@@ -806,6 +1056,66 @@ mod tests {
         let _builder = ClassBuilder::new("TestClassBuilderDrop", cls).unwrap();
     }
 
+    #[test]
+    fn test_classbuilder_register_owned() {
+        let name = "TestClassBuilderRegisterOwned";
+        for _ in 0..2 {
+            let cls = ClassBuilder::new(name, NSObject::class())
+                .unwrap()
+                .register_owned();
+            assert_eq!(cls.name(), name);
+            assert_eq!(AnyClass::get(name), Some(&*cls));
+
+            let obj: Id<NSObject> = unsafe { msg_send_id![&*cls, new] };
+            assert_eq!(obj.class(), &*cls);
+            drop(obj);
+
+            unsafe { cls.dispose() };
+            // After disposing the class, we can create a new one with the
+            // same name.
+            assert_eq!(AnyClass::get(name), None);
+        }
+    }
+
+    #[test]
+    #[cfg_attr(
+        debug_assertions,
+        should_panic = "cannot dispose of class TestClassBuilderDisposeAlive, it still has 1 live instances"
+    )]
+    #[cfg_attr(not(debug_assertions), ignore = "only checked in debug builds")]
+    fn test_classbuilder_dispose_alive() {
+        let cls = ClassBuilder::new("TestClassBuilderDisposeAlive", NSObject::class())
+            .unwrap()
+            .register_owned();
+        let _obj: Id<NSObject> = unsafe { msg_send_id![&*cls, new] };
+        unsafe { cls.dispose() };
+    }
+
+    #[test]
+    #[cfg_attr(
+        debug_assertions,
+        should_panic = "cannot dispose of class TestClassBuilderDisposeSuperclass, it has the subclass TestClassBuilderDisposeSubclass"
+    )]
+    #[cfg_attr(not(debug_assertions), ignore = "only checked in debug builds")]
+    fn test_classbuilder_dispose_superclass() {
+        let cls = ClassBuilder::new("TestClassBuilderDisposeSuperclass", NSObject::class())
+            .unwrap()
+            .register_owned();
+        let subclass = ClassBuilder::new("TestClassBuilderDisposeSubclass", &cls)
+            .unwrap()
+            .register_owned();
+
+        // Instances of subclasses are not counted for the superclass
+        let obj: Id<NSObject> = unsafe { msg_send_id![&*subclass, new] };
+        drop(obj);
+        unsafe { subclass.dispose() };
+
+        let _subclass = ClassBuilder::new("TestClassBuilderDisposeSubclass", &cls)
+            .unwrap()
+            .register();
+        unsafe { cls.dispose() };
+    }
+
     #[test]
     fn test_custom_class() {
         // Registering the custom class is in test_utils
@@ -0,0 +1,341 @@
+use core::fmt;
+use core::ops::Deref;
+use core::ptr::NonNull;
+
+use crate::ffi;
+use crate::runtime::AnyClass;
+
+/// An owned handle to a class that was registered with
+/// [`ClassBuilder::register_owned`].
+///
+/// Unlike the `&'static AnyClass` returned by [`ClassBuilder::register`],
+/// this allows disposing of the class again with [`dispose`], which is
+/// useful for short-lived classes, e.g. classes created in tests, or
+/// classes belonging to a plugin that is reloaded.
+///
+/// Dropping the handle does _not_ dispose of the class, it is then simply
+/// left registered for the rest of the program.
+///
+/// In debug builds, the number of live instances of the class is tracked
+/// (by hooking `+allocWithZone:`), and [`dispose`] panics if any instances
+/// are still alive.
+///
+/// [`ClassBuilder::register_owned`]: crate::declare::ClassBuilder::register_owned
+/// [`ClassBuilder::register`]: crate::declare::ClassBuilder::register
+/// [`dispose`]: Self::dispose
+#[allow(missing_copy_implementations)]
+pub struct OwnedClass {
+    cls: NonNull<AnyClass>,
+}
+
+// SAFETY: `AnyClass` is `Send + Sync`, and disposing of the class requires
+// ownership of the handle.
+unsafe impl Send for OwnedClass {}
+unsafe impl Sync for OwnedClass {}
+
+impl OwnedClass {
+    /// # Safety
+    ///
+    /// The class must have been allocated with `ClassBuilder`, and have just
+    /// been registered.
+    pub(super) unsafe fn new(cls: NonNull<AnyClass>) -> Self {
+        // SAFETY: The class was allocated with `ClassBuilder`.
+        #[cfg(debug_assertions)]
+        unsafe {
+            tracking::install(cls.as_ref())
+        };
+        Self { cls }
+    }
+
+    /// Dispose of the class, and its metaclass.
+    ///
+    /// After this, the class can no longer be found with [`AnyClass::get`],
+    /// and a new class with the same name may be registered.
+    ///
+    /// Note that method implementations that were created from blocks are
+    /// not released.
+    ///
+    /// See [Apple's documentation](https://developer.apple.com/documentation/objectivec/1418912-objc_disposeclasspair?language=objc).
+    ///
+    ///
+    /// # Panics
+    ///
+    /// In debug builds, panics if any instances of the class created with
+    /// `+alloc`, `+allocWithZone:` or `+new` are still alive (unless the
+    /// class implements `+allocWithZone:` itself), or if the class has any
+    /// subclasses.
+    ///
+    ///
+    /// # Safety
+    ///
+    /// - No instances of the class, or of any of its subclasses, may be
+    ///   alive, and the class must not have any subclasses.
+    /// - No references to the class, its metaclass, or their methods, ivars
+    ///   and properties may be used after this, including any obtained
+    ///   through [`AnyClass::get`], [`AnyClass::classes`], or
+    ///   [`Swizzle`][crate::runtime::Swizzle] handles.
+    /// - The class must not be used concurrently on other threads.
+    ///
+    /// [`AnyClass::classes`]: crate::runtime::AnyClass::classes
+    #[doc(alias = "objc_disposeClassPair")]
+    pub unsafe fn dispose(self) {
+        #[cfg(debug_assertions)]
+        tracking::assert_disposable(&self);
+
+        // SAFETY: The class was allocated with `objc_allocateClassPair`, and
+        // the caller upholds the rest.
+        unsafe { ffi::objc_disposeClassPair(self.cls.as_ptr().cast()) }
+    }
+}
+
+impl Deref for OwnedClass {
+    type Target = AnyClass;
+
+    #[inline]
+    fn deref(&self) -> &AnyClass {
+        // SAFETY: The class is valid until it is disposed, which requires
+        // ownership of the handle.
+        unsafe { self.cls.as_ref() }
+    }
+}
+
+impl fmt::Debug for OwnedClass {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("OwnedClass").field(&**self).finish()
+    }
+}
+
+/// Tracking of live instances of owned classes, to check that they are not
+/// disposed while in use.
+///
+/// The tracker is stored in the indexed ivars of the class object, which
+/// `ClassBuilder` allocates in debug builds. The number of instances is
+/// incremented by a hook on `+allocWithZone:`, which also associates a
+/// sentinel object with the new instance. The runtime releases that sentinel
+/// when the instance is destroyed, which decrements the number again; this
+/// avoids having to hook `-dealloc`, which the class may implement itself.
+#[cfg(debug_assertions)]
+pub(super) mod tracking {
+    use alloc::boxed::Box;
+    use alloc::vec::Vec;
+    use core::mem;
+    use core::ptr::{self, NonNull};
+    use core::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::OwnedClass;
+    use crate::declare::IvarDrop;
+    use crate::ffi;
+    use crate::mutability::Mutable;
+    use crate::rc::Id;
+    use crate::runtime::{AnyClass, AnyObject, Imp, NSObject, NSZone, Sel};
+    use crate::{declare_class, msg_send_id, sel, ClassType};
+
+    type AllocWithZone = extern "C" fn(&AnyClass, Sel, *mut NSZone) -> *mut AnyObject;
+
+    #[repr(C)]
+    pub(crate) struct InstanceTracker {
+        live: AtomicUsize,
+        /// The implementation of `+allocWithZone:` that the hook forwards
+        /// to.
+        next: Option<Imp>,
+    }
+
+    /// The number of extra bytes to allocate for class objects.
+    pub(crate) const EXTRA_BYTES: usize = mem::size_of::<InstanceTracker>();
+
+    /// # Safety
+    ///
+    /// The class must have been allocated with `ClassBuilder`.
+    unsafe fn tracker_ptr(cls: &AnyClass) -> *mut InstanceTracker {
+        // `ClassBuilder` allocated, and zero-initialized, the tracker at the
+        // end of the class object.
+        unsafe { ffi::object_getIndexedIvars(cls.as_ptr().cast()) as *mut InstanceTracker }
+    }
+
+    /// # Safety
+    ///
+    /// The class must have been allocated with `ClassBuilder`.
+    unsafe fn tracker(cls: &AnyClass) -> &InstanceTracker {
+        // SAFETY: The tracker is valid, and only modified in `install`.
+        unsafe { &*tracker_ptr(cls) }
+    }
+
+    fn hook() -> Imp {
+        let hook: AllocWithZone = alloc_with_zone;
+        // SAFETY: Transmuting to an `unsafe` function pointer.
+        unsafe { mem::transmute::<AllocWithZone, Imp>(hook) }
+    }
+
+    /// Whether the class itself (i.e. not a superclass) implements
+    /// `+allocWithZone:` with the hook, which is only the case for classes
+    /// allocated with `ClassBuilder`.
+    fn is_tracked(cls: &AnyClass) -> bool {
+        let sel = sel!(allocWithZone:);
+        match cls.class_method(sel) {
+            Some(method) if method.implementation() as usize == hook() as usize => cls
+                .superclass()
+                .and_then(|superclass| superclass.class_method(sel))
+                .map_or(true, |inherited| !ptr::eq(method, inherited)),
+            _ => false,
+        }
+    }
+
+    /// The nearest tracked class, starting at `cls` itself.
+    fn owner(cls: &AnyClass) -> Option<&AnyClass> {
+        let mut cls = Some(cls);
+        while let Some(current) = cls {
+            if is_tracked(current) {
+                return Some(current);
+            }
+            cls = current.superclass();
+        }
+        None
+    }
+
+    /// # Safety
+    ///
+    /// The class must have been allocated with `ClassBuilder`, and just
+    /// been registered.
+    pub(super) unsafe fn install(cls: &AnyClass) {
+        let sel = sel!(allocWithZone:);
+        let superclass = match cls.superclass() {
+            Some(superclass) => superclass,
+            // Root classes are not tracked.
+            None => return,
+        };
+        let inherited = match superclass.class_method(sel) {
+            Some(method) => method,
+            None => return,
+        };
+        let mut next = inherited.implementation();
+        if next as usize == hook() as usize {
+            // Forward directly to the implementation that the tracked
+            // superclass forwards to, such that instances are only counted
+            // once, for their own class.
+            let owner = owner(superclass).expect("hook without tracked class");
+            // SAFETY: Tracked classes are allocated with `ClassBuilder`.
+            next = unsafe { tracker(owner) }
+                .next
+                .expect("tracked class without next");
+        }
+
+        // SAFETY: The class was allocated with `ClassBuilder`, and the hook
+        // has not yet been installed, so no-one else accesses the tracker.
+        unsafe { ptr::addr_of_mut!((*tracker_ptr(cls)).next).write(Some(next)) };
+
+        // SAFETY: The hook has the same signature as the inherited method.
+        //
+        // This fails if the class implements `+allocWithZone:` itself, in
+        // which case its instances are not tracked.
+        let _ = unsafe {
+            ffi::class_addMethod(
+                cls.metaclass().as_ptr() as *mut _,
+                sel.as_ptr(),
+                Some(hook()),
+                ffi::method_getTypeEncoding(inherited.as_ptr()),
+            )
+        };
+    }
+
+    extern "C" fn alloc_with_zone(cls: &AnyClass, sel: Sel, zone: *mut NSZone) -> *mut AnyObject {
+        let owner = owner(cls).expect("hook without tracked class");
+        // SAFETY: Tracked classes are allocated with `ClassBuilder`.
+        let tracker = unsafe { tracker(owner) };
+        let next = tracker.next.expect("tracked class without next");
+        // SAFETY: `next` was the implementation of `+allocWithZone:` before
+        // the hook was installed.
+        let next = unsafe { mem::transmute::<Imp, AllocWithZone>(next) };
+        let obj = next(cls, sel, zone);
+
+        // Instances of subclasses are not tracked, `dispose` instead checks
+        // that there are no subclasses.
+        if ptr::eq(owner, cls) && !obj.is_null() {
+            tracker.live.fetch_add(1, Ordering::Relaxed);
+            let mut sentinel: Id<Sentinel> = unsafe { msg_send_id![Sentinel::class(), new] };
+            *sentinel.live = Some(Box::new(LiveInstance(NonNull::from(&tracker.live))));
+            // SAFETY: The object is valid, and the key is a static.
+            unsafe {
+                ffi::objc_setAssociatedObject(
+                    obj.cast(),
+                    (&SENTINEL_KEY as *const u8).cast(),
+                    Id::as_ptr(&sentinel) as *mut _,
+                    ffi::OBJC_ASSOCIATION_RETAIN_NONATOMIC,
+                )
+            };
+        }
+        obj
+    }
+
+    static SENTINEL_KEY: u8 = 0;
+
+    /// Decrements the number of live instances when dropped.
+    #[allow(unreachable_pub)]
+    pub struct LiveInstance(NonNull<AtomicUsize>);
+
+    impl Drop for LiveInstance {
+        fn drop(&mut self) {
+            // SAFETY: The class, and hence the tracker, cannot be disposed
+            // while instances are alive.
+            unsafe { self.0.as_ref() }.fetch_sub(1, Ordering::Release);
+        }
+    }
+
+    declare_class!(
+        struct Sentinel {
+            live: IvarDrop<Option<Box<LiveInstance>>, "_live">,
+        }
+
+        mod ivars;
+
+        unsafe impl ClassType for Sentinel {
+            type Super = NSObject;
+            type Mutability = Mutable;
+            const NAME: &'static str = "__Objc2OwnedClassSentinel";
+        }
+    );
+
+    fn subclass_of(cls: &AnyClass) -> Option<&'static AnyClass> {
+        // SAFETY: Getting the number of classes is always safe.
+        let count = unsafe { ffi::objc_getClassList(ptr::null_mut(), 0) };
+        let mut classes: Vec<*const ffi::objc_class> = Vec::with_capacity(count as usize);
+        // SAFETY: The buffer has space for `count` classes.
+        let new_count = unsafe { ffi::objc_getClassList(classes.as_mut_ptr(), count) };
+        // The runtime returns the total number of classes, which may be
+        // larger than the buffer if classes were registered in the meantime.
+        //
+        // SAFETY: The runtime wrote `min(new_count, count)` classes.
+        unsafe { classes.set_len(new_count.min(count) as usize) };
+        classes
+            .into_iter()
+            .map(|subclass| {
+                // SAFETY: Registered classes are valid, and live for as long
+                // as they're not disposed.
+                unsafe { &*subclass.cast::<AnyClass>() }
+            })
+            .find(|subclass| {
+                subclass
+                    .superclass()
+                    .map_or(false, |superclass| ptr::eq(superclass, cls))
+            })
+    }
+
+    pub(super) fn assert_disposable(cls: &OwnedClass) {
+        if is_tracked(cls) {
+            // SAFETY: The class was allocated with `ClassBuilder`.
+            let live = unsafe { tracker(cls) }.live.load(Ordering::Acquire);
+            assert_eq!(
+                live,
+                0,
+                "cannot dispose of class {}, it still has {live} live instances",
+                cls.name(),
+            );
+        }
+        if let Some(subclass) = subclass_of(cls) {
+            panic!(
+                "cannot dispose of class {}, it has the subclass {}",
+                cls.name(),
+                subclass.name(),
+            );
+        }
+    }
+}
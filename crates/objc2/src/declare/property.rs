@@ -0,0 +1,488 @@
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use std::ffi::CString;
+use std::os::raw::c_uint;
+
+use super::{ClassBuilder, IvarEncode, IvarType, ProtocolBuilder};
+use crate::encode::{Encode, Encoding};
+use crate::ffi;
+use crate::runtime::{AnyObject, Bool, PropertyAttributes, PropertyOwnership, Sel};
+
+/// The attributes in the form expected by `class_addProperty` and
+/// `protocol_addProperty`.
+struct RawAttributes {
+    // Kept alive for the pointers in `attributes`.
+    _strings: Vec<CString>,
+    attributes: Vec<ffi::objc_property_attribute_t>,
+}
+
+impl RawAttributes {
+    fn new(encoding: &Encoding, attributes: &PropertyAttributes<'_>) -> Self {
+        let type_encoding = encoding.to_string();
+        let type_encoding = match attributes.type_encoding {
+            Some(given) => {
+                // Allow specifying the class of object types, e.g. `@"NSString"`.
+                debug_assert!(
+                    given == type_encoding || given.starts_with(&format!("{type_encoding}\"")),
+                    "property type encoding {given} did not match {type_encoding}",
+                );
+                given
+            }
+            None => &type_encoding,
+        };
+        let attributes = PropertyAttributes {
+            type_encoding: Some(type_encoding),
+            ..attributes.clone()
+        };
+
+        let mut strings = Vec::new();
+        let attributes = attributes
+            .entries()
+            .map(|(code, value)| {
+                let code = CString::new(code).unwrap();
+                let value = CString::new(value).unwrap();
+                let raw = ffi::objc_property_attribute_t {
+                    name: code.as_ptr(),
+                    value: value.as_ptr(),
+                };
+                // Moving a `CString` does not move its heap allocation.
+                strings.push(code);
+                strings.push(value);
+                raw
+            })
+            .collect();
+        Self {
+            _strings: strings,
+            attributes,
+        }
+    }
+
+    fn as_ptr(&self) -> *const ffi::objc_property_attribute_t {
+        self.attributes.as_ptr()
+    }
+
+    fn len(&self) -> c_uint {
+        self.attributes.len() as c_uint
+    }
+}
+
+/// The default setter name of a property, e.g. `setFoo:` for `foo`.
+pub(crate) fn default_setter(name: &str) -> String {
+    let mut chars = name.chars();
+    match chars.next() {
+        Some(first) => format!("set{}{}:", first.to_uppercase(), chars.as_str()),
+        None => String::from("set:"),
+    }
+}
+
+extern "C" fn synthesized_getter<I, T>(this: &AnyObject, _cmd: Sel) -> T
+where
+    I: IvarType<Type = IvarEncode<T>>,
+    T: Encode + Copy,
+{
+    // SAFETY: The ivar was declared with this type, and it is either zero
+    // initialized, which the caller of `add_synthesized_property` ensured is
+    // a valid `T`, or it has been set by the setter.
+    unsafe { this.ivar_ptr::<T>(I::NAME).read() }
+}
+
+extern "C" fn synthesized_setter<I, T>(this: &AnyObject, _cmd: Sel, value: T)
+where
+    I: IvarType<Type = IvarEncode<T>>,
+    T: Encode + Copy,
+{
+    // SAFETY: The ivar was declared with this type, and the caller of
+    // `add_synthesized_property` ensured that the property is not accessed
+    // concurrently.
+    unsafe { this.ivar_ptr::<T>(I::NAME).write(value) }
+}
+
+impl ClassBuilder {
+    pub(crate) fn assert_has_ivar(&mut self, ivar_name: &str, property_name: &str) {
+        let c_ivar = CString::new(ivar_name).unwrap();
+        let ivar = unsafe { ffi::class_getInstanceVariable(self.as_mut_ptr(), c_ivar.as_ptr()) };
+        assert!(
+            !ivar.is_null(),
+            "ivar {ivar_name} must be added before synthesizing property {property_name}",
+        );
+    }
+
+    /// Adds a property with the given type encoding.
+    ///
+    ///
+    /// # Safety
+    ///
+    /// Same as [`add_property`][Self::add_property].
+    pub(crate) unsafe fn add_property_with_encoding(
+        &mut self,
+        name: &str,
+        encoding: &Encoding,
+        attributes: &PropertyAttributes<'_>,
+    ) {
+        let c_name = CString::new(name).unwrap();
+        let raw = RawAttributes::new(encoding, attributes);
+        let success = Bool::from_raw(unsafe {
+            ffi::class_addProperty(self.as_mut_ptr(), c_name.as_ptr(), raw.as_ptr(), raw.len())
+        });
+        assert!(success.as_bool(), "failed to add property {name}");
+    }
+
+    /// Adds a property with the given name, type and attributes.
+    ///
+    /// The property is only a declaration; use
+    /// [`add_synthesized_property`][Self::add_synthesized_property] to also
+    /// generate the accessor methods.
+    ///
+    /// The type encoding of the property is that of `T`, unless
+    /// `attributes.type_encoding` is set, which is useful for specifying the
+    /// class of an object property (e.g. `@"NSString"`).
+    ///
+    /// See [Apple's documentation](https://developer.apple.com/documentation/objectivec/1441115-class_addproperty?language=objc).
+    ///
+    ///
+    /// # Panics
+    ///
+    /// Panics if the property wasn't successfully added (e.g. a property
+    /// with that name already exists), or if any of the names contain an
+    /// internal NUL byte.
+    ///
+    /// If `debug_assertions` are enabled, this also panics if the given type
+    /// encoding does not match `T`.
+    ///
+    ///
+    /// # Safety
+    ///
+    /// The attributes must correctly describe the property, since they are
+    /// used by e.g. key-value coding to access it. In particular, the getter
+    /// and setter must exist with the correct types (unless the property is
+    /// `dynamic`), and the backing instance variable, if any, must have type
+    /// `T`.
+    ///
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use objc2::declare::ClassBuilder;
+    /// use objc2::runtime::{NSObject, PropertyAttributes, PropertyOwnership};
+    /// use objc2::ClassType;
+    ///
+    /// let mut builder = ClassBuilder::new("PropertyExample", NSObject::class()).unwrap();
+    ///
+    /// let mut attributes = PropertyAttributes::default();
+    /// attributes.type_encoding = Some("@\"NSString\"");
+    /// attributes.ownership = PropertyOwnership::Copy;
+    /// attributes.nonatomic = true;
+    /// attributes.dynamic = true;
+    /// unsafe { builder.add_property::<*mut NSObject>("title", &attributes) };
+    ///
+    /// let cls = builder.register();
+    /// let property = cls.property("title").unwrap();
+    /// assert_eq!(property.attribute_string(), "T@\"NSString\",C,N,D");
+    /// ```
+    #[doc(alias = "class_addProperty")]
+    pub unsafe fn add_property<T: Encode>(
+        &mut self,
+        name: &str,
+        attributes: &PropertyAttributes<'_>,
+    ) {
+        // SAFETY: Upheld by the caller.
+        unsafe { self.add_property_with_encoding(name, &T::ENCODING, attributes) }
+    }
+
+    /// Adds a property backed by the instance variable `I`, along with a
+    /// getter and (unless the property is `readonly`) a setter that access
+    /// it.
+    ///
+    /// The accessors use the names given in the attributes, or the default
+    /// `name` and `setName:` if none are given. The `ivar` attribute is set
+    /// to the name of `I`, which must already have been added with
+    /// [`add_static_ivar`][Self::add_static_ivar].
+    ///
+    /// The accessors simply copy the value, so the property must be
+    /// `nonatomic`, and its ownership must be
+    /// [`Assign`][crate::runtime::PropertyOwnership::Assign].
+    ///
+    ///
+    /// # Panics
+    ///
+    /// Panics in the same cases as [`add_property`][Self::add_property] and
+    /// [`add_method`][Self::add_method].
+    ///
+    /// Also panics if the instance variable has not been added, or if the
+    /// property is not `nonatomic` or its ownership is not `Assign`.
+    ///
+    ///
+    /// # Safety
+    ///
+    /// The property must not be set while it is being accessed from another
+    /// thread.
+    ///
+    /// The instance variable is zero-initialized, and may be read by the
+    /// getter before it has been set, so the all-zeroes bit pattern must be
+    /// a valid `T` (which it is for e.g. integers and raw pointers, but not
+    /// for references or `NonNull`).
+    ///
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use objc2::declare::{ClassBuilder, IvarEncode, IvarType};
+    /// use objc2::rc::Id;
+    /// use objc2::runtime::{NSObject, PropertyAttributes};
+    /// use objc2::{msg_send, msg_send_id, ClassType};
+    ///
+    /// struct Count;
+    ///
+    /// unsafe impl IvarType for Count {
+    ///     type Type = IvarEncode<u32>;
+    ///     const NAME: &'static str = "_count";
+    /// }
+    ///
+    /// let mut builder = ClassBuilder::new("SynthesizedExample", NSObject::class()).unwrap();
+    /// builder.add_static_ivar::<Count>();
+    ///
+    /// let mut attributes = PropertyAttributes::default();
+    /// attributes.nonatomic = true;
+    /// unsafe { builder.add_synthesized_property::<Count, u32>("count", &attributes) };
+    ///
+    /// let cls = builder.register();
+    /// let obj: Id<NSObject> = unsafe { msg_send_id![cls, new] };
+    /// let _: () = unsafe { msg_send![&obj, setCount: 5u32] };
+    /// let count: u32 = unsafe { msg_send![&obj, count] };
+    /// assert_eq!(count, 5);
+    ///
+    /// let property = cls.property("count").unwrap();
+    /// assert_eq!(property.attribute_string(), "TI,N,V_count");
+    /// ```
+    pub unsafe fn add_synthesized_property<I, T>(
+        &mut self,
+        name: &str,
+        attributes: &PropertyAttributes<'_>,
+    ) where
+        I: IvarType<Type = IvarEncode<T>>,
+        T: Encode + Copy,
+    {
+        assert!(
+            attributes.nonatomic,
+            "synthesized property {name} must be nonatomic"
+        );
+        assert_eq!(
+            attributes.ownership,
+            PropertyOwnership::Assign,
+            "synthesized property {name} must use the assign ownership",
+        );
+        self.assert_has_ivar(I::NAME, name);
+
+        let getter = Sel::register(attributes.getter.unwrap_or(name));
+        let get: extern "C" fn(_, _) -> _ = synthesized_getter::<I, T>;
+        // SAFETY: The getter has the correct signature
+        unsafe { self.add_method(getter, get) };
+        if !attributes.readonly {
+            let setter = match attributes.setter {
+                Some(setter) => Sel::register(setter),
+                None => Sel::register(&default_setter(name)),
+            };
+            let set: extern "C" fn(_, _, _) = synthesized_setter::<I, T>;
+            // SAFETY: The setter has the correct signature, and the caller
+            // ensures that it is not called concurrently.
+            unsafe { self.add_method(setter, set) };
+        }
+
+        let attributes = PropertyAttributes {
+            ivar: Some(I::NAME),
+            ..attributes.clone()
+        };
+        // SAFETY: The accessors and ivar were added above.
+        unsafe { self.add_property::<T>(name, &attributes) };
+    }
+}
+
+impl ProtocolBuilder {
+    fn add_property_inner<T: Encode>(
+        &mut self,
+        name: &str,
+        attributes: &PropertyAttributes<'_>,
+        required: bool,
+        instance_property: bool,
+    ) {
+        let c_name = CString::new(name).unwrap();
+        let raw = RawAttributes::new(&T::ENCODING, attributes);
+        unsafe {
+            ffi::protocol_addProperty(
+                self.as_mut_ptr(),
+                c_name.as_ptr(),
+                raw.as_ptr(),
+                raw.len(),
+                Bool::new(required).as_raw(),
+                Bool::new(instance_property).as_raw(),
+            );
+        }
+    }
+
+    /// Adds an instance property declaration with the given type and
+    /// attributes.
+    ///
+    /// See [`ClassBuilder::add_property`] for how the type encoding is
+    /// determined.
+    ///
+    ///
+    /// # Panics
+    ///
+    /// Panics if any of the names contain an internal NUL byte.
+    #[doc(alias = "protocol_addProperty")]
+    pub fn add_property<T: Encode>(
+        &mut self,
+        name: &str,
+        attributes: &PropertyAttributes<'_>,
+        required: bool,
+    ) {
+        self.add_property_inner::<T>(name, attributes, required, true)
+    }
+
+    /// Adds a class property declaration with the given type and
+    /// attributes.
+    ///
+    /// See [`ClassBuilder::add_property`] for how the type encoding is
+    /// determined.
+    ///
+    ///
+    /// # Panics
+    ///
+    /// Panics if any of the names contain an internal NUL byte.
+    pub fn add_class_property<T: Encode>(
+        &mut self,
+        name: &str,
+        attributes: &PropertyAttributes<'_>,
+        required: bool,
+    ) {
+        self.add_property_inner::<T>(name, attributes, required, false)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runtime::{NSObject, PropertyOwnership};
+    use crate::{msg_send, sel, ClassType};
+
+    #[test]
+    fn test_default_setter() {
+        assert_eq!(default_setter("foo"), "setFoo:");
+        assert_eq!(default_setter("fooBar"), "setFooBar:");
+        assert_eq!(default_setter("URL"), "setURL:");
+        assert_eq!(default_setter("ølle"), "setØlle:");
+    }
+
+    #[test]
+    fn test_add_property() {
+        let mut builder = ClassBuilder::new("TestAddProperty", NSObject::class()).unwrap();
+
+        let attributes = PropertyAttributes {
+            readonly: true,
+            ownership: PropertyOwnership::Weak,
+            getter: Some("isFoo"),
+            dynamic: true,
+            ..Default::default()
+        };
+        unsafe { builder.add_property::<*mut NSObject>("foo", &attributes) };
+
+        let cls = builder.register();
+        let property = cls.property("foo").unwrap();
+        assert_eq!(property.attribute_string(), "T@,R,W,GisFoo,D");
+    }
+
+    #[test]
+    #[should_panic = "failed to add property foo"]
+    fn test_add_property_duplicate() {
+        let mut builder = ClassBuilder::new("TestAddPropertyDuplicate", NSObject::class()).unwrap();
+        unsafe { builder.add_property::<i32>("foo", &Default::default()) };
+        unsafe { builder.add_property::<i32>("foo", &Default::default()) };
+    }
+
+    struct Value;
+
+    unsafe impl IvarType for Value {
+        type Type = IvarEncode<i64>;
+        const NAME: &'static str = "_value";
+    }
+
+    #[test]
+    fn test_synthesized_property() {
+        let mut builder = ClassBuilder::new("TestSynthesizedProperty", NSObject::class()).unwrap();
+        builder.add_static_ivar::<Value>();
+
+        let attributes = PropertyAttributes {
+            nonatomic: true,
+            getter: Some("currentValue"),
+            setter: Some("updateValue:"),
+            ..Default::default()
+        };
+        unsafe { builder.add_synthesized_property::<Value, i64>("value", &attributes) };
+
+        let cls = builder.register();
+        assert!(cls.instance_method(sel!(value)).is_none());
+        assert!(cls.instance_method(sel!(setValue:)).is_none());
+
+        let obj: *mut AnyObject = unsafe { msg_send![cls, new] };
+        let obj = unsafe { &*obj };
+        let value: i64 = unsafe { msg_send![obj, currentValue] };
+        assert_eq!(value, 0);
+        let _: () = unsafe { msg_send![obj, updateValue: -3i64] };
+        let value: i64 = unsafe { msg_send![obj, currentValue] };
+        assert_eq!(value, -3);
+        let _: () = unsafe { msg_send![obj, release] };
+
+        let property = cls.property("value").unwrap();
+        assert_eq!(
+            property.attribute_string(),
+            "Tq,N,GcurrentValue,SupdateValue:,V_value"
+        );
+    }
+
+    #[test]
+    fn test_synthesized_readonly() {
+        let mut builder = ClassBuilder::new("TestSynthesizedReadonly", NSObject::class()).unwrap();
+        builder.add_static_ivar::<Value>();
+
+        let attributes = PropertyAttributes {
+            nonatomic: true,
+            readonly: true,
+            ..Default::default()
+        };
+        unsafe { builder.add_synthesized_property::<Value, i64>("value", &attributes) };
+
+        let cls = builder.register();
+        assert!(cls.instance_method(sel!(value)).is_some());
+        assert!(cls.instance_method(sel!(setValue:)).is_none());
+    }
+
+    #[test]
+    #[should_panic = "ivar _value must be added before synthesizing property value"]
+    fn test_synthesized_missing_ivar() {
+        let mut builder =
+            ClassBuilder::new("TestSynthesizedMissingIvar", NSObject::class()).unwrap();
+        let attributes = PropertyAttributes {
+            nonatomic: true,
+            ..Default::default()
+        };
+        unsafe { builder.add_synthesized_property::<Value, i64>("value", &attributes) };
+    }
+
+    #[test]
+    #[cfg(feature = "malloc")]
+    fn test_protocol_property() {
+        let mut builder = ProtocolBuilder::new("TestProtocolProperty").unwrap();
+        let attributes = PropertyAttributes {
+            nonatomic: true,
+            ..Default::default()
+        };
+        builder.add_property::<f64>("ratio", &attributes, true);
+        let proto = builder.register();
+
+        let properties = proto.properties();
+        assert_eq!(properties.len(), 1);
+        assert_eq!(properties[0].name(), "ratio");
+        assert_eq!(properties[0].attribute_string(), "Td,N");
+    }
+}
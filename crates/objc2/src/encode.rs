@@ -43,6 +43,23 @@
 //! assert!(MyStruct::ENCODING_REF.equivalent_to_str("^{MyStruct=fs}"));
 //! ```
 //!
+//! With the `"derive"` feature enabled, the above can instead be written as:
+//!
+#![cfg_attr(feature = "derive", doc = "```")]
+#![cfg_attr(not(feature = "derive"), doc = "```ignore")]
+//! use objc2::encode::{Encode, RefEncode};
+//!
+//! #[repr(C)]
+//! #[derive(Encode, RefEncode)]
+//! struct MyStruct {
+//!     a: f32,
+//!     b: i16,
+//! }
+//!
+//! assert!(MyStruct::ENCODING.equivalent_to_str("{MyStruct=fs}"));
+//! assert!(MyStruct::ENCODING_REF.equivalent_to_str("^{MyStruct=fs}"));
+//! ```
+//!
 //! Implementing [`Encode`] for a few core-graphics types.
 //!
 //! Note that these are available in `icrate`, so the implementation here is
@@ -82,7 +99,57 @@ use core::ptr::NonNull;
 use core::sync::atomic;
 
 #[doc(inline)]
-pub use objc2_encode::{Encoding, EncodingBox, ParseError};
+pub use objc2_encode::{
+    AbiClass, AbiMismatch, CallingConvention, ContainerKind, DataModel, Encoding, EncodingBox,
+    MethodEncoding, MethodEncodingItem, ParseError, ParseErrorKind, Qualifier, RegisterClass,
+};
+
+/// Derive macros for [`Encode`] and [`RefEncode`].
+///
+/// These work on `#[repr(C)]` structs and unions, and produce an
+/// [`Encoding::Struct`] or [`Encoding::Union`] containing the encodings of
+/// each field, in declaration order.
+///
+/// By default, the Rust name of the type is used as the name in the
+/// encoding; use `#[encoding(name = "...")]` if the type is named
+/// differently in C. The name can be any constant expression of type
+/// `&'static str`.
+///
+/// Types that are not `#[repr(C)]` (or are `#[repr(packed)]`), as well as
+/// enums and generic types, are rejected at compile-time.
+///
+/// # Examples
+///
+/// ```
+/// use objc2::encode::{Encode, Encoding, RefEncode};
+///
+/// #[repr(C)]
+/// #[derive(Encode, RefEncode)]
+/// #[encoding(name = "_NSRange")]
+/// struct NSRange {
+///     location: usize,
+///     length: usize,
+/// }
+///
+/// assert_eq!(
+///     NSRange::ENCODING,
+///     Encoding::Struct("_NSRange", &[usize::ENCODING, usize::ENCODING]),
+/// );
+/// assert_eq!(NSRange::ENCODING_REF, Encoding::Pointer(&NSRange::ENCODING));
+/// ```
+///
+/// Types that are not `#[repr(C)]` are rejected:
+///
+/// ```compile_fail
+/// use objc2::encode::Encode;
+///
+/// #[derive(Encode)]
+/// struct NotReprC {
+///     a: i32,
+/// }
+/// ```
+#[cfg(feature = "derive")]
+pub use objc2_proc_macros::{Encode, RefEncode};
 
 use crate::runtime::{AnyObject, Imp, Sel};
 
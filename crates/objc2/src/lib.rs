@@ -212,6 +212,8 @@ pub mod declare;
 pub mod encode;
 pub mod exception;
 mod macros;
+#[cfg(feature = "mock")]
+pub mod mock;
 pub mod mutability;
 pub mod rc;
 pub mod runtime;
@@ -47,8 +47,8 @@ macro_rules! __extract_and_apply_cfg_attributes {
     };
 }
 
-/// Extract `#[method(...)]` or `#[method_id(...)]` and the `#[optional]`
-/// attribute, and send it to another macro.
+/// Extract `#[method(...)]` or `#[method_id(...)]`, and the `#[optional]`
+/// and `#[property(...)]` attributes, and send it to another macro.
 ///
 /// This will ensure that there is one and only one of the method attributes
 /// present.
@@ -62,7 +62,7 @@ macro_rules! __extract_and_apply_cfg_attributes {
 ///
 /// Further arguments are passed on to the output macro, with the following
 /// arguments appended to it:
-/// 1. The `method` or `method_id` attribute.
+/// 1. The `method` or `method_id` attribute, or the `forward` attribute.
 ///    (#[$method_or_method_id:ident($($sel:tt)*)])
 ///
 /// 2. The retain semantics, if any was present in the selector for
@@ -74,7 +74,10 @@ macro_rules! __extract_and_apply_cfg_attributes {
 /// 3. The `optional` attribute, if any.
 ///    ($(#[optional])?)
 ///
-/// 4. The remaining attributes.
+/// 4. The `property` attribute, if any.
+///    ($(#[property($($property_args:tt)*)])?)
+///
+/// 5. The remaining attributes.
 ///    ($(#[$($m_checked:tt)*])*)
 #[doc(hidden)]
 #[macro_export]
@@ -91,6 +94,7 @@ macro_rules! __extract_custom_attributes {
             () // method/method_id
             () // retain semantics
             () // optional
+            () // property
             () // checked
 
             ($out_macro)
@@ -110,6 +114,7 @@ macro_rules! __extract_custom_attributes_inner {
         ()
         ($($retain_semantics:tt)*)
         ($($m_optional:tt)*)
+        ($($m_property:tt)*)
         ($($m_checked:tt)*)
 
         ($out_macro:path)
@@ -126,6 +131,7 @@ macro_rules! __extract_custom_attributes_inner {
         ($($m_method:tt)*)
         ($($retain_semantics:tt)*)
         ($($m_optional:tt)*)
+        ($($m_property:tt)*)
         ($($m_checked:tt)*)
 
         ($out_macro:path)
@@ -138,6 +144,7 @@ macro_rules! __extract_custom_attributes_inner {
             ($($m_method)*)
             ($($retain_semantics)*)
             ($($m_optional)*)
+            ($($m_property)*)
             ($($m_checked)*)
         }
     };
@@ -152,6 +159,7 @@ macro_rules! __extract_custom_attributes_inner {
         ()
         ($($retain_semantics:tt)*)
         ($($m_optional:tt)*)
+        ($($m_property:tt)*)
         ($($m_checked:tt)*)
 
         ($out_macro:path)
@@ -163,6 +171,7 @@ macro_rules! __extract_custom_attributes_inner {
             (#[method($($sel)*)])
             ($($retain_semantics)*)
             ($($m_optional)*)
+            ($($m_property)*)
             ($($m_checked)*)
 
             ($out_macro)
@@ -178,6 +187,7 @@ macro_rules! __extract_custom_attributes_inner {
         ($($m_method:tt)*)
         ($($retain_semantics:tt)*)
         ($($m_optional:tt)*)
+        ($($m_property:tt)*)
         ($($m_checked:tt)*)
 
         ($out_macro:path)
@@ -196,6 +206,7 @@ macro_rules! __extract_custom_attributes_inner {
         ()
         ()
         ($($m_optional:tt)*)
+        ($($m_property:tt)*)
         ($($m_checked:tt)*)
 
         ($out_macro:path)
@@ -207,6 +218,7 @@ macro_rules! __extract_custom_attributes_inner {
             (#[method_id($($sel)*)])
             ($retain_semantics)
             ($($m_optional)*)
+            ($($m_property)*)
             ($($m_checked)*)
 
             ($out_macro)
@@ -223,6 +235,7 @@ macro_rules! __extract_custom_attributes_inner {
         ()
         ($($retain_semantics:tt)*)
         ($($m_optional:tt)*)
+        ($($m_property:tt)*)
         ($($m_checked:tt)*)
 
         ($out_macro:path)
@@ -234,6 +247,7 @@ macro_rules! __extract_custom_attributes_inner {
             (#[method_id($($sel)*)])
             ($($retain_semantics)*)
             ($($m_optional)*)
+            ($($m_property)*)
             ($($m_checked)*)
 
             ($out_macro)
@@ -249,6 +263,7 @@ macro_rules! __extract_custom_attributes_inner {
         ($($m_method:tt)*)
         ($($retain_semantics:tt)*)
         ($($m_optional:tt)*)
+        ($($m_property:tt)*)
         ($($m_checked:tt)*)
 
         ($out_macro:path)
@@ -257,6 +272,53 @@ macro_rules! __extract_custom_attributes_inner {
         $crate::__macro_helpers::compile_error!("cannot specify the `method`/`method_id` attribute twice");
     };
 
+    // `forward` attribute
+    {
+        (
+            #[forward]
+            $($rest:tt)*
+        )
+        // If no existing `method` nor `method_id` attributes exist
+        ()
+        ($($retain_semantics:tt)*)
+        ($($m_optional:tt)*)
+        ($($m_property:tt)*)
+        ($($m_checked:tt)*)
+
+        ($out_macro:path)
+        $($macro_args:tt)*
+    } => {
+        $crate::__extract_custom_attributes_inner! {
+            ($($rest)*)
+            // Add forward attribute
+            (#[forward])
+            ($($retain_semantics)*)
+            ($($m_optional)*)
+            ($($m_property)*)
+            ($($m_checked)*)
+
+            ($out_macro)
+            $($macro_args)*
+        }
+    };
+    // `forward` together with `method` or `method_id` attributes
+    {
+        (
+            #[forward]
+            $($rest:tt)*
+        )
+        ($($m_method:tt)*)
+        ($($retain_semantics:tt)*)
+        ($($m_optional:tt)*)
+        ($($m_property:tt)*)
+        ($($m_checked:tt)*)
+
+        ($out_macro:path)
+        $($macro_args:tt)*
+    } => {
+        $crate::__macro_helpers::compile_error!("cannot specify the `forward` attribute together with `method`/`method_id`");
+    };
+
     // `optional` attribute
     {
         (
@@ -267,6 +329,7 @@ macro_rules! __extract_custom_attributes_inner {
         ($($retain_semantics:tt)*)
         // If no existing `optional` attributes exist
         ()
+        ($($m_property:tt)*)
         ($($m_checked:tt)*)
 
         ($out_macro:path)
@@ -278,6 +341,7 @@ macro_rules! __extract_custom_attributes_inner {
             ($($retain_semantics)*)
             // Add optional attribute
             (#[optional])
+            ($($m_property)*)
             ($($m_checked)*)
 
             ($out_macro)
@@ -293,6 +357,7 @@ macro_rules! __extract_custom_attributes_inner {
         ($($m_method:tt)*)
         ($($retain_semantics:tt)*)
         ($($m_optional:tt)*)
+        ($($m_property:tt)*)
         ($($m_checked:tt)*)
 
         ($out_macro:path)
@@ -301,6 +366,53 @@ macro_rules! __extract_custom_attributes_inner {
         $crate::__macro_helpers::compile_error!("cannot specify the `optional` attribute twice");
     };
 
+    // `property` attribute
+    {
+        (
+            #[property($($args:tt)*)]
+            $($rest:tt)*
+        )
+        ($($m_method:tt)*)
+        ($($retain_semantics:tt)*)
+        ($($m_optional:tt)*)
+        // If no existing `property` attributes exist
+        ()
+        ($($m_checked:tt)*)
+
+        ($out_macro:path)
+        $($macro_args:tt)*
+    } => {
+        $crate::__extract_custom_attributes_inner! {
+            ($($rest)*)
+            ($($m_method)*)
+            ($($retain_semantics)*)
+            ($($m_optional)*)
+            // Add property attribute
+            (#[property($($args)*)])
+            ($($m_checked)*)
+
+            ($out_macro)
+            $($macro_args)*
+        }
+    };
+    // Duplicate `property` attributes
+    {
+        (
+            #[property($($args:tt)*)]
+            $($rest:tt)*
+        )
+        ($($m_method:tt)*)
+        ($($retain_semantics:tt)*)
+        ($($m_optional:tt)*)
+        ($($m_property:tt)*)
+        ($($m_checked:tt)*)
+
+        ($out_macro:path)
+        $($macro_args:tt)*
+    } => {
+        $crate::__macro_helpers::compile_error!("cannot specify the `property` attribute twice");
+    };
+
     // Other attributes
     {
         (
@@ -310,6 +422,7 @@ macro_rules! __extract_custom_attributes_inner {
         ($($m_method:tt)*)
         ($($retain_semantics:tt)*)
         ($($m_optional:tt)*)
+        ($($m_property:tt)*)
         ($($m_checked:tt)*)
 
         ($out_macro:path)
@@ -320,6 +433,7 @@ macro_rules! __extract_custom_attributes_inner {
             ($($m_method)*)
             ($($retain_semantics)*)
             ($($m_optional)*)
+            ($($m_property)*)
             (
                 $($m_checked)*
                 // The attribute is appended to the current set, since we've
@@ -332,3 +446,65 @@ macro_rules! __extract_custom_attributes_inner {
         }
     };
 }
+
+/// Parse the arguments of a `#[property(...)]` attribute, and set the
+/// corresponding fields on the given `PropertyAttributes` variable.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __parse_property_attributes {
+    // Base case
+    ($attributes:ident; $(,)?) => {};
+
+    ($attributes:ident; nonatomic $(, $($rest:tt)*)?) => {
+        $attributes.nonatomic = true;
+        $crate::__parse_property_attributes!($attributes; $($($rest)*)?);
+    };
+    ($attributes:ident; readonly $(, $($rest:tt)*)?) => {
+        $attributes.readonly = true;
+        $crate::__parse_property_attributes!($attributes; $($($rest)*)?);
+    };
+    ($attributes:ident; assign $(, $($rest:tt)*)?) => {
+        $attributes.ownership = $crate::runtime::PropertyOwnership::Assign;
+        $crate::__parse_property_attributes!($attributes; $($($rest)*)?);
+    };
+    ($attributes:ident; strong $(, $($rest:tt)*)?) => {
+        $attributes.ownership = $crate::runtime::PropertyOwnership::Retain;
+        $crate::__parse_property_attributes!($attributes; $($($rest)*)?);
+    };
+    ($attributes:ident; retain $(, $($rest:tt)*)?) => {
+        $attributes.ownership = $crate::runtime::PropertyOwnership::Retain;
+        $crate::__parse_property_attributes!($attributes; $($($rest)*)?);
+    };
+    ($attributes:ident; copy $(, $($rest:tt)*)?) => {
+        $attributes.ownership = $crate::runtime::PropertyOwnership::Copy;
+        $crate::__parse_property_attributes!($attributes; $($($rest)*)?);
+    };
+    ($attributes:ident; weak $(, $($rest:tt)*)?) => {
+        $attributes.ownership = $crate::runtime::PropertyOwnership::Weak;
+        $crate::__parse_property_attributes!($attributes; $($($rest)*)?);
+    };
+    ($attributes:ident; getter = $getter:ident $(, $($rest:tt)*)?) => {
+        $attributes.getter = $crate::__macro_helpers::Some(
+            $crate::__macro_helpers::stringify!($getter),
+        );
+        $crate::__parse_property_attributes!($attributes; $($($rest)*)?);
+    };
+    ($attributes:ident; setter = $setter:ident : $(, $($rest:tt)*)?) => {
+        $attributes.setter = $crate::__macro_helpers::Some(
+            $crate::__macro_helpers::concat!($crate::__macro_helpers::stringify!($setter), ":"),
+        );
+        $crate::__parse_property_attributes!($attributes; $($($rest)*)?);
+    };
+
+    ($attributes:ident; atomic $(, $($rest:tt)*)?) => {
+        $crate::__macro_helpers::compile_error!(
+            "atomic properties are not supported, use `nonatomic` instead"
+        );
+    };
+    ($attributes:ident; $($unknown:tt)*) => {
+        $crate::__macro_helpers::compile_error!($crate::__macro_helpers::concat!(
+            "unknown property attribute: ",
+            $crate::__macro_helpers::stringify!($($unknown)*),
+        ));
+    };
+}
@@ -134,6 +134,11 @@ macro_rules! __parse_fields {
                 $(
                     __objc2_builder.add_static_ivar::<$ivar_type_name>();
                 )+
+
+                // Properties
+                $(
+                    $ivar_type_name::__objc2_declare_property(__objc2_builder);
+                )+
             }
         }
 
@@ -183,7 +188,7 @@ macro_rules! __parse_fields {
     // IvarDrop
     (
         (
-            $(#[$m:meta])*
+            $(#[$($m:tt)*])*
             $vis:vis $field_name:ident: IvarDrop<$ty:ty, $ivar_name:literal>
             $(, $($rest_fields:tt)*)?
         )
@@ -194,33 +199,18 @@ macro_rules! __parse_fields {
         ($out_macro:path)
         $($macro_args:tt)*
     ) => {
-        $crate::__parse_fields! {
-            ($($($rest_fields)*)?)
-            ($($ivar_helper_module_v mod $ivar_helper_module)?)
-            (
-                $($ivar_output)*
+        $crate::__extract_property_attribute! {
+            ($(#[$($m)*])*)
+            () // No property attribute
+            () // No other attributes
 
-                #[allow(non_camel_case_types)]
-                #[allow(unreachable_pub)]
-                pub struct $field_name {
-                    __priv: (),
-                }
+            ($crate::__parse_fields_ivar)
+            ($vis) ($field_name) (IvarDrop<$ty>) ($ivar_name)
 
-                // SAFETY:
-                // - The ivars are in a type used as an Objective-C object.
-                // - The ivar is added to the class in `__objc2_declare_ivars`.
-                // - Caller upholds that the ivars are properly initialized.
-                unsafe impl $crate::declare::IvarType for $field_name {
-                    type Type = IvarDrop<$ty>;
-                    const NAME: &'static $crate::__macro_helpers::str = $ivar_name;
-                }
-            ) ($($ivar_type_name)* $field_name)
-            (
-                $($parsed_fields)*
-
-                $(#[$m])*
-                $vis $field_name: $crate::declare::Ivar<$($ivar_helper_module ::)? $field_name>,
-            )
+            ($($($rest_fields)*)?)
+            ($($ivar_helper_module_v mod $ivar_helper_module)?)
+            ($($ivar_output)*) ($($ivar_type_name)*)
+            ($($parsed_fields)*)
 
             ($out_macro)
             $($macro_args)*
@@ -230,7 +220,7 @@ macro_rules! __parse_fields {
     // IvarEncode
     (
         (
-            $(#[$m:meta])*
+            $(#[$($m:tt)*])*
             $vis:vis $field_name:ident: IvarEncode<$ty:ty, $ivar_name:literal>
             $(, $($rest_fields:tt)*)?
         )
@@ -241,30 +231,18 @@ macro_rules! __parse_fields {
         ($out_macro:path)
         $($macro_args:tt)*
     ) => {
-        $crate::__parse_fields! {
-            ($($($rest_fields)*)?)
-            ($($ivar_helper_module_v mod $ivar_helper_module)?)
-            (
-                $($ivar_output)*
+        $crate::__extract_property_attribute! {
+            ($(#[$($m)*])*)
+            () // No property attribute
+            () // No other attributes
 
-                #[allow(non_camel_case_types)]
-                #[allow(unreachable_pub)]
-                pub struct $field_name {
-                    __priv: (),
-                }
+            ($crate::__parse_fields_ivar)
+            ($vis) ($field_name) (IvarEncode<$ty>) ($ivar_name)
 
-                // SAFETY: See above
-                unsafe impl $crate::declare::IvarType for $field_name {
-                    type Type = IvarEncode<$ty>;
-                    const NAME: &'static $crate::__macro_helpers::str = $ivar_name;
-                }
-            ) ($($ivar_type_name)* $field_name)
-            (
-                $($parsed_fields)*
-
-                $(#[$m])*
-                $vis $field_name: $crate::declare::Ivar<$($ivar_helper_module ::)? $field_name>,
-            )
+            ($($($rest_fields)*)?)
+            ($($ivar_helper_module_v mod $ivar_helper_module)?)
+            ($($ivar_output)*) ($($ivar_type_name)*)
+            ($($parsed_fields)*)
 
             ($out_macro)
             $($macro_args)*
@@ -274,7 +252,7 @@ macro_rules! __parse_fields {
     // IvarBool
     (
         (
-            $(#[$m:meta])*
+            $(#[$($m:tt)*])*
             $vis:vis $field_name:ident: IvarBool<$ivar_name:literal>
             $(, $($rest_fields:tt)*)?
         )
@@ -285,30 +263,18 @@ macro_rules! __parse_fields {
         ($out_macro:path)
         $($macro_args:tt)*
     ) => {
-        $crate::__parse_fields! {
-            ($($($rest_fields)*)?)
-            ($($ivar_helper_module_v mod $ivar_helper_module)?)
-            (
-                $($ivar_output)*
-
-                #[allow(non_camel_case_types)]
-                #[allow(unreachable_pub)]
-                pub struct $field_name {
-                    __priv: (),
-                }
+        $crate::__extract_property_attribute! {
+            ($(#[$($m)*])*)
+            () // No property attribute
+            () // No other attributes
 
-                // SAFETY: See above
-                unsafe impl $crate::declare::IvarType for $field_name {
-                    type Type = IvarBool;
-                    const NAME: &'static $crate::__macro_helpers::str = $ivar_name;
-                }
-            ) ($($ivar_type_name)* $field_name)
-            (
-                $($parsed_fields)*
+            ($crate::__parse_fields_ivar)
+            ($vis) ($field_name) (IvarBool) ($ivar_name)
 
-                $(#[$m])*
-                $vis $field_name: $crate::declare::Ivar<$($ivar_helper_module ::)? $field_name>,
-            )
+            ($($($rest_fields)*)?)
+            ($($ivar_helper_module_v mod $ivar_helper_module)?)
+            ($($ivar_output)*) ($($ivar_type_name)*)
+            ($($parsed_fields)*)
 
             ($out_macro)
             $($macro_args)*
@@ -353,3 +319,174 @@ macro_rules! __parse_fields {
         }
     }
 }
+
+/// Output an instance variable, along with its `#[property(...)]`, if any.
+///
+/// This is the continuation of `__parse_fields!` after the field's
+/// attributes have been extracted.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __parse_fields_ivar {
+    (
+        ($(#[property($($property_args:tt)*)])?)
+        ($(#[$($m:tt)*])*)
+
+        ($vis:vis) ($field_name:ident) ($ivar_type:ty) ($ivar_name:literal)
+
+        ($($rest_fields:tt)*)
+        ($($ivar_helper_module_v:vis mod $ivar_helper_module:ident)?)
+        ($($ivar_output:tt)*) ($($ivar_type_name:ident)*)
+        ($($parsed_fields:tt)*)
+
+        ($out_macro:path)
+        $($macro_args:tt)*
+    ) => {
+        $crate::__parse_fields! {
+            ($($rest_fields)*)
+            ($($ivar_helper_module_v mod $ivar_helper_module)?)
+            (
+                $($ivar_output)*
+
+                #[allow(non_camel_case_types)]
+                #[allow(unreachable_pub)]
+                pub struct $field_name {
+                    __priv: (),
+                }
+
+                // SAFETY:
+                // - The ivars are in a type used as an Objective-C object.
+                // - The ivar is added to the class in `__objc2_declare_ivars`.
+                // - Caller upholds that the ivars are properly initialized.
+                unsafe impl $crate::declare::IvarType for $field_name {
+                    type Type = $ivar_type;
+                    const NAME: &'static $crate::__macro_helpers::str = $ivar_name;
+                }
+
+                impl $field_name {
+                    #[inline]
+                    fn __objc2_declare_property<T: ?$crate::__macro_helpers::Sized + $crate::ClassType>(
+                        __objc2_builder: &mut $crate::__macro_helpers::ClassBuilderHelper<T>,
+                    ) {
+                        $(
+                            #[allow(unused_mut)]
+                            let mut __objc2_attributes: $crate::runtime::PropertyAttributes<'_> =
+                                $crate::__macro_helpers::Default::default();
+                            $crate::__parse_property_attributes!(__objc2_attributes; $($property_args)*);
+                            // SAFETY: The caller of `declare_class!` upholds
+                            // that the ivar is not referenced while the
+                            // setter is called.
+                            unsafe {
+                                __objc2_builder.add_ivar_property::<Self>(
+                                    $crate::__macro_helpers::stringify!($field_name),
+                                    &__objc2_attributes,
+                                );
+                            }
+                        )?
+                    }
+                }
+            ) ($($ivar_type_name)* $field_name)
+            (
+                $($parsed_fields)*
+
+                $(#[$($m)*])*
+                $vis $field_name: $crate::declare::Ivar<$($ivar_helper_module ::)? $field_name>,
+            )
+
+            ($out_macro)
+            $($macro_args)*
+        }
+    };
+}
+
+/// Extract the `#[property(...)]` attribute from a field's attributes.
+///
+/// This takes the following arguments:
+/// 1. The attributes to parse.
+/// 2. The `property` attribute found so far.
+/// 3. The remaining attributes found so far.
+/// 4. The output macro.
+///
+/// Further arguments are passed on to the output macro, with the
+/// `property` attribute and the remaining attributes prepended to them.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __extract_property_attribute {
+    // Base case
+    {
+        () // No attributes left to process
+        ($($m_property:tt)*)
+        ($($m_checked:tt)*)
+
+        ($out_macro:path)
+        $($macro_args:tt)*
+    } => {
+        $out_macro! {
+            ($($m_property)*)
+            ($($m_checked)*)
+
+            $($macro_args)*
+        }
+    };
+
+    // `property` attribute
+    {
+        (
+            #[property($($args:tt)*)]
+            $($rest:tt)*
+        )
+        // If no existing `property` attribute exist
+        ()
+        ($($m_checked:tt)*)
+
+        ($out_macro:path)
+        $($macro_args:tt)*
+    } => {
+        $crate::__extract_property_attribute! {
+            ($($rest)*)
+            (#[property($($args)*)])
+            ($($m_checked)*)
+
+            ($out_macro)
+            $($macro_args)*
+        }
+    };
+    // Duplicate `property` attributes
+    {
+        (
+            #[property($($args:tt)*)]
+            $($rest:tt)*
+        )
+        ($($m_property:tt)*)
+        ($($m_checked:tt)*)
+
+        ($out_macro:path)
+        $($macro_args:tt)*
+    } => {
+        $crate::__macro_helpers::compile_error!("cannot specify the `property` attribute twice");
+    };
+
+    // Other attributes
+    {
+        (
+            #[$($checked:tt)*]
+            $($rest:tt)*
+        )
+        ($($m_property:tt)*)
+        ($($m_checked:tt)*)
+
+        ($out_macro:path)
+        $($macro_args:tt)*
+    } => {
+        $crate::__extract_property_attribute! {
+            ($($rest)*)
+            ($($m_property)*)
+            (
+                $($m_checked)*
+                #[$($checked)*]
+            )
+
+            ($out_macro)
+            $($macro_args)*
+        }
+    };
+}
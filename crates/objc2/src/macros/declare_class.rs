@@ -53,6 +53,43 @@
 /// [`declare::IvarType`]: crate::declare::IvarType
 ///
 ///
+/// ## Properties
+///
+/// Instance variables can be exposed as Objective-C properties by putting a
+/// `#[property(...)]` attribute on the field. This registers the property
+/// with the runtime (making the class key-value coding compliant), and
+/// generates a getter, and unless `readonly` is specified, a setter.
+///
+/// The property is named after the field, and the accessors are named
+/// `field` and `setField:`, unless `getter = myGetter` or
+/// `setter = mySetter:` is specified.
+///
+/// The accessors' memory management semantics depend on the type of the
+/// instance variable:
+/// - `IvarEncode<T, "...">` and `IvarBool<"...">` are copied in and out
+///   (`assign`, the default). Boolean properties use `BOOL` in their
+///   accessors.
+/// - `IvarDrop<Id<T>, "...">` and `IvarDrop<Option<Id<T>>, "...">` must be
+///   `strong` (or `retain`), or `copy`. The getter returns the object
+///   retained and autoreleased, and the setter retains or copies the new
+///   value. Setting a property backed by a non-optional `Id` to `nil` does
+///   nothing, the old value is kept.
+/// - `IvarDrop<Option<WeakId<T>>, "...">` must be `weak`.
+///
+/// The object type `T` must implement [`IsRetainable`], since the getter
+/// hands out new references to the object.
+///
+/// [`IsRetainable`]: crate::mutability::IsRetainable
+///
+/// Atomic properties are not supported, so `nonatomic` must always be
+/// specified.
+///
+/// A `#[property(...)]` attribute can also be put on an instance method
+/// without arguments, which then declares a property with that method as
+/// the getter, and the selector as the name. Note that in this case, the
+/// setter must be implemented separately.
+///
+///
 /// ## `ClassType` implementation
 ///
 /// This also resembles that in [`extern_class!`], except that
@@ -109,8 +146,13 @@
 /// make it behave similarly to the Objective-C `BOOL`. Use [`runtime::Bool`]
 /// if you want to control this manually.
 ///
-/// Note that `&mut Id<_>` and other such out parameters are not yet
-/// supported, and may generate a panic at runtime.
+/// Out parameters such as `NSError **` can be received as `&mut Id<_>`,
+/// `&mut Option<Id<_>>`, `Option<&mut Id<_>>` or `Option<&mut Option<Id<_>>>`
+/// (the latter is usually what you want, since callers may pass `NULL`, or a
+/// pointer to `nil`). The value can then be modified like any other mutable
+/// reference, and the value that is stored when the method returns is
+/// autoreleased, as the caller expects. Receiving `nil` in `&mut Id<_>` will
+/// panic.
 ///
 /// ["associated functions"]: https://doc.rust-lang.org/reference/items/associated-items.html#methods
 /// ["methods"]: https://doc.rust-lang.org/reference/items/associated-items.html#methods
@@ -137,16 +179,39 @@
 /// [`extern_protocol!`]: crate::extern_protocol
 ///
 ///
+/// ## Forwarding
+///
+/// Messages that the class does not implement can be forwarded, which is
+/// useful for proxies and mock objects.
+///
+/// To forward all such messages to another object, implement
+/// `forwardingTargetForSelector:` with `#[method(...)]` as you would in
+/// Objective-C.
+///
+/// To handle the messages yourself, mark a method taking `&self` and a
+/// [`ForwardedMessage`] and returning a [`runtime::Value`] with `#[forward]`
+/// (this requires the `"invocation"` feature). The method is called with the
+/// selector and the arguments of messages that are declared as instance
+/// methods by the protocols that the class adopts, but which the class does
+/// not implement; see [`ClassBuilder::add_forwarding_handler`] for details.
+/// Required protocol methods may then be left unimplemented.
+///
+/// [`ForwardedMessage`]: crate::declare::ForwardedMessage
+/// [`runtime::Value`]: crate::runtime::Value
+/// [`ClassBuilder::add_forwarding_handler`]: crate::declare::ClassBuilder::add_forwarding_handler
+///
+///
 /// # Panics
 ///
 /// The implemented `ClassType::class` method may panic in a few cases, such
 /// as if:
 /// - A class with the specified name already exists.
 /// - One of the class' instance variables already exist on a superclass.
+/// - A property's ownership is not supported by its instance variable.
 /// - Debug assertions are enabled, and an overriden method's signature is not
 ///   equal to the one on the superclass.
 /// - The `verify` feature and debug assertions are enabled, and the required
-///   protocol methods are not implemented.
+///   protocol methods are not implemented (or forwarded).
 /// - And possibly more similar cases.
 ///
 ///
@@ -161,6 +226,8 @@
 /// - Any instance variables you specify under the struct definition must
 ///   either be able to be created using [`MaybeUninit::zeroed`], or be
 ///   properly initialized in an `init` method.
+/// - Instance variables with a property setter must not be referenced while
+///   the setter may be called, since it replaces the value.
 ///
 /// `unsafe impl T { ... }` asserts that the types match those that are
 /// expected when the method is invoked from Objective-C. Note that unlike
@@ -923,6 +990,31 @@ macro_rules! __declare_class_register_methods {
 #[doc(hidden)]
 #[macro_export]
 macro_rules! __declare_class_method_out {
+    // #[forward]
+    {
+        ($($qualifiers:tt)*)
+        ($name:ident)
+        ($($ret:ty)?)
+        ($body:block)
+
+        (add_method)
+        ($__receiver:expr)
+        ($__receiver_ty:ty)
+        (&$self:ident, _: $__sel_ty:ty,)
+        ($($params_rest:tt)*)
+
+        (#[forward])
+        ()
+        ($($__m_optional:tt)*)
+        ()
+        ($($m_checked:tt)*)
+    } => {
+        // The handler is a normal Rust method, called by the forwarding
+        // machinery rather than by the runtime.
+        $($m_checked)*
+        $($qualifiers)* fn $name(&$self, $($params_rest)*) $(-> $ret)? $body
+    };
+
     {
         ($($qualifiers:tt)*)
         ($name:ident)
@@ -938,6 +1030,7 @@ macro_rules! __declare_class_method_out {
         ($($m_method:tt)*)
         ($($retain_semantics:tt)*)
         ($($m_optional:tt)*)
+        ($($m_property:tt)*)
         ($($m_checked:tt)*)
     } => {
         $crate::__declare_class_rewrite_params! {
@@ -960,6 +1053,7 @@ macro_rules! __declare_class_method_out {
             ($($m_method)*)
             ($($retain_semantics)*)
             ($($m_optional)*)
+            ($($m_property)*)
             ($($m_checked)*)
         }
     };
@@ -1000,7 +1094,11 @@ macro_rules! __declare_class_rewrite_params {
             ($($params_converted)* $param : <$param_ty as $crate::__macro_helpers::ConvertArgument>::__Inner,)
             (
                 $($body_prefix)*
-                let mut $param = <$param_ty as $crate::__macro_helpers::ConvertArgument>::__from_declared_param($param);
+                // SAFETY: The parameter is valid, as the method is only
+                // called by the runtime, with the declared signature.
+                let (mut $param, __objc2_writeback) = unsafe {
+                    <$param_ty as $crate::__macro_helpers::ConvertArgument>::__from_declared_param($param)
+                };
             )
 
             ($out_macro)
@@ -1021,7 +1119,11 @@ macro_rules! __declare_class_rewrite_params {
             ($($params_converted)* $param : <$param_ty as $crate::__macro_helpers::ConvertArgument>::__Inner,)
             (
                 $($body_prefix)*
-                let $param = <$param_ty as $crate::__macro_helpers::ConvertArgument>::__from_declared_param($param);
+                // SAFETY: The parameter is valid, as the method is only
+                // called by the runtime, with the declared signature.
+                let ($param, __objc2_writeback) = unsafe {
+                    <$param_ty as $crate::__macro_helpers::ConvertArgument>::__from_declared_param($param)
+                };
             )
 
             ($out_macro)
@@ -1064,6 +1166,7 @@ macro_rules! __declare_class_method_out_inner {
         (#[method($($__sel:tt)*)])
         ()
         ($($__m_optional:tt)*)
+        ($($__m_property:tt)*)
         ($($m_checked:tt)*)
 
         ($($params_converted:tt)*)
@@ -1096,6 +1199,7 @@ macro_rules! __declare_class_method_out_inner {
         (#[method_id($($sel:tt)*)])
         () // Specifying retain semantics is unsupported in declare_class! for now
         ($($__m_optional:tt)*)
+        ($($__m_property:tt)*)
         ($($m_checked:tt)*)
 
         ($($params_converted:tt)*)
@@ -1139,6 +1243,7 @@ macro_rules! __declare_class_method_out_inner {
         (#[method_id($($sel:tt)*)])
         ($($retain_semantics:tt)*)
         ($($__m_optional:tt)*)
+        ($($__m_property:tt)*)
         ($($m_checked:tt)*)
 
         ($($params_converted:tt)*)
@@ -1149,6 +1254,33 @@ macro_rules! __declare_class_method_out_inner {
             $crate::__macro_helpers::compile_error!("`#[method_id(...)]` must have a return type")
         }
     };
+
+    // #[forward] that was not handled by `__declare_class_method_out`
+    {
+        ($($qualifiers:tt)*)
+        ($name:ident)
+        ($($__ret:ty)?)
+        ($__body:block)
+
+        ($__builder_method:ident)
+        ($__receiver:expr)
+        ($__receiver_ty:ty)
+        ($($__params_prefix:tt)*)
+
+        (#[forward])
+        ($($__retain_semantics:tt)*)
+        ($($__m_optional:tt)*)
+        ($($__m_property:tt)*)
+        ($($m_checked:tt)*)
+
+        ($($__params_converted:tt)*)
+        ($($__body_prefix:tt)*)
+    } => {
+        $($m_checked)*
+        $($qualifiers)* fn $name() {
+            $crate::__macro_helpers::compile_error!("`#[forward]` must be used on an instance method taking `&self`, and cannot be combined with `#[property(...)]`")
+        }
+    };
 }
 
 #[doc(hidden)]
@@ -1167,6 +1299,56 @@ macro_rules! __convert_result {
 #[doc(hidden)]
 #[macro_export]
 macro_rules! __declare_class_register_out {
+    // #[forward]
+    {
+        ($builder:ident)
+        ($($qualifiers:tt)*)
+        ($name:ident)
+        ($($__ret:ty)?)
+        ($__body:block)
+
+        (add_method)
+        ($__receiver:expr)
+        ($__receiver_ty:ty)
+        (&$__self:ident, _: $__sel_ty:ty,)
+        ($($__params_rest:tt)*)
+
+        (#[forward])
+        ()
+        ($($m_optional:tt)*)
+        ()
+        ($($m_checked:tt)*)
+    } => {
+        $crate::__extract_and_apply_cfg_attributes! {
+            ($($m_checked)*)
+
+            $crate::__extern_methods_no_optional!($($m_optional)*);
+
+            $crate::__declare_class_forward!($builder, Self::$name);
+        }
+    };
+    // Invalid #[forward], the error is emitted by
+    // `__declare_class_method_out_inner`.
+    {
+        ($builder:ident)
+        ($($qualifiers:tt)*)
+        ($name:ident)
+        ($($__ret:ty)?)
+        ($__body:block)
+
+        ($builder_method:ident)
+        ($__receiver:expr)
+        ($__receiver_ty:ty)
+        ($($__params_prefix:tt)*)
+        ($($params_rest:tt)*)
+
+        (#[forward])
+        ($($retain_semantics:tt)*)
+        ($($m_optional:tt)*)
+        ($($m_property:tt)*)
+        ($($m_checked:tt)*)
+    } => {};
+
     {
         ($builder:ident)
         ($($qualifiers:tt)*)
@@ -1183,6 +1365,7 @@ macro_rules! __declare_class_register_out {
         (#[$method_or_method_id:ident($($sel:tt)*)])
         ($($retain_semantics:tt)*)
         ($($m_optional:tt)*)
+        ($($m_property:tt)*)
         ($($m_checked:tt)*)
     } => {
         $crate::__extract_and_apply_cfg_attributes! {
@@ -1191,18 +1374,104 @@ macro_rules! __declare_class_register_out {
             $crate::__declare_class_invalid_selectors!(#[$method_or_method_id($($sel)*)]);
             $crate::__extern_methods_no_optional!($($m_optional)*);
 
+            let __objc2_func = Self::$name as $crate::__fn_ptr! {
+                ($($qualifiers)*)
+                (_, _,)
+                $($params_rest)*
+            };
+
             $builder.$builder_method(
                 $crate::sel!($($sel)*),
-                Self::$name as $crate::__fn_ptr! {
-                    ($($qualifiers)*)
-                    (_, _,)
-                    $($params_rest)*
-                },
+                __objc2_func,
             );
+
+            $crate::__declare_class_method_property! {
+                ($builder)
+                ($builder_method)
+                ($($sel)*)
+                ($($m_property)*)
+                (__objc2_func)
+            }
         }
     };
 }
 
+#[doc(hidden)]
+#[macro_export]
+#[cfg(feature = "invocation")]
+macro_rules! __declare_class_forward {
+    ($builder:ident, $handler:expr) => {
+        $builder.add_forwarding_handler($handler)
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+#[cfg(not(feature = "invocation"))]
+macro_rules! __declare_class_forward {
+    ($builder:ident, $handler:expr) => {
+        $crate::__macro_helpers::compile_error!("`#[forward]` requires the `\"invocation\"` feature")
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __declare_class_method_property {
+    // No property
+    {
+        ($builder:ident)
+        ($builder_method:ident)
+        ($($sel:tt)*)
+        ()
+        ($func:ident)
+    } => {};
+
+    // Getter
+    {
+        ($builder:ident)
+        (add_method)
+        ($getter:ident)
+        (#[property($($args:tt)*)])
+        ($func:ident)
+    } => {
+        #[allow(unused_mut)]
+        let mut __objc2_attributes: $crate::runtime::PropertyAttributes<'_> =
+            $crate::__macro_helpers::Default::default();
+        $crate::__parse_property_attributes!(__objc2_attributes; $($args)*);
+        $builder.add_method_property(
+            $crate::__macro_helpers::stringify!($getter),
+            &__objc2_attributes,
+            $func,
+        );
+    };
+
+    // Selector with arguments
+    {
+        ($builder:ident)
+        (add_method)
+        ($($sel:tt)*)
+        (#[property($($args:tt)*)])
+        ($func:ident)
+    } => {
+        $crate::__macro_helpers::compile_error!(
+            "`#[property(...)]` must be used on a getter method, whose selector takes no arguments"
+        );
+    };
+
+    // Class method
+    {
+        ($builder:ident)
+        (add_class_method)
+        ($($sel:tt)*)
+        (#[property($($args:tt)*)])
+        ($func:ident)
+    } => {
+        $crate::__macro_helpers::compile_error!(
+            "`#[property(...)]` is only supported on instance methods"
+        );
+    };
+}
+
 #[doc(hidden)]
 #[macro_export]
 macro_rules! __declare_class_invalid_selectors {
@@ -0,0 +1,129 @@
+/// Get the type-encoding of a type as a [`&'static CStr`][std::ffi::CStr],
+/// computed at compile-time.
+///
+/// This is useful when you need to pass the encoding to a runtime function,
+/// or store it in static data, without allocating the string representation
+/// of the [`Encoding`] at runtime.
+///
+/// The type must implement [`Encode`], and because the length of the string
+/// is computed in a `const`, it cannot refer to generic parameters (or
+/// `Self`) from the surrounding scope.
+///
+/// See also [`method_encoding_cstr!`] for getting the type-encoding of a
+/// method.
+///
+/// [`Encoding`]: crate::encode::Encoding
+/// [`Encode`]: crate::encode::Encode
+/// [`method_encoding_cstr!`]: crate::method_encoding_cstr
+///
+///
+/// # Examples
+///
+/// ```
+/// use std::ffi::CStr;
+/// use objc2::encoding_cstr;
+/// use objc2::encode::{Encode, Encoding};
+/// use objc2::runtime::AnyObject;
+///
+/// #[repr(C)]
+/// struct MyPoint {
+///     x: f32,
+///     y: f32,
+/// }
+///
+/// unsafe impl Encode for MyPoint {
+///     const ENCODING: Encoding = Encoding::Struct("MyPoint", &[f32::ENCODING, f32::ENCODING]);
+/// }
+///
+/// static ENCODING: &CStr = encoding_cstr!(MyPoint);
+/// assert_eq!(ENCODING.to_str(), Ok("{MyPoint=ff}"));
+///
+/// assert_eq!(encoding_cstr!(*mut AnyObject).to_str(), Ok("@"));
+/// ```
+///
+/// Generic parameters cannot be used.
+///
+/// ```compile_fail
+/// use std::ffi::CStr;
+/// use objc2::encoding_cstr;
+/// use objc2::encode::Encode;
+///
+/// fn encoding<T: Encode>() -> &'static CStr {
+///     encoding_cstr!(T)
+/// }
+/// ```
+#[macro_export]
+macro_rules! encoding_cstr {
+    ($t:ty) => {{
+        const __ENCODING: $crate::encode::Encoding = <$t as $crate::encode::Encode>::ENCODING;
+        const __DATA: [$crate::__macro_helpers::u8; __ENCODING.str_len() + 1] =
+            __ENCODING.str_array();
+        const __CSTR: &$crate::__macro_helpers::CStr =
+            $crate::__macro_helpers::cstr_from_encoding_bytes(&__DATA);
+        __CSTR
+    }};
+}
+
+/// Get the type-encoding of a method as a
+/// [`&'static CStr`][std::ffi::CStr], computed at compile-time.
+///
+/// The method is given as a function pointer type, without the receiver and
+/// selector, which are always added as `@` and `:`. This is the same
+/// encoding that [`ClassBuilder::add_method`] registers for an
+/// implementation taking the given arguments and returning the given type.
+///
+/// The argument types must implement [`EncodeArgument`], and the return type
+/// must implement [`EncodeReturn`]. Like [`encoding_cstr!`], the types
+/// cannot refer to generic parameters from the surrounding scope.
+///
+/// [`ClassBuilder::add_method`]: crate::declare::ClassBuilder::add_method
+/// [`EncodeArgument`]: crate::encode::EncodeArgument
+/// [`EncodeReturn`]: crate::encode::EncodeReturn
+/// [`encoding_cstr!`]: crate::encoding_cstr
+///
+///
+/// # Examples
+///
+/// Create a table of method type-encodings in static data.
+///
+/// ```
+/// use std::ffi::CStr;
+/// use objc2::method_encoding_cstr;
+/// use objc2::runtime::AnyObject;
+///
+/// static TYPES: [&CStr; 3] = [
+///     method_encoding_cstr!(fn()),
+///     method_encoding_cstr!(fn(i32, *mut AnyObject) -> f64),
+///     method_encoding_cstr!(fn() -> *mut AnyObject),
+/// ];
+///
+/// assert_eq!(TYPES[0].to_str(), Ok("v@:"));
+/// assert_eq!(TYPES[1].to_str(), Ok("d@:i@"));
+/// assert_eq!(TYPES[2].to_str(), Ok("@@:"));
+/// ```
+#[macro_export]
+macro_rules! method_encoding_cstr {
+    (fn($($arg:ty),* $(,)?) $(-> $ret:ty)?) => {{
+        const __RET: &$crate::encode::Encoding = &$crate::__method_encoding_cstr_ret!($($ret)?);
+        const __ARGS: &[$crate::encode::Encoding] =
+            <($($arg,)*) as $crate::encode::EncodeArguments>::ENCODINGS;
+        const __DATA: [
+            $crate::__macro_helpers::u8;
+            $crate::__macro_helpers::method_types_len(__RET, __ARGS) + 1
+        ] = $crate::__macro_helpers::method_types_array(__RET, __ARGS);
+        const __CSTR: &$crate::__macro_helpers::CStr =
+            $crate::__macro_helpers::cstr_from_encoding_bytes(&__DATA);
+        __CSTR
+    }};
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __method_encoding_cstr_ret {
+    () => {
+        <() as $crate::encode::EncodeReturn>::ENCODING_RETURN
+    };
+    ($ret:ty) => {
+        <$ret as $crate::encode::EncodeReturn>::ENCODING_RETURN
+    };
+}
@@ -303,6 +303,7 @@ macro_rules! __extern_methods_method_out {
         (#[method($($sel:tt)*)])
         ()
         ($($m_optional:tt)*)
+        ($($m_property:tt)*)
         ($($m_checked:tt)*)
     } => {
         $($m_checked)*
@@ -311,6 +312,7 @@ macro_rules! __extern_methods_method_out {
             $($where : $bound,)*
         {
             $crate::__extern_methods_no_optional!($($m_optional)*);
+            $crate::__extern_methods_no_property!($($m_property)*);
 
             #[allow(unused_unsafe)]
             unsafe {
@@ -340,6 +342,7 @@ macro_rules! __extern_methods_method_out {
         (#[method_id($($sel:tt)*)])
         ($($retain_semantics:tt)*)
         ($($m_optional:tt)*)
+        ($($m_property:tt)*)
         ($($m_checked:tt)*)
     } => {
         $($m_checked)*
@@ -348,6 +351,7 @@ macro_rules! __extern_methods_method_out {
             $($where : $bound,)*
         {
             $crate::__extern_methods_no_optional!($($m_optional)*);
+            $crate::__extern_methods_no_property!($($m_property)*);
 
             #[allow(unused_unsafe)]
             unsafe {
@@ -375,3 +379,14 @@ macro_rules! __extern_methods_no_optional {
         )
     };
 }
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __extern_methods_no_property {
+    () => {};
+    (#[property($($args:tt)*)]) => {
+        $crate::__macro_helpers::compile_error!(
+            "`#[property(...)]` is only supported in `declare_class!`"
+        )
+    };
+}
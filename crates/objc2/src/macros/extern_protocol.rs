@@ -282,6 +282,7 @@ macro_rules! __extern_protocol_method_out {
         (#[method($($sel:tt)*)])
         ()
         ($($m_optional:tt)*)
+        ($($m_property:tt)*)
         ($($m_checked:tt)*)
     } => {
         $($m_checked)*
@@ -290,6 +291,8 @@ macro_rules! __extern_protocol_method_out {
             Self: $crate::__macro_helpers::Sized + $crate::Message
             $(, $where : $bound)*
         {
+            $crate::__extern_methods_no_property!($($m_property)*);
+
             #[allow(unused_unsafe)]
             unsafe {
                 $crate::__method_msg_send! {
@@ -318,6 +321,7 @@ macro_rules! __extern_protocol_method_out {
         (#[method_id($($sel:tt)*)])
         ($($retain_semantics:tt)*)
         ($($m_optional:tt)*)
+        ($($m_property:tt)*)
         ($($m_checked:tt)*)
     } => {
         $($m_checked)*
@@ -326,6 +330,8 @@ macro_rules! __extern_protocol_method_out {
             Self: $crate::__macro_helpers::Sized + $crate::Message
             $(, $where : $bound)*
         {
+            $crate::__extern_methods_no_property!($($m_property)*);
+
             #[allow(unused_unsafe)]
             unsafe {
                 $crate::__method_msg_send_id! {
@@ -355,6 +361,7 @@ macro_rules! __extern_protocol_method_out {
         (#[method($($sel:tt)*)])
         ()
         ($($m_optional:tt)*)
+        ($($m_property:tt)*)
         ($($m_checked:tt)*)
     } => {
         $($m_checked)*
@@ -363,6 +370,8 @@ macro_rules! __extern_protocol_method_out {
             Self: $crate::__macro_helpers::Sized + $crate::ClassType
             $(, $where : $bound)*
         {
+            $crate::__extern_methods_no_property!($($m_property)*);
+
             #[allow(unused_unsafe)]
             unsafe {
                 $crate::__method_msg_send! {
@@ -391,6 +400,7 @@ macro_rules! __extern_protocol_method_out {
         (#[method_id($($sel:tt)*)])
         ($($retain_semantics:tt)*)
         ($($m_optional:tt)*)
+        ($($m_property:tt)*)
         ($($m_checked:tt)*)
     } => {
         $($m_checked)*
@@ -399,6 +409,8 @@ macro_rules! __extern_protocol_method_out {
             Self: $crate::__macro_helpers::Sized + $crate::ClassType
             $(, $where : $bound)*
         {
+            $crate::__extern_methods_no_property!($($m_property)*);
+
             #[allow(unused_unsafe)]
             unsafe {
                 $crate::__method_msg_send_id! {
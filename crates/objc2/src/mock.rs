@@ -0,0 +1,776 @@
+//! # Mock objects for testing.
+//!
+//! This module lets you create objects whose methods are declared at
+//! runtime from a set of expectations, which is useful for unit-testing code
+//! that sends messages to Objective-C objects, without having to write a
+//! [`declare_class!`] for each test.
+//!
+//! Each expectation consists of a selector and the types of the method,
+//! optionally along with matchers for the arguments, the value to return,
+//! and the number of times the method is expected to be called. Every
+//! message with an expected selector that the mock receives is recorded,
+//! and the number of calls is verified when the mock is dropped. Other
+//! messages are handled by the superclass, and are not recorded.
+//!
+//! This is only available when the `"mock"` feature is enabled, and uses
+//! [`Invocation`] internally, see that for the supported types.
+//!
+//! [`declare_class!`]: crate::declare_class
+//! [`Invocation`]: crate::runtime::Invocation
+//!
+//!
+//! ## Example
+//!
+//! ```
+//! use objc2::mock::{Matcher, MockBuilder};
+//! use objc2::runtime::Value;
+//! use objc2::{msg_send, sel};
+//!
+//! let mut builder = MockBuilder::new();
+//! builder
+//!     .expect::<(u32,), u32>(sel!(doubleNumber:))
+//!     .with([Matcher::equal_to(Value::U32(21))])
+//!     .returning(Value::U32(42))
+//!     .times(1);
+//! // SAFETY: The method is only called with the declared types.
+//! let mock = unsafe { builder.build() };
+//!
+//! let res: u32 = unsafe { msg_send![&*mock, doubleNumber: 21u32] };
+//! assert_eq!(res, 42);
+//!
+//! let calls = mock.calls();
+//! assert_eq!(calls.len(), 1);
+//! assert_eq!(calls[0].sel(), sel!(doubleNumber:));
+//! // The number of calls is verified when the mock is dropped.
+//! ```
+use alloc::boxed::Box;
+use alloc::format;
+use alloc::string::String;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::ffi::c_void;
+use core::fmt;
+use core::ops::Deref;
+use core::ptr;
+use core::sync::atomic::{AtomicUsize, Ordering};
+use std::ffi::CStr;
+use std::sync::{Mutex, MutexGuard, PoisonError};
+
+use libffi::low::ffi_cif;
+
+use crate::__macro_helpers::{retain_semantics, MethodTypes};
+use crate::declare::{method_type_encoding, ClassBuilder};
+use crate::encode::{EncodeArguments, EncodeReturn, Encoding, MethodEncoding};
+use crate::rc::Id;
+use crate::runtime::{
+    leak_closure, AnyClass, AnyObject, AnyProtocol, Bool, Invocation, NSObject, Sel, Value,
+};
+use crate::{msg_send, msg_send_id, sel, ClassType};
+
+/// Matches an argument of a message sent to a [`Mock`].
+pub struct Matcher(MatcherInner);
+
+enum MatcherInner {
+    Any,
+    EqualTo(Value),
+    Predicate(Box<dyn Fn(&Value) -> bool + Send + Sync>),
+}
+
+impl Matcher {
+    /// Match any argument.
+    pub fn any() -> Self {
+        Self(MatcherInner::Any)
+    }
+
+    /// Match arguments equal to the given value.
+    ///
+    /// Objects are equal if they are the same object, or if `isEqual:`
+    /// returns `YES`. Other values are compared by value.
+    pub fn equal_to(value: Value) -> Self {
+        Self(MatcherInner::EqualTo(value))
+    }
+
+    /// Match arguments for which the predicate returns `true`.
+    ///
+    /// The predicate is called on the thread that the message is sent on.
+    pub fn predicate<F: Fn(&Value) -> bool + Send + Sync + 'static>(predicate: F) -> Self {
+        Self(MatcherInner::Predicate(Box::new(predicate)))
+    }
+
+    fn matches(&self, value: &Value) -> bool {
+        match &self.0 {
+            MatcherInner::Any => true,
+            MatcherInner::EqualTo(expected) => values_equal(expected, value),
+            MatcherInner::Predicate(predicate) => predicate(value),
+        }
+    }
+}
+
+impl fmt::Debug for Matcher {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.0 {
+            MatcherInner::Any => f.write_str("Matcher::any()"),
+            MatcherInner::EqualTo(value) => write!(f, "Matcher::equal_to({value:?})"),
+            MatcherInner::Predicate(_) => f.write_str("Matcher::predicate(..)"),
+        }
+    }
+}
+
+fn values_equal(a: &Value, b: &Value) -> bool {
+    match (a, b) {
+        (Value::Void, Value::Void) => true,
+        (Value::I8(a), Value::I8(b)) => a == b,
+        (Value::I16(a), Value::I16(b)) => a == b,
+        (Value::I32(a), Value::I32(b)) => a == b,
+        (Value::I64(a), Value::I64(b)) => a == b,
+        (Value::U8(a), Value::U8(b)) => a == b,
+        (Value::U16(a), Value::U16(b)) => a == b,
+        (Value::U32(a), Value::U32(b)) => a == b,
+        (Value::U64(a), Value::U64(b)) => a == b,
+        (Value::F32(a), Value::F32(b)) => a == b,
+        (Value::F64(a), Value::F64(b)) => a == b,
+        (Value::Bool(a), Value::Bool(b)) => a == b,
+        (Value::Object(a), Value::Object(b)) => match (a.as_deref(), b.as_deref()) {
+            (None, None) => true,
+            (Some(a), Some(b)) => {
+                ptr::eq(a, b)
+                    || (a.class().responds_to(sel!(isEqual:)) && {
+                        // SAFETY: The object implements `isEqual:`, which
+                        // takes an object and returns `BOOL`.
+                        let res: Bool = unsafe { msg_send![a, isEqual: b] };
+                        res.as_bool()
+                    })
+            }
+            _ => false,
+        },
+        (Value::Class(a), Value::Class(b)) => a == b,
+        (Value::Sel(a), Value::Sel(b)) => a == b,
+        (Value::Pointer(a), Value::Pointer(b)) => a == b,
+        (Value::Struct(a), Value::Struct(b)) => {
+            a.len() == b.len() && a.iter().zip(b).all(|(a, b)| values_equal(a, b))
+        }
+        _ => false,
+    }
+}
+
+/// An expected message, created with [`MockBuilder::expect`].
+///
+/// By default, any arguments are accepted, a zeroed value (e.g. `0` or
+/// `nil`) is returned, and the method may be called any number of times.
+#[derive(Debug)]
+pub struct Expectation {
+    sel: Sel,
+    args: &'static [Encoding],
+    ret: Encoding,
+    types: Option<&'static CStr>,
+    matchers: Option<Vec<Matcher>>,
+    value: Option<Value>,
+    times: Option<usize>,
+}
+
+impl Expectation {
+    /// Only match messages whose arguments match the given matchers, one
+    /// for each argument.
+    pub fn with<I: IntoIterator<Item = Matcher>>(&mut self, matchers: I) -> &mut Self {
+        self.matchers = Some(matchers.into_iter().collect());
+        self
+    }
+
+    /// Return the given value when the message is received.
+    ///
+    /// Objects are returned autoreleased, unless the selector is in the
+    /// `new`, `alloc`, `init`, `copy` or `mutableCopy` families.
+    pub fn returning(&mut self, value: Value) -> &mut Self {
+        self.value = Some(value);
+        self
+    }
+
+    /// Expect the message to be received exactly the given number of times.
+    pub fn times(&mut self, times: usize) -> &mut Self {
+        self.times = Some(times);
+        self
+    }
+
+    /// Expect the message to never be received.
+    pub fn never(&mut self) -> &mut Self {
+        self.times(0)
+    }
+
+    fn matches(&self, arguments: &[Value]) -> bool {
+        match &self.matchers {
+            Some(matchers) => matchers
+                .iter()
+                .zip(arguments)
+                .all(|(matcher, argument)| matcher.matches(argument)),
+            None => true,
+        }
+    }
+
+    fn is_saturated(&self, count: usize) -> bool {
+        self.times.map_or(false, |times| count >= times)
+    }
+}
+
+/// A message received by a [`Mock`].
+#[derive(Debug, Clone)]
+pub struct Call {
+    sel: Sel,
+    arguments: Vec<Value>,
+}
+
+impl Call {
+    /// The selector of the message.
+    #[inline]
+    pub fn sel(&self) -> Sel {
+        self.sel
+    }
+
+    /// The arguments of the message, excluding the receiver and the
+    /// selector.
+    #[inline]
+    pub fn arguments(&self) -> &[Value] {
+        &self.arguments
+    }
+}
+
+/// A builder for [`Mock`] objects.
+#[derive(Debug)]
+pub struct MockBuilder {
+    superclass: &'static AnyClass,
+    protocols: Vec<&'static AnyProtocol>,
+    expectations: Vec<Expectation>,
+}
+
+impl MockBuilder {
+    /// Create a builder for a mock that is a subclass of [`NSObject`].
+    pub fn new() -> Self {
+        Self::with_superclass(NSObject::class())
+    }
+
+    /// Create a builder for a mock that is a subclass of the given class.
+    ///
+    /// Messages that are not expected are handled by the superclass.
+    pub fn with_superclass(superclass: &'static AnyClass) -> Self {
+        Self {
+            superclass,
+            protocols: Vec::new(),
+            expectations: Vec::new(),
+        }
+    }
+
+    /// Make the mock conform to the given protocol.
+    pub fn add_protocol(&mut self, protocol: &'static AnyProtocol) -> &mut Self {
+        self.protocols.push(protocol);
+        self
+    }
+
+    /// Expect the mock to receive a message with the given selector, and
+    /// with the given argument and return types.
+    ///
+    /// A selector may be expected multiple times, e.g. with different
+    /// argument matchers, but the types must be the same. When receiving a
+    /// message, the first expectation whose matchers match the arguments
+    /// and which has not yet been called the expected number of times is
+    /// used.
+    ///
+    ///
+    /// # Panics
+    ///
+    /// Panics if the number of arguments does not match the selector.
+    #[track_caller]
+    pub fn expect<Args, Ret>(&mut self, sel: Sel) -> &mut Expectation
+    where
+        Args: EncodeArguments,
+        Ret: EncodeReturn,
+    {
+        let sel_args = sel.number_of_arguments();
+        assert_eq!(
+            sel_args,
+            Args::ENCODINGS.len(),
+            "selector {sel} accepts {sel_args} arguments, but {} were given",
+            Args::ENCODINGS.len(),
+        );
+        self.expectations.push(Expectation {
+            sel,
+            args: Args::ENCODINGS,
+            ret: Ret::ENCODING_RETURN,
+            types: MethodTypes::<Ret, Args>::cstr(),
+            matchers: None,
+            value: None,
+            times: None,
+        });
+        self.expectations.last_mut().unwrap()
+    }
+
+    /// Register a new class with the expected methods, and create an
+    /// instance of it.
+    ///
+    /// The class (and the methods) are never deallocated, so this should
+    /// only be used in tests.
+    ///
+    ///
+    /// # Panics
+    ///
+    /// Panics if:
+    /// - A selector was expected with different types.
+    /// - The types are not supported by [`Invocation`].
+    /// - The number of argument matchers or the type of the return value of
+    ///   an expectation does not match the types.
+    /// - Debug assertions are enabled, and an overridden method's types are
+    ///   not equal to the ones on the superclass.
+    ///
+    ///
+    /// # Safety
+    ///
+    /// The types given to [`expect`][Self::expect] must match the types
+    /// that the messages are sent with, and methods overriding methods on
+    /// the superclass must uphold the same requirements as those (e.g. a
+    /// mocked `-hash` must be consistent with `-isEqual:`).
+    ///
+    /// The mock may be messaged from any thread, so the objects given to
+    /// the expectations, and the objects that the mocked methods are called
+    /// with, must be safe to send between the threads that the mock is used
+    /// on.
+    #[track_caller]
+    pub unsafe fn build(self) -> Mock {
+        static COUNTER: AtomicUsize = AtomicUsize::new(0);
+        let mut builder = loop {
+            let name = format!("Objc2Mock{}", COUNTER.fetch_add(1, Ordering::Relaxed));
+            if let Some(builder) = ClassBuilder::new(&name, self.superclass) {
+                break builder;
+            }
+        };
+        for protocol in &self.protocols {
+            builder.add_protocol(protocol);
+        }
+
+        let state: &'static Mutex<State> = Box::leak(Box::new(Mutex::new(State {
+            expectations: Vec::new(),
+            calls: Vec::new(),
+            unexpected: Vec::new(),
+        })));
+        let mut expectations: Vec<ExpectationState> = Vec::new();
+        for expectation in self.expectations {
+            let sel = expectation.sel;
+            let types = method_type_encoding(&expectation.ret, expectation.args, expectation.types);
+            let encoding: MethodEncoding = types
+                .to_str()
+                .unwrap()
+                .parse()
+                .unwrap_or_else(|err| panic!("failed parsing the types of {sel}: {err}"));
+            let invocation = Invocation::new(sel, encoding)
+                .unwrap_or_else(|err| panic!("cannot mock {sel}: {err}"));
+
+            if let Some(matchers) = &expectation.matchers {
+                assert_eq!(
+                    matchers.len(),
+                    invocation.number_of_arguments(),
+                    "expected one matcher for each argument of {sel}",
+                );
+            }
+            let value = match &expectation.value {
+                Some(value) => {
+                    assert!(
+                        invocation.return_matches(value),
+                        "the return value {value:?} does not match the return type of {sel}",
+                    );
+                    value.clone()
+                }
+                None => invocation.zeroed_return(),
+            };
+
+            let previous = expectations
+                .iter()
+                .find(|previous| previous.expectation.sel == sel);
+            if let Some(previous) = previous {
+                assert!(
+                    previous.expectation.args == expectation.args
+                        && previous.expectation.ret == expectation.ret,
+                    "{sel} was expected with different types",
+                );
+            } else {
+                let method: &'static MockMethod =
+                    Box::leak(Box::new(MockMethod { invocation, state }));
+                let imp = leak_closure(method.invocation.cif().clone(), call, method);
+                // SAFETY: The closure has the signature given by the
+                // encodings, and the caller ensures that those are correct.
+                unsafe {
+                    builder.add_method_inner(
+                        sel,
+                        expectation.args,
+                        &expectation.ret,
+                        expectation.types,
+                        imp,
+                    )
+                };
+            }
+            // The expectation is shared with the threads that message the
+            // mock, see the `Send` implementation of `State`.
+            #[allow(clippy::arc_with_non_send_sync)]
+            let expectation = Arc::new(expectation);
+            expectations.push(ExpectationState {
+                expectation,
+                value,
+                count: 0,
+            });
+        }
+        lock(state).expectations = expectations;
+
+        let cls = builder.register();
+        // SAFETY: Creating an instance of a subclass of the given class.
+        let obj: Id<AnyObject> = unsafe { msg_send_id![cls, new] };
+        Mock { obj, state }
+    }
+}
+
+impl Default for MockBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+struct ExpectationState {
+    expectation: Arc<Expectation>,
+    value: Value,
+    count: usize,
+}
+
+struct State {
+    expectations: Vec<ExpectationState>,
+    calls: Vec<Call>,
+    unexpected: Vec<Call>,
+}
+
+// SAFETY: The predicates of the matchers are `Send + Sync`, and the caller
+// of `MockBuilder::build` ensures that the objects in the expectations and
+// in the recorded calls can be used from the threads that the mock is
+// messaged on.
+unsafe impl Send for State {}
+
+impl State {
+    /// The expectations for the selector, along with their index and
+    /// whether they have been called the expected number of times.
+    fn candidates(&self, sel: Sel) -> Vec<(usize, Arc<Expectation>, bool)> {
+        self.expectations
+            .iter()
+            .enumerate()
+            .filter(|(_, state)| state.expectation.sel == sel)
+            .map(|(index, state)| {
+                let saturated = state.expectation.is_saturated(state.count);
+                (index, state.expectation.clone(), saturated)
+            })
+            .collect()
+    }
+
+    /// Record the message, and return the value of the expectation that it
+    /// matched, if any.
+    fn record(&mut self, call: Call, index: Option<usize>) -> Option<Value> {
+        // The expectations are cleared when the mock is dropped, so the
+        // index may no longer be valid.
+        let ret = match index.and_then(|index| self.expectations.get_mut(index)) {
+            Some(state) => {
+                state.count += 1;
+                Some(state.value.clone())
+            }
+            None => {
+                self.unexpected.push(call.clone());
+                None
+            }
+        };
+        self.calls.push(call);
+        ret
+    }
+}
+
+fn lock(state: &Mutex<State>) -> MutexGuard<'_, State> {
+    state.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Find the expectation for the message, record it, and return the value to
+/// return.
+///
+/// Matching may send messages (e.g. `isEqual:`, or from a predicate), which
+/// may be to the mock itself, so the lock is not held while matching.
+fn handle(state: &Mutex<State>, sel: Sel, arguments: Vec<Value>) -> Option<Value> {
+    let candidates = lock(state).candidates(sel);
+    let matching: Vec<_> = candidates
+        .iter()
+        .filter(|(_, expectation, _)| expectation.matches(&arguments))
+        .collect();
+    let index = matching
+        .iter()
+        .find(|(_, _, saturated)| !saturated)
+        .or_else(|| matching.first())
+        .map(|(index, _, _)| *index);
+    lock(state).record(Call { sel, arguments }, index)
+}
+
+struct MockMethod {
+    invocation: Invocation,
+    state: &'static Mutex<State>,
+}
+
+/// The implementation of each mocked method.
+unsafe extern "C" fn call(
+    _cif: &ffi_cif,
+    result: &mut c_void,
+    args: *const *const c_void,
+    method: &MockMethod,
+) {
+    let invocation = &method.invocation;
+    let sel = invocation.sel();
+    // SAFETY: The closure is called with the arguments in the encoding.
+    let arguments = unsafe { invocation.read_arguments(args) };
+    let ret = handle(method.state, sel, arguments).unwrap_or_else(|| invocation.zeroed_return());
+    // SAFETY: The result pointer is the one given to the closure, and the
+    // value was checked to match the return type when building the mock.
+    unsafe { invocation.write_return(ret, result) };
+
+    // `init` consumes the receiver.
+    if retain_semantics(sel.name()) == 3 {
+        // SAFETY: The receiver is valid, and we own it.
+        let receiver = unsafe { *(*args).cast::<*mut AnyObject>() };
+        let _ = unsafe { Id::new(receiver) };
+    }
+}
+
+/// An object whose methods were declared from expectations with a
+/// [`MockBuilder`].
+///
+/// This dereferences to the object, which messages can then be sent to.
+///
+///
+/// # Panics
+///
+/// When dropped, this panics if any expectation was not called the expected
+/// number of times, or if any message did not match an expectation (unless
+/// the thread is already panicking). See [`verify`][Self::verify].
+pub struct Mock {
+    obj: Id<AnyObject>,
+    state: &'static Mutex<State>,
+}
+
+impl Mock {
+    /// The mock object.
+    #[inline]
+    pub fn object(&self) -> &Id<AnyObject> {
+        &self.obj
+    }
+
+    /// The messages with an expected selector that were received by the
+    /// mock so far, in the order they were received.
+    pub fn calls(&self) -> Vec<Call> {
+        lock(self.state).calls.clone()
+    }
+
+    /// Check that each expectation was called the expected number of times,
+    /// and that every message matched an expectation.
+    ///
+    ///
+    /// # Panics
+    ///
+    /// Panics with a description of each failed expectation, if any.
+    #[track_caller]
+    pub fn verify(&self) {
+        let cls = self.obj.class();
+        let state = lock(self.state);
+        let mut failures: Vec<String> = Vec::new();
+        for state in &state.expectations {
+            if let Some(times) = state.expectation.times {
+                if state.count != times {
+                    failures.push(format!(
+                        "-[{cls} {}] was expected to be called {times} times, but was called {} times",
+                        state.expectation.sel, state.count,
+                    ));
+                }
+            }
+        }
+        for call in &state.unexpected {
+            failures.push(format!(
+                "unexpected call to -[{cls} {}] with arguments {:?}",
+                call.sel, call.arguments,
+            ));
+        }
+        if !failures.is_empty() {
+            panic!("mock verification failed:\n{}", failures.join("\n"));
+        }
+    }
+}
+
+impl Deref for Mock {
+    type Target = AnyObject;
+
+    #[inline]
+    fn deref(&self) -> &AnyObject {
+        &self.obj
+    }
+}
+
+impl Drop for Mock {
+    fn drop(&mut self) {
+        if !std::thread::panicking() {
+            self.verify();
+        }
+        // The class is never deallocated, so release the objects that the
+        // recorded calls and the expectations hold on to.
+        let mut state = lock(self.state);
+        state.expectations.clear();
+        state.calls.clear();
+        state.unexpected.clear();
+    }
+}
+
+impl fmt::Debug for Mock {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Mock")
+            .field("obj", &self.obj)
+            .field("calls", &self.calls())
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec;
+
+    use core::sync::atomic::AtomicPtr;
+
+    use super::*;
+    use crate::runtime::NSObject;
+
+    #[test]
+    fn test_matchers() {
+        assert!(Matcher::any().matches(&Value::U32(1)));
+        assert!(Matcher::equal_to(Value::U32(1)).matches(&Value::U32(1)));
+        assert!(!Matcher::equal_to(Value::U32(1)).matches(&Value::U32(2)));
+        assert!(!Matcher::equal_to(Value::U32(1)).matches(&Value::I32(1)));
+        assert!(Matcher::equal_to(Value::Object(None)).matches(&Value::Object(None)));
+        let matcher = Matcher::equal_to(Value::Struct(vec![Value::I8(1), Value::F64(2.0)]));
+        assert!(matcher.matches(&Value::Struct(vec![Value::I8(1), Value::F64(2.0)])));
+        assert!(!matcher.matches(&Value::Struct(vec![Value::I8(1)])));
+        let matcher = Matcher::predicate(|value| matches!(value, Value::I64(n) if *n > 0));
+        assert!(matcher.matches(&Value::I64(1)));
+        assert!(!matcher.matches(&Value::I64(-1)));
+    }
+
+    #[test]
+    fn test_mock() {
+        let mut builder = MockBuilder::new();
+        builder
+            .expect::<(i32,), i32>(sel!(square:))
+            .with([Matcher::equal_to(Value::I32(3))])
+            .returning(Value::I32(9))
+            .times(1);
+        builder
+            .expect::<(i32,), i32>(sel!(square:))
+            .returning(Value::I32(-1));
+        builder.expect::<(), ()>(sel!(reset)).times(2);
+        builder.expect::<(), u64>(sel!(count));
+        let mock = unsafe { builder.build() };
+
+        let res: i32 = unsafe { msg_send![&*mock, square: 3i32] };
+        assert_eq!(res, 9);
+        // The first expectation is saturated
+        let res: i32 = unsafe { msg_send![&*mock, square: 3i32] };
+        assert_eq!(res, -1);
+        let res: i32 = unsafe { msg_send![&*mock, square: 4i32] };
+        assert_eq!(res, -1);
+        let _: () = unsafe { msg_send![&*mock, reset] };
+        let _: () = unsafe { msg_send![&*mock, reset] };
+        // Zeroed by default
+        let res: u64 = unsafe { msg_send![&*mock, count] };
+        assert_eq!(res, 0);
+        // Messages that were not expected are handled by the superclass
+        let _: usize = unsafe { msg_send![&*mock, hash] };
+
+        let calls = mock.calls();
+        assert_eq!(calls.len(), 6);
+        assert_eq!(calls[0].sel(), sel!(square:));
+        assert!(matches!(calls[0].arguments(), [Value::I32(3)]));
+        assert!(matches!(calls[2].arguments(), [Value::I32(4)]));
+        assert_eq!(calls[5].sel(), sel!(count));
+        mock.verify();
+    }
+
+    #[test]
+    fn test_objects() {
+        let obj = NSObject::new();
+        let mut builder = MockBuilder::new();
+        builder
+            .expect::<(*mut AnyObject,), *mut AnyObject>(sel!(objectForKey:))
+            .with([Matcher::equal_to(Value::Object(Some(Id::into_super(
+                obj.clone(),
+            ))))])
+            .returning(Value::Object(Some(Id::into_super(obj.clone()))));
+        builder
+            .expect::<(), *mut AnyObject>(sel!(newObject))
+            .returning(Value::Object(Some(Id::into_super(obj.clone()))));
+        let mock = unsafe { builder.build() };
+
+        let res: Option<Id<NSObject>> = unsafe { msg_send_id![&*mock, objectForKey: &*obj] };
+        assert_eq!(res.as_deref(), Some(&*obj));
+        let res: Option<Id<NSObject>> = unsafe { msg_send_id![&*mock, newObject] };
+        assert_eq!(res.as_deref(), Some(&*obj));
+        drop(res);
+
+        drop(mock);
+        // The recorded arguments and the return values are released
+        let count: usize = unsafe { msg_send![&obj, retainCount] };
+        assert_eq!(count, 1);
+    }
+
+    #[test]
+    fn test_reentrant() {
+        static MOCK: AtomicPtr<AnyObject> = AtomicPtr::new(ptr::null_mut());
+
+        let mut builder = MockBuilder::new();
+        builder
+            .expect::<(i32,), ()>(sel!(setValue:))
+            .with([Matcher::predicate(|_| {
+                // Messaging the mock while matching must not deadlock
+                let mock = MOCK.load(Ordering::Relaxed);
+                let count: u64 = unsafe { msg_send![mock, count] };
+                count == 0
+            })])
+            .times(1);
+        builder.expect::<(), u64>(sel!(count)).times(1);
+        let mock = unsafe { builder.build() };
+        MOCK.store(
+            Id::as_ptr(mock.object()) as *mut AnyObject,
+            Ordering::Relaxed,
+        );
+
+        let _: () = unsafe { msg_send![&*mock, setValue: 1i32] };
+        assert_eq!(mock.calls().len(), 2);
+    }
+
+    #[test]
+    #[should_panic = "-[Objc2Mock"]
+    fn test_unexpected_call() {
+        let mut builder = MockBuilder::new();
+        builder
+            .expect::<(i32,), ()>(sel!(setValue:))
+            .with([Matcher::equal_to(Value::I32(1))]);
+        let mock = unsafe { builder.build() };
+        let _: () = unsafe { msg_send![&*mock, setValue: 2i32] };
+    }
+
+    #[test]
+    #[should_panic = "was expected to be called 2 times, but was called 1 times"]
+    fn test_times() {
+        let mut builder = MockBuilder::new();
+        builder.expect::<(), ()>(sel!(reset)).times(2);
+        let mock = unsafe { builder.build() };
+        let _: () = unsafe { msg_send![&*mock, reset] };
+    }
+
+    #[test]
+    #[should_panic = "does not match the return type of count"]
+    fn test_invalid_return_value() {
+        let mut builder = MockBuilder::new();
+        builder
+            .expect::<(), u32>(sel!(count))
+            .returning(Value::I64(1));
+        let _mock = unsafe { builder.build() };
+    }
+}
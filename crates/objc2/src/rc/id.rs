@@ -469,7 +469,7 @@ impl<T: Message> Id<T> {
     }
 
     #[inline]
-    pub(super) fn autorelease_inner(this: Self) -> *mut T {
+    pub(crate) fn autorelease_inner(this: Self) -> *mut T {
         let ptr = ManuallyDrop::new(this).ptr.as_ptr();
         // SAFETY:
         // - The `ptr` is guaranteed to be valid and have at least one
@@ -0,0 +1,320 @@
+use core::fmt;
+use core::marker::PhantomData;
+use core::ptr;
+
+use crate::ffi;
+use crate::mutability::IsRetainable;
+use crate::rc::Id;
+use crate::runtime::{AnyClass, AnyObject};
+use crate::ClassType;
+
+/// The policy used when storing an associated object.
+///
+/// See [Apple's documentation](https://developer.apple.com/documentation/objectivec/objc_associationpolicy?language=objc).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[doc(alias = "objc_AssociationPolicy")]
+pub enum AssociationPolicy {
+    /// The associated object is stored without retaining it.
+    #[doc(alias = "OBJC_ASSOCIATION_ASSIGN")]
+    Assign,
+    /// The associated object is retained, and the association is made
+    /// atomically.
+    #[doc(alias = "OBJC_ASSOCIATION_RETAIN")]
+    Retain,
+    /// The associated object is retained, and the association is not made
+    /// atomically.
+    #[doc(alias = "OBJC_ASSOCIATION_RETAIN_NONATOMIC")]
+    RetainNonatomic,
+    /// The associated object is copied, and the association is made
+    /// atomically.
+    #[doc(alias = "OBJC_ASSOCIATION_COPY")]
+    Copy,
+    /// The associated object is copied, and the association is not made
+    /// atomically.
+    #[doc(alias = "OBJC_ASSOCIATION_COPY_NONATOMIC")]
+    CopyNonatomic,
+}
+
+impl AssociationPolicy {
+    const fn as_raw(self) -> ffi::objc_AssociationPolicy {
+        match self {
+            Self::Assign => ffi::OBJC_ASSOCIATION_ASSIGN,
+            Self::Retain => ffi::OBJC_ASSOCIATION_RETAIN,
+            Self::RetainNonatomic => ffi::OBJC_ASSOCIATION_RETAIN_NONATOMIC,
+            Self::Copy => ffi::OBJC_ASSOCIATION_COPY,
+            Self::CopyNonatomic => ffi::OBJC_ASSOCIATION_COPY_NONATOMIC,
+        }
+    }
+}
+
+/// A key used to associate objects of type `T` with other objects.
+///
+/// The address of the key is what identifies the association, so this must
+/// be stored in a `static` (not a `const`), and the same static must be used
+/// when setting and getting the associated object.
+///
+/// The type parameter acts as a witness for the type of the associated
+/// object; [`AnyObject::associated_object`] checks that the stored object
+/// is an instance of `T` before returning it.
+///
+/// See [`AnyObject::set_associated_object`] for an example.
+pub struct AssociatedObjectKey<T: ?Sized> {
+    policy: AssociationPolicy,
+    p: PhantomData<*const T>,
+}
+
+// SAFETY: The key only contains the policy, `T` is only used as a marker.
+unsafe impl<T: ?Sized> Sync for AssociatedObjectKey<T> {}
+unsafe impl<T: ?Sized> Send for AssociatedObjectKey<T> {}
+
+impl<T: ?Sized> AssociatedObjectKey<T> {
+    /// Create a new key whose associated objects are retained atomically.
+    #[inline]
+    pub const fn new() -> Self {
+        Self {
+            policy: AssociationPolicy::Retain,
+            p: PhantomData,
+        }
+    }
+
+    /// Create a new key whose associated objects are stored with the given
+    /// policy.
+    ///
+    ///
+    /// # Safety
+    ///
+    /// - If the policy is [`AssociationPolicy::Assign`], any object set with
+    ///   this key must outlive the association (or be removed before it is
+    ///   deallocated).
+    /// - If the policy is non-atomic, the association must not be modified
+    ///   while it is being read from another thread.
+    /// - If the policy copies the object, the objects must implement
+    ///   `NSCopying`.
+    #[inline]
+    pub const unsafe fn with_policy(policy: AssociationPolicy) -> Self {
+        Self {
+            policy,
+            p: PhantomData,
+        }
+    }
+
+    /// The policy that this key uses.
+    #[inline]
+    pub const fn policy(&self) -> AssociationPolicy {
+        self.policy
+    }
+
+    #[inline]
+    fn as_ptr(&self) -> *const std::os::raw::c_void {
+        let ptr: *const Self = self;
+        ptr.cast()
+    }
+}
+
+impl<T: ?Sized> Default for AssociatedObjectKey<T> {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: ?Sized> fmt::Debug for AssociatedObjectKey<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AssociatedObjectKey")
+            .field("policy", &self.policy)
+            .finish_non_exhaustive()
+    }
+}
+
+fn is_kind_of(cls: &AnyClass, target: &AnyClass) -> bool {
+    let mut current = Some(cls);
+    while let Some(cls) = current {
+        if cls == target {
+            return true;
+        }
+        current = cls.superclass();
+    }
+    false
+}
+
+impl AnyObject {
+    /// Associate an object with self using the given key, or remove the
+    /// association if `value` is [`None`].
+    ///
+    /// This is useful for attaching state to objects whose class you do not
+    /// control, and hence cannot add instance variables to.
+    ///
+    /// See [Apple's documentation](https://developer.apple.com/documentation/objectivec/1418509-objc_setassociatedobject?language=objc).
+    ///
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use objc2::runtime::{AnyObject, AssociatedObjectKey, NSObject};
+    ///
+    /// static KEY: AssociatedObjectKey<NSObject> = AssociatedObjectKey::new();
+    ///
+    /// let obj = NSObject::new();
+    /// let state = NSObject::new();
+    ///
+    /// assert!(obj.associated_object(&KEY).is_none());
+    /// obj.set_associated_object(&KEY, Some(&state));
+    /// assert_eq!(obj.associated_object(&KEY).as_deref(), Some(&*state));
+    ///
+    /// obj.set_associated_object(&KEY, None);
+    /// assert!(obj.associated_object(&KEY).is_none());
+    /// ```
+    #[doc(alias = "objc_setAssociatedObject")]
+    pub fn set_associated_object<T: ClassType + IsRetainable>(
+        &self,
+        key: &'static AssociatedObjectKey<T>,
+        value: Option<&T>,
+    ) {
+        let value: *const T = value.map_or(ptr::null(), |value| value as *const T);
+        // SAFETY: The object is valid, and the key is a static.
+        //
+        // The value is retained or copied unless the policy is `Assign`, in
+        // which case the caller of `AssociatedObjectKey::with_policy` has
+        // ensured that it outlives the association.
+        unsafe {
+            ffi::objc_setAssociatedObject(
+                self.as_ptr() as *mut _,
+                key.as_ptr(),
+                value as *mut _,
+                key.policy.as_raw(),
+            )
+        }
+    }
+
+    /// Get the object associated with self using the given key, if any.
+    ///
+    /// See [`set_associated_object`][Self::set_associated_object] for an
+    /// example.
+    ///
+    /// See [Apple's documentation](https://developer.apple.com/documentation/objectivec/1418865-objc_getassociatedobject?language=objc).
+    ///
+    ///
+    /// # Panics
+    ///
+    /// Panics if the associated object is not an instance of `T` (or a
+    /// subclass of it). This may happen if the key uses a copying policy,
+    /// and copying the object produced an instance of a different class.
+    #[doc(alias = "objc_getAssociatedObject")]
+    pub fn associated_object<T: ClassType + IsRetainable>(
+        &self,
+        key: &'static AssociatedObjectKey<T>,
+    ) -> Option<Id<T>> {
+        // SAFETY: The object is valid, and the key is a static.
+        let value = unsafe { ffi::objc_getAssociatedObject(self.as_ptr(), key.as_ptr()) };
+        // SAFETY: Associated objects are valid objects (or NULL); for the
+        // `Assign` policy, this is upheld by the caller of `with_policy`.
+        let obj: &AnyObject = unsafe { value.cast::<AnyObject>().as_ref() }?;
+        let cls = obj.class();
+        if !is_kind_of(cls, T::class()) {
+            panic!(
+                "associated object had class {cls}, but expected {}",
+                T::class()
+            );
+        }
+        // SAFETY: Just checked that the object is an instance of `T`, and
+        // `T` is `IsRetainable`.
+        unsafe { Id::retain(value as *mut T) }
+    }
+
+    /// Remove all associated objects from self.
+    ///
+    /// See [Apple's documentation](https://developer.apple.com/documentation/objectivec/1418683-objc_removeassociatedobjects?language=objc).
+    ///
+    ///
+    /// # Safety
+    ///
+    /// This removes associations made by all other code, including
+    /// frameworks, that may rely on their associated objects being present.
+    /// The caller must ensure that no such code exists; you should usually
+    /// use [`set_associated_object`][Self::set_associated_object] with
+    /// [`None`] instead.
+    #[doc(alias = "objc_removeAssociatedObjects")]
+    pub unsafe fn remove_associated_objects(&self) {
+        unsafe { ffi::objc_removeAssociatedObjects(self.as_ptr() as *mut _) }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rc::{__RcTestObject, __ThreadTestData, autoreleasepool};
+    use crate::runtime::NSObject;
+    use crate::test_utils;
+
+    static KEY: AssociatedObjectKey<__RcTestObject> = AssociatedObjectKey::new();
+    static OTHER_KEY: AssociatedObjectKey<__RcTestObject> = AssociatedObjectKey::new();
+
+    #[test]
+    fn test_set_get() {
+        let obj = NSObject::new();
+        let value = __RcTestObject::new();
+        assert!(obj.associated_object(&KEY).is_none());
+
+        obj.set_associated_object(&KEY, Some(&value));
+        autoreleasepool(|_| {
+            let res = obj.associated_object(&KEY).unwrap();
+            assert!(ptr::eq(&*res, &*value));
+            assert!(obj.associated_object(&OTHER_KEY).is_none());
+        });
+
+        obj.set_associated_object(&KEY, None);
+        assert!(obj.associated_object(&KEY).is_none());
+    }
+
+    #[test]
+    fn test_retained() {
+        let obj = NSObject::new();
+        let value = __RcTestObject::new();
+        let mut expected = __ThreadTestData::current();
+
+        obj.set_associated_object(&KEY, Some(&value));
+        drop(value);
+        expected.retain += 1;
+        expected.release += 1;
+        expected.assert_current();
+
+        drop(obj);
+        expected.release += 1;
+        expected.dealloc += 1;
+        expected.assert_current();
+    }
+
+    #[test]
+    fn test_assign() {
+        static ASSIGN: AssociatedObjectKey<__RcTestObject> =
+            unsafe { AssociatedObjectKey::with_policy(AssociationPolicy::Assign) };
+        assert_eq!(ASSIGN.policy(), AssociationPolicy::Assign);
+
+        let obj = NSObject::new();
+        let value = __RcTestObject::new();
+        let expected = __ThreadTestData::current();
+
+        obj.set_associated_object(&ASSIGN, Some(&value));
+        obj.set_associated_object(&ASSIGN, None);
+        expected.assert_current();
+    }
+
+    #[test]
+    #[should_panic = "associated object had class CustomObject, but expected __RcTestObject"]
+    fn test_wrong_class() {
+        static CUSTOM: AssociatedObjectKey<__RcTestObject> = AssociatedObjectKey::new();
+
+        let obj = NSObject::new();
+        let value = test_utils::custom_object();
+        // SAFETY: Set a value of the wrong type using the raw API
+        unsafe {
+            ffi::objc_setAssociatedObject(
+                obj.as_ptr() as *mut _,
+                CUSTOM.as_ptr(),
+                Id::as_ptr(&value) as *mut _,
+                ffi::OBJC_ASSOCIATION_RETAIN,
+            )
+        };
+        let _ = obj.associated_object(&CUSTOM);
+    }
+}
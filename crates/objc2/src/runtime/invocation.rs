@@ -0,0 +1,1038 @@
+//! Sending messages whose types are only known at runtime.
+use alloc::vec;
+use alloc::vec::Vec;
+use core::ffi::c_void;
+use core::fmt;
+use core::mem;
+use core::ptr;
+use std::error::Error;
+
+use libffi::low::{ffi_arg, Callback};
+use libffi::middle::{Cif, Closure, Type};
+
+use crate::__macro_helpers::retain_semantics;
+use crate::encode::{EncodingBox, MethodEncoding, ParseError};
+use crate::ffi;
+use crate::rc::Id;
+use crate::runtime::{AnyClass, AnyObject, Imp, MessageReceiver, Method, Sel};
+
+/// A dynamically typed argument to, or return value from, a message sent
+/// with an [`Invocation`].
+///
+/// Each variant corresponds to one or more type-encodings, see
+/// [`Invocation`] for the full list.
+#[derive(Debug, Clone)]
+pub enum Value {
+    /// The return value of a method returning `void`.
+    Void,
+    /// A `char` or `signed char`.
+    I8(i8),
+    /// A `short`.
+    I16(i16),
+    /// An `int` or `long`.
+    I32(i32),
+    /// A `long long`.
+    I64(i64),
+    /// An `unsigned char`.
+    U8(u8),
+    /// An `unsigned short`.
+    U16(u16),
+    /// An `unsigned int` or `unsigned long`.
+    U32(u32),
+    /// An `unsigned long long`.
+    U64(u64),
+    /// A `float`.
+    F32(f32),
+    /// A `double`.
+    F64(f64),
+    /// A C99 `_Bool`.
+    ///
+    /// Note that `BOOL` is encoded as a `signed char` on some platforms, and
+    /// is then passed as [`Value::I8`] instead.
+    Bool(bool),
+    /// An object or a block, or `nil`.
+    Object(Option<Id<AnyObject>>),
+    /// A class, or `Nil`.
+    Class(Option<&'static AnyClass>),
+    /// A selector, or `NULL`.
+    Sel(Option<Sel>),
+    /// A C-string, a function pointer or any other pointer.
+    Pointer(*mut c_void),
+    /// A struct, with the value of each of its fields.
+    Struct(Vec<Value>),
+}
+
+impl Value {
+    fn type_name(&self) -> &'static str {
+        match self {
+            Self::Void => "void",
+            Self::I8(_) => "i8",
+            Self::I16(_) => "i16",
+            Self::I32(_) => "i32",
+            Self::I64(_) => "i64",
+            Self::U8(_) => "u8",
+            Self::U16(_) => "u16",
+            Self::U32(_) => "u32",
+            Self::U64(_) => "u64",
+            Self::F32(_) => "f32",
+            Self::F64(_) => "f64",
+            Self::Bool(_) => "bool",
+            Self::Object(_) => "object",
+            Self::Class(_) => "class",
+            Self::Sel(_) => "selector",
+            Self::Pointer(_) => "pointer",
+            Self::Struct(_) => "struct",
+        }
+    }
+}
+
+/// The type of a [`Value`], along with the information needed to pass it
+/// through `libffi`.
+#[derive(Debug, Clone, PartialEq)]
+enum ValueType {
+    Void,
+    I8,
+    I16,
+    I32,
+    I64,
+    U8,
+    U16,
+    U32,
+    U64,
+    F32,
+    F64,
+    Bool,
+    Object,
+    Class,
+    Sel,
+    Pointer,
+    Struct(Vec<ValueType>),
+}
+
+impl ValueType {
+    fn from_encoding(encoding: &EncodingBox) -> Option<Self> {
+        Some(match encoding {
+            EncodingBox::Void => Self::Void,
+            EncodingBox::Char => Self::I8,
+            EncodingBox::Short => Self::I16,
+            // `l` is always 32-bit in type-encodings.
+            EncodingBox::Int | EncodingBox::Long => Self::I32,
+            EncodingBox::LongLong => Self::I64,
+            EncodingBox::UChar => Self::U8,
+            EncodingBox::UShort => Self::U16,
+            EncodingBox::UInt | EncodingBox::ULong => Self::U32,
+            EncodingBox::ULongLong => Self::U64,
+            EncodingBox::Float => Self::F32,
+            EncodingBox::Double => Self::F64,
+            EncodingBox::Bool => Self::Bool,
+            EncodingBox::Object | EncodingBox::Block => Self::Object,
+            EncodingBox::Class => Self::Class,
+            EncodingBox::Sel => Self::Sel,
+            EncodingBox::String | EncodingBox::Unknown | EncodingBox::Pointer(_) => Self::Pointer,
+            EncodingBox::Struct(_, Some(fields)) if !fields.is_empty() => Self::Struct(
+                fields
+                    .iter()
+                    .map(Self::from_encoding)
+                    .collect::<Option<_>>()?,
+            ),
+            EncodingBox::Atomic(inner) | EncodingBox::Qualified(_, inner) => {
+                Self::from_encoding(inner)?
+            }
+            _ => return None,
+        })
+    }
+
+    fn ffi_type(&self) -> Type {
+        match self {
+            Self::Void => Type::void(),
+            Self::I8 => Type::i8(),
+            Self::I16 => Type::i16(),
+            Self::I32 => Type::i32(),
+            Self::I64 => Type::i64(),
+            // `_Bool` is passed as a single byte.
+            Self::U8 | Self::Bool => Type::u8(),
+            Self::U16 => Type::u16(),
+            Self::U32 => Type::u32(),
+            Self::U64 => Type::u64(),
+            Self::F32 => Type::f32(),
+            Self::F64 => Type::f64(),
+            Self::Object | Self::Class | Self::Sel | Self::Pointer => Type::pointer(),
+            Self::Struct(fields) => Type::structure(fields.iter().map(Self::ffi_type)),
+        }
+    }
+
+    /// The size and alignment of the type, following C's layout rules.
+    fn layout(&self) -> (usize, usize) {
+        match self {
+            Self::Void => (0, 1),
+            Self::I8 | Self::U8 | Self::Bool => (1, 1),
+            Self::I16 | Self::U16 => (2, 2),
+            Self::I32 | Self::U32 | Self::F32 => (4, 4),
+            Self::I64 | Self::U64 | Self::F64 => (8, mem::align_of::<u64>()),
+            Self::Object | Self::Class | Self::Sel | Self::Pointer => (
+                mem::size_of::<*mut c_void>(),
+                mem::align_of::<*mut c_void>(),
+            ),
+            Self::Struct(fields) => {
+                let mut size = 0;
+                let mut align = 1;
+                for field in fields {
+                    let (field_size, field_align) = field.layout();
+                    size = round_up(size, field_align) + field_size;
+                    align = align.max(field_align);
+                }
+                (round_up(size, align), align)
+            }
+        }
+    }
+
+    /// The offset of each field of a struct.
+    fn field_offsets(fields: &[Self]) -> impl Iterator<Item = (usize, &Self)> + '_ {
+        let mut offset = 0;
+        fields.iter().map(move |field| {
+            let (size, align) = field.layout();
+            let field_offset = round_up(offset, align);
+            offset = field_offset + size;
+            (field_offset, field)
+        })
+    }
+
+    fn is_integer(&self) -> bool {
+        matches!(
+            self,
+            Self::I8
+                | Self::I16
+                | Self::I32
+                | Self::I64
+                | Self::U8
+                | Self::U16
+                | Self::U32
+                | Self::U64
+                | Self::Bool
+        )
+    }
+
+    fn matches(&self, value: &Value) -> bool {
+        match (self, value) {
+            (Self::Struct(fields), Value::Struct(values)) => {
+                fields.len() == values.len()
+                    && fields
+                        .iter()
+                        .zip(values)
+                        .all(|(field, value)| field.matches(value))
+            }
+            (Self::Void, Value::Void)
+            | (Self::I8, Value::I8(_))
+            | (Self::I16, Value::I16(_))
+            | (Self::I32, Value::I32(_))
+            | (Self::I64, Value::I64(_))
+            | (Self::U8, Value::U8(_))
+            | (Self::U16, Value::U16(_))
+            | (Self::U32, Value::U32(_))
+            | (Self::U64, Value::U64(_))
+            | (Self::F32, Value::F32(_))
+            | (Self::F64, Value::F64(_))
+            | (Self::Bool, Value::Bool(_))
+            | (Self::Object, Value::Object(_))
+            | (Self::Class, Value::Class(_))
+            | (Self::Sel, Value::Sel(_))
+            | (Self::Pointer, Value::Pointer(_)) => true,
+            _ => false,
+        }
+    }
+
+    /// Allocate a zeroed buffer that can hold the type, and that is large
+    /// enough for `libffi` to write a return value into.
+    fn buffer(&self) -> Vec<u64> {
+        let (size, _) = self.layout();
+        let size = size.max(mem::size_of::<ffi_arg>());
+        vec![0; (size + 7) / 8]
+    }
+
+    /// Write the value to the given pointer.
+    ///
+    /// Objects are written without being retained.
+    ///
+    ///
+    /// # Safety
+    ///
+    /// The pointer must be valid for writes of the size of the type, and
+    /// the value must match the type.
+    unsafe fn write(&self, value: &Value, ptr: *mut u8) {
+        // SAFETY: Checked by caller
+        unsafe {
+            match value {
+                Value::Void => {}
+                Value::I8(value) => ptr.cast::<i8>().write_unaligned(*value),
+                Value::I16(value) => ptr.cast::<i16>().write_unaligned(*value),
+                Value::I32(value) => ptr.cast::<i32>().write_unaligned(*value),
+                Value::I64(value) => ptr.cast::<i64>().write_unaligned(*value),
+                Value::U8(value) => ptr.cast::<u8>().write_unaligned(*value),
+                Value::U16(value) => ptr.cast::<u16>().write_unaligned(*value),
+                Value::U32(value) => ptr.cast::<u32>().write_unaligned(*value),
+                Value::U64(value) => ptr.cast::<u64>().write_unaligned(*value),
+                Value::F32(value) => ptr.cast::<f32>().write_unaligned(*value),
+                Value::F64(value) => ptr.cast::<f64>().write_unaligned(*value),
+                Value::Bool(value) => ptr.cast::<u8>().write_unaligned(*value as u8),
+                Value::Object(value) => {
+                    let obj = value.as_deref().map_or(ptr::null(), |obj| obj as *const _);
+                    ptr.cast::<*const AnyObject>().write_unaligned(obj);
+                }
+                Value::Class(value) => {
+                    let cls = value.map_or(ptr::null(), |cls| cls as *const _);
+                    ptr.cast::<*const AnyClass>().write_unaligned(cls);
+                }
+                Value::Sel(value) => {
+                    let sel = value.map_or(ptr::null(), |sel| sel.as_ptr());
+                    ptr.cast::<*const ffi::objc_selector>().write_unaligned(sel);
+                }
+                Value::Pointer(value) => ptr.cast::<*mut c_void>().write_unaligned(*value),
+                Value::Struct(values) => {
+                    if let Self::Struct(fields) = self {
+                        for ((offset, field), value) in Self::field_offsets(fields).zip(values) {
+                            field.write(value, ptr.add(offset));
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Read a value of this type from the given pointer.
+    ///
+    /// Objects are retained if `owned` is `false`, and are otherwise assumed
+    /// to be +1 already.
+    ///
+    ///
+    /// # Safety
+    ///
+    /// The pointer must be valid for reads of the size of the type, and
+    /// contain a valid value of the type.
+    unsafe fn read(&self, ptr: *const u8, owned: bool) -> Value {
+        // SAFETY: Checked by caller
+        unsafe {
+            match self {
+                Self::Void => Value::Void,
+                Self::I8 => Value::I8(ptr.cast::<i8>().read_unaligned()),
+                Self::I16 => Value::I16(ptr.cast::<i16>().read_unaligned()),
+                Self::I32 => Value::I32(ptr.cast::<i32>().read_unaligned()),
+                Self::I64 => Value::I64(ptr.cast::<i64>().read_unaligned()),
+                Self::U8 => Value::U8(ptr.cast::<u8>().read_unaligned()),
+                Self::U16 => Value::U16(ptr.cast::<u16>().read_unaligned()),
+                Self::U32 => Value::U32(ptr.cast::<u32>().read_unaligned()),
+                Self::U64 => Value::U64(ptr.cast::<u64>().read_unaligned()),
+                Self::F32 => Value::F32(ptr.cast::<f32>().read_unaligned()),
+                Self::F64 => Value::F64(ptr.cast::<f64>().read_unaligned()),
+                Self::Bool => Value::Bool(ptr.cast::<u8>().read_unaligned() != 0),
+                Self::Object => {
+                    let obj = ptr.cast::<*mut AnyObject>().read_unaligned();
+                    if owned {
+                        Value::Object(Id::new(obj))
+                    } else {
+                        Value::Object(Id::retain(obj))
+                    }
+                }
+                Self::Class => {
+                    let cls = ptr.cast::<*const AnyClass>().read_unaligned();
+                    Value::Class(cls.as_ref())
+                }
+                Self::Sel => {
+                    let sel = ptr.cast::<*const ffi::objc_selector>().read_unaligned();
+                    Value::Sel(Sel::from_ptr(sel))
+                }
+                Self::Pointer => Value::Pointer(ptr.cast::<*mut c_void>().read_unaligned()),
+                Self::Struct(fields) => Value::Struct(
+                    Self::field_offsets(fields)
+                        .map(|(offset, field)| field.read(ptr.add(offset), false))
+                        .collect(),
+                ),
+            }
+        }
+    }
+
+    /// Convert an integer smaller than `ffi_arg` that was returned through
+    /// `libffi`, which widens such return values.
+    fn convert_ffi_arg(&self, value: ffi_arg) -> Value {
+        match self {
+            Self::I8 => Value::I8(value as i8),
+            Self::I16 => Value::I16(value as i16),
+            Self::I32 => Value::I32(value as i32),
+            Self::U8 => Value::U8(value as u8),
+            Self::U16 => Value::U16(value as u16),
+            Self::U32 => Value::U32(value as u32),
+            Self::Bool => Value::Bool(value as u8 != 0),
+            _ => unreachable!("not a small integer type"),
+        }
+    }
+}
+
+fn round_up(size: usize, align: usize) -> usize {
+    (size + align - 1) / align * align
+}
+
+/// Failed creating or invoking an [`Invocation`].
+///
+/// This implements [`Error`], and a description of the error can be retrieved
+/// using [`fmt::Display`].
+#[derive(Debug, PartialEq, Eq)]
+pub struct InvocationError(Inner);
+
+#[derive(Debug, PartialEq, Eq)]
+enum Inner {
+    EncodingParseError(ParseError),
+    UnsupportedEncoding(EncodingBox),
+    MissingReceiver,
+    MethodNotFound(Sel),
+    MismatchedArgumentsCount(usize, usize),
+    MismatchedArgument(usize, EncodingBox, &'static str),
+}
+
+impl fmt::Display for Inner {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::EncodingParseError(e) => {
+                write!(f, "failed parsing encoding: {e}")
+            }
+            Self::UnsupportedEncoding(encoding) => {
+                write!(f, "the type {encoding} is not supported")
+            }
+            Self::MissingReceiver => {
+                write!(
+                    f,
+                    "method encoding must begin with a receiver and a selector"
+                )
+            }
+            Self::MethodNotFound(sel) => {
+                write!(f, "method {sel} not found")
+            }
+            Self::MismatchedArgumentsCount(expected, actual) => {
+                write!(f, "expected {expected} arguments, but {actual} were given")
+            }
+            Self::MismatchedArgument(i, expected, actual) => {
+                write!(f, "expected argument at index {i} to have type {expected}, but found a value of type {actual}")
+            }
+        }
+    }
+}
+
+impl From<ParseError> for InvocationError {
+    fn from(e: ParseError) -> Self {
+        Self(Inner::EncodingParseError(e))
+    }
+}
+
+impl fmt::Display for InvocationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Delegate to inner
+        fmt::Display::fmt(&self.0, f)
+    }
+}
+
+impl Error for InvocationError {}
+
+fn value_type(encoding: &EncodingBox) -> Result<ValueType, InvocationError> {
+    ValueType::from_encoding(encoding)
+        .ok_or_else(|| InvocationError(Inner::UnsupportedEncoding(encoding.clone())))
+}
+
+/// A message whose argument and return types are only known at runtime.
+///
+/// This is similar to [`NSInvocation`], and is useful for e.g. scripting
+/// bridges that discover methods at runtime with
+/// [`AnyClass::instance_methods`].
+///
+/// The call is made with [`libffi`], so this is only available when the
+/// `"invocation"` feature is enabled.
+///
+/// [`NSInvocation`]: https://developer.apple.com/documentation/foundation/nsinvocation?language=objc
+/// [`libffi`]: https://sourceware.org/libffi/
+///
+///
+/// # Supported types
+///
+/// The arguments and the return value are passed as [`Value`]s, with the
+/// following type-encodings being supported:
+///
+/// - `v`: [`Value::Void`] (only as the return type).
+/// - `c`, `s`, `i`, `l` and `q`: [`Value::I8`], [`Value::I16`],
+///   [`Value::I32`] and [`Value::I64`].
+/// - `C`, `S`, `I`, `L` and `Q`: [`Value::U8`], [`Value::U16`],
+///   [`Value::U32`] and [`Value::U64`].
+/// - `f` and `d`: [`Value::F32`] and [`Value::F64`].
+/// - `B`: [`Value::Bool`].
+/// - `@` and `@?`: [`Value::Object`].
+/// - `#`: [`Value::Class`].
+/// - `:`: [`Value::Sel`].
+/// - `*`, `?` and `^...`: [`Value::Pointer`].
+/// - `{...}`: [`Value::Struct`], as long as the fields are known and
+///   supported.
+///
+/// Qualifiers such as `const` and `_Atomic` are ignored.
+///
+///
+/// # Memory management
+///
+/// Objects returned from methods in the `new`, `alloc`, `copy` and
+/// `mutableCopy` families are assumed to be +1, while other objects are
+/// retained. Methods in the `init` family consume their receiver, so it is
+/// retained before the message is sent.
+///
+///
+/// # Examples
+///
+/// ```
+/// use objc2::rc::Id;
+/// use objc2::runtime::{Invocation, NSObject, Value};
+/// use objc2::{sel, ClassType};
+///
+/// let obj = NSObject::new();
+/// let method = NSObject::class().instance_method(sel!(isEqual:)).unwrap();
+/// let invocation = Invocation::from_method(method).unwrap();
+///
+/// let arg = Value::Object(Some(Id::into_super(obj.clone())));
+/// // SAFETY: The encoding was retrieved from the runtime, and the argument
+/// // is a valid object.
+/// let res = unsafe { invocation.invoke(&*obj, &[arg]) }.unwrap();
+/// // `BOOL` is encoded differently depending on the platform.
+/// assert!(matches!(res, Value::Bool(true) | Value::I8(1) | Value::U8(1)));
+/// ```
+pub struct Invocation {
+    sel: Sel,
+    encoding: MethodEncoding,
+    ret: ValueType,
+    args: Vec<ValueType>,
+    cif: Cif,
+}
+
+impl Invocation {
+    /// Prepare a message with the given selector and method encoding.
+    ///
+    /// The arguments in the encoding must start with the receiver and the
+    /// selector, like the encodings retrieved from the runtime do.
+    ///
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the encoding contains unsupported types, or does
+    /// not start with a receiver and a selector.
+    pub fn new(sel: Sel, encoding: MethodEncoding) -> Result<Self, InvocationError> {
+        let ret = value_type(&encoding.return_type.encoding)?;
+        let args = encoding
+            .arguments
+            .iter()
+            .map(|arg| value_type(&arg.encoding))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        match &*args {
+            [ValueType::Object | ValueType::Class, ValueType::Sel, ..] => {}
+            _ => return Err(InvocationError(Inner::MissingReceiver)),
+        }
+        if let Some(i) = args.iter().position(|arg| *arg == ValueType::Void) {
+            let encoding = encoding.arguments[i].encoding.clone();
+            return Err(InvocationError(Inner::UnsupportedEncoding(encoding)));
+        }
+
+        let cif = Cif::new(args.iter().map(ValueType::ffi_type), ret.ffi_type());
+        let args = args.into_iter().skip(2).collect();
+        Ok(Self {
+            sel,
+            encoding,
+            ret,
+            args,
+            cif,
+        })
+    }
+
+    /// Prepare a message to the given method.
+    ///
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the encoding of the method could not be parsed,
+    /// or contains unsupported types.
+    pub fn from_method(method: &Method) -> Result<Self, InvocationError> {
+        Self::new(method.name(), method.encoding()?)
+    }
+
+    /// The selector of the message.
+    #[inline]
+    pub fn sel(&self) -> Sel {
+        self.sel
+    }
+
+    /// The encoding of the message, including the receiver and the
+    /// selector.
+    #[inline]
+    pub fn encoding(&self) -> &MethodEncoding {
+        &self.encoding
+    }
+
+    /// Send the message to the given receiver, with the given arguments
+    /// (excluding the receiver and the selector).
+    ///
+    /// Messages to `nil` return a zeroed value.
+    ///
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the number of arguments or the type of any of
+    /// them does not match the encoding.
+    ///
+    ///
+    /// # Safety
+    ///
+    /// The encoding must match the actual types of the method that the
+    /// receiver implements for the selector, and the arguments must be valid
+    /// for the method, same as when using [`msg_send!`].
+    ///
+    /// [`msg_send!`]: crate::msg_send
+    pub unsafe fn invoke<T: MessageReceiver>(
+        &self,
+        receiver: T,
+        args: &[Value],
+    ) -> Result<Value, InvocationError> {
+        if args.len() != self.args.len() {
+            return Err(InvocationError(Inner::MismatchedArgumentsCount(
+                self.args.len(),
+                args.len(),
+            )));
+        }
+        for (i, (ty, value)) in self.args.iter().zip(args).enumerate() {
+            if !ty.matches(value) {
+                let expected = self.encoding.arguments[i + 2].encoding.clone();
+                let err = Inner::MismatchedArgument(i, expected, value.type_name());
+                return Err(InvocationError(err));
+            }
+        }
+
+        let receiver = receiver.__as_raw_receiver();
+        let mut ret_buf = self.ret.buffer();
+        if receiver.is_null() {
+            // SAFETY: The buffer is zeroed, which is a valid value for all
+            // the supported types.
+            return Ok(unsafe { self.ret.read(ret_buf.as_ptr().cast(), true) });
+        }
+
+        let mut arg_bufs: Vec<Vec<u64>> = self.args.iter().map(ValueType::buffer).collect();
+        for ((ty, value), buf) in self.args.iter().zip(args).zip(&mut arg_bufs) {
+            // SAFETY: The buffer is large enough, and the value was checked
+            // to match the type above.
+            unsafe { ty.write(value, buf.as_mut_ptr().cast()) };
+        }
+        let mut sel = self.sel.as_ptr();
+        let mut receiver_arg = receiver;
+        let mut arg_ptrs: Vec<*mut c_void> = Vec::with_capacity(args.len() + 2);
+        arg_ptrs.push(ptr::addr_of_mut!(receiver_arg).cast());
+        arg_ptrs.push(ptr::addr_of_mut!(sel).cast());
+        arg_ptrs.extend(
+            arg_bufs
+                .iter_mut()
+                .map(|buf| buf.as_mut_ptr().cast::<c_void>()),
+        );
+
+        let owned = match retain_semantics(self.sel.name()) {
+            // `init` consumes the receiver.
+            3 => {
+                // SAFETY: The receiver is a valid object.
+                mem::forget(unsafe { Id::retain(receiver) });
+                true
+            }
+            1 | 2 | 4 => true,
+            _ => false,
+        };
+
+        let imp = msg_send_fn(receiver, self.sel, &self.ret);
+        // SAFETY: Only the ABI differs, `Imp` is `extern "C-unwind"` with the
+        // `"unstable-c-unwind"` feature.
+        #[allow(clippy::useless_transmute)]
+        let imp = unsafe { mem::transmute::<Imp, unsafe extern "C" fn()>(imp) };
+        // SAFETY: The caller ensures that the encoding is correct, and
+        // hence that the CIF describes the signature of the method. The
+        // return buffer is large enough for the return type, and each of
+        // the argument pointers point to a valid argument.
+        unsafe {
+            libffi::raw::ffi_call(
+                self.cif.as_raw_ptr(),
+                Some(imp),
+                ret_buf.as_mut_ptr().cast(),
+                arg_ptrs.as_mut_ptr(),
+            )
+        };
+
+        let ret = if self.ret.is_integer() && self.ret.layout().0 < mem::size_of::<ffi_arg>() {
+            // SAFETY: The buffer is at least as large as `ffi_arg`, and
+            // `libffi` widens small integer return values.
+            self.ret
+                .convert_ffi_arg(unsafe { ret_buf.as_ptr().cast::<ffi_arg>().read() })
+        } else {
+            // SAFETY: The method wrote a value of the return type to the
+            // buffer.
+            unsafe { self.ret.read(ret_buf.as_ptr().cast(), owned) }
+        };
+        Ok(ret)
+    }
+
+    /// The call interface describing the method, for use with `libffi`
+    /// closures.
+    pub(crate) fn cif(&self) -> &Cif {
+        &self.cif
+    }
+
+    /// Whether the value can be returned from the method.
+    #[cfg(feature = "mock")]
+    pub(crate) fn return_matches(&self, value: &Value) -> bool {
+        self.ret.matches(value)
+    }
+
+    /// A zeroed value of the return type.
+    #[cfg(feature = "mock")]
+    pub(crate) fn zeroed_return(&self) -> Value {
+        let buf = self.ret.buffer();
+        // SAFETY: The buffer is zeroed, which is a valid value for all the
+        // supported types.
+        unsafe { self.ret.read(buf.as_ptr().cast(), true) }
+    }
+
+    /// The number of arguments, excluding the receiver and the selector.
+    #[cfg(feature = "mock")]
+    pub(crate) fn number_of_arguments(&self) -> usize {
+        self.args.len()
+    }
+
+    /// Read the arguments (excluding the receiver and the selector) that a
+    /// `libffi` closure created with [`Invocation::cif`] was called with.
+    ///
+    ///
+    /// # Safety
+    ///
+    /// The pointers must point to valid values of the types in the encoding.
+    pub(crate) unsafe fn read_arguments(&self, args: *const *const c_void) -> Vec<Value> {
+        self.args
+            .iter()
+            .enumerate()
+            // SAFETY: Checked by caller
+            .map(|(i, ty)| unsafe { ty.read((*args.add(i + 2)).cast(), false) })
+            .collect()
+    }
+
+    /// Write the return value of a `libffi` closure created with
+    /// [`Invocation::cif`].
+    ///
+    /// Returned objects are +1 if the selector is in the `new`, `alloc`,
+    /// `init`, `copy` or `mutableCopy` families, and are otherwise
+    /// autoreleased.
+    ///
+    ///
+    /// # Panics
+    ///
+    /// Panics if the value does not match the return type.
+    ///
+    ///
+    /// # Safety
+    ///
+    /// The pointer must be the result pointer given to the closure.
+    pub(crate) unsafe fn write_return(&self, value: Value, result: *mut c_void) {
+        assert!(
+            self.ret.matches(&value),
+            "expected a return value of type {}, but found a value of type {}",
+            self.encoding.return_type.encoding,
+            value.type_name(),
+        );
+        if self.ret.is_integer() && self.ret.layout().0 < mem::size_of::<ffi_arg>() {
+            // SAFETY: `libffi` expects small integer return values to be
+            // widened, and the buffer is large enough for that.
+            unsafe { result.cast::<ffi_arg>().write(to_ffi_arg(&value)) };
+            return;
+        }
+        // SAFETY: The result buffer is large enough for the return type, and
+        // the value was checked to match it above.
+        unsafe { self.ret.write(&value, result.cast()) };
+        let owned = matches!(retain_semantics(self.sel.name()), 1..=4);
+        leak_returned_objects(value, owned);
+    }
+}
+
+/// Create a `libffi` closure that calls the callback with the user data, and
+/// return its code pointer.
+///
+/// The closure is leaked, since methods can never be removed from a class.
+pub(crate) fn leak_closure<U>(
+    cif: Cif,
+    callback: Callback<U, c_void>,
+    userdata: &'static U,
+) -> Imp {
+    let closure = Closure::new(cif, callback, userdata);
+    // SAFETY: Only the ABI differs, `Imp` is `extern "C-unwind"` with the
+    // `"unstable-c-unwind"` feature.
+    #[allow(clippy::useless_transmute)]
+    let imp = unsafe { mem::transmute::<unsafe extern "C" fn(), Imp>(*closure.code_ptr()) };
+    mem::forget(closure);
+    imp
+}
+
+/// Make sure that the objects written by [`ValueType::write`] stay alive
+/// after the value has been returned.
+fn leak_returned_objects(value: Value, owned: bool) {
+    match value {
+        Value::Object(Some(obj)) if owned => mem::forget(obj),
+        Value::Object(Some(obj)) => {
+            let _ = Id::autorelease_inner(obj);
+        }
+        // Objects in structs cannot be returned as +1.
+        Value::Struct(fields) => {
+            for field in fields {
+                leak_returned_objects(field, false);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Widen a small integer, the opposite of [`ValueType::convert_ffi_arg`].
+fn to_ffi_arg(value: &Value) -> ffi_arg {
+    match *value {
+        Value::I8(value) => value as ffi_arg,
+        Value::I16(value) => value as ffi_arg,
+        Value::I32(value) => value as ffi_arg,
+        Value::U8(value) => value as ffi_arg,
+        Value::U16(value) => value as ffi_arg,
+        Value::U32(value) => value as ffi_arg,
+        Value::Bool(value) => value as ffi_arg,
+        _ => unreachable!("not a small integer type"),
+    }
+}
+
+impl fmt::Debug for Invocation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Invocation")
+            .field("sel", &self.sel)
+            .field("encoding", &self.encoding)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Find the function that should be used to send a message with the given
+/// return type, same as `msg_send_primitive::send` does statically.
+#[cfg(feature = "apple")]
+#[allow(unused_variables)]
+fn msg_send_fn(receiver: *mut AnyObject, sel: Sel, ret: &ValueType) -> Imp {
+    let size = ret.layout().0;
+    let is_struct = matches!(ret, ValueType::Struct(_));
+
+    #[cfg(target_arch = "aarch64")]
+    let stret = false;
+    #[cfg(target_arch = "arm")]
+    let stret = is_struct && size > 4;
+    #[cfg(target_arch = "x86")]
+    let stret = is_struct && !matches!(size, 0 | 1 | 2 | 4 | 8);
+    #[cfg(target_arch = "x86_64")]
+    let stret = is_struct && size > 16;
+
+    #[cfg(not(target_arch = "aarch64"))]
+    if stret {
+        return ffi::objc_msgSend_stret;
+    }
+    #[cfg(target_arch = "x86")]
+    if matches!(ret, ValueType::F32 | ValueType::F64) {
+        return ffi::objc_msgSend_fpret;
+    }
+    ffi::objc_msgSend
+}
+
+#[cfg(feature = "gnustep-1-7")]
+fn msg_send_fn(receiver: *mut AnyObject, sel: Sel, _ret: &ValueType) -> Imp {
+    // SAFETY: The receiver is not NULL, and the selector is valid.
+    let imp = unsafe { ffi::objc_msg_lookup(receiver.cast(), sel.as_ptr()) };
+    // `objc_msg_lookup` never returns NULL, see `msg_send_primitive::send`.
+    imp.expect("objc_msg_lookup returned NULL")
+}
+
+impl AnyObject {
+    /// Send a message to the object, with the argument and return types
+    /// determined at runtime from the object's implementation of the method.
+    ///
+    /// This is a shorthand for creating an [`Invocation`] from the method
+    /// found on the object's class, and invoking it; see that for details.
+    ///
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the class does not implement the method, if its
+    /// encoding contains unsupported types, or if the arguments do not
+    /// match it.
+    ///
+    ///
+    /// # Safety
+    ///
+    /// The arguments must be valid for the method, and the encoding that the
+    /// runtime reports for it must be correct.
+    ///
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use objc2::runtime::{NSObject, Value};
+    /// use objc2::sel;
+    ///
+    /// let obj = NSObject::new();
+    /// let hash = unsafe { obj.perform(sel!(hash), &[]) }.unwrap();
+    /// assert!(matches!(hash, Value::U64(_) | Value::U32(_)));
+    /// ```
+    pub unsafe fn perform(&self, sel: Sel, args: &[Value]) -> Result<Value, InvocationError> {
+        let method = self
+            .class()
+            .instance_method(sel)
+            .ok_or(InvocationError(Inner::MethodNotFound(sel)))?;
+        let invocation = Invocation::from_method(method)?;
+        // SAFETY: Upheld by caller
+        unsafe { invocation.invoke(self, args) }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::boxed::Box;
+    use alloc::string::ToString;
+
+    use super::*;
+    use crate::runtime::NSObject;
+    use crate::test_utils;
+    use crate::{msg_send, sel, ClassType};
+
+    #[test]
+    fn test_from_encoding() {
+        assert_eq!(
+            ValueType::from_encoding(&EncodingBox::Long),
+            Some(ValueType::I32)
+        );
+        assert_eq!(
+            ValueType::from_encoding(&EncodingBox::Pointer(Box::new(EncodingBox::Object))),
+            Some(ValueType::Pointer)
+        );
+        let encoding =
+            EncodingBox::Struct("CustomStruct".into(), Some(vec![EncodingBox::ULongLong; 4]));
+        let ty = ValueType::from_encoding(&encoding);
+        assert_eq!(ty, Some(ValueType::Struct(vec![ValueType::U64; 4])));
+
+        assert_eq!(ValueType::from_encoding(&EncodingBox::LongDouble), None);
+        assert_eq!(
+            ValueType::from_encoding(&EncodingBox::Struct("Opaque".into(), None)),
+            None
+        );
+    }
+
+    #[test]
+    fn test_layout() {
+        let encoding = EncodingBox::Struct(
+            "a".into(),
+            Some(vec![
+                EncodingBox::UChar,
+                EncodingBox::UInt,
+                EncodingBox::UShort,
+            ]),
+        );
+        let ty = ValueType::from_encoding(&encoding).unwrap();
+        assert_eq!(ty.layout(), (12, 4));
+        if let ValueType::Struct(fields) = &ty {
+            let offsets: Vec<_> = ValueType::field_offsets(fields)
+                .map(|(offset, _)| offset)
+                .collect();
+            assert_eq!(offsets, [0, 4, 8]);
+        }
+    }
+
+    #[test]
+    fn test_invalid_encoding() {
+        let encoding = MethodEncoding::new(EncodingBox::Void, [EncodingBox::Int]);
+        let err = Invocation::new(sel!(foo), encoding).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "method encoding must begin with a receiver and a selector"
+        );
+
+        let encoding = MethodEncoding::new(
+            EncodingBox::LongDouble,
+            [EncodingBox::Object, EncodingBox::Sel],
+        );
+        let err = Invocation::new(sel!(foo), encoding).unwrap_err();
+        assert_eq!(err.to_string(), "the type D is not supported");
+    }
+
+    #[test]
+    fn test_invoke() {
+        let obj = test_utils::custom_object();
+        let cls = test_utils::custom_class();
+
+        let set_foo = Invocation::from_method(cls.instance_method(sel!(setFoo:)).unwrap()).unwrap();
+        let res = unsafe { set_foo.invoke(&*obj, &[Value::U32(42)]) }.unwrap();
+        assert!(matches!(res, Value::Void));
+        let foo: u32 = unsafe { msg_send![&obj, foo] };
+        assert_eq!(foo, 42);
+
+        let res = unsafe { obj.perform(sel!(foo), &[]) }.unwrap();
+        assert!(matches!(res, Value::U32(42)));
+
+        let res = unsafe { obj.perform(sel!(customStruct), &[]) }.unwrap();
+        match res {
+            Value::Struct(fields) => {
+                let fields: Vec<_> = fields
+                    .into_iter()
+                    .map(|field| match field {
+                        Value::U64(field) => field,
+                        field => panic!("unexpected field {field:?}"),
+                    })
+                    .collect();
+                assert_eq!(fields, [1, 2, 3, 4]);
+            }
+            res => panic!("unexpected return value {res:?}"),
+        }
+
+        let add =
+            Invocation::from_method(cls.class_method(sel!(addNumber:toNumber:)).unwrap()).unwrap();
+        let res = unsafe { add.invoke(cls, &[Value::I32(3), Value::I32(4)]) }.unwrap();
+        assert!(matches!(res, Value::I32(7)));
+    }
+
+    #[test]
+    fn test_invoke_objects() {
+        let obj = NSObject::new();
+        let res = unsafe { obj.perform(sel!(self), &[]) }.unwrap();
+        match res {
+            Value::Object(Some(res)) => assert!(ptr::eq(&*res, &**obj)),
+            res => panic!("unexpected return value {res:?}"),
+        }
+
+        let new =
+            Invocation::from_method(NSObject::class().class_method(sel!(new)).unwrap()).unwrap();
+        let res = unsafe { new.invoke(NSObject::class(), &[]) }.unwrap();
+        match res {
+            Value::Object(Some(res)) => assert_eq!(res.class(), NSObject::class()),
+            res => panic!("unexpected return value {res:?}"),
+        }
+    }
+
+    #[test]
+    fn test_invoke_nil() {
+        let method = NSObject::class().instance_method(sel!(hash)).unwrap();
+        let invocation = Invocation::from_method(method).unwrap();
+        let res = unsafe { invocation.invoke(ptr::null_mut::<NSObject>(), &[]) }.unwrap();
+        assert!(matches!(res, Value::U64(0) | Value::U32(0)));
+    }
+
+    #[test]
+    fn test_invoke_invalid_arguments() {
+        let obj = test_utils::custom_object();
+
+        let err = unsafe { obj.perform(sel!(setFoo:), &[]) }.unwrap_err();
+        assert_eq!(err.to_string(), "expected 1 arguments, but 0 were given");
+
+        let err = unsafe { obj.perform(sel!(setFoo:), &[Value::I32(1)]) }.unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "expected argument at index 0 to have type I, but found a value of type i32"
+        );
+
+        let err = unsafe { obj.perform(sel!(unknownMethod), &[]) }.unwrap_err();
+        assert_eq!(err.to_string(), "method unknownMethod not found");
+    }
+}
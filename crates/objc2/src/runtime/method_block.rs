@@ -0,0 +1,207 @@
+use core::ffi::c_void;
+
+use crate::__macro_helpers::MethodTypes;
+use crate::declare::method_type_encoding;
+use crate::encode::{EncodeArguments, EncodeReturn, RefEncode};
+use crate::ffi;
+use crate::runtime::{AnyClass, Imp, Sel};
+use crate::verify::{verify_method_signature, Inner, VerificationError};
+
+/// Blocks that can be used as the implementation of an Objective-C method.
+///
+/// Unlike [`MethodImplementation`], the body of such a method can capture
+/// state. The block is called with the receiver as its first argument,
+/// followed by the arguments of the method (the selector is not passed).
+///
+/// This is implemented in the [`block2`] crate for heap-allocated and global
+/// blocks whose first argument is a valid receiver. Blocks on the stack must
+/// be copied to the heap first.
+///
+/// [`MethodImplementation`]: crate::runtime::MethodImplementation
+/// [`block2`]: https://docs.rs/block2/
+///
+///
+/// # Safety
+///
+/// The block returned by `__as_block_ptr` must be a valid block, which takes
+/// the callee, followed by [`Arguments`][Self::Arguments], and returns
+/// [`Return`][Self::Return].
+///
+/// Additionally, it must be safe to copy the block with `_Block_copy` while
+/// `self` is still in use, i.e. it must not be a block on the stack.
+pub unsafe trait MethodBlock {
+    /// The callee type of the method.
+    type Callee: ?Sized + RefEncode;
+
+    /// The argument types of the method, excluding the callee.
+    type Arguments: EncodeArguments;
+
+    /// The return type of the method.
+    type Return: EncodeReturn;
+
+    #[doc(hidden)]
+    fn __as_block_ptr(&self) -> *mut c_void;
+}
+
+/// Create a method implementation from a block.
+///
+/// The block is copied, and the copy is owned by the implementation until
+/// it is released with `imp_removeBlock`.
+///
+///
+/// # Safety
+///
+/// The block must be safe to copy, and must be callable from any thread for
+/// as long as the implementation is in use.
+pub(crate) unsafe fn imp_from_block<B: MethodBlock>(block: &B) -> Imp {
+    // SAFETY: The block is valid, and its signature matches that of a method
+    // implementation once the trampoline has moved the arguments around.
+    let imp = unsafe { ffi::imp_implementationWithBlock(block.__as_block_ptr().cast()) };
+    imp.expect("failed creating method implementation from block")
+}
+
+/// Release the block backing an implementation, if it was created from one.
+///
+///
+/// # Safety
+///
+/// The implementation must not be in use, or be used again afterwards.
+pub(crate) unsafe fn remove_block(imp: Imp) {
+    // SAFETY: Checked by the caller.
+    if !unsafe { ffi::imp_getBlock(Some(imp)) }.is_null() {
+        let _ = unsafe { ffi::imp_removeBlock(Some(imp)) };
+    }
+}
+
+impl AnyClass {
+    unsafe fn replace_method_with_block_inner<B: MethodBlock>(
+        &self,
+        sel: Sel,
+        block: &B,
+    ) -> Result<(), VerificationError> {
+        let enc_args = B::Arguments::ENCODINGS;
+        let enc_ret = &B::Return::ENCODING_RETURN;
+        // Verify the signature against the existing method (which may be
+        // inherited from a superclass).
+        if let Some(method) = self.instance_method(sel) {
+            verify_method_signature(method, enc_args, enc_ret)?;
+        } else if sel.number_of_arguments() != enc_args.len() {
+            return Err(
+                Inner::MismatchedArgumentsCount(sel.number_of_arguments(), enc_args.len()).into(),
+            );
+        }
+
+        let types = method_type_encoding(
+            enc_ret,
+            enc_args,
+            MethodTypes::<B::Return, B::Arguments>::cstr(),
+        );
+        // SAFETY: Upheld by the caller.
+        let imp = unsafe { imp_from_block(block) };
+        // SAFETY: The signature was verified above. If the method is only
+        // defined on a superclass, this adds it to this class with the
+        // given types instead.
+        let previous = unsafe {
+            ffi::class_replaceMethod(
+                self.as_ptr() as *mut _,
+                sel.as_ptr(),
+                Some(imp),
+                types.as_ptr(),
+            )
+        };
+        if let Some(previous) = previous {
+            // SAFETY: The caller ensures that the previous implementation is
+            // not in use anymore.
+            unsafe { remove_block(previous) };
+        }
+        Ok(())
+    }
+
+    /// Replace the implementation of an instance method on this class with a
+    /// block, or add the method if this class does not define it.
+    ///
+    /// If the method already exists, either on this class or on a
+    /// superclass, the signature of the block is verified against it. An
+    /// inherited method is added to this class instead of changing the
+    /// superclass' implementation.
+    ///
+    /// The block is copied, and the copy is kept alive for as long as the
+    /// method uses it. If the previous implementation was itself created
+    /// from a block, that block is released.
+    ///
+    /// See [Apple's documentation](https://developer.apple.com/documentation/objectivec/1418707-class_replacemethod?language=objc).
+    ///
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the signature of the block does not match the
+    /// existing method, or if the number of arguments does not match the
+    /// selector. In that case, nothing is changed.
+    ///
+    ///
+    /// # Panics
+    ///
+    /// Panics if the runtime fails creating an implementation from the block.
+    ///
+    ///
+    /// # Safety
+    ///
+    /// - The block must be at least as safe as the existing method, see
+    ///   [`Method::set_implementation`] for details, and the callee type must
+    ///   be correct for all instances that the method may be called on.
+    /// - The block, and everything it captures, must be safe to use from any
+    ///   thread that the method may be called on, and must remain valid for
+    ///   as long as the method can be called.
+    /// - If the previous implementation was created from a block, it must
+    ///   not be in use anymore (e.g. executing on another thread, or held by
+    ///   a [`Swizzle`][crate::runtime::Swizzle] handle).
+    ///
+    /// [`Method::set_implementation`]: crate::runtime::Method::set_implementation
+    #[doc(alias = "class_replaceMethod")]
+    #[doc(alias = "imp_implementationWithBlock")]
+    pub unsafe fn replace_method_with_block<B>(
+        &self,
+        sel: Sel,
+        block: &B,
+    ) -> Result<(), VerificationError>
+    where
+        B: MethodBlock,
+    {
+        // SAFETY: Upheld by caller
+        unsafe { self.replace_method_with_block_inner(sel, block) }
+    }
+
+    /// Replace the implementation of a class method on this class with a
+    /// block, or add the method if this class does not define it.
+    ///
+    /// This is the same as [`replace_method_with_block`] on the metaclass,
+    /// except that the callee of the block must be a class.
+    ///
+    /// [`replace_method_with_block`]: Self::replace_method_with_block
+    ///
+    ///
+    /// # Errors
+    ///
+    /// Same as [`replace_method_with_block`].
+    ///
+    ///
+    /// # Panics
+    ///
+    /// Same as [`replace_method_with_block`].
+    ///
+    ///
+    /// # Safety
+    ///
+    /// Same as [`replace_method_with_block`].
+    pub unsafe fn replace_class_method_with_block<B>(
+        &self,
+        sel: Sel,
+        block: &B,
+    ) -> Result<(), VerificationError>
+    where
+        B: MethodBlock<Callee = AnyClass>,
+    {
+        // SAFETY: Upheld by caller
+        unsafe { self.metaclass().replace_method_with_block_inner(sel, block) }
+    }
+}
@@ -6,7 +6,7 @@ use core::num::ParseIntError;
 use core::str;
 use std::error::Error;
 
-use crate::encode::{Encoding, EncodingBox, ParseError};
+use crate::encode::{Encoding, EncodingBox, ParseError, Qualifier};
 
 #[derive(Debug, PartialEq, Eq)]
 pub(crate) struct MethodEncodingIter<'a> {
@@ -48,6 +48,10 @@ impl<'a> MethodEncodingIter<'a> {
         // libobjc2: https://github.com/gnustep/libobjc2/blob/v2.1/encoding2.c
         // objc4: https://github.com/apple-oss-distributions/objc4/blob/objc4-841.13/runtime/objc-typeencoding.mm
 
+        // Qualifiers on the argument itself are irrelevant to us
+        self.s = self
+            .s
+            .trim_start_matches(|c: char| c.is_ascii() && Qualifier::from_code(c as u8).is_some());
         let encoding = EncodingBox::from_start_of_str(&mut self.s)?;
         let stack_layout = parse_stack_layout(&mut self.s)?;
 
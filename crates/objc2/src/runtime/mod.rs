@@ -31,19 +31,31 @@ use std::os::raw::c_uint;
 // since `icrate` relies on it.
 #[doc(hidden)]
 pub mod __nsstring;
+mod associated;
 mod bool;
+#[cfg(feature = "invocation")]
+mod invocation;
 mod message_receiver;
+mod method_block;
 mod method_encoding_iter;
 mod method_implementation;
 mod nsobject;
 mod nsproxy;
 mod nszone;
+mod property;
 mod protocol_object;
 mod retain_release_fast;
+mod swizzle;
 
+#[cfg(feature = "invocation")]
+pub(crate) use self::invocation::leak_closure;
+pub(crate) use self::method_block::{imp_from_block, remove_block};
 pub(crate) use self::method_encoding_iter::{EncodingParseError, MethodEncodingIter};
 pub(crate) use self::retain_release_fast::{objc_release_fast, objc_retain_fast};
-use crate::encode::{Encode, EncodeArguments, EncodeReturn, Encoding, OptionEncode, RefEncode};
+use crate::encode::{
+    Encode, EncodeArguments, EncodeReturn, Encoding, MethodEncoding, OptionEncode, ParseError,
+    RefEncode,
+};
 use crate::verify::{verify_method_signature, Inner};
 use crate::{ffi, Message};
 
@@ -52,12 +64,18 @@ use crate::{ffi, Message};
 #[doc(hidden)]
 pub use self::nsproxy::NSProxy as __NSProxy;
 
+pub use self::associated::{AssociatedObjectKey, AssociationPolicy};
 pub use self::bool::Bool;
+#[cfg(feature = "invocation")]
+pub use self::invocation::{Invocation, InvocationError, Value};
 pub use self::message_receiver::MessageReceiver;
+pub use self::method_block::MethodBlock;
 pub use self::method_implementation::MethodImplementation;
 pub use self::nsobject::{NSObject, NSObjectProtocol};
 pub use self::nszone::NSZone;
+pub use self::property::{Property, PropertyAttributes, PropertyOwnership};
 pub use self::protocol_object::{ImplementedBy, ProtocolObject};
+pub use self::swizzle::Swizzle;
 pub use crate::verify::VerificationError;
 
 /// Implement PartialEq, Eq and Hash using pointer semantics; there's not
@@ -346,16 +364,27 @@ impl fmt::Debug for Ivar {
     }
 }
 
-#[cfg_attr(not(feature = "malloc"), allow(dead_code))]
-#[derive(Debug, PartialEq, Eq, Hash)]
-pub(crate) struct MethodDescription {
-    pub(crate) sel: Sel,
-    pub(crate) types: &'static str,
+/// A description of a method declared in a protocol.
+///
+/// This contains the selector and type-encoding of the method, along with
+/// whether it is required, and whether it is an instance or a class method.
+///
+/// See [`AnyProtocol::method_descriptions`] for how to get this.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[doc(alias = "objc_method_description")]
+pub struct MethodDescription {
+    sel: Sel,
+    types: &'static str,
+    required: bool,
+    instance: bool,
 }
 
 impl MethodDescription {
-    #[cfg_attr(not(feature = "malloc"), allow(dead_code))]
-    pub(crate) unsafe fn from_raw(raw: ffi::objc_method_description) -> Option<Self> {
+    pub(crate) unsafe fn from_raw(
+        raw: ffi::objc_method_description,
+        required: bool,
+        instance: bool,
+    ) -> Option<Self> {
         // SAFETY: Sel::from_ptr checks for NULL, rest is checked by caller.
         let sel = unsafe { Sel::from_ptr(raw.name) }?;
         if raw.types.is_null() {
@@ -364,7 +393,50 @@ impl MethodDescription {
         // SAFETY: We've checked that the pointer is not NULL, rest is checked
         // by caller.
         let types = unsafe { CStr::from_ptr(raw.types) }.to_str().unwrap();
-        Some(Self { sel, types })
+        Some(Self {
+            sel,
+            types,
+            required,
+            instance,
+        })
+    }
+
+    /// The selector of the method.
+    #[inline]
+    pub fn sel(&self) -> Sel {
+        self.sel
+    }
+
+    /// The `@encode` string of the method's return type and arguments.
+    ///
+    /// Use [`encoding`][Self::encoding] to get the parsed encoding.
+    #[inline]
+    pub fn types(&self) -> &'static str {
+        self.types
+    }
+
+    /// The parsed type-encoding of the method.
+    ///
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the runtime returned an encoding that could not
+    /// be parsed.
+    pub fn encoding(&self) -> Result<MethodEncoding, ParseError> {
+        self.types.parse()
+    }
+
+    /// Whether the protocol requires conforming classes to implement the
+    /// method (as opposed to it being `@optional`).
+    #[inline]
+    pub fn is_required(&self) -> bool {
+        self.required
+    }
+
+    /// Whether this is an instance method (as opposed to a class method).
+    #[inline]
+    pub fn is_instance_method(&self) -> bool {
+        self.instance
     }
 }
 
@@ -435,8 +507,12 @@ impl Method {
     /// }
     /// assert!(types.next().is_none());
     /// ```
-    #[doc(alias = "method_getTypeEncoding")]
     pub(crate) fn types(&self) -> MethodEncodingIter<'_> {
+        MethodEncodingIter::new(self.type_encoding())
+    }
+
+    #[doc(alias = "method_getTypeEncoding")]
+    fn type_encoding(&self) -> &str {
         // SAFETY: The method pointer is valid and non-null
         let cstr = unsafe { ffi::method_getTypeEncoding(self.as_ptr()) };
         if cstr.is_null() {
@@ -445,8 +521,19 @@ impl Method {
         // SAFETY: `method_getTypeEncoding` returns a C-string, and we just
         // checked that it is non-null.
         let encoding = unsafe { CStr::from_ptr(cstr) };
-        let s = str::from_utf8(encoding.to_bytes()).expect("method type encoding to be UTF-8");
-        MethodEncodingIter::new(s)
+        str::from_utf8(encoding.to_bytes()).expect("method type encoding to be UTF-8")
+    }
+
+    /// The parsed type-encoding of the method's return type and arguments.
+    ///
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the runtime returned an encoding that could not
+    /// be parsed.
+    #[doc(alias = "method_getTypeEncoding")]
+    pub fn encoding(&self) -> Result<MethodEncoding, ParseError> {
+        self.type_encoding().parse()
     }
 
     /// Returns the number of arguments accepted by self.
@@ -771,6 +858,37 @@ impl AnyClass {
         }
     }
 
+    /// Returns the property with the given name declared by self, or
+    /// [`None`] if self has no such property.
+    ///
+    /// Note that properties declared by superclasses or adopted protocols are
+    /// not included.
+    ///
+    ///
+    /// # Panics
+    ///
+    /// Panics if `name` contains an internal NUL byte.
+    #[doc(alias = "class_getProperty")]
+    pub fn property(&self, name: &str) -> Option<&Property> {
+        let name = CString::new(name).unwrap();
+        unsafe {
+            let property = ffi::class_getProperty(self.as_ptr(), name.as_ptr());
+            property.cast::<Property>().as_ref()
+        }
+    }
+
+    /// Describes the properties declared by self.
+    #[cfg(feature = "malloc")]
+    #[doc(alias = "class_copyPropertyList")]
+    pub fn properties(&self) -> Malloc<[&Property]> {
+        unsafe {
+            let mut count: c_uint = 0;
+            let properties: *mut &Property =
+                ffi::class_copyPropertyList(self.as_ptr(), &mut count).cast();
+            Malloc::from_array(properties, count as usize)
+        }
+    }
+
     /// Check whether instances of this class respond to the given selector.
     ///
     /// This doesn't call `respondsToSelector:`, but works entirely within the
@@ -790,8 +908,6 @@ impl AnyClass {
         Bool::from_raw(res).as_bool()
     }
 
-    // fn property(&self, name: &str) -> Option<&Property>;
-    // fn properties(&self) -> Malloc<[&Property]>;
     // unsafe fn replace_method(&self, name: Sel, imp: Imp, types: &str) -> Imp;
     // unsafe fn replace_property(&self, name: &str, attributes: &[ffi::objc_property_attribute_t]);
     // fn method_imp(&self, name: Sel) -> Imp; // + _stret
@@ -928,6 +1044,18 @@ impl AnyProtocol {
         str::from_utf8(name.to_bytes()).unwrap()
     }
 
+    /// Describes the required instance properties declared by self.
+    #[cfg(feature = "malloc")]
+    #[doc(alias = "protocol_copyPropertyList")]
+    pub fn properties(&self) -> Malloc<[&Property]> {
+        unsafe {
+            let mut count: c_uint = 0;
+            let properties: *mut &Property =
+                ffi::protocol_copyPropertyList(self.as_ptr(), &mut count).cast();
+            Malloc::from_array(properties, count as usize)
+        }
+    }
+
     #[cfg(feature = "malloc")]
     fn method_descriptions_inner(&self, required: bool, instance: bool) -> Vec<MethodDescription> {
         let mut count: c_uint = 0;
@@ -946,24 +1074,86 @@ impl AnyProtocol {
         descriptions
             .iter()
             .map(|desc| {
-                unsafe { MethodDescription::from_raw(*desc) }.expect("invalid method description")
+                unsafe { MethodDescription::from_raw(*desc, required, instance) }
+                    .expect("invalid method description")
             })
             .collect()
     }
 
+    /// Describes the instance methods declared by self, either the required
+    /// or the optional ones.
+    ///
+    /// Methods declared by adopted protocols are not included.
+    ///
+    ///
+    /// # Panics
+    ///
+    /// Panics if the runtime returned an invalid method description.
+    ///
+    ///
+    /// # Example
+    ///
+    /// Check that a class implements all the methods that a protocol
+    /// requires.
+    ///
+    /// ```
+    /// use objc2::runtime::{AnyProtocol, NSObject};
+    /// use objc2::ClassType;
+    ///
+    /// let cls = NSObject::class();
+    /// let proto = AnyProtocol::get("NSObject").unwrap();
+    ///
+    /// for desc in proto.method_descriptions(true) {
+    ///     assert!(desc.is_required());
+    ///     assert!(desc.is_instance_method());
+    ///     assert!(cls.responds_to(desc.sel()), "missing {}", desc.sel());
+    /// }
+    /// ```
     #[cfg(feature = "malloc")]
-    #[allow(dead_code)]
     #[doc(alias = "protocol_copyMethodDescriptionList")]
-    pub(crate) fn method_descriptions(&self, required: bool) -> Vec<MethodDescription> {
+    pub fn method_descriptions(&self, required: bool) -> Vec<MethodDescription> {
         self.method_descriptions_inner(required, true)
     }
 
+    /// Describes the class methods declared by self, either the required or
+    /// the optional ones.
+    ///
+    /// Methods declared by adopted protocols are not included.
+    ///
+    ///
+    /// # Panics
+    ///
+    /// Panics if the runtime returned an invalid method description.
     #[cfg(feature = "malloc")]
-    #[allow(dead_code)]
     #[doc(alias = "protocol_copyMethodDescriptionList")]
-    pub(crate) fn class_method_descriptions(&self, required: bool) -> Vec<MethodDescription> {
+    pub fn class_method_descriptions(&self, required: bool) -> Vec<MethodDescription> {
         self.method_descriptions_inner(required, false)
     }
+
+    /// Returns the description of the method with the given selector, or
+    /// [`None`] if self or the protocols it adopts do not declare such a
+    /// method.
+    ///
+    /// The `required` and `instance` flags specify which kind of method to
+    /// look for.
+    #[doc(alias = "protocol_getMethodDescription")]
+    pub fn method_description(
+        &self,
+        sel: Sel,
+        required: bool,
+        instance: bool,
+    ) -> Option<MethodDescription> {
+        let desc = unsafe {
+            ffi::protocol_getMethodDescription(
+                self.as_ptr(),
+                sel.as_ptr(),
+                Bool::new(required).as_raw(),
+                Bool::new(instance).as_raw(),
+            )
+        };
+        // SAFETY: The types string is valid for as long as the protocol is.
+        unsafe { MethodDescription::from_raw(desc, required, instance) }
+    }
 }
 
 impl PartialEq for AnyProtocol {
@@ -1340,6 +1530,37 @@ mod tests {
         assert!(cls.instance_variables().len() > 0);
     }
 
+    #[test]
+    fn test_property() {
+        let cls = test_utils::custom_class();
+        let property = cls.property("foo").unwrap();
+        assert_eq!(property.name(), "foo");
+        let attributes = property.attributes();
+        assert!(<u32>::ENCODING.equivalent_to_str(attributes.type_encoding.unwrap()));
+        assert!(attributes.nonatomic);
+        assert!(!attributes.readonly);
+        assert_eq!(attributes.ownership, PropertyOwnership::Assign);
+        assert_eq!(attributes.ivar, Some("_foo"));
+        assert!(cls.property("bar").is_none());
+
+        #[cfg(feature = "malloc")]
+        {
+            assert_eq!(&*cls.properties(), &[property]);
+            assert_eq!(test_utils::custom_protocol().properties().len(), 0);
+        }
+    }
+
+    #[test]
+    #[cfg(all(feature = "apple", feature = "malloc"))]
+    fn test_protocol_properties() {
+        let proto = AnyProtocol::get("NSObject").unwrap();
+        let properties = proto.properties();
+        let hash = properties.iter().find(|p| p.name() == "hash").unwrap();
+        let attributes = hash.attributes();
+        assert!(attributes.readonly);
+        assert!(usize::ENCODING.equivalent_to_str(attributes.type_encoding.unwrap()));
+    }
+
     #[test]
     fn test_instance_method() {
         let cls = test_utils::custom_class();
@@ -1423,23 +1644,47 @@ mod tests {
 
         #[cfg(feature = "malloc")]
         {
+            use crate::encode::EncodingBox;
+
             // The selectors are broken somehow on GNUStep < 2.0
             if cfg!(any(not(feature = "gnustep-1-7"), feature = "gnustep-2-0")) {
                 let desc = MethodDescription {
                     sel: sel!(setBar:),
                     types: "v@:i",
+                    required: true,
+                    instance: true,
                 };
                 assert_eq!(&proto.method_descriptions(true), &[desc]);
+                assert_eq!(
+                    proto.method_description(sel!(setBar:), true, true),
+                    Some(desc)
+                );
+                assert_eq!(proto.method_description(sel!(setBar:), false, true), None);
+                assert_eq!(
+                    desc.encoding().unwrap(),
+                    MethodEncoding::new(
+                        EncodingBox::Void,
+                        [EncodingBox::Object, EncodingBox::Sel, EncodingBox::Int]
+                    )
+                );
                 let desc = MethodDescription {
                     sel: sel!(getName),
                     types: "*@:",
+                    required: false,
+                    instance: true,
                 };
                 assert_eq!(&proto.method_descriptions(false), &[desc]);
                 let desc = MethodDescription {
                     sel: sel!(addNumber:toNumber:),
                     types: "i@:ii",
+                    required: true,
+                    instance: false,
                 };
                 assert_eq!(&proto.class_method_descriptions(true), &[desc]);
+                assert_eq!(
+                    proto.method_description(sel!(addNumber:toNumber:), true, false),
+                    Some(desc)
+                );
             }
             assert_eq!(&proto.class_method_descriptions(false), &[]);
 
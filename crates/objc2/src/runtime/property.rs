@@ -0,0 +1,333 @@
+use core::fmt;
+use core::hash;
+use core::panic::{RefUnwindSafe, UnwindSafe};
+use core::str;
+use std::ffi::CStr;
+
+use crate::ffi;
+
+/// A type that represents a property in a class or protocol.
+///
+/// See [Apple's documentation](https://developer.apple.com/documentation/objectivec/objc_property_t?language=objc).
+#[repr(C)]
+#[doc(alias = "objc_property")]
+#[doc(alias = "objc_property_t")]
+pub struct Property(ffi::objc_property);
+
+// SAFETY: Property is immutable (and can be retrieved from AnyClass anyhow).
+unsafe impl Sync for Property {}
+unsafe impl Send for Property {}
+impl UnwindSafe for Property {}
+impl RefUnwindSafe for Property {}
+
+impl Property {
+    #[inline]
+    pub(crate) fn as_ptr(&self) -> *const ffi::objc_property {
+        let ptr: *const Self = self;
+        ptr.cast()
+    }
+
+    /// Returns the name of the property.
+    ///
+    /// See [Apple's documentation](https://developer.apple.com/documentation/objectivec/1418903-property_getname?language=objc).
+    ///
+    ///
+    /// # Panics
+    ///
+    /// Panics if the name is not valid UTF-8.
+    #[doc(alias = "property_getName")]
+    pub fn name(&self) -> &str {
+        let name = unsafe { CStr::from_ptr(ffi::property_getName(self.as_ptr())) };
+        str::from_utf8(name.to_bytes()).unwrap()
+    }
+
+    /// Returns the property's attribute string, e.g. `T@"NSString",C,N,V_name`.
+    ///
+    /// Use [`attributes`][Self::attributes] to get the parsed attributes.
+    ///
+    /// See [Apple's documentation](https://developer.apple.com/documentation/objectivec/1418675-property_getattributes?language=objc).
+    ///
+    ///
+    /// # Panics
+    ///
+    /// Panics if the attribute string is not valid UTF-8.
+    #[doc(alias = "property_getAttributes")]
+    pub fn attribute_string(&self) -> &str {
+        let attributes = unsafe { ffi::property_getAttributes(self.as_ptr()) };
+        if attributes.is_null() {
+            return "";
+        }
+        let attributes = unsafe { CStr::from_ptr(attributes) };
+        str::from_utf8(attributes.to_bytes()).unwrap()
+    }
+
+    /// Returns the parsed attributes of the property.
+    pub fn attributes(&self) -> PropertyAttributes<'_> {
+        PropertyAttributes::parse(self.attribute_string())
+    }
+}
+
+impl PartialEq for Property {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        self.as_ptr() == other.as_ptr()
+    }
+}
+
+impl Eq for Property {}
+
+impl hash::Hash for Property {
+    #[inline]
+    fn hash<H: hash::Hasher>(&self, state: &mut H) {
+        self.as_ptr().hash(state)
+    }
+}
+
+impl fmt::Debug for Property {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Property")
+            .field("name", &self.name())
+            .field("attributes", &self.attributes())
+            .finish_non_exhaustive()
+    }
+}
+
+/// How a property stores the value it is set to.
+///
+/// This corresponds to the `assign`, `strong`/`retain`, `copy` and `weak`
+/// attributes in `@property` declarations.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum PropertyOwnership {
+    /// The value is simply assigned (`assign` or `unsafe_unretained`).
+    ///
+    /// This is the default for non-object properties.
+    Assign,
+    /// The value is retained (`strong` or `retain`).
+    ///
+    /// Corresponds to the `&` attribute.
+    Retain,
+    /// The value is copied (`copy`).
+    ///
+    /// Corresponds to the `C` attribute.
+    Copy,
+    /// The value is stored as a weak reference (`weak`).
+    ///
+    /// Corresponds to the `W` attribute.
+    Weak,
+}
+
+impl Default for PropertyOwnership {
+    #[inline]
+    fn default() -> Self {
+        Self::Assign
+    }
+}
+
+/// The attributes of a [`Property`].
+///
+/// This can be parsed from the attribute string of a property, and its
+/// [`Display`][fmt::Display] implementation produces such a string.
+///
+/// See [Apple's documentation](https://developer.apple.com/library/archive/documentation/Cocoa/Conceptual/ObjCRuntimeGuide/Articles/ocrtPropertyIntrospection.html)
+/// for details on the format.
+///
+///
+/// # Examples
+///
+/// ```
+/// use objc2::runtime::{PropertyAttributes, PropertyOwnership};
+///
+/// let attributes = PropertyAttributes::parse(r#"T@"NSString",C,N,GgetName,V_name"#);
+/// assert_eq!(attributes.type_encoding, Some(r#"@"NSString""#));
+/// assert_eq!(attributes.ownership, PropertyOwnership::Copy);
+/// assert!(attributes.nonatomic);
+/// assert!(!attributes.readonly);
+/// assert_eq!(attributes.getter, Some("getName"));
+/// assert_eq!(attributes.setter, None);
+/// assert_eq!(attributes.ivar, Some("_name"));
+///
+/// assert_eq!(attributes.to_string(), r#"T@"NSString",C,N,GgetName,V_name"#);
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub struct PropertyAttributes<'a> {
+    /// The `@encode` type string of the property, if any.
+    ///
+    /// Object types may contain the class name in quotes, e.g.
+    /// `@"NSString"`. Corresponds to the `T` attribute.
+    pub type_encoding: Option<&'a str>,
+    /// Whether the property is read-only.
+    ///
+    /// Corresponds to the `R` attribute.
+    pub readonly: bool,
+    /// How the property stores its value.
+    pub ownership: PropertyOwnership,
+    /// Whether the property is non-atomic.
+    ///
+    /// Corresponds to the `N` attribute.
+    pub nonatomic: bool,
+    /// The name of a custom getter method, if any.
+    ///
+    /// Corresponds to the `G` attribute.
+    pub getter: Option<&'a str>,
+    /// The name of a custom setter method, if any.
+    ///
+    /// Corresponds to the `S` attribute.
+    pub setter: Option<&'a str>,
+    /// Whether the property is dynamic (`@dynamic`).
+    ///
+    /// Corresponds to the `D` attribute.
+    pub dynamic: bool,
+    /// The name of the instance variable backing the property, if any.
+    ///
+    /// Corresponds to the `V` attribute.
+    pub ivar: Option<&'a str>,
+}
+
+impl<'a> PropertyAttributes<'a> {
+    /// Parse a property attribute string.
+    ///
+    /// Unknown attributes are ignored.
+    pub fn parse(s: &'a str) -> Self {
+        let mut attributes = Self::default();
+        for attribute in split_attributes(s) {
+            let mut chars = attribute.chars();
+            let code = match chars.next() {
+                Some(code) => code,
+                None => continue,
+            };
+            let value = chars.as_str();
+            match code {
+                'T' => attributes.type_encoding = Some(value),
+                'R' => attributes.readonly = true,
+                '&' => attributes.ownership = PropertyOwnership::Retain,
+                'C' => attributes.ownership = PropertyOwnership::Copy,
+                'W' => attributes.ownership = PropertyOwnership::Weak,
+                'N' => attributes.nonatomic = true,
+                'G' => attributes.getter = Some(value),
+                'S' => attributes.setter = Some(value),
+                'D' => attributes.dynamic = true,
+                'V' => attributes.ivar = Some(value),
+                // `P`, `t` and any future attributes
+                _ => {}
+            }
+        }
+        attributes
+    }
+
+    /// The attribute codes and values, in the order that `clang` emits them.
+    pub(crate) fn entries(&self) -> impl Iterator<Item = (&'static str, &'a str)> {
+        let flag = |set: bool, code| if set { Some((code, "")) } else { None };
+        let ownership = match self.ownership {
+            PropertyOwnership::Assign => None,
+            PropertyOwnership::Retain => Some(("&", "")),
+            PropertyOwnership::Copy => Some(("C", "")),
+            PropertyOwnership::Weak => Some(("W", "")),
+        };
+        [
+            self.type_encoding.map(|value| ("T", value)),
+            flag(self.readonly, "R"),
+            ownership,
+            flag(self.nonatomic, "N"),
+            self.getter.map(|value| ("G", value)),
+            self.setter.map(|value| ("S", value)),
+            flag(self.dynamic, "D"),
+            self.ivar.map(|value| ("V", value)),
+        ]
+        .into_iter()
+        .flatten()
+    }
+}
+
+impl fmt::Display for PropertyAttributes<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, (code, value)) in self.entries().enumerate() {
+            if i != 0 {
+                write!(f, ",")?;
+            }
+            write!(f, "{code}{value}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Split an attribute string on commas, taking care to not split inside the
+/// type encoding (which may contain commas in quoted names or vectors).
+fn split_attributes(s: &str) -> impl Iterator<Item = &str> {
+    let mut rest = s;
+    core::iter::from_fn(move || {
+        if rest.is_empty() {
+            return None;
+        }
+        let mut depth: usize = 0;
+        let mut quoted = false;
+        let mut end = rest.len();
+        for (i, c) in rest.char_indices() {
+            match c {
+                '"' => quoted = !quoted,
+                '{' | '(' | '[' if !quoted => depth += 1,
+                '}' | ')' | ']' if !quoted => depth = depth.saturating_sub(1),
+                ',' if !quoted && depth == 0 => {
+                    end = i;
+                    break;
+                }
+                _ => {}
+            }
+        }
+        let item = &rest[..end];
+        rest = rest.get(end + 1..).unwrap_or("");
+        Some(item)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::string::ToString;
+    use alloc::vec::Vec;
+
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let attributes = PropertyAttributes::parse("Ti,R,N,D");
+        assert_eq!(
+            attributes,
+            PropertyAttributes {
+                type_encoding: Some("i"),
+                readonly: true,
+                nonatomic: true,
+                dynamic: true,
+                ..Default::default()
+            }
+        );
+
+        let attributes = PropertyAttributes::parse("T@,W,SsetFoo:,V_foo");
+        assert_eq!(attributes.type_encoding, Some("@"));
+        assert_eq!(attributes.ownership, PropertyOwnership::Weak);
+        assert_eq!(attributes.setter, Some("setFoo:"));
+        assert_eq!(attributes.ivar, Some("_foo"));
+
+        assert_eq!(PropertyAttributes::parse(""), Default::default());
+        assert_eq!(
+            PropertyAttributes::parse("T^v,&,P,t^v").ownership,
+            PropertyOwnership::Retain
+        );
+    }
+
+    #[test]
+    fn test_parse_nested_commas() {
+        let attributes = PropertyAttributes::parse(r#"T{S="a,b"![16,16f]},N"#);
+        assert_eq!(attributes.type_encoding, Some(r#"{S="a,b"![16,16f]}"#));
+        assert!(attributes.nonatomic);
+
+        let parts: Vec<_> = split_attributes("T@\"A,B\",C,").collect();
+        assert_eq!(parts, ["T@\"A,B\"", "C"]);
+    }
+
+    #[test]
+    fn test_display() {
+        let s = "Tq,R,&,N,Gfoo,Sbar:,D,V_foo";
+        assert_eq!(PropertyAttributes::parse(s).to_string(), s);
+        assert_eq!(PropertyAttributes::default().to_string(), "");
+    }
+}
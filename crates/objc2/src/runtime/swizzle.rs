@@ -0,0 +1,371 @@
+use core::fmt;
+use core::marker::PhantomData;
+use core::mem;
+
+use crate::encode::{EncodeArguments, EncodeReturn};
+use crate::ffi;
+use crate::runtime::{AnyClass, Imp, Method, MethodImplementation, Sel};
+use crate::verify::{verify_method_signature, Inner, VerificationError};
+
+/// A handle to a method whose implementation has been replaced.
+///
+/// This is returned by [`AnyClass::swizzle_instance_method`] and
+/// [`AnyClass::swizzle_class_method`], and allows calling the original
+/// implementation, or restoring it.
+///
+/// Dropping the handle does _not_ restore the original implementation; use
+/// [`undo`][Self::undo] for that.
+pub struct Swizzle<F> {
+    cls: &'static AnyClass,
+    method: &'static Method,
+    original: Imp,
+    replacement: Imp,
+    p: PhantomData<F>,
+}
+
+impl<F: MethodImplementation> Swizzle<F> {
+    /// # Safety
+    ///
+    /// The caller must uphold the safety requirements of
+    /// [`AnyClass::swizzle_instance_method`].
+    unsafe fn new(cls: &'static AnyClass, sel: Sel, imp: F) -> Result<Self, VerificationError> {
+        let method = cls.instance_method(sel).ok_or(Inner::MethodNotFound)?;
+        verify_method_signature(method, F::Arguments::ENCODINGS, &F::Return::ENCODING_RETURN)?;
+
+        // If the method is inherited from a superclass, add it to this class
+        // first, such that the superclass' implementation is left untouched.
+        //
+        // SAFETY: The implementation and types are taken from the existing
+        // method, so they match.
+        let _added = unsafe {
+            ffi::class_addMethod(
+                cls.as_ptr() as *mut _,
+                sel.as_ptr(),
+                Some(method.implementation()),
+                ffi::method_getTypeEncoding(method.as_ptr()),
+            )
+        };
+        // Look up the method again, since it might have changed above.
+        let method = cls.instance_method(sel).ok_or(Inner::MethodNotFound)?;
+
+        let replacement = imp.__imp();
+        // SAFETY: The signature was verified above, the rest is upheld by
+        // the caller.
+        let original = unsafe { method.set_implementation(replacement) };
+
+        Ok(Self {
+            cls,
+            method,
+            original,
+            replacement,
+            p: PhantomData,
+        })
+    }
+
+    /// The class whose method was replaced.
+    ///
+    /// For class methods, this is the metaclass.
+    #[inline]
+    pub fn class(&self) -> &'static AnyClass {
+        self.cls
+    }
+
+    /// The method whose implementation was replaced.
+    #[inline]
+    pub fn method(&self) -> &'static Method {
+        self.method
+    }
+
+    /// The original implementation of the method.
+    ///
+    /// This has the same signature as the replacement, and can be called
+    /// from within it to forward to the original behaviour.
+    #[inline]
+    pub fn original(&self) -> F {
+        // SAFETY: `F` is a function pointer type (`MethodImplementation` is
+        // only implemented for those), and the original implementation's
+        // signature was verified to match it when swizzling.
+        unsafe { mem::transmute_copy::<Imp, F>(&self.original) }
+    }
+
+    /// Restore the original implementation of the method.
+    ///
+    ///
+    /// # Panics
+    ///
+    /// Panics if the implementation of the method has been changed since it
+    /// was swizzled, since undoing it would then also undo those changes.
+    pub fn undo(self) {
+        let current = self.method.implementation();
+        if current as usize != self.replacement as usize {
+            panic!(
+                "cannot undo swizzling of {:?}: the implementation was changed again since",
+                self.method.name()
+            );
+        }
+        // SAFETY: Restoring the original implementation.
+        unsafe { self.method.set_implementation(self.original) };
+    }
+}
+
+impl<F> fmt::Debug for Swizzle<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Swizzle")
+            .field("class", &self.cls)
+            .field("method", &self.method)
+            .field("original", &self.original)
+            .finish_non_exhaustive()
+    }
+}
+
+impl AnyClass {
+    /// Replace the implementation of an instance method on this class,
+    /// after verifying that the signature of the new implementation matches
+    /// the existing method.
+    ///
+    /// If the method is inherited from a superclass, it is added to this
+    /// class instead of changing the superclass' implementation.
+    ///
+    /// The returned [`Swizzle`] handle can be used to call the original
+    /// implementation, and to undo the replacement.
+    ///
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the method is not found, or if the encoding of
+    /// the new implementation does not match the existing method. In that
+    /// case, nothing is changed.
+    ///
+    ///
+    /// # Safety
+    ///
+    /// The verification only checks the type-encodings, so the new
+    /// implementation must additionally be at least as safe as the existing
+    /// method (see [`Method::set_implementation`] for details), and the
+    /// callee type must be correct for all instances that the method may be
+    /// called on.
+    ///
+    ///
+    /// # Example
+    ///
+    /// Count the number of calls to a method.
+    ///
+    /// ```
+    /// use std::sync::atomic::{AtomicUsize, Ordering};
+    /// use objc2::rc::Id;
+    /// use objc2::runtime::{NSObject, Sel};
+    /// use objc2::{declare_class, msg_send, msg_send_id, mutability, sel, ClassType};
+    ///
+    /// declare_class!(
+    ///     struct MyObject;
+    ///
+    ///     unsafe impl ClassType for MyObject {
+    ///         type Super = NSObject;
+    ///         type Mutability = mutability::InteriorMutable;
+    ///         const NAME: &'static str = "SwizzledObject";
+    ///     }
+    /// );
+    ///
+    /// static CALLS: AtomicUsize = AtomicUsize::new(0);
+    ///
+    /// extern "C" fn hash(_this: &NSObject, _cmd: Sel) -> usize {
+    ///     CALLS.fetch_add(1, Ordering::Relaxed);
+    ///     42
+    /// }
+    ///
+    /// let cls = MyObject::class();
+    /// let swizzle = unsafe {
+    ///     cls.swizzle_instance_method(sel!(hash), hash as extern "C" fn(_, _) -> _)
+    /// }
+    /// .unwrap();
+    ///
+    /// let obj: Id<NSObject> = unsafe { msg_send_id![cls, new] };
+    /// let res: usize = unsafe { msg_send![&obj, hash] };
+    /// assert_eq!(res, 42);
+    /// assert_eq!(CALLS.load(Ordering::Relaxed), 1);
+    ///
+    /// // Call the original implementation from `NSObject`.
+    /// let original = swizzle.original();
+    /// let res: usize = original(&*obj, sel!(hash));
+    /// assert_ne!(res, 42);
+    ///
+    /// swizzle.undo();
+    /// let res: usize = unsafe { msg_send![&obj, hash] };
+    /// assert_ne!(res, 42);
+    /// assert_eq!(CALLS.load(Ordering::Relaxed), 1);
+    /// ```
+    ///
+    /// Mismatched signatures are rejected.
+    ///
+    /// ```
+    /// use objc2::runtime::{NSObject, Sel};
+    /// use objc2::{sel, ClassType};
+    ///
+    /// extern "C" fn hash(_this: &NSObject, _cmd: Sel) -> f32 {
+    ///     0.0
+    /// }
+    ///
+    /// let res = unsafe {
+    ///     NSObject::class().swizzle_instance_method(sel!(hash), hash as extern "C" fn(_, _) -> _)
+    /// };
+    /// assert!(res.is_err());
+    /// ```
+    pub unsafe fn swizzle_instance_method<F>(
+        &'static self,
+        sel: Sel,
+        imp: F,
+    ) -> Result<Swizzle<F>, VerificationError>
+    where
+        F: MethodImplementation,
+    {
+        // SAFETY: Upheld by caller
+        unsafe { Swizzle::new(self, sel, imp) }
+    }
+
+    /// Replace the implementation of a class method on this class.
+    ///
+    /// This is the same as [`swizzle_instance_method`] on the metaclass,
+    /// except that the callee of the new implementation must be a class.
+    ///
+    /// [`swizzle_instance_method`]: Self::swizzle_instance_method
+    ///
+    ///
+    /// # Errors
+    ///
+    /// Same as [`swizzle_instance_method`].
+    ///
+    ///
+    /// # Safety
+    ///
+    /// Same as [`swizzle_instance_method`].
+    pub unsafe fn swizzle_class_method<F>(
+        &'static self,
+        sel: Sel,
+        imp: F,
+    ) -> Result<Swizzle<F>, VerificationError>
+    where
+        F: MethodImplementation<Callee = AnyClass>,
+    {
+        // SAFETY: Upheld by caller
+        unsafe { Swizzle::new(self.metaclass(), sel, imp) }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::string::ToString;
+
+    use super::*;
+    use crate::declare::ClassBuilder;
+    use crate::runtime::AnyObject;
+    use crate::{msg_send, sel, test_utils};
+
+    fn subclass(name: &str) -> &'static AnyClass {
+        ClassBuilder::new(name, test_utils::custom_class())
+            .unwrap()
+            .register()
+    }
+
+    #[test]
+    fn test_swizzle() {
+        let cls = subclass("TestSwizzleSubclass");
+        let superclass = test_utils::custom_class();
+
+        extern "C" fn foo(_this: &AnyObject, _cmd: Sel) -> u32 {
+            42
+        }
+
+        let swizzle =
+            unsafe { cls.swizzle_instance_method(sel!(foo), foo as extern "C" fn(_, _) -> _) }
+                .unwrap();
+        assert_eq!(swizzle.class(), cls);
+        assert_eq!(swizzle.method().name(), sel!(foo));
+
+        // The superclass is untouched
+        assert_ne!(
+            superclass.instance_method(sel!(foo)).unwrap(),
+            cls.instance_method(sel!(foo)).unwrap()
+        );
+
+        let obj: *mut AnyObject = unsafe { msg_send![cls, new] };
+        let obj = unsafe { &mut *obj };
+        let _: () = unsafe { msg_send![&mut *obj, setFoo: 7u32] };
+        let res: u32 = unsafe { msg_send![&*obj, foo] };
+        assert_eq!(res, 42);
+        assert_eq!(swizzle.original()(&*obj, sel!(foo)), 7u32);
+
+        swizzle.undo();
+        let res: u32 = unsafe { msg_send![&*obj, foo] };
+        assert_eq!(res, 7);
+        let _: () = unsafe { msg_send![obj, release] };
+    }
+
+    #[test]
+    fn test_swizzle_class_method() {
+        let cls = subclass("TestSwizzleClassMethod");
+
+        extern "C" fn class_foo(_cls: &AnyClass, _cmd: Sel) -> u32 {
+            3
+        }
+
+        let swizzle = unsafe {
+            cls.swizzle_class_method(sel!(classFoo), class_foo as extern "C" fn(_, _) -> _)
+        }
+        .unwrap();
+        assert_eq!(swizzle.class(), cls.metaclass());
+        let res: u32 = unsafe { msg_send![cls, classFoo] };
+        assert_eq!(res, 3);
+        assert_eq!(swizzle.original()(cls, sel!(classFoo)), 7u32);
+    }
+
+    #[test]
+    fn test_swizzle_invalid() {
+        let cls = subclass("TestSwizzleInvalid");
+
+        extern "C" fn foo(_this: &AnyObject, _cmd: Sel) -> i64 {
+            42
+        }
+
+        let err =
+            unsafe { cls.swizzle_instance_method(sel!(foo), foo as extern "C" fn(_, _) -> _) }
+                .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "expected return to have type code 'I', but found 'q'"
+        );
+
+        let err =
+            unsafe { cls.swizzle_instance_method(sel!(bar), foo as extern "C" fn(_, _) -> _) }
+                .unwrap_err();
+        assert_eq!(err.to_string(), "method not found");
+
+        // Nothing was changed
+        assert_eq!(
+            test_utils::custom_class()
+                .instance_method(sel!(foo))
+                .unwrap(),
+            cls.instance_method(sel!(foo)).unwrap()
+        );
+    }
+
+    #[test]
+    #[should_panic = "cannot undo swizzling of foo: the implementation was changed again since"]
+    fn test_undo_changed() {
+        let cls = subclass("TestSwizzleUndoChanged");
+
+        extern "C" fn foo1(_this: &AnyObject, _cmd: Sel) -> u32 {
+            1
+        }
+        extern "C" fn foo2(_this: &AnyObject, _cmd: Sel) -> u32 {
+            2
+        }
+
+        let swizzle1 =
+            unsafe { cls.swizzle_instance_method(sel!(foo), foo1 as extern "C" fn(_, _) -> _) }
+                .unwrap();
+        let _swizzle2 =
+            unsafe { cls.swizzle_instance_method(sel!(foo), foo2 as extern "C" fn(_, _) -> _) }
+                .unwrap();
+        swizzle1.undo();
+    }
+}
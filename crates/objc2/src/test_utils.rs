@@ -5,7 +5,7 @@ use std::sync::Once;
 use crate::declare::{ClassBuilder, ProtocolBuilder};
 use crate::encode::{Encode, Encoding, RefEncode};
 use crate::rc::Id;
-use crate::runtime::{AnyClass, AnyObject, AnyProtocol, Sel};
+use crate::runtime::{AnyClass, AnyObject, AnyProtocol, PropertyAttributes, Sel};
 use crate::{ffi, msg_send, mutability, sel, ClassType, Message};
 
 #[derive(Debug)]
@@ -192,6 +192,13 @@ pub(crate) fn custom_class() -> &'static AnyClass {
             builder.add_method(sel!(test::test::), f);
             let f: extern "C" fn(_, _, _, _, _, _) -> _ = custom_obj_multiple_colon_class;
             builder.add_class_method(sel!(test::test::), f);
+
+            let attributes = PropertyAttributes {
+                nonatomic: true,
+                ivar: Some("_foo"),
+                ..Default::default()
+            };
+            builder.add_property::<u32>("foo", &attributes);
         }
 
         builder.register();
@@ -2,7 +2,7 @@ use core::fmt;
 use core::hash::Hash;
 use std::error::Error;
 
-use crate::encode::{Encoding, EncodingBox};
+use crate::encode::{CallingConvention, Encoding, EncodingBox};
 use crate::runtime::{EncodingParseError, Method};
 
 #[derive(Debug, PartialEq, Eq, Hash)]
@@ -71,19 +71,43 @@ impl fmt::Display for VerificationError {
 
 impl Error for VerificationError {}
 
-/// Relaxed version of `Encoding::equivalent_to_box` that allows
-/// `*mut c_void` and `*const c_void` to be used in place of other pointers.
+/// Relaxed version of `Encoding::equivalent_to_box` that, when the
+/// `"relax-void-encoding"` feature is enabled, allows any encoding that is
+/// ABI-compatible with the expected one on the current target.
 ///
-/// Note: This is a top-level comparison; `*mut *mut c_void` or structures
-/// containing `*mut c_void` are not allowed differently than usual.
+/// This includes `*mut c_void` and `*const c_void` in place of other
+/// pointers, but also e.g. differently named structs with the same layout, or
+/// integers of different signedness.
+///
+/// Objects, classes, blocks and selectors are never relaxed, since while e.g.
+/// an integer may be passed the same way as an object, treating it as such
+/// would still be incorrect.
 fn relaxed_equivalent_to_box(encoding: &Encoding, expected: &EncodingBox) -> bool {
-    if cfg!(feature = "relax-void-encoding")
-        && matches!(encoding, Encoding::Pointer(&Encoding::Void))
-        && matches!(expected, EncodingBox::Pointer(_))
-    {
+    fn is_special(encoding: &Encoding) -> bool {
+        matches!(
+            encoding,
+            Encoding::Object | Encoding::Class | Encoding::Block | Encoding::Sel
+        )
+    }
+
+    fn is_special_box(encoding: &EncodingBox) -> bool {
+        matches!(
+            encoding,
+            EncodingBox::Object | EncodingBox::Class | EncodingBox::Block | EncodingBox::Sel
+        )
+    }
+
+    if encoding.equivalent_to_box(expected) {
         true
+    } else if cfg!(feature = "relax-void-encoding")
+        && !is_special(encoding)
+        && !is_special_box(expected)
+    {
+        encoding
+            .abi_compatible_with_box(expected, CallingConvention::CURRENT)
+            .is_ok()
     } else {
-        encoding.equivalent_to_box(expected)
+        false
     }
 }
 
@@ -192,7 +216,7 @@ mod tests {
     }
 
     #[test]
-    #[cfg(debug_assertions)]
+    #[cfg(all(debug_assertions, not(feature = "relax-void-encoding")))]
     #[should_panic = "invalid message send to -[CustomObject foo]: expected return to have type code 'I', but found 'i'"]
     fn test_send_message_verified() {
         let obj = test_utils::custom_object();
@@ -1,11 +1,12 @@
 #![deny(deprecated, unreachable_code)]
 use core::ptr::{self, NonNull};
 
-use objc2::declare::IvarEncode;
-use objc2::mutability::{Immutable, Mutable};
-use objc2::rc::Id;
-use objc2::runtime::NSObject;
-use objc2::{declare_class, extern_methods, sel, ClassType};
+use objc2::declare::{IvarBool, IvarDrop, IvarEncode};
+use objc2::encode::Encode;
+use objc2::mutability::{Immutable, InteriorMutable, Mutable};
+use objc2::rc::{autoreleasepool, Id, WeakId};
+use objc2::runtime::{Bool, NSObject, PropertyOwnership};
+use objc2::{declare_class, extern_methods, msg_send, msg_send_id, sel, ClassType};
 
 // Test that adding the `deprecated` attribute does not mean that warnings
 // when using the method internally are output.
@@ -464,17 +465,35 @@ declare_class!(
     }
 
     unsafe impl OutParam {
-        #[method(unsupported1:)]
-        fn _unsupported1(_param: &mut Id<Self>) {}
+        #[method(replace:)]
+        fn _replace(param: &mut Id<Self>) {
+            *param = Self::new();
+        }
 
-        #[method(unsupported2:)]
-        fn _unsupported2(_param: Option<&mut Id<Self>>) {}
+        #[method(replaceOptional:)]
+        fn _replace_optional(param: Option<&mut Id<Self>>) {
+            if let Some(param) = param {
+                *param = Self::new();
+            }
+        }
 
-        #[method(unsupported3:)]
-        fn _unsupported3(_param: &mut Option<Id<Self>>) {}
+        #[method(toggle:)]
+        fn _toggle(param: &mut Option<Id<Self>>) {
+            *param = match param.take() {
+                Some(_) => None,
+                None => Some(Self::new()),
+            };
+        }
 
-        #[method(unsupported4:)]
-        fn _unsupported4(_param: Option<&mut Option<Id<Self>>>) {}
+        #[method(shouldError:error:)]
+        fn _should_error(should_error: bool, error: Option<&mut Option<Id<Self>>>) -> bool {
+            if should_error {
+                if let Some(error) = error {
+                    *error = Some(Self::new());
+                }
+            }
+            !should_error
+        }
     }
 );
 
@@ -483,60 +502,65 @@ extern_methods!(
         #[method_id(new)]
         fn new() -> Id<Self>;
 
-        #[method(unsupported1:)]
-        fn unsupported1(_param: &mut Id<Self>);
+        #[method(replace:)]
+        fn replace(param: &mut Id<Self>);
 
-        #[method(unsupported2:)]
-        fn unsupported2(_param: Option<&mut Id<Self>>);
+        #[method(replaceOptional:)]
+        fn replace_optional(param: Option<&mut Id<Self>>);
 
-        #[method(unsupported3:)]
-        fn unsupported3(_param: &mut Option<Id<Self>>);
+        #[method(toggle:)]
+        fn toggle(param: &mut Option<Id<Self>>);
 
-        #[method(unsupported4:)]
-        fn unsupported4(_param: Option<&mut Option<Id<Self>>>);
+        #[method(shouldError:error:)]
+        fn should_error(should_error: bool, error: Option<&mut Option<Id<Self>>>) -> bool;
     }
 );
 
 #[test]
-#[should_panic = "`&mut Id<_>` is not supported in `declare_class!` yet"]
-#[cfg_attr(
-    not(all(target_pointer_width = "64", not(feature = "catch-all"))),
-    ignore = "unwinds through FFI boundary"
-)]
-fn out_param1() {
+fn out_param_id() {
     let mut param = OutParam::new();
-    OutParam::unsupported1(&mut param);
+    let old = param.clone();
+    autoreleasepool(|_| OutParam::replace(&mut param));
+    assert!(!ptr::eq(&*param, &*old));
+
+    let old = param.clone();
+    autoreleasepool(|_| OutParam::replace_optional(Some(&mut param)));
+    assert!(!ptr::eq(&*param, &*old));
+
+    OutParam::replace_optional(None);
 }
 
 #[test]
-#[should_panic = "`Option<&mut Id<_>>` is not supported in `declare_class!` yet"]
-#[cfg_attr(
-    not(all(target_pointer_width = "64", not(feature = "catch-all"))),
-    ignore = "unwinds through FFI boundary"
-)]
-fn out_param2() {
-    OutParam::unsupported2(None);
+fn out_param_option() {
+    let mut param = None;
+    autoreleasepool(|_| OutParam::toggle(&mut param));
+    assert!(param.is_some());
+    autoreleasepool(|_| OutParam::toggle(&mut param));
+    assert!(param.is_none());
 }
 
 #[test]
-#[should_panic = "`&mut Option<Id<_>>` is not supported in `declare_class!` yet"]
-#[cfg_attr(
-    not(all(target_pointer_width = "64", not(feature = "catch-all"))),
-    ignore = "unwinds through FFI boundary"
-)]
-fn out_param3() {
-    let mut param = Some(OutParam::new());
-    OutParam::unsupported3(&mut param);
+fn out_param_error() {
+    let mut error = None;
+    autoreleasepool(|_| {
+        assert!(OutParam::should_error(false, Some(&mut error)));
+        assert!(error.is_none());
+        assert!(!OutParam::should_error(true, Some(&mut error)));
+    });
+    assert!(error.is_some());
+
+    assert!(!OutParam::should_error(true, None));
 }
 
 #[test]
-#[should_panic = "`Option<&mut Option<Id<_>>>` is not supported in `declare_class!` yet"]
+#[should_panic = "received NULL in `&mut Id<_>` out parameter, which is UB! You should handle this with `&mut Option<Id<_>>` instead"]
 #[cfg_attr(
     not(all(target_pointer_width = "64", not(feature = "catch-all"))),
     ignore = "unwinds through FFI boundary"
 )]
-fn out_param4() {
-    OutParam::unsupported4(None);
+fn out_param_null() {
+    let mut param: Option<Id<OutParam>> = None;
+    let _: () = unsafe { msg_send![OutParam::class(), replace: &mut param] };
 }
 
 #[test]
@@ -565,3 +589,195 @@ fn test_pointer_receiver_allowed() {
 
     let _ = PointerReceiver::class();
 }
+
+declare_class!(
+    #[derive(Debug, PartialEq, Eq, Hash)]
+    pub struct DeclareClassPropertyValue;
+
+    unsafe impl ClassType for DeclareClassPropertyValue {
+        type Super = NSObject;
+        type Mutability = Immutable;
+        const NAME: &'static str = "DeclareClassPropertyValue";
+    }
+);
+
+declare_class!(
+    struct DeclareClassProperties {
+        #[property(nonatomic)]
+        count: IvarEncode<u32, "_count">,
+        #[property(nonatomic, readonly, getter = isEnabled)]
+        enabled: IvarBool<"_enabled">,
+        #[property(strong, nonatomic)]
+        object: IvarDrop<Option<Id<DeclareClassPropertyValue>>, "_object">,
+        #[property(weak, nonatomic)]
+        delegate: IvarDrop<Option<WeakId<DeclareClassPropertyValue>>, "_delegate">,
+        #[property(copy, nonatomic, setter = setNameTo:)]
+        name: IvarDrop<Option<Id<DeclareClassPropertyValue>>, "_name">,
+    }
+
+    mod declare_class_properties;
+
+    unsafe impl ClassType for DeclareClassProperties {
+        type Super = NSObject;
+        type Mutability = InteriorMutable;
+        const NAME: &'static str = "DeclareClassProperties";
+    }
+
+    unsafe impl DeclareClassProperties {
+        #[method(doubled)]
+        #[property(nonatomic, readonly)]
+        fn doubled(&self) -> u32 {
+            *self.count * 2
+        }
+    }
+);
+
+#[test]
+fn test_property_attributes() {
+    let cls = DeclareClassProperties::class();
+    let attributes = |name| cls.property(name).unwrap().attribute_string();
+
+    assert_eq!(attributes("count"), "TI,N,V_count");
+    assert_eq!(attributes("object"), "T@,&,N,V_object");
+    assert_eq!(attributes("delegate"), "T@,W,N");
+    assert_eq!(attributes("name"), "T@,C,N,SsetNameTo:,V_name");
+    assert_eq!(attributes("doubled"), "TI,R,N");
+
+    let enabled = cls.property("enabled").unwrap();
+    let enabled = enabled.attributes();
+    assert_eq!(enabled.type_encoding, Some(&*Bool::ENCODING.to_string()));
+    assert!(enabled.readonly);
+    assert_eq!(enabled.ownership, PropertyOwnership::Assign);
+    assert_eq!(enabled.getter, Some("isEnabled"));
+
+    assert!(cls.instance_method(sel!(setCount:)).is_some());
+    assert!(cls.instance_method(sel!(isEnabled)).is_some());
+    assert!(cls.instance_method(sel!(setEnabled:)).is_none());
+    assert!(cls.instance_method(sel!(setNameTo:)).is_some());
+    assert!(cls.instance_method(sel!(setName:)).is_none());
+}
+
+#[test]
+fn test_property_accessors() {
+    let obj: Id<DeclareClassProperties> =
+        unsafe { msg_send_id![DeclareClassProperties::class(), new] };
+
+    let _: () = unsafe { msg_send![&obj, setCount: 21u32] };
+    assert_eq!(*obj.count, 21);
+    let doubled: u32 = unsafe { msg_send![&obj, doubled] };
+    assert_eq!(doubled, 42);
+
+    let enabled: bool = unsafe { msg_send![&obj, isEnabled] };
+    assert!(!enabled);
+
+    let object: Id<DeclareClassPropertyValue> =
+        unsafe { msg_send_id![DeclareClassPropertyValue::class(), new] };
+    let _: () = unsafe { msg_send![&obj, setObject: &*object] };
+    assert_eq!(obj.object.as_deref(), Some(&*object));
+    let res: Option<Id<DeclareClassPropertyValue>> = unsafe { msg_send_id![&obj, object] };
+    assert_eq!(res.as_deref(), Some(&*object));
+    let _: () = unsafe { msg_send![&obj, setObject: ptr::null::<DeclareClassPropertyValue>()] };
+    assert!(obj.object.is_none());
+
+    autoreleasepool(|_| {
+        let _: () = unsafe { msg_send![&obj, setDelegate: &*object] };
+        let res: Option<Id<DeclareClassPropertyValue>> = unsafe { msg_send_id![&obj, delegate] };
+        assert_eq!(res.as_deref(), Some(&*object));
+    });
+    drop(object);
+    let res: Option<Id<DeclareClassPropertyValue>> = unsafe { msg_send_id![&obj, delegate] };
+    assert!(res.is_none());
+}
+
+#[cfg(feature = "invocation")]
+#[allow(clippy::missing_safety_doc)]
+mod forwarding {
+    use std::sync::Once;
+
+    use objc2::declare::{ForwardedMessage, ProtocolBuilder};
+    use objc2::encode::EncodingBox;
+    use objc2::mutability::Immutable;
+    use objc2::rc::Id;
+    use objc2::runtime::{Bool, NSObject, Sel, Value};
+    use objc2::{declare_class, extern_protocol, msg_send_id, sel, ClassType, ProtocolType};
+
+    extern_protocol!(
+        unsafe trait DeclareClassForwardingProtocol {
+            #[method(doubleNumber:)]
+            fn double_number(&self, number: u32) -> u32;
+
+            #[method(setName:)]
+            fn set_name(&self, name: *const u8);
+
+            #[optional]
+            #[method(isForwarded)]
+            fn is_forwarded(&self) -> bool;
+        }
+
+        unsafe impl ProtocolType for dyn DeclareClassForwardingProtocol {}
+    );
+
+    fn register_protocol() {
+        static REGISTER: Once = Once::new();
+        REGISTER.call_once(|| {
+            let mut builder = ProtocolBuilder::new("DeclareClassForwardingProtocol").unwrap();
+            builder.add_method_description::<(u32,), u32>(sel!(doubleNumber:), true);
+            builder.add_method_description::<(*const u8,), ()>(sel!(setName:), true);
+            builder.add_method_description::<(), Bool>(sel!(isForwarded), false);
+            builder.register();
+        });
+    }
+
+    declare_class!(
+        struct DeclareClassForwarding;
+
+        unsafe impl ClassType for DeclareClassForwarding {
+            type Super = NSObject;
+            type Mutability = Immutable;
+            const NAME: &'static str = "DeclareClassForwarding";
+        }
+
+        unsafe impl DeclareClassForwarding {
+            // Returning `nil` makes the message go to `#[forward]`.
+            #[method(forwardingTargetForSelector:)]
+            fn forwarding_target(&self, _sel: Sel) -> *mut NSObject {
+                core::ptr::null_mut()
+            }
+
+            #[forward]
+            fn forward(&self, message: ForwardedMessage<'_>) -> Value {
+                if message.sel() == sel!(doubleNumber:) {
+                    match message.arguments() {
+                        [Value::U32(number)] => Value::U32(number * 2),
+                        args => panic!("unexpected arguments {args:?}"),
+                    }
+                } else if message.sel() == sel!(isForwarded) {
+                    // `BOOL` is encoded differently depending on the platform.
+                    match message.encoding().return_type.encoding {
+                        EncodingBox::Bool => Value::Bool(true),
+                        EncodingBox::UChar => Value::U8(1),
+                        _ => Value::I8(1),
+                    }
+                } else {
+                    Value::Void
+                }
+            }
+        }
+
+        unsafe impl DeclareClassForwardingProtocol for DeclareClassForwarding {}
+    );
+
+    #[test]
+    fn test_forward() {
+        register_protocol();
+        let obj: Id<DeclareClassForwarding> =
+            unsafe { msg_send_id![DeclareClassForwarding::class(), new] };
+
+        assert_eq!(obj.double_number(21), 42);
+        obj.set_name(b"foo\0".as_ptr());
+        assert!(obj.is_forwarded());
+        let cls = DeclareClassForwarding::class();
+        assert!(cls.responds_to(sel!(doubleNumber:)));
+        assert!(!cls.responds_to(sel!(unknownMethod)));
+    }
+}
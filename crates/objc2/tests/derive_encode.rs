@@ -0,0 +1,123 @@
+#![cfg(feature = "derive")]
+use core::ffi::c_void;
+use core::ptr::NonNull;
+
+use objc2::encode::{Encode, Encoding, RefEncode};
+use objc2::runtime::{AnyObject, Sel};
+
+#[repr(C)]
+#[derive(Encode, RefEncode)]
+struct Empty;
+
+#[repr(C)]
+#[derive(Encode, RefEncode)]
+pub struct Point {
+    pub x: f64,
+    pub y: f64,
+}
+
+#[repr(C)]
+#[derive(Encode, RefEncode)]
+#[encoding(name = "CGRect")]
+struct Rect {
+    /// Doc comments and attributes on fields are skipped.
+    #[allow(dead_code)]
+    origin: Point,
+    pub(crate) size: Point,
+}
+
+#[repr(C)]
+#[derive(Encode)]
+struct Tuple(i32, pub u8, [u16; 3]);
+
+#[repr(C)]
+#[derive(Encode)]
+struct Complex {
+    ptr: *const Point,
+    opt: Option<NonNull<Point>>,
+    void: *mut c_void,
+    obj: *mut AnyObject,
+    sel: Sel,
+    func: Option<extern "C" fn(i32, u8) -> i32>,
+    last: u32,
+}
+
+const NAME: &str = "Renamed";
+
+#[repr(C)]
+#[derive(Encode, RefEncode)]
+#[encoding(name = NAME)]
+union Union {
+    a: i32,
+    b: f32,
+}
+
+#[repr(C, align(16))]
+#[derive(Encode)]
+struct Aligned {
+    a: u8,
+}
+
+#[test]
+fn empty() {
+    assert_eq!(Empty::ENCODING, Encoding::Struct("Empty", &[]));
+    assert_eq!(Empty::ENCODING.to_string(), "{Empty=}");
+}
+
+#[test]
+fn named_fields() {
+    assert_eq!(
+        Point::ENCODING,
+        Encoding::Struct("Point", &[Encoding::Double, Encoding::Double]),
+    );
+    assert_eq!(Point::ENCODING_REF, Encoding::Pointer(&Point::ENCODING));
+    assert_eq!(<&Point>::ENCODING, Point::ENCODING_REF);
+}
+
+#[test]
+fn custom_name() {
+    assert_eq!(
+        Rect::ENCODING,
+        Encoding::Struct("CGRect", &[Point::ENCODING, Point::ENCODING]),
+    );
+    assert_eq!(Rect::ENCODING.to_string(), "{CGRect={Point=dd}{Point=dd}}");
+    assert_eq!(Rect::ENCODING_REF.to_string(), "^{CGRect={Point=dd}{Point=dd}}");
+}
+
+#[test]
+fn tuple_struct() {
+    assert_eq!(Tuple::ENCODING.to_string(), "{Tuple=iC[3S]}");
+}
+
+#[test]
+fn complex_field_types() {
+    assert_eq!(
+        Complex::ENCODING,
+        Encoding::Struct(
+            "Complex",
+            &[
+                Encoding::Pointer(&Point::ENCODING),
+                Encoding::Pointer(&Point::ENCODING),
+                Encoding::Pointer(&Encoding::Void),
+                Encoding::Object,
+                Encoding::Sel,
+                Encoding::Pointer(&Encoding::Unknown),
+                Encoding::UInt,
+            ],
+        ),
+    );
+}
+
+#[test]
+fn union() {
+    assert_eq!(
+        Union::ENCODING,
+        Encoding::Union("Renamed", &[Encoding::Int, Encoding::Float]),
+    );
+    assert_eq!(Union::ENCODING_REF.to_string(), "^(Renamed=if)");
+}
+
+#[test]
+fn aligned() {
+    assert_eq!(Aligned::ENCODING.to_string(), "{Aligned=C}");
+}
@@ -15,6 +15,8 @@ mod test_declare_class_protocol;
 #[cfg(test)]
 mod test_encode_utils;
 #[cfg(test)]
+mod test_method_block;
+#[cfg(test)]
 mod test_object;
 
 use crate::ffi::LargeStruct;
@@ -54,8 +56,11 @@ pub fn invoke_large_struct_block(
 #[cfg(test)]
 mod tests {
     use super::*;
+    use alloc::format;
+    use alloc::rc::Rc;
     use alloc::string::ToString;
-    use block2::{global_block, ConcreteBlock, RcBlock};
+    use block2::{global_block, ConcreteBlock, NoEscapeBlock, RcBlock};
+    use core::cell::Cell;
 
     global_block! {
         /// Test `global_block` in an external crate
@@ -85,6 +90,17 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_try_call_block() {
+        let block = get_add_block_with(13);
+        assert_eq!(unsafe { block.try_call((2,)) }, Ok(15));
+
+        let ptr: *const Block<(i32,), i32> = &*block;
+        let block = unsafe { &*ptr.cast::<Block<(i32, i32), i32>>() };
+        let err = unsafe { block.try_call((2, 3)) }.unwrap_err();
+        assert_eq!(err.to_string(), "expected 1 arguments, but 2 were given");
+    }
+
     #[test]
     fn test_create_block() {
         let block = ConcreteBlock::new(|| 13);
@@ -99,6 +115,91 @@ mod tests {
         assert_eq!(result, 11);
     }
 
+    #[test]
+    fn test_block_signature() {
+        let block = ConcreteBlock::new(|a: i32| a + 5);
+        assert!(format!("{:?}", &*block).contains(r#"encoding: Some("i@?i")"#));
+        let block = block.copy();
+        assert!(format!("{block:?}").contains(r#"encoding: Some("i@?i")"#));
+
+        let s = "Hello!".to_string();
+        let block = ConcreteBlock::new(move |b: u8| s.len() as i32 + b as i32);
+        assert!(format!("{:?}", &*block).contains(r#"encoding: Some("i@?C")"#));
+        let block = block.copy();
+        assert!(format!("{block:?}").contains(r#"encoding: Some("i@?C")"#));
+
+        assert!(format!("{MY_BLOCK:?}").contains(r#"encoding: Some("i@?")"#));
+    }
+
+    #[test]
+    fn test_mut_block() {
+        let mut sum = 0;
+        let block = ConcreteBlock::new_mut(|a: i32| {
+            sum += a;
+            sum
+        });
+        assert_eq!(invoke_add_block(&block, 3), 3);
+        assert_eq!(invoke_add_block(&block, 4), 7);
+        drop(block);
+        assert_eq!(sum, 7);
+
+        let mut calls = 0;
+        let block = ConcreteBlock::new_mut(move || {
+            calls += 1;
+            calls
+        })
+        .copy();
+        assert_eq!(invoke_int_block(&block), 1);
+        assert_eq!(invoke_int_block(&block), 2);
+    }
+
+    #[test]
+    fn test_once_block() {
+        let s = "Hello!".to_string();
+        let block = ConcreteBlock::new_once(move || {
+            let s = s;
+            s.len() as i32
+        });
+        assert_eq!(invoke_int_block(&block), 6);
+
+        let s = "Hello!".to_string();
+        let block = ConcreteBlock::new_once(move |a: i32| s.len() as i32 + a).copy();
+        assert_eq!(invoke_add_block(&block, 2), 8);
+
+        // Calling a block returning `()` again does nothing
+        let calls = Rc::new(Cell::new(0));
+        let block = ConcreteBlock::new_once({
+            let calls = calls.clone();
+            move || calls.set(calls.get() + 1)
+        });
+        unsafe { block.call(()) };
+        unsafe { block.call(()) };
+        assert_eq!(calls.get(), 1);
+    }
+
+    #[test]
+    fn test_noescape_block() {
+        let offset = 5;
+        let block = NoEscapeBlock::new(|a: i32| a + offset);
+        assert_eq!(invoke_add_block(&block, 6), 11);
+
+        let mut items = alloc::vec::Vec::new();
+        let block = NoEscapeBlock::new_mut(|a: i32| {
+            items.push(a);
+            items.len() as i32
+        });
+        assert_eq!(invoke_add_block(&block, 1), 1);
+        assert_eq!(invoke_add_block(&block, 2), 2);
+        drop(block);
+        assert_eq!(items, [1, 2]);
+
+        #[cfg(feature = "apple")]
+        {
+            let block = NoEscapeBlock::new(|| offset);
+            assert!(format!("{:?}", &*block).contains("is_noescape: true"));
+        }
+    }
+
     #[test]
     fn test_concrete_block_copy() {
         let s = "Hello!".to_string();
@@ -0,0 +1,106 @@
+use alloc::string::ToString;
+use alloc::sync::Arc;
+use core::sync::atomic::{AtomicU32, Ordering};
+
+use block2::{global_block, ConcreteBlock};
+use objc2::declare::ClassBuilder;
+use objc2::rc::Id;
+use objc2::runtime::{AnyClass, NSObject};
+use objc2::{msg_send, msg_send_id, sel, ClassType};
+
+#[test]
+fn test_add_method_with_block() {
+    let value = Arc::new(AtomicU32::new(5));
+
+    let mut builder = ClassBuilder::new("TestMethodBlock", NSObject::class()).unwrap();
+
+    let captured = value.clone();
+    let get = ConcreteBlock::new(move |_this: &NSObject| captured.load(Ordering::Relaxed)).copy();
+    let captured = value.clone();
+    let add = ConcreteBlock::new(move |_this: &NSObject, n: u32| {
+        captured.fetch_add(n, Ordering::Relaxed)
+    })
+    .copy();
+    let class_block = ConcreteBlock::new(|_cls: &AnyClass, a: i32, b: i32| a * b).copy();
+    unsafe {
+        builder.add_method_with_block(sel!(value), &get);
+        builder.add_method_with_block(sel!(addValue:), &add);
+        builder.add_class_method_with_block(sel!(multiply:by:), &class_block);
+    }
+    // The blocks are retained by the methods, so ours can be dropped
+    drop(get);
+    drop(add);
+    assert_eq!(Arc::strong_count(&value), 3);
+
+    let cls = builder.register();
+    let obj: Id<NSObject> = unsafe { msg_send_id![cls, new] };
+
+    let res: u32 = unsafe { msg_send![&obj, value] };
+    assert_eq!(res, 5);
+    let res: u32 = unsafe { msg_send![&obj, addValue: 3u32] };
+    assert_eq!(res, 5);
+    let res: u32 = unsafe { msg_send![&obj, value] };
+    assert_eq!(res, 8);
+    assert_eq!(value.load(Ordering::Relaxed), 8);
+
+    let res: i32 = unsafe { msg_send![cls, multiply: 6i32, by: 7i32] };
+    assert_eq!(res, 42);
+}
+
+#[test]
+fn test_replace_method_with_block() {
+    global_block! {
+        static HASH = |_this: &NSObject| -> usize {
+            42
+        };
+    }
+
+    let cls = ClassBuilder::new("TestReplaceMethodBlock", NSObject::class())
+        .unwrap()
+        .register();
+    let obj: Id<NSObject> = unsafe { msg_send_id![cls, new] };
+
+    // Inherited methods are added to the class itself
+    unsafe { cls.replace_method_with_block(sel!(hash), &HASH) }.unwrap();
+    let res: usize = unsafe { msg_send![&obj, hash] };
+    assert_eq!(res, 42);
+    assert_ne!(
+        cls.instance_method(sel!(hash)).unwrap(),
+        NSObject::class().instance_method(sel!(hash)).unwrap()
+    );
+
+    // Replacing a block implementation releases the previous block
+    let captured = Arc::new(AtomicU32::new(0));
+    let block = ConcreteBlock::new({
+        let captured = captured.clone();
+        move |_this: &NSObject| captured.fetch_add(1, Ordering::Relaxed) as usize
+    })
+    .copy();
+    unsafe { cls.replace_method_with_block(sel!(hash), &block) }.unwrap();
+    // The method holds the only other reference to the block
+    drop(block);
+    assert_eq!(Arc::strong_count(&captured), 2);
+    let res: usize = unsafe { msg_send![&obj, hash] };
+    assert_eq!(res, 0);
+
+    unsafe { cls.replace_method_with_block(sel!(hash), &HASH) }.unwrap();
+    assert_eq!(Arc::strong_count(&captured), 1);
+    let res: usize = unsafe { msg_send![&obj, hash] };
+    assert_eq!(res, 42);
+}
+
+#[test]
+fn test_replace_method_with_block_invalid() {
+    let cls = ClassBuilder::new("TestReplaceMethodBlockInvalid", NSObject::class())
+        .unwrap()
+        .register();
+
+    let block = ConcreteBlock::new(|_this: &NSObject| 1.0f32).copy();
+    let err = unsafe { cls.replace_method_with_block(sel!(hash), &block) }.unwrap_err();
+    assert!(err.to_string().contains("expected return"), "{err}");
+
+    let block = ConcreteBlock::new(|_this: &NSObject| 1u32).copy();
+    let err = unsafe { cls.replace_method_with_block(sel!(newMethod:), &block) }.unwrap_err();
+    assert_eq!(err.to_string(), "expected 1 arguments, but 0 were given");
+    assert!(cls.instance_method(sel!(newMethod:)).is_none());
+}